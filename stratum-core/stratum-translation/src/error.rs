@@ -7,6 +7,7 @@ pub enum StratumTranslationError {
     IncompatibleVersionRollingMask,
     InvalidExtranonceLength,
     InvalidUserIdentity(String),
+    InvalidDifficulty(f64),
     // SV2 -> SV1
     FailedToTryToStripBip141(StripBip141Error),
     FailedToSerializeToB064K,
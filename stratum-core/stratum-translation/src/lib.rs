@@ -8,14 +8,15 @@
 //! - Converters from SV2 messages/values to SV1 messages (e.g. SetTarget → mining.set_difficulty)
 //! - Converters from SV1 messages/values to SV2 messages (e.g. mining.submit →
 //!   SubmitSharesExtended)
+//! - Mappings from channels_sv2 share validation errors to SV1 mining.submit rejections
 //! - Uses existing utilities from channels_sv2 (e.g. target_to_difficulty)
 //!
 //! What it does not contain:
 //! - Networking, async runtimes, channels, or long-running tasks
 //!
 //! Error handling:
-//! - All public functions return `Result<_, error::StratumTranslationError>` with specific error
-//!   kinds to aid debugging and integration.
+//! - All fallible public functions return `Result<_, error::StratumTranslationError>` with
+//!   specific error kinds to aid debugging and integration.
 
 pub mod error;
 pub mod sv1_to_sv2;
@@ -1,5 +1,6 @@
 use crate::error::{Result, StratumTranslationError};
 use bitcoin::Target;
+use channels_sv2::target::hash_rate_to_target;
 use mining_sv2::{OpenExtendedMiningChannel, SubmitSharesExtended};
 use v1::{client_to_server, utils::HexU32Be};
 
@@ -80,6 +81,28 @@ pub fn build_sv2_submit_shares_extended_from_sv1_submit(
     Ok(submit_share_extended)
 }
 
+/// Converts an SV1 share difficulty (e.g. suggested by a miner with `mining.suggest_difficulty`)
+/// into the equivalent SV2 target, to be used as the initial target of the miner's channel.
+///
+/// Difficulty 1 corresponds to [`Target::MAX`], i.e. about `2^48 / 0xffff` hashes per share.
+///
+/// # Arguments
+/// * `difficulty` - The SV1 share difficulty.
+///
+/// # Returns
+/// * `Ok(Target)` - The target of shares of the given difficulty.
+/// * `Err(StratumTranslationError::InvalidDifficulty)` if `difficulty` is not a positive finite
+///   number.
+pub fn build_sv2_target_from_sv1_difficulty(difficulty: f64) -> Result<Target> {
+    if !(difficulty.is_finite() && difficulty > 0.0) {
+        return Err(StratumTranslationError::InvalidDifficulty(difficulty));
+    }
+    // the expected number of hashes per share, found at a rate of one share per second
+    let hashes_per_share = difficulty * 2f64.powi(48) / 65535.0;
+    hash_rate_to_target(hashes_per_share, 60.0)
+        .map_err(|_| StratumTranslationError::InvalidDifficulty(difficulty))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -93,7 +116,7 @@ mod tests {
             time: HexU32Be(0),
             nonce: HexU32Be(0),
             version_bits: Some(HexU32Be(0)),
-            id: 0,
+            id: 0.into(),
         }
     }
 
@@ -193,4 +216,26 @@ mod tests {
             assert!(matches!(e, StratumTranslationError::InvalidUserIdentity(_)));
         }
     }
+
+    #[test]
+    fn test_build_sv2_target_from_sv1_difficulty() {
+        for difficulty in [1.0, 0.5, 2048.0, 1e12] {
+            let target = build_sv2_target_from_sv1_difficulty(difficulty).unwrap();
+            let error = (target.difficulty_float() - difficulty).abs() / difficulty;
+            assert!(error < 1e-6, "difficulty {difficulty} -> {target:?}");
+        }
+
+        // tiny difficulties saturate to the easiest target
+        assert_eq!(
+            build_sv2_target_from_sv1_difficulty(1e-80).unwrap(),
+            Target::from_le_bytes([0xff; 32])
+        );
+
+        for difficulty in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert!(matches!(
+                build_sv2_target_from_sv1_difficulty(difficulty),
+                Err(StratumTranslationError::InvalidDifficulty(_))
+            ));
+        }
+    }
 }
@@ -7,13 +7,15 @@
 //! The main functions convert:
 //! - SV2 mining jobs to SV1 notify messages
 //! - SV2 difficulty targets to SV1 set_difficulty messages
+//! - SV2 share validation errors to SV1 mining.submit rejections
 
 use crate::error::{Result, StratumTranslationError};
 use bitcoin::Target;
-use channels_sv2::bip141::try_strip_bip141;
+use channels_sv2::{bip141::try_strip_bip141, client, server};
 use mining_sv2::{NewExtendedMiningJob, SetNewPrevHash, SetTarget};
 use tracing::debug;
 use v1::{
+    client_to_server::SubmitRejection,
     json_rpc, server_to_client,
     utils::{HexU32Be, MerkleNode, PrevHash},
 };
@@ -124,6 +126,72 @@ pub fn build_sv1_set_difficulty_from_sv2_target(target: Target) -> Result<json_r
     Ok(set_target.into())
 }
 
+/// Maps a share validation error of a server channel to the SV1 `mining.submit` rejection
+/// reported to the miner.
+///
+/// Reasons without a conventional SV1 error code are reported as
+/// [`SubmitRejection::Other`] with a descriptive message.
+///
+/// # Arguments
+/// * `error` - The `ShareValidationError` returned by the server channel.
+///
+/// # Returns
+/// * `SubmitRejection` - The rejection to respond to the `mining.submit` with.
+pub fn build_sv1_submit_rejection_from_share_validation_error(
+    error: &server::share_accounting::ShareValidationError,
+) -> SubmitRejection {
+    use server::share_accounting::ShareValidationError;
+    match error {
+        ShareValidationError::Stale | ShareValidationError::JobEvicted => SubmitRejection::Stale,
+        ShareValidationError::InvalidJobId => SubmitRejection::JobNotFound,
+        ShareValidationError::DoesNotMeetTarget => SubmitRejection::LowDifficulty,
+        ShareValidationError::DuplicateShare => SubmitRejection::DuplicateShare,
+        ShareValidationError::VersionRollingNotAllowed | ShareValidationError::InvalidVersion => {
+            SubmitRejection::Other("Invalid version".to_string())
+        }
+        ShareValidationError::InvalidNtime => SubmitRejection::Other("Invalid ntime".to_string()),
+        ShareValidationError::BadExtranonceSize => {
+            SubmitRejection::Other("Invalid extranonce2 size".to_string())
+        }
+        ShareValidationError::Invalid
+        | ShareValidationError::InvalidCoinbase
+        | ShareValidationError::NoChainTip
+        | ShareValidationError::NotGroupMember => {
+            SubmitRejection::Other("Invalid share".to_string())
+        }
+    }
+}
+
+/// Maps a share validation error of a client channel (e.g. a translator proxy checking SV1 shares
+/// before sending them upstream) to the SV1 `mining.submit` rejection reported to the miner, as
+/// in [`build_sv1_submit_rejection_from_share_validation_error`].
+///
+/// # Arguments
+/// * `error` - The `ShareValidationError` returned by the client channel.
+///
+/// # Returns
+/// * `SubmitRejection` - The rejection to respond to the `mining.submit` with.
+pub fn build_sv1_submit_rejection_from_client_share_validation_error(
+    error: &client::share_accounting::ShareValidationError,
+) -> SubmitRejection {
+    use client::share_accounting::ShareValidationError;
+    use server::share_accounting::ShareValidationError as ServerShareValidationError;
+    // client validation errors are a subset of the server ones, map them the same way
+    let error = match error {
+        ShareValidationError::Invalid => ServerShareValidationError::Invalid,
+        ShareValidationError::Stale => ServerShareValidationError::Stale,
+        ShareValidationError::InvalidJobId => ServerShareValidationError::InvalidJobId,
+        ShareValidationError::DoesNotMeetTarget => ServerShareValidationError::DoesNotMeetTarget,
+        ShareValidationError::VersionRollingNotAllowed => {
+            ServerShareValidationError::VersionRollingNotAllowed
+        }
+        ShareValidationError::DuplicateShare => ServerShareValidationError::DuplicateShare,
+        ShareValidationError::NoChainTip => ServerShareValidationError::NoChainTip,
+        ShareValidationError::BadExtranonceSize => ServerShareValidationError::BadExtranonceSize,
+    };
+    build_sv1_submit_rejection_from_share_validation_error(&error)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -135,6 +203,33 @@ mod tests {
         Target::from_le_bytes([0xffu8; 32])
     }
 
+    #[test]
+    fn test_build_sv1_submit_rejection_from_share_validation_error() {
+        use channels_sv2::{client, server};
+
+        let rejection = build_sv1_submit_rejection_from_share_validation_error(
+            &server::share_accounting::ShareValidationError::JobEvicted,
+        );
+        assert_eq!(rejection, SubmitRejection::Stale);
+        let rejection = build_sv1_submit_rejection_from_share_validation_error(
+            &server::share_accounting::ShareValidationError::DoesNotMeetTarget,
+        );
+        assert_eq!(rejection.code(), 23);
+        let rejection = build_sv1_submit_rejection_from_share_validation_error(
+            &server::share_accounting::ShareValidationError::InvalidNtime,
+        );
+        assert_eq!(rejection.code(), 20);
+
+        let rejection = build_sv1_submit_rejection_from_client_share_validation_error(
+            &client::share_accounting::ShareValidationError::DuplicateShare,
+        );
+        assert_eq!(rejection, SubmitRejection::DuplicateShare);
+        let rejection = build_sv1_submit_rejection_from_client_share_validation_error(
+            &client::share_accounting::ShareValidationError::InvalidJobId,
+        );
+        assert_eq!(rejection, SubmitRejection::JobNotFound);
+    }
+
     #[test]
     fn test_build_sv1_set_difficulty_from_sv2_target() {
         let msg = build_sv1_set_difficulty_from_sv2_target(dummy_target())
@@ -1,6 +1,6 @@
 use std::{
     convert::{TryFrom, TryInto},
-    io::{BufRead, BufReader, Write},
+    io::Write,
     net::{SocketAddr, TcpListener, TcpStream},
     process::exit,
     sync::{mpsc, Arc, Mutex},
@@ -16,6 +16,7 @@ type Sender<T> = mpsc::Sender<T>;
 
 use sv1_api::{
     client_to_server,
+    codec::{self, Decoder},
     error::Error,
     json_rpc, server_to_client,
     utils::{Extranonce, HexU32Be, MerkleNode, PrevHash},
@@ -61,6 +62,26 @@ fn prevhash_from_hex<'a>(hex: &str) -> PrevHash<'a> {
     }
 }
 
+// Decodes the messages received on `stream`, skipping malformed lines.
+fn read_messages(mut stream: TcpStream, sender_incoming: Sender<json_rpc::Message>) {
+    let mut decoder = Decoder::new();
+    while let Ok(n) = decoder.read_from(&mut stream) {
+        if n == 0 {
+            return;
+        }
+        while let Some(message) = decoder.next_message() {
+            match message {
+                Ok(message) => {
+                    if sender_incoming.send(message).is_err() {
+                        return;
+                    }
+                }
+                Err(e) => println!("Skipping line: {e}"),
+            }
+        }
+    }
+}
+
 fn new_extranonce2_size() -> usize {
     4
 }
@@ -78,8 +99,8 @@ struct Server<'a> {
     extranonce2_size: usize,
     version_rolling_mask: Option<HexU32Be>,
     version_rolling_min_bit: Option<HexU32Be>,
-    receiver_incoming: Receiver<String>,
-    sender_outgoing: Sender<String>,
+    receiver_incoming: Receiver<json_rpc::Message>,
+    sender_outgoing: Sender<Vec<u8>>,
 }
 
 fn server_pool_listen(listener: TcpListener) {
@@ -100,25 +121,18 @@ fn server_pool_listen(listener: TcpListener) {
 
 impl Server<'_> {
     pub fn new(stream: TcpStream) -> Arc<Mutex<Server<'static>>> {
-        let (sender_incoming, receiver_incoming) = mpsc::channel::<String>();
-        let (sender_outgoing, receiver_outgoing) = mpsc::channel::<String>();
+        let (sender_incoming, receiver_incoming) = mpsc::channel::<json_rpc::Message>();
+        let (sender_outgoing, receiver_outgoing) = mpsc::channel::<Vec<u8>>();
 
         let reader_stream = stream.try_clone().expect("Failed to clone stream (read)");
         let mut writer_stream = stream;
 
         // read thread
-        thread::spawn(move || {
-            let reader = BufReader::new(reader_stream);
-            for line in reader.lines().map_while(Result::ok) {
-                if sender_incoming.send(line).is_err() {
-                    break;
-                }
-            }
-        });
+        thread::spawn(move || read_messages(reader_stream, sender_incoming));
 
         thread::spawn(move || {
             for msg in receiver_outgoing {
-                let _ = writer_stream.write_all(msg.as_bytes());
+                let _ = writer_stream.write_all(&msg);
             }
         });
 
@@ -138,16 +152,13 @@ impl Server<'_> {
             let cloned = Arc::clone(&server_arc);
             thread::spawn(move || loop {
                 if let Ok(mut self_) = cloned.try_lock() {
-                    if let Ok(line) = self_.receiver_incoming.try_recv() {
-                        println!("SERVER - message: {line}");
-                        let message: Result<json_rpc::Message, _> = serde_json::from_str(&line);
-                        if let Ok(message) = message {
-                            if let Ok(Some(resp)) = self_.handle_message(message) {
-                                Self::send_message(
-                                    &self_.sender_outgoing,
-                                    json_rpc::Message::OkResponse(resp),
-                                );
-                            }
+                    if let Ok(message) = self_.receiver_incoming.try_recv() {
+                        println!("SERVER - message: {message}");
+                        if let Ok(Some(resp)) = self_.handle_message(message) {
+                            Self::send_message(
+                                &self_.sender_outgoing,
+                                json_rpc::Message::OkResponse(resp),
+                            );
                         }
                     }
                 }
@@ -188,9 +199,8 @@ impl Server<'_> {
         Ok(None)
     }
 
-    fn send_message(sender_outgoing: &Sender<String>, msg: json_rpc::Message) {
-        let msg = format!("{}\n", serde_json::to_string(&msg).unwrap());
-        let _ = sender_outgoing.send(msg);
+    fn send_message(sender_outgoing: &Sender<Vec<u8>>, msg: json_rpc::Message) {
+        let _ = sender_outgoing.send(codec::encode(&msg));
     }
 }
 
@@ -293,10 +303,10 @@ struct Client<'a> {
     version_rolling_min_bit: Option<HexU32Be>,
     status: ClientStatus,
     last_notify: Option<server_to_client::Notify<'a>>,
-    sented_authorize_request: Vec<(u64, String)>, // (id, user_name)
+    sented_authorize_request: Vec<(json_rpc::Id, String)>, // (id, user_name)
     authorized: Vec<String>,
-    receiver_incoming: Receiver<String>,
-    sender_outgoing: Sender<String>,
+    receiver_incoming: Receiver<json_rpc::Message>,
+    sender_outgoing: Sender<Vec<u8>>,
 }
 
 impl Client<'static> {
@@ -306,24 +316,17 @@ impl Client<'static> {
             match TcpStream::connect(socket) {
                 Ok(st) => {
                     println!("CLIENT - connected to server at {socket}");
-                    let (sender_incoming, receiver_incoming) = mpsc::channel::<String>();
-                    let (sender_outgoing, receiver_outgoing) = mpsc::channel::<String>();
+                    let (sender_incoming, receiver_incoming) = mpsc::channel::<json_rpc::Message>();
+                    let (sender_outgoing, receiver_outgoing) = mpsc::channel::<Vec<u8>>();
 
                     let reader_stream = st.try_clone().unwrap();
                     let mut writer_stream = st;
 
-                    thread::spawn(move || {
-                        let reader = BufReader::new(reader_stream);
-                        for line in reader.lines().map_while(Result::ok) {
-                            if sender_incoming.send(line).is_err() {
-                                break;
-                            }
-                        }
-                    });
+                    thread::spawn(move || read_messages(reader_stream, sender_incoming));
 
                     thread::spawn(move || {
                         for msg in receiver_outgoing {
-                            let _ = writer_stream.write_all(msg.as_bytes());
+                            let _ = writer_stream.write_all(&msg);
                         }
                     });
 
@@ -347,13 +350,9 @@ impl Client<'static> {
                         let cloned = Arc::clone(&arc_client);
                         thread::spawn(move || loop {
                             if let Ok(mut this) = cloned.try_lock() {
-                                if let Ok(line) = this.receiver_incoming.try_recv() {
-                                    println!("CLIENT {} - message: {}", this.client_id, line);
-                                    if let Ok(msg) =
-                                        serde_json::from_str::<json_rpc::Message>(&line)
-                                    {
-                                        this.handle_message(msg).ok();
-                                    }
+                                if let Ok(msg) = this.receiver_incoming.try_recv() {
+                                    println!("CLIENT {} - message: {}", this.client_id, msg);
+                                    this.handle_message(msg).ok();
                                 }
                             }
                             thread::sleep(Duration::from_millis(100));
@@ -370,9 +369,8 @@ impl Client<'static> {
         }
     }
 
-    fn send_message(sender_outgoing: &Sender<String>, msg: json_rpc::Message) {
-        let s = format!("{}\n", serde_json::to_string(&msg).unwrap());
-        let _ = sender_outgoing.send(s);
+    fn send_message(sender_outgoing: &Sender<Vec<u8>>, msg: json_rpc::Message) {
+        let _ = sender_outgoing.send(codec::encode(&msg));
     }
 
     pub fn send_subscribe(&mut self) {
@@ -382,7 +380,8 @@ impl Client<'static> {
         let id = SystemTime::now()
             .duration_since(SystemTime::UNIX_EPOCH)
             .unwrap()
-            .as_secs();
+            .as_secs()
+            .into();
         if let Ok(subscribe) = self.subscribe(id, None) {
             Self::send_message(&self.sender_outgoing, subscribe);
         }
@@ -392,7 +391,8 @@ impl Client<'static> {
         let id = SystemTime::now()
             .duration_since(SystemTime::UNIX_EPOCH)
             .unwrap()
-            .as_secs();
+            .as_secs()
+            .into();
         if let Ok(authorize) = self.authorize(id, "user".to_string(), "user".to_string()) {
             Self::send_message(&self.sender_outgoing, authorize);
         }
@@ -402,7 +402,8 @@ impl Client<'static> {
         let id = SystemTime::now()
             .duration_since(SystemTime::UNIX_EPOCH)
             .unwrap()
-            .as_secs();
+            .as_secs()
+            .into();
         let extranonce2 = extranonce_from_hex("00");
         let nonce = 78;
         let version_bits = None;
@@ -422,7 +423,8 @@ impl Client<'static> {
         let id = SystemTime::now()
             .duration_since(SystemTime::UNIX_EPOCH)
             .unwrap()
-            .as_secs();
+            .as_secs()
+            .into();
         let configure = self.configure(id);
         Self::send_message(&self.sender_outgoing, configure);
     }
@@ -513,8 +515,8 @@ impl<'a> IsClient<'a> for Client<'a> {
         self.version_rolling_min_bit.clone()
     }
 
-    fn id_is_authorize(&mut self, id: &u64) -> Option<String> {
-        let req: Vec<&(u64, String)> = self
+    fn id_is_authorize(&mut self, id: &json_rpc::Id) -> Option<String> {
+        let req: Vec<&(json_rpc::Id, String)> = self
             .sented_authorize_request
             .iter()
             .filter(|x| x.0 == *id)
@@ -525,7 +527,7 @@ impl<'a> IsClient<'a> for Client<'a> {
         }
     }
 
-    fn id_is_submit(&mut self, _: &u64) -> bool {
+    fn id_is_submit(&mut self, _: &json_rpc::Id) -> bool {
         false
     }
 
@@ -539,14 +541,15 @@ impl<'a> IsClient<'a> for Client<'a> {
 
     fn authorize(
         &mut self,
-        id: u64,
+        id: json_rpc::Id,
         name: String,
         password: String,
     ) -> Result<json_rpc::Message, Error> {
         match self.status() {
             ClientStatus::Init => Err(Error::IncorrectClientStatus("mining.authorize".to_string())),
             _ => {
-                self.sented_authorize_request.push((id, "user".to_string()));
+                self.sented_authorize_request
+                    .push((id.clone(), "user".to_string()));
                 Ok(client_to_server::Authorize { id, name, password }.into())
             }
         }
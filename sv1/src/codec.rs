@@ -0,0 +1,303 @@
+//! Line-delimited framing of json_rpc messages.
+//!
+//! SV1 sends one json_rpc message per line. [`Decoder`] is fed the bytes read from a connection,
+//! in chunks of any size, and yields the messages of the complete lines received so far. Each
+//! line is decoded on its own, so a malformed or overlong line is reported as an error and
+//! decoding goes on with the next line. [`encode`] serializes a message to a line.
+//!
+//! The codec does no I/O itself, so it can be used with any transport. [`Decoder::read_from`] is
+//! a helper for blocking [`Read`] implementations.
+use crate::json_rpc::Message;
+use std::{
+    fmt,
+    io::{self, Read},
+};
+
+/// Default maximum length of a line, in bytes, excluding the line terminator.
+pub const DEFAULT_MAX_LINE_LENGTH: usize = 64 * 1024;
+
+/// Maximum number of characters of a malformed line kept in [`CodecError::Malformed`].
+pub const MALFORMED_LINE_EXCERPT_LENGTH: usize = 256;
+
+// Size of the chunks read by `Decoder::read_from`.
+const READ_CHUNK_SIZE: usize = 4096;
+
+/// Errors returned by [`Decoder::next_message`] for a single line.
+#[derive(Debug)]
+pub enum CodecError {
+    /// Errors if a line is longer than the maximum line length. The rest of the line is
+    /// discarded.
+    LineTooLong(usize),
+    /// Errors if a line is not valid UTF-8.
+    InvalidUtf8,
+    /// Errors if a line is not a valid `json_rpc` message. The offending line, truncated to
+    /// [`MALFORMED_LINE_EXCERPT_LENGTH`] characters, is given in the error message.
+    Malformed(String, serde_json::Error),
+}
+
+impl fmt::Display for CodecError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CodecError::LineTooLong(max) => {
+                write!(f, "Line exceeds the maximum line length of `{max}` bytes")
+            }
+            CodecError::InvalidUtf8 => write!(f, "Line is not valid UTF-8"),
+            CodecError::Malformed(line, e) => {
+                write!(f, "Line `{line}` is not a valid `json_rpc` message: `{e}`")
+            }
+        }
+    }
+}
+
+/// Splits a stream of bytes in lines and decodes them as [`Message`]s.
+#[derive(Debug)]
+pub struct Decoder {
+    buffer: Vec<u8>,
+    // Bytes at the start of `buffer` belonging to lines already decoded, dropped on the next
+    // `extend`.
+    consumed: usize,
+    // Bytes of `buffer` already consumed or known not to contain a line terminator.
+    scanned: usize,
+    // Whether the bytes up to the next line terminator belong to an overlong line.
+    discarding: bool,
+    max_line_length: usize,
+}
+
+impl Default for Decoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Decoder {
+    /// Creates a new [`Decoder`] accepting lines of up to [`DEFAULT_MAX_LINE_LENGTH`] bytes.
+    pub fn new() -> Self {
+        Self::new_with_max_line_length(DEFAULT_MAX_LINE_LENGTH)
+    }
+
+    /// Creates a new [`Decoder`] accepting lines of up to `max_line_length` bytes.
+    pub fn new_with_max_line_length(max_line_length: usize) -> Self {
+        Self {
+            buffer: Vec::new(),
+            consumed: 0,
+            scanned: 0,
+            discarding: false,
+            max_line_length,
+        }
+    }
+
+    pub fn get_max_line_length(&self) -> usize {
+        self.max_line_length
+    }
+
+    /// Returns the number of buffered bytes that are not part of a complete line yet.
+    pub fn get_buffered_len(&self) -> usize {
+        self.buffer.len() - self.consumed
+    }
+
+    /// Buffers `bytes` received from the connection.
+    pub fn extend(&mut self, bytes: &[u8]) {
+        // drop the decoded lines at once, rather than shifting the buffer for every line
+        if self.consumed > 0 {
+            self.buffer.drain(..self.consumed);
+            self.scanned -= self.consumed;
+            self.consumed = 0;
+        }
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    fn clear(&mut self) {
+        self.buffer.clear();
+        self.consumed = 0;
+        self.scanned = 0;
+    }
+
+    /// Reads once from `reader` and buffers the bytes read.
+    ///
+    /// Returns the number of bytes read, `0` meaning that the connection was closed.
+    pub fn read_from<R: Read>(&mut self, reader: &mut R) -> io::Result<usize> {
+        let mut chunk = [0; READ_CHUNK_SIZE];
+        let n = reader.read(&mut chunk)?;
+        self.extend(&chunk[..n]);
+        Ok(n)
+    }
+
+    /// Decodes the next buffered line.
+    ///
+    /// Returns `None` if no complete line is buffered, in which case more bytes must be
+    /// received. Empty lines are skipped. An error only concerns the line it is returned for, so
+    /// this should be called until it returns `None` after every [`Decoder::extend`].
+    pub fn next_message(&mut self) -> Option<Result<Message, CodecError>> {
+        loop {
+            let line_end = match self.buffer[self.scanned..]
+                .iter()
+                .position(|byte| *byte == b'\n')
+            {
+                Some(position) => self.scanned + position,
+                None => {
+                    self.scanned = self.buffer.len();
+                    if self.discarding {
+                        self.clear();
+                    } else if self.get_buffered_len() > self.max_line_length {
+                        self.clear();
+                        self.discarding = true;
+                        return Some(Err(CodecError::LineTooLong(self.max_line_length)));
+                    }
+                    return None;
+                }
+            };
+
+            let line_start = self.consumed;
+            self.consumed = line_end + 1;
+            self.scanned = self.consumed;
+            if self.discarding {
+                self.discarding = false;
+                continue;
+            }
+
+            let mut line = &self.buffer[line_start..line_end];
+            if let Some(stripped) = line.strip_suffix(b"\r") {
+                line = stripped;
+            }
+            if line.len() > self.max_line_length {
+                return Some(Err(CodecError::LineTooLong(self.max_line_length)));
+            }
+            let line = match std::str::from_utf8(line) {
+                Ok(line) => line,
+                Err(_) => return Some(Err(CodecError::InvalidUtf8)),
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            return Some(serde_json::from_str(line).map_err(|e| {
+                let excerpt = line.chars().take(MALFORMED_LINE_EXCERPT_LENGTH).collect();
+                CodecError::Malformed(excerpt, e)
+            }));
+        }
+    }
+}
+
+/// Serializes `message` to a line, terminator included.
+pub fn encode(message: &Message) -> Vec<u8> {
+    // infallible, messages only contain json values with string keys
+    let mut line = serde_json::to_vec(message).unwrap();
+    line.push(b'\n');
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json_rpc::{Notification, StandardRequest};
+
+    fn subscribe() -> Message {
+        StandardRequest {
+            id: 1.into(),
+            method: "mining.subscribe".to_string(),
+            params: serde_json::json!([]),
+        }
+        .into()
+    }
+
+    #[test]
+    fn test_partial_and_multiple_messages() {
+        let notification: Message = Notification {
+            method: "mining.set_difficulty".to_string(),
+            params: serde_json::json!([1]),
+        }
+        .into();
+        let mut bytes = encode(&subscribe());
+        bytes.extend(b"\r\n");
+        bytes.extend(encode(&notification));
+        bytes.extend(encode(&subscribe()));
+
+        let mut decoder = Decoder::new();
+        let mut decoded = vec![];
+        // the last message is split across reads
+        let (first, second) = bytes.split_at(bytes.len() - 5);
+        for chunk in first.chunks(7).chain([second]) {
+            decoder.extend(chunk);
+            while let Some(message) = decoder.next_message() {
+                decoded.push(message.unwrap());
+            }
+        }
+        assert_eq!(decoded.len(), 3);
+        assert!(matches!(decoded[0], Message::StandardRequest(_)));
+        assert!(matches!(decoded[1], Message::Notification(_)));
+        assert!(matches!(decoded[2], Message::StandardRequest(_)));
+        assert_eq!(decoder.get_buffered_len(), 0);
+
+        let mut reader: &[u8] = &bytes;
+        let mut decoder = Decoder::new();
+        while decoder.read_from(&mut reader).unwrap() > 0 {}
+        assert_eq!(std::iter::from_fn(|| decoder.next_message()).count(), 3);
+
+        // many lines received at once, followed by a partial line
+        let mut decoder = Decoder::new();
+        let bytes: Vec<u8> = (0..1_000).flat_map(|_| encode(&subscribe())).collect();
+        decoder.extend(&bytes);
+        decoder.extend(&bytes[..5]);
+        assert_eq!(std::iter::from_fn(|| decoder.next_message()).count(), 1_000);
+        assert_eq!(decoder.get_buffered_len(), 5);
+        decoder.extend(&bytes[5..bytes.len() / 1_000]);
+        assert!(matches!(decoder.next_message(), Some(Ok(_))));
+        assert_eq!(decoder.get_buffered_len(), 0);
+    }
+
+    #[test]
+    fn test_recovery() {
+        let mut decoder = Decoder::new_with_max_line_length(64);
+
+        decoder.extend(b"{\"id\": 1, \"method\"\n\xff\xfe\n");
+        decoder.extend(&encode(&subscribe()));
+        assert!(matches!(
+            decoder.next_message(),
+            Some(Err(CodecError::Malformed(_, _)))
+        ));
+        assert!(matches!(
+            decoder.next_message(),
+            Some(Err(CodecError::InvalidUtf8))
+        ));
+        assert!(matches!(decoder.next_message(), Some(Ok(_))));
+        assert!(decoder.next_message().is_none());
+
+        // an overlong line is reported once and skipped, even across reads
+        decoder.extend(&[b'x'; 50]);
+        assert!(decoder.next_message().is_none());
+        decoder.extend(&[b'x'; 50]);
+        assert!(matches!(
+            decoder.next_message(),
+            Some(Err(CodecError::LineTooLong(64)))
+        ));
+        decoder.extend(&[b'x'; 50]);
+        assert!(decoder.next_message().is_none());
+        assert_eq!(decoder.get_buffered_len(), 0);
+        decoder.extend(b"xx\n");
+        decoder.extend(&encode(&subscribe()));
+        assert!(matches!(decoder.next_message(), Some(Ok(_))));
+
+        // an overlong line received at once
+        let mut line = vec![b' '; 100];
+        line.push(b'\n');
+        decoder.extend(&line);
+        assert!(matches!(
+            decoder.next_message(),
+            Some(Err(CodecError::LineTooLong(64)))
+        ));
+        assert!(decoder.next_message().is_none());
+    }
+
+    #[test]
+    fn test_malformed_line_is_truncated() {
+        let mut decoder = Decoder::new();
+        let mut line = vec![b'x'; 1_000];
+        line.push(b'\n');
+        decoder.extend(&line);
+        match decoder.next_message() {
+            Some(Err(CodecError::Malformed(excerpt, _))) => {
+                assert_eq!(excerpt, "x".repeat(MALFORMED_LINE_EXCERPT_LENGTH))
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use crate::{
+    json_rpc::Id,
     methods::{Method, MethodError},
     utils::HexU32Be,
 };
@@ -29,7 +30,7 @@ pub enum Error<'a> {
     /// unauthorized. The client username is given in the error message.
     UnauthorizedClient(String),
     /// Errors if server does not recognize the client's `id`.
-    UnknownID(u64),
+    UnknownID(Id),
     InvalidVersionMask(HexU32Be),
     /// Errors when an unexpected or unsupported message/method is called.
     UnexpectedMessage(String),
@@ -1,7 +1,73 @@
 //! https://www.jsonrpc.org/specification#response_object
-use serde::{Deserialize, Serialize};
+use serde::{ser::SerializeStruct, Deserialize, Deserializer, Serialize};
 use std::{fmt, fmt::Display};
 
+/// Id of a json_rpc message, used to pair requests and responses.
+///
+/// Ids can be numbers or strings, and are null in notifications and in error responses to
+/// requests whose id could not be read. Ids are kept as received, so that they round-trip
+/// exactly.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq, Hash)]
+#[serde(untagged)]
+pub enum Id {
+    Number(serde_json::Number),
+    String(String),
+    Null,
+}
+
+impl Id {
+    /// Returns the id as an unsigned integer, if it is one.
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            Id::Number(number) => number.as_u64(),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if the id is null.
+    pub fn is_null(&self) -> bool {
+        matches!(self, Id::Null)
+    }
+}
+
+impl From<u64> for Id {
+    fn from(id: u64) -> Self {
+        Id::Number(id.into())
+    }
+}
+
+impl From<String> for Id {
+    fn from(id: String) -> Self {
+        Id::String(id)
+    }
+}
+
+impl From<&str> for Id {
+    fn from(id: &str) -> Self {
+        Id::String(id.to_string())
+    }
+}
+
+impl Display for Id {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Id::Number(number) => write!(f, "{number}"),
+            Id::String(string) => write!(f, "{string:?}"),
+            Id::Null => write!(f, "null"),
+        }
+    }
+}
+
+// A request with a null id is a notification.
+fn deserialize_request_id<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Id, D::Error> {
+    match Id::deserialize(deserializer)? {
+        Id::Null => Err(serde::de::Error::custom(
+            "standard requests can't have a null id",
+        )),
+        id => Ok(id),
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug)]
 #[serde(untagged)]
 pub enum Message {
@@ -36,7 +102,8 @@ impl Display for Message {
 
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
 pub struct StandardRequest {
-    pub id: u64,
+    #[serde(deserialize_with = "deserialize_request_id")]
+    pub id: Id,
     pub method: String,
     pub params: serde_json::Value,
 }
@@ -70,7 +137,7 @@ impl fmt::Display for Notification {
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct Response {
-    pub id: u64,
+    pub id: Id,
     pub error: Option<JsonRpcError>,
     pub result: serde_json::Value,
 }
@@ -92,13 +159,70 @@ impl fmt::Display for Response {
     }
 }
 
-#[derive(Clone, Serialize, Deserialize, Debug)]
+/// Error of a json_rpc response.
+///
+/// Serialized as a json_rpc 2.0 `{code, message, data}` object. SV1 pools reject `mining.submit`
+/// with a `[code, message, data]` tuple instead (see [`TupleErrorResponse`]), so both forms are
+/// accepted when deserializing.
+#[derive(Clone, Serialize, Debug, PartialEq)]
 pub struct JsonRpcError {
     pub code: i32, // json do not specify precision which one should be used?
     pub message: String,
     pub data: Option<serde_json::Value>,
 }
 
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonRpcErrorRepr {
+    Tuple(i32, String, #[serde(default)] Option<serde_json::Value>),
+    Object {
+        code: i32,
+        message: String,
+        #[serde(default)]
+        data: Option<serde_json::Value>,
+    },
+}
+
+impl<'de> Deserialize<'de> for JsonRpcError {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let (code, message, data) = match JsonRpcErrorRepr::deserialize(deserializer)? {
+            JsonRpcErrorRepr::Tuple(code, message, data) => (code, message, data),
+            JsonRpcErrorRepr::Object {
+                code,
+                message,
+                data,
+            } => (code, message, data),
+        };
+        Ok(JsonRpcError {
+            code,
+            message,
+            data,
+        })
+    }
+}
+
+/// Serializes a [`Response`] with its error, if any, as the `[code, message, data]` tuple SV1
+/// pools reject `mining.submit` with, rather than a json_rpc 2.0 error object.
+///
+/// See [`Submit::respond_rejected`](crate::methods::client_to_server::Submit::respond_rejected).
+#[derive(Clone, Copy, Debug)]
+pub struct TupleErrorResponse<'a>(pub &'a Response);
+
+impl Serialize for TupleErrorResponse<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let response = self.0;
+        let error = response
+            .error
+            .as_ref()
+            .map(|error| (error.code, &error.message, &error.data));
+        let mut state = serializer.serialize_struct("Response", 3)?;
+        state.serialize_field("id", &response.id)?;
+        state.serialize_field("error", &error)?;
+        state.serialize_field("result", &response.result)?;
+        state.end()
+    }
+}
+
 impl From<Response> for Message {
     fn from(res: Response) -> Self {
         if res.error.is_some() {
@@ -120,3 +244,38 @@ impl From<Notification> for Message {
         Message::Notification(n)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_id_round_trip() {
+        for raw in [
+            r#"{"id":1,"method":"mining.subscribe","params":[]}"#,
+            r#"{"id":"a1b2","method":"mining.subscribe","params":[]}"#,
+            r#"{"id":-3,"method":"mining.subscribe","params":[]}"#,
+            r#"{"id":1.5,"method":"mining.subscribe","params":[]}"#,
+            r#"{"id":"7","error":null,"result":true}"#,
+            r#"{"id":null,"error":{"code":20,"message":"Other/Unknown","data":null},"result":null}"#,
+        ] {
+            let message: Message = serde_json::from_str(raw).unwrap();
+            assert_eq!(serde_json::to_string(&message).unwrap(), raw);
+        }
+
+        assert_eq!(Id::from("a1b2").as_u64(), None);
+        assert_eq!(Id::from(12).as_u64(), Some(12));
+    }
+
+    #[test]
+    fn test_null_id() {
+        // a request with a null id is a notification
+        let message: Message =
+            serde_json::from_str(r#"{"id":null,"method":"mining.notify","params":[]}"#).unwrap();
+        assert!(matches!(message, Message::Notification(_)));
+
+        let response: Response =
+            serde_json::from_str(r#"{"id":null,"error":null,"result":true}"#).unwrap();
+        assert_eq!(response.id, Id::Null);
+    }
+}
@@ -37,6 +37,7 @@
 //! [https://en.bitcoin.it/wiki/BIP_0310]
 //! [https://docs.google.com/spreadsheets/d/1z8a3S9gFkS8NGhBCxOMUDqs7h9SQltz8-VX3KPHk7Jw/edit#gid=0]
 
+pub mod codec;
 pub mod error;
 pub mod json_rpc;
 pub mod methods;
@@ -51,9 +52,8 @@ pub use json_rpc::Message;
 pub use methods::{client_to_server, server_to_client, Method, MethodError, ParsingMethodError};
 use utils::{Extranonce, HexU32Be};
 
-/// json_rpc Response are not handled because stratum v1 does not have any request from a server to
-/// a client
-/// TODO: Should update to accommodate miner requesting a difficulty change
+/// json_rpc Response are not handled, except for responses to `client.get_version` which is the
+/// only request from a server to a client
 ///
 /// A stratum v1 server represent a single connection with a client
 pub trait IsServer<'a> {
@@ -75,6 +75,16 @@ pub trait IsServer<'a> {
                 // handle valid server notification
                 self.handle_request(msg)
             }
+            Message::OkResponse(response) if self.id_is_get_version(&response.id) => {
+                let get_version =
+                    (&response)
+                        .try_into()
+                        .map_err(|e: methods::ParsingMethodError| {
+                            e.as_method_error(Message::OkResponse(response.clone()))
+                        })?;
+                self.handle_get_version(&get_version);
+                Ok(None)
+            }
             _ => {
                 // Server shouldn't receive json_rpc responses
                 Err(Error::InvalidJsonRpcMessageKind)
@@ -93,8 +103,10 @@ pub trait IsServer<'a> {
         let request = msg.try_into()?;
 
         match request {
-            // TODO: Handle suggested difficulty
-            methods::Client2Server::SuggestDifficulty() => Ok(None),
+            methods::Client2Server::SuggestDifficulty(suggest_difficulty) => {
+                let accepted = self.handle_suggest_difficulty(&suggest_difficulty);
+                Ok(Some(suggest_difficulty.respond(accepted)))
+            }
             methods::Client2Server::Authorize(authorize) => {
                 let authorized = self.handle_authorize(&authorize);
                 if authorized {
@@ -190,6 +202,17 @@ pub trait IsServer<'a> {
     /// Indicates to the server that the client supports the mining.set_extranonce method.
     fn handle_extranonce_subscribe(&self);
 
+    /// The miner suggests the share difficulty it wishes to work at. Return `true` if the
+    /// suggestion is honored.
+    ///
+    /// The default implementation ignores the suggestion.
+    fn handle_suggest_difficulty(
+        &mut self,
+        _request: &client_to_server::SuggestDifficulty,
+    ) -> bool {
+        false
+    }
+
     fn is_authorized(&self, name: &str) -> bool;
 
     fn authorize(&mut self, name: &str);
@@ -210,6 +233,23 @@ pub trait IsServer<'a> {
 
     fn set_version_rolling_min_bit(&mut self, mask: Option<HexU32Be>);
 
+    /// Check if the server sent a `client.get_version` request with the given id. The default
+    /// implementation never sends any.
+    fn id_is_get_version(&mut self, _id: &json_rpc::Id) -> bool {
+        false
+    }
+
+    /// The client answered a `client.get_version` request with the name and version of its
+    /// software. The default implementation ignores it.
+    fn handle_get_version(&mut self, _response: &client_to_server::GetVersion) {}
+
+    /// Ask the client for the name and version of its software. The answer is passed to
+    /// [handle_get_version](IsServer::handle_get_version) if
+    /// [id_is_get_version](IsServer::id_is_get_version) returns `true` for `id`.
+    fn get_version(&mut self, id: json_rpc::Id) -> json_rpc::Message {
+        server_to_client::GetVersion { id }.into()
+    }
+
     fn update_extranonce(
         &mut self,
         extra_nonce1: Extranonce<'a>,
@@ -280,7 +320,7 @@ pub trait IsClient<'a> {
                     (None, false) => Ok(methods::Server2ClientResponse::Submit(
                         general.clone().into_submit(),
                     )),
-                    _ => Err(Error::UnknownID(general.id)),
+                    _ => Err(Error::UnknownID(general.id.clone())),
                 }
             }
             _ => Ok(response),
@@ -312,6 +352,22 @@ pub trait IsClient<'a> {
                 self.handle_set_version_mask(&mut set_version_mask)?;
                 Ok(None)
             }
+            methods::Server2Client::Reconnect(reconnect) => {
+                self.handle_reconnect(&reconnect)?;
+                Ok(None)
+            }
+            methods::Server2Client::ShowMessage(show_message) => {
+                self.handle_show_message(&show_message)?;
+                Ok(None)
+            }
+            methods::Server2Client::GetVersion(get_version) => {
+                let version = self.handle_get_version(&get_version)?;
+                // notifications are not answered
+                if get_version.id == json_rpc::Id::Null {
+                    return Ok(None);
+                }
+                Ok(Some(get_version.respond(version).into()))
+            }
         }
     }
 
@@ -369,10 +425,10 @@ pub trait IsClient<'a> {
 
     /// Check if the client sent an Authorize request with the given id, if so it return the
     /// authorized name
-    fn id_is_authorize(&mut self, id: &u64) -> Option<String>;
+    fn id_is_authorize(&mut self, id: &json_rpc::Id) -> Option<String>;
 
     /// Check if the client sent a Submit request with the given id
-    fn id_is_submit(&mut self, id: &u64) -> bool;
+    fn id_is_submit(&mut self, id: &json_rpc::Id) -> bool;
 
     fn handle_notify(&mut self, notify: server_to_client::Notify<'a>) -> Result<(), Error<'a>>;
 
@@ -399,6 +455,32 @@ pub trait IsClient<'a> {
         subscribe: &server_to_client::Subscribe<'a>,
     ) -> Result<(), Error<'a>>;
 
+    /// The server asks to reconnect, possibly to another host. The default implementation ignores
+    /// the request.
+    fn handle_reconnect(
+        &mut self,
+        _reconnect: &server_to_client::Reconnect,
+    ) -> Result<(), Error<'a>> {
+        Ok(())
+    }
+
+    /// The server sent a message for the operator. The default implementation ignores it.
+    fn handle_show_message(
+        &mut self,
+        _show_message: &server_to_client::ShowMessage,
+    ) -> Result<(), Error<'a>> {
+        Ok(())
+    }
+
+    /// The server asks for the name and version of the client's software. The default
+    /// implementation answers with the client's [signature](IsClient::signature).
+    fn handle_get_version(
+        &mut self,
+        _get_version: &server_to_client::GetVersion,
+    ) -> Result<String, Error<'a>> {
+        Ok(self.signature())
+    }
+
     fn set_extranonce1(&mut self, extranonce1: Extranonce<'a>);
 
     fn extranonce1(&self) -> Extranonce<'a>;
@@ -430,7 +512,7 @@ pub trait IsClient<'a> {
     /// Register the given user_name has authorized by the server
     fn authorize_user_name(&mut self, name: String);
 
-    fn configure(&mut self, id: u64) -> json_rpc::Message {
+    fn configure(&mut self, id: json_rpc::Id) -> json_rpc::Message {
         if self.version_rolling_min_bit().is_none() && self.version_rolling_mask().is_none() {
             client_to_server::Configure::void(id).into()
         } else {
@@ -445,7 +527,7 @@ pub trait IsClient<'a> {
 
     fn subscribe(
         &mut self,
-        id: u64,
+        id: json_rpc::Id,
         extranonce1: Option<Extranonce<'a>>,
     ) -> Result<json_rpc::Message, Error<'a>> {
         match self.status() {
@@ -461,7 +543,7 @@ pub trait IsClient<'a> {
 
     fn authorize(
         &mut self,
-        id: u64,
+        id: json_rpc::Id,
         name: String,
         password: String,
     ) -> Result<json_rpc::Message, Error<'_>> {
@@ -473,7 +555,7 @@ pub trait IsClient<'a> {
 
     fn submit(
         &mut self,
-        id: u64,
+        id: json_rpc::Id,
         user_name: String,
         extra_nonce2: Extranonce<'a>,
         time: i64,
@@ -526,6 +608,9 @@ mod tests {
         extranonce2_size: usize,
         version_rolling_mask: Option<HexU32Be>,
         version_rolling_min_bit: Option<HexU32Be>,
+        initial_difficulty: Option<f64>,
+        get_version_id: Option<json_rpc::Id>,
+        client_version: Option<String>,
     }
 
     impl<'a> TestServer<'a> {
@@ -536,6 +621,9 @@ mod tests {
                 extranonce2_size,
                 version_rolling_mask: None,
                 version_rolling_min_bit: None,
+                initial_difficulty: None,
+                get_version_id: None,
+                client_version: None,
             }
         }
     }
@@ -562,7 +650,7 @@ mod tests {
         fn notify(&mut self) -> Result<json_rpc::Message, Error<'_>> {
             Ok(json_rpc::Message::StandardRequest(
                 json_rpc::StandardRequest {
-                    id: 1,
+                    id: 1.into(),
                     method: "mining.notify".to_string(),
                     params: serde_json::json!([]),
                 },
@@ -575,6 +663,25 @@ mod tests {
 
         fn handle_extranonce_subscribe(&self) {}
 
+        fn handle_suggest_difficulty(
+            &mut self,
+            request: &client_to_server::SuggestDifficulty,
+        ) -> bool {
+            if !(request.value.is_finite() && request.value > 0.0) {
+                return false;
+            }
+            self.initial_difficulty = Some(request.value);
+            true
+        }
+
+        fn id_is_get_version(&mut self, id: &json_rpc::Id) -> bool {
+            self.get_version_id.as_ref() == Some(id)
+        }
+
+        fn handle_get_version(&mut self, response: &client_to_server::GetVersion) {
+            self.client_version = Some(response.version.clone());
+        }
+
         fn is_authorized(&self, name: &str) -> bool {
             self.authorized_users.contains(name)
         }
@@ -625,7 +732,7 @@ mod tests {
 
         // Create an invalid message (response)
         let request_message = json_rpc::Message::StandardRequest(json_rpc::StandardRequest {
-            id: 42,
+            id: 42.into(),
             method: "mining.subscribe_bad".to_string(),
             params: serde_json::json!([]),
         });
@@ -641,4 +748,65 @@ mod tests {
             other => panic!("Expected Error::Method, got {:?}", other),
         }
     }
+
+    #[test]
+    fn test_server_handle_suggest_difficulty() {
+        let extranonce1 = Extranonce::try_from(hex::decode("08000002").unwrap()).unwrap();
+        let mut server = TestServer::new(extranonce1, 4);
+
+        let request_message = json_rpc::Message::StandardRequest(json_rpc::StandardRequest {
+            id: 7.into(),
+            method: "mining.suggest_difficulty".to_string(),
+            params: serde_json::json!([1]),
+        });
+        let response = server.handle_message(request_message).unwrap().unwrap();
+        assert_eq!(response.id, json_rpc::Id::from(7));
+        assert_eq!(response.result, serde_json::json!(true));
+        assert_eq!(server.initial_difficulty, Some(1.0));
+
+        // a difficulty that isn't positive is not honored
+        let request_message = json_rpc::Message::StandardRequest(json_rpc::StandardRequest {
+            id: 8.into(),
+            method: "mining.suggest_difficulty".to_string(),
+            params: serde_json::json!([0]),
+        });
+        let response = server.handle_message(request_message).unwrap().unwrap();
+        assert_eq!(response.result, serde_json::json!(false));
+
+        // string ids are answered with the same id
+        let request_message: json_rpc::Message = serde_json::from_str(
+            r#"{"id":"a1b2","method":"mining.suggest_difficulty","params":[1]}"#,
+        )
+        .unwrap();
+        let response = server.handle_message(request_message).unwrap().unwrap();
+        assert_eq!(response.id, json_rpc::Id::from("a1b2"));
+    }
+
+    #[test]
+    fn test_server_handle_get_version_response() {
+        let extranonce1 = Extranonce::try_from(hex::decode("08000002").unwrap()).unwrap();
+        let mut server = TestServer::new(extranonce1, 4);
+
+        let request = match server.get_version(9.into()) {
+            json_rpc::Message::StandardRequest(request) => request,
+            _ => panic!(),
+        };
+        assert_eq!(request.method, "client.get_version");
+        server.get_version_id = Some(9.into());
+
+        let response: json_rpc::Message = server_to_client::GetVersion { id: 9.into() }
+            .respond("cgminer/4.12.1".to_string())
+            .into();
+        assert!(server.handle_message(response).unwrap().is_none());
+        assert_eq!(server.client_version.as_deref(), Some("cgminer/4.12.1"));
+
+        // other responses are still rejected
+        let response: json_rpc::Message = server_to_client::GetVersion { id: 10.into() }
+            .respond("cgminer/4.12.1".to_string())
+            .into();
+        assert!(matches!(
+            server.handle_message(response),
+            Err(Error::InvalidJsonRpcMessageKind)
+        ));
+    }
 }
@@ -7,11 +7,14 @@ use std::convert::{TryFrom, TryInto};
 
 use crate::{
     error::Error,
-    json_rpc::{Message, Response, StandardRequest},
+    json_rpc::{Id, JsonRpcError, Message, Response, StandardRequest},
     methods::ParsingMethodError,
     utils::{Extranonce, HexU32Be},
 };
 
+#[cfg(test)]
+use crate::json_rpc::TupleErrorResponse;
+
 #[cfg(test)]
 use quickcheck::{Arbitrary, Gen};
 
@@ -24,7 +27,7 @@ use quickcheck_macros;
 /// The password may be omitted if the server does not require passwords.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Authorize {
-    pub id: u64,
+    pub id: Id,
     pub name: String,
     pub password: String,
 }
@@ -75,7 +78,7 @@ impl Arbitrary for Authorize {
         Authorize {
             name: String::arbitrary(g),
             password: String::arbitrary(g),
-            id: u64::arbitrary(g),
+            id: u64::arbitrary(g).into(),
         }
     }
 }
@@ -122,7 +125,7 @@ pub struct Submit<'a> {
     pub time: HexU32Be,               //string
     pub nonce: HexU32Be,
     pub version_bits: Option<HexU32Be>,
-    pub id: u64,
+    pub id: Id,
 }
 //"{"params": ["spotbtc1.m30s40x16", "2", "147a3f0000000000", "6436eddf", "41d5deb0", "00000000"],
 //"{"params": "id": 2196, "method": "mining.submit"}"
@@ -137,6 +140,81 @@ impl Submit<'_> {
             error: None,
         }
     }
+
+    /// Rejects the share with the json_rpc error conventionally used for `rejection`.
+    ///
+    /// Miners expect the error as a `[code, message, data]` tuple, serialize the response with
+    /// [`TupleErrorResponse`](crate::json_rpc::TupleErrorResponse) to send it.
+    pub fn respond_rejected(self, rejection: SubmitRejection) -> Response {
+        Response {
+            id: self.id,
+            result: Null,
+            error: Some(rejection.into()),
+        }
+    }
+
+    /// Accepts the share on `Ok`, otherwise rejects it as in [`Submit::respond_rejected`].
+    pub fn respond_with(self, result: Result<(), SubmitRejection>) -> Response {
+        match result {
+            Ok(()) => self.respond(true),
+            Err(rejection) => self.respond_rejected(rejection),
+        }
+    }
+}
+
+/// Reason for rejecting a `mining.submit`, mapped to the error codes conventionally used by SV1
+/// pools and understood by miners.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SubmitRejection {
+    /// 20, with a free-form message.
+    Other(String),
+    /// 21, the job is unknown to the server.
+    JobNotFound,
+    /// 21, the job is known but no longer valid.
+    Stale,
+    /// 22
+    DuplicateShare,
+    /// 23
+    LowDifficulty,
+    /// 24
+    UnauthorizedWorker,
+    /// 25
+    NotSubscribed,
+}
+
+impl SubmitRejection {
+    pub fn code(&self) -> i32 {
+        match self {
+            SubmitRejection::Other(_) => 20,
+            SubmitRejection::JobNotFound | SubmitRejection::Stale => 21,
+            SubmitRejection::DuplicateShare => 22,
+            SubmitRejection::LowDifficulty => 23,
+            SubmitRejection::UnauthorizedWorker => 24,
+            SubmitRejection::NotSubscribed => 25,
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            SubmitRejection::Other(message) => message,
+            SubmitRejection::JobNotFound => "Job not found",
+            SubmitRejection::Stale => "Stale share",
+            SubmitRejection::DuplicateShare => "Duplicate share",
+            SubmitRejection::LowDifficulty => "Low difficulty share",
+            SubmitRejection::UnauthorizedWorker => "Unauthorized worker",
+            SubmitRejection::NotSubscribed => "Not subscribed",
+        }
+    }
+}
+
+impl From<SubmitRejection> for JsonRpcError {
+    fn from(rejection: SubmitRejection) -> Self {
+        JsonRpcError {
+            code: rejection.code(),
+            message: rejection.message().to_string(),
+            data: None,
+        }
+    }
 }
 
 impl From<Submit<'_>> for Message {
@@ -239,7 +317,7 @@ impl Arbitrary for Submit<'static> {
             time: HexU32Be(u32::arbitrary(g)),
             nonce: HexU32Be(u32::arbitrary(g)),
             version_bits: bits,
-            id: u64::arbitrary(g),
+            id: u64::arbitrary(g).into(),
         }
     }
 }
@@ -267,7 +345,7 @@ fn submit_from_to_json_rpc(submit: Submit<'static>) -> bool {
 /// [a]: crate::methods::server_to_client::Notify
 #[derive(Debug, Clone)]
 pub struct Subscribe<'a> {
-    pub id: u64,
+    pub id: Id,
     pub agent_signature: String,
     pub extranonce1: Option<Extranonce<'a>>,
 }
@@ -340,11 +418,11 @@ impl TryFrom<StandardRequest> for Subscribe<'_> {
 #[derive(Debug, Clone)]
 pub struct Configure {
     extensions: Vec<ConfigureExtension>,
-    id: u64,
+    id: Id,
 }
 
 impl Configure {
-    pub fn new(id: u64, mask: Option<HexU32Be>, min_bit_count: Option<HexU32Be>) -> Self {
+    pub fn new(id: Id, mask: Option<HexU32Be>, min_bit_count: Option<HexU32Be>) -> Self {
         let extension = ConfigureExtension::VersionRolling(VersionRollingParams {
             mask,
             min_bit_count,
@@ -355,7 +433,7 @@ impl Configure {
         }
     }
 
-    pub fn void(id: u64) -> Self {
+    pub fn void(id: Id) -> Self {
         Configure {
             extensions: vec![],
             id,
@@ -652,7 +730,86 @@ impl From<InfoParams> for serde_json::Map<String, Value> {
     }
 }
 
-// mining.suggest_difficulty
+/// _mining.suggest_difficulty(difficulty)_
+///
+/// Miners can suggest the share difficulty they wish to work at, usually right after
+/// subscribing. Some ASICs can't work below a minimum difficulty, and send it this way.
+///
+/// The difficulty is usually sent as a number, but some firmware send it as a numeric string
+/// (e.g. `["2048"]`), which is accepted too.
+///
+/// The server is free to ignore the suggestion, and answers with `true` if it is honored, `false`
+/// otherwise.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SuggestDifficulty {
+    pub id: Id,
+    pub value: f64,
+}
+
+impl SuggestDifficulty {
+    pub fn respond(self, is_ok: bool) -> Response {
+        // infallible
+        let result = serde_json::to_value(is_ok).unwrap();
+        Response {
+            id: self.id,
+            result,
+            error: None,
+        }
+    }
+}
+
+impl From<SuggestDifficulty> for Message {
+    fn from(suggest_difficulty: SuggestDifficulty) -> Self {
+        Message::StandardRequest(StandardRequest {
+            id: suggest_difficulty.id,
+            method: "mining.suggest_difficulty".into(),
+            params: (&[suggest_difficulty.value][..]).into(),
+        })
+    }
+}
+
+impl TryFrom<StandardRequest> for SuggestDifficulty {
+    type Error = ParsingMethodError;
+
+    fn try_from(msg: StandardRequest) -> Result<Self, Self::Error> {
+        let params = msg
+            .params
+            .as_array()
+            .ok_or_else(|| ParsingMethodError::not_array_from_value(msg.params.clone()))?;
+        let value = match &params[..] {
+            [JNumber(a)] => a
+                .as_f64()
+                .ok_or_else(|| ParsingMethodError::not_float_from_value(params[0].clone()))?,
+            [JString(a)] => a
+                .parse::<f64>()
+                .map_err(|_| ParsingMethodError::not_float_from_value(params[0].clone()))?,
+            _ => return Err(ParsingMethodError::wrong_args_from_value(msg.params)),
+        };
+        Ok(SuggestDifficulty { id: msg.id, value })
+    }
+}
+
+/// Response to a [client.get_version](crate::server_to_client::GetVersion) request, carrying
+/// the name and version of the client's software.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GetVersion {
+    pub id: Id,
+    pub version: String,
+}
+
+impl TryFrom<&Response> for GetVersion {
+    type Error = ParsingMethodError;
+
+    fn try_from(msg: &Response) -> Result<Self, Self::Error> {
+        let version = msg.result.as_str().ok_or_else(|| {
+            ParsingMethodError::ImpossibleToParseResultField(Box::new(msg.clone()))
+        })?;
+        Ok(GetVersion {
+            id: msg.id.clone(),
+            version: version.to_string(),
+        })
+    }
+}
 
 // mining.suggest_target
 
@@ -747,3 +904,97 @@ fn test_subscribe_with_even_length_extranonce() {
     let extranonce = subscribe.extranonce1.unwrap();
     assert_eq!(extranonce.0.inner_as_ref(), &[0xab, 0xcd]); // "abcd" -> [171, 205]
 }
+
+#[test]
+fn test_suggest_difficulty() {
+    let client_message = r#"{"id":3,
+            "method": "mining.suggest_difficulty",
+            "params":[2048]
+        }"#;
+    let client_message: StandardRequest = serde_json::from_str(client_message).unwrap();
+    let suggest_difficulty = SuggestDifficulty::try_from(client_message).unwrap();
+    assert_eq!(
+        suggest_difficulty,
+        SuggestDifficulty {
+            id: 3.into(),
+            value: 2048.0
+        }
+    );
+
+    let message: Message = suggest_difficulty.clone().into();
+    let request = match message {
+        Message::StandardRequest(request) => request,
+        _ => panic!(),
+    };
+    assert_eq!(
+        SuggestDifficulty::try_from(request).unwrap(),
+        suggest_difficulty
+    );
+
+    let client_message = r#"{"id":3,
+            "method": "mining.suggest_difficulty",
+            "params":["2048"]
+        }"#;
+    let client_message: StandardRequest = serde_json::from_str(client_message).unwrap();
+    assert_eq!(
+        SuggestDifficulty::try_from(client_message).unwrap(),
+        suggest_difficulty
+    );
+
+    let client_message = r#"{"id":3,
+            "method": "mining.suggest_difficulty",
+            "params":["high"]
+        }"#;
+    let client_message: StandardRequest = serde_json::from_str(client_message).unwrap();
+    assert!(SuggestDifficulty::try_from(client_message).is_err());
+}
+
+#[test]
+fn test_submit_rejection() {
+    let submit = Submit {
+        user_name: "user".to_string(),
+        job_id: "1".to_string(),
+        extra_nonce2: Extranonce::try_from(vec![0; 4]).unwrap(),
+        time: HexU32Be(0),
+        nonce: HexU32Be(0),
+        version_bits: None,
+        id: 5.into(),
+    };
+
+    let response = submit
+        .clone()
+        .respond_rejected(SubmitRejection::LowDifficulty);
+    assert_eq!(
+        serde_json::to_string(&TupleErrorResponse(&response)).unwrap(),
+        r#"{"id":5,"error":[23,"Low difficulty share",null],"result":null}"#
+    );
+    // the tuple form is only used when asked for
+    assert_eq!(
+        serde_json::to_string(&response).unwrap(),
+        r#"{"id":5,"error":{"code":23,"message":"Low difficulty share","data":null},"result":null}"#
+    );
+    let round_trip: Response =
+        serde_json::from_str(&serde_json::to_string(&TupleErrorResponse(&response)).unwrap())
+            .unwrap();
+    assert_eq!(round_trip.error, response.error);
+    assert!(matches!(Message::from(response), Message::ErrorResponse(_)));
+
+    let response = submit
+        .clone()
+        .respond_with(Err(SubmitRejection::Other("Bad nonce".to_string())));
+    let error = response.error.unwrap();
+    assert_eq!((error.code, error.message.as_str()), (20, "Bad nonce"));
+
+    let response = submit.respond_with(Ok(()));
+    assert!(response.error.is_none());
+    assert_eq!(response.result, Value::Bool(true));
+
+    // json_rpc 2.0 style errors are still understood
+    let response: Response = serde_json::from_str(
+        r#"{"id":5,"error":{"code":22,"message":"Duplicate share"},"result":null}"#,
+    )
+    .unwrap();
+    let error = response.error.unwrap();
+    assert_eq!(error.code, SubmitRejection::DuplicateShare.code());
+    assert_eq!(error.data, None);
+}
@@ -125,7 +125,7 @@ pub enum Method<'a> {
 
 #[derive(Debug, Clone)]
 pub enum Client2Server<'a> {
-    SuggestDifficulty(),
+    SuggestDifficulty(client_to_server::SuggestDifficulty),
     Subscribe(client_to_server::Subscribe<'a>),
     Authorize(client_to_server::Authorize),
     ExtranonceSubscribe(client_to_server::ExtranonceSubscribe),
@@ -159,6 +159,9 @@ pub enum Server2Client<'a> {
     SetDifficulty(server_to_client::SetDifficulty),
     SetExtranonce(server_to_client::SetExtranonce<'a>),
     SetVersionMask(server_to_client::SetVersionMask),
+    Reconnect(server_to_client::Reconnect),
+    ShowMessage(server_to_client::ShowMessage),
+    GetVersion(server_to_client::GetVersion),
 }
 
 impl<'a> From<Server2Client<'a>> for Method<'a> {
@@ -218,7 +221,13 @@ impl<'a> TryFrom<Message> for Method<'a> {
         match &msg {
             Message::StandardRequest(request) => match &request.method[..] {
                 "mining.suggest_difficulty" => {
-                    Ok(Method::Client2Server(Client2Server::SuggestDifficulty()))
+                    let method = request
+                        .clone()
+                        .try_into()
+                        .map_err(|e: ParsingMethodError| e.as_method_error(msg))?;
+                    Ok(Method::Client2Server(Client2Server::SuggestDifficulty(
+                        method,
+                    )))
                 }
                 "mining.subscribe" => {
                     let method = request
@@ -251,6 +260,23 @@ impl<'a> TryFrom<Message> for Method<'a> {
                         .map_err(|e: ParsingMethodError| e.as_method_error(msg))?;
                     Ok(Method::Client2Server(Client2Server::Configure(method)))
                 }
+                "client.get_version" => Ok(Method::Server2Client(Server2Client::GetVersion(
+                    request.clone().into(),
+                ))),
+                "client.reconnect" => {
+                    let method = request
+                        .clone()
+                        .try_into()
+                        .map_err(|e: ParsingMethodError| e.as_method_error(msg))?;
+                    Ok(Method::Server2Client(Server2Client::Reconnect(method)))
+                }
+                "client.show_message" => {
+                    let method = request
+                        .clone()
+                        .try_into()
+                        .map_err(|e: ParsingMethodError| e.as_method_error(msg))?;
+                    Ok(Method::Server2Client(Server2Client::ShowMessage(method)))
+                }
                 _ => Err(MethodError::MethodNotFound(request.clone().method)),
             },
             Message::Notification(notification) => match &notification.method[..] {
@@ -282,6 +308,23 @@ impl<'a> TryFrom<Message> for Method<'a> {
                         .map_err(|e: ParsingMethodError| e.as_method_error(msg))?;
                     Ok(Method::Server2Client(Server2Client::SetExtranonce(method)))
                 }
+                "client.reconnect" => {
+                    let method = notification
+                        .clone()
+                        .try_into()
+                        .map_err(|e: ParsingMethodError| e.as_method_error(msg))?;
+                    Ok(Method::Server2Client(Server2Client::Reconnect(method)))
+                }
+                "client.show_message" => {
+                    let method = notification
+                        .clone()
+                        .try_into()
+                        .map_err(|e: ParsingMethodError| e.as_method_error(msg))?;
+                    Ok(Method::Server2Client(Server2Client::ShowMessage(method)))
+                }
+                "client.get_version" => Ok(Method::Server2Client(Server2Client::GetVersion(
+                    notification.clone().into(),
+                ))),
                 _ => Err(MethodError::MethodNotFound(notification.clone().method)),
             },
             Message::OkResponse(response) => response
@@ -6,16 +6,195 @@ use std::convert::{TryFrom, TryInto};
 
 use crate::{
     error::Error,
-    json_rpc::{Message, Notification, Response},
+    json_rpc::{Id, Message, Notification, Response, StandardRequest},
     methods::ParsingMethodError,
     utils::{Extranonce, HexBytes, HexU32Be, MerkleNode, PrevHash},
 };
 
-// client.get_version()
+/// _client.get_version()_
+///
+/// The server asks the client for the name and version of its software, usually to identify the
+/// firmware running on miners. The client answers with a string such as `"bmminer/2.0.0"`, see
+/// [client_to_server::GetVersion](crate::client_to_server::GetVersion).
+///
+/// Some servers send it as a notification, in which case `id` is [`Id::Null`] and no answer is
+/// expected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GetVersion {
+    pub id: Id,
+}
+
+impl GetVersion {
+    pub fn respond(self, version: String) -> Response {
+        Response {
+            id: self.id,
+            result: version.into(),
+            error: None,
+        }
+    }
+}
+
+impl From<GetVersion> for Message {
+    fn from(get_version: GetVersion) -> Self {
+        Message::StandardRequest(StandardRequest {
+            id: get_version.id,
+            method: "client.get_version".to_string(),
+            params: JArrary(vec![]),
+        })
+    }
+}
+
+impl From<StandardRequest> for GetVersion {
+    fn from(msg: StandardRequest) -> Self {
+        GetVersion { id: msg.id }
+    }
+}
 
-// client.reconnect
+impl From<Notification> for GetVersion {
+    fn from(_msg: Notification) -> Self {
+        GetVersion { id: Id::Null }
+    }
+}
 
-// client.show_message
+/// _client.reconnect("hostname", port, wait_time)_
+///
+/// The server asks the client to reconnect, to the given host and port if provided (e.g. to
+/// redirect miners during maintenance), after waiting `wait_time` seconds if provided. Without
+/// parameters, the client reconnects to the same server.
+///
+/// Parameters are positional: `port` is only serialized with a `hostname`, and `wait_time` with a
+/// `port`. It is serialized as a notification, but also parsed from requests, as some servers send
+/// it with an `id`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reconnect {
+    pub hostname: Option<String>,
+    pub port: Option<u16>,
+    pub wait_time: Option<u64>,
+}
+
+impl From<Reconnect> for Message {
+    fn from(reconnect: Reconnect) -> Self {
+        let mut params: Vec<Value> = vec![];
+        if let Some(hostname) = reconnect.hostname {
+            params.push(hostname.into());
+            if let Some(port) = reconnect.port {
+                params.push(port.into());
+                if let Some(wait_time) = reconnect.wait_time {
+                    params.push(wait_time.into());
+                }
+            }
+        }
+        Message::Notification(Notification {
+            method: "client.reconnect".to_string(),
+            params: params.into(),
+        })
+    }
+}
+
+impl TryFrom<Notification> for Reconnect {
+    type Error = ParsingMethodError;
+
+    fn try_from(msg: Notification) -> Result<Self, Self::Error> {
+        let params = msg
+            .params
+            .as_array()
+            .ok_or_else(|| ParsingMethodError::not_array_from_value(msg.params.clone()))?;
+        // the port is sent either as a string or as a number
+        let parse_port = |port: &Value| match port {
+            JNumber(a) => a.as_u64().and_then(|a| u16::try_from(a).ok()),
+            JString(a) => a.parse().ok(),
+            _ => None,
+        };
+        let (hostname, port, wait_time) =
+            match &params[..] {
+                [] => (None, None, None),
+                [JString(a)] => (Some(a.clone()), None, None),
+                [JString(a), b] => (
+                    Some(a.clone()),
+                    Some(parse_port(b).ok_or_else(|| {
+                        ParsingMethodError::unexpected_value_from_value(b.clone())
+                    })?),
+                    None,
+                ),
+                [JString(a), b, JNumber(c)] => {
+                    (
+                        Some(a.clone()),
+                        Some(parse_port(b).ok_or_else(|| {
+                            ParsingMethodError::unexpected_value_from_value(b.clone())
+                        })?),
+                        Some(c.as_u64().ok_or_else(|| {
+                            ParsingMethodError::not_unsigned_from_value(c.clone())
+                        })?),
+                    )
+                }
+                _ => return Err(ParsingMethodError::wrong_args_from_value(msg.params)),
+            };
+        Ok(Reconnect {
+            hostname,
+            port,
+            wait_time,
+        })
+    }
+}
+
+impl TryFrom<StandardRequest> for Reconnect {
+    type Error = ParsingMethodError;
+
+    fn try_from(msg: StandardRequest) -> Result<Self, Self::Error> {
+        Notification {
+            method: msg.method,
+            params: msg.params,
+        }
+        .try_into()
+    }
+}
+
+/// _client.show_message("message")_
+///
+/// The server asks the client to display a human readable message to its operator. It is
+/// serialized as a notification, but also parsed from requests, as some servers send it with an
+/// `id`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShowMessage {
+    pub message: String,
+}
+
+impl From<ShowMessage> for Message {
+    fn from(show_message: ShowMessage) -> Self {
+        Message::Notification(Notification {
+            method: "client.show_message".to_string(),
+            params: (&[show_message.message][..]).into(),
+        })
+    }
+}
+
+impl TryFrom<Notification> for ShowMessage {
+    type Error = ParsingMethodError;
+
+    fn try_from(msg: Notification) -> Result<Self, Self::Error> {
+        let params = msg
+            .params
+            .as_array()
+            .ok_or_else(|| ParsingMethodError::not_array_from_value(msg.params.clone()))?;
+        let message = match &params[..] {
+            [JString(a)] => a.clone(),
+            _ => return Err(ParsingMethodError::wrong_args_from_value(msg.params)),
+        };
+        Ok(ShowMessage { message })
+    }
+}
+
+impl TryFrom<StandardRequest> for ShowMessage {
+    type Error = ParsingMethodError;
+
+    fn try_from(msg: StandardRequest) -> Result<Self, Self::Error> {
+        Notification {
+            method: msg.method,
+            params: msg.params,
+        }
+        .try_into()
+    }
+}
 
 /// Fields in order:
 ///
@@ -269,7 +448,7 @@ impl TryFrom<Notification> for SetVersionMask {
 /// Authorize and Submit responsed are identical
 #[derive(Debug, Clone)]
 pub struct GeneralResponse {
-    pub id: u64,
+    pub id: Id,
     result: bool,
 }
 
@@ -293,7 +472,7 @@ impl TryFrom<&Response> for GeneralResponse {
     type Error = ParsingMethodError;
 
     fn try_from(msg: &Response) -> Result<Self, Self::Error> {
-        let id = msg.id;
+        let id = msg.id.clone();
         let result = msg.result.as_bool().ok_or_else(|| {
             ParsingMethodError::ImpossibleToParseResultField(Box::new(msg.clone()))
         })?;
@@ -303,7 +482,7 @@ impl TryFrom<&Response> for GeneralResponse {
 
 #[derive(Debug, Clone)]
 pub struct Authorize {
-    pub id: u64,
+    pub id: Id,
     authorized: bool,
     pub prev_request_name: String,
 }
@@ -320,7 +499,7 @@ impl Authorize {
 
 #[derive(Debug, Clone)]
 pub struct Submit {
-    pub id: u64,
+    pub id: Id,
     is_ok: bool,
 }
 
@@ -350,7 +529,7 @@ impl Submit {
 ///    ExtraNonce2_size. - The number of bytes that the miner users for its ExtraNonce2 counter.
 #[derive(Debug, Clone)]
 pub struct Subscribe<'a> {
-    pub id: u64,
+    pub id: Id,
     pub extra_nonce1: Extranonce<'a>,
     pub extra_nonce2_size: usize,
     pub subscriptions: Vec<(String, String)>,
@@ -378,7 +557,7 @@ impl TryFrom<&Response> for Subscribe<'_> {
     type Error = ParsingMethodError;
 
     fn try_from(msg: &Response) -> Result<Self, Self::Error> {
-        let id = msg.id;
+        let id = msg.id.clone();
         let params = msg.result.as_array().ok_or_else(|| {
             ParsingMethodError::ImpossibleToParseResultField(Box::new(msg.clone()))
         })?;
@@ -421,7 +600,7 @@ impl TryFrom<&Response> for Subscribe<'_> {
 
 #[derive(Debug, Clone)]
 pub struct Configure {
-    pub id: u64,
+    pub id: Id,
     pub version_rolling: Option<VersionRollingParams>,
     pub minimum_difficulty: Option<bool>,
 }
@@ -476,7 +655,7 @@ impl TryFrom<&Response> for Configure {
     type Error = ParsingMethodError;
 
     fn try_from(msg: &Response) -> Result<Self, ParsingMethodError> {
-        let id = msg.id;
+        let id = msg.id.clone();
         let params = msg.result.as_object().ok_or_else(|| {
             ParsingMethodError::ImpossibleToParseResultField(Box::new(msg.clone()))
         })?;
@@ -631,3 +810,99 @@ impl From<VersionRollingParams> for serde_json::Map<String, Value> {
         params
     }
 }
+
+#[test]
+fn reconnect_parsing() {
+    let server_message = r#"{"id":null,
+            "method": "client.reconnect",
+            "params":["backup.pool.example", "3333", 10]
+        }"#;
+    let server_message: Notification = serde_json::from_str(server_message).unwrap();
+    let reconnect = Reconnect::try_from(server_message).unwrap();
+    assert_eq!(
+        reconnect,
+        Reconnect {
+            hostname: Some("backup.pool.example".to_string()),
+            port: Some(3333),
+            wait_time: Some(10),
+        }
+    );
+
+    // the port is serialized as a number
+    let notification = match Message::from(reconnect.clone()) {
+        Message::Notification(notification) => notification,
+        _ => panic!(),
+    };
+    assert_eq!(
+        notification.params,
+        serde_json::json!(["backup.pool.example", 3333, 10])
+    );
+    assert_eq!(Reconnect::try_from(notification).unwrap(), reconnect);
+
+    let server_message = r#"{"id":null, "method": "client.reconnect", "params":[]}"#;
+    let server_message: Notification = serde_json::from_str(server_message).unwrap();
+    let reconnect = Reconnect::try_from(server_message).unwrap();
+    assert!(reconnect.hostname.is_none() && reconnect.port.is_none());
+
+    let server_message = r#"{"id":null, "method": "client.reconnect", "params":["a", "port"]}"#;
+    let server_message: Notification = serde_json::from_str(server_message).unwrap();
+    assert!(Reconnect::try_from(server_message).is_err());
+}
+
+#[test]
+fn show_message_and_get_version_parsing() {
+    let server_message = r#"{"id":null,
+            "method": "client.show_message",
+            "params":["maintenance at 12:00 UTC"]
+        }"#;
+    let server_message: Notification = serde_json::from_str(server_message).unwrap();
+    let show_message = ShowMessage::try_from(server_message).unwrap();
+    assert_eq!(show_message.message, "maintenance at 12:00 UTC");
+
+    let server_message = r#"{"id":4, "method": "client.get_version", "params":[]}"#;
+    let server_message: StandardRequest = serde_json::from_str(server_message).unwrap();
+    let get_version = GetVersion::from(server_message);
+    let response = get_version.respond("bmminer/2.0.0".to_string());
+    assert_eq!(response.id, Id::from(4));
+    let version = crate::client_to_server::GetVersion::try_from(&response).unwrap();
+    assert_eq!(version.version, "bmminer/2.0.0");
+}
+
+#[test]
+fn client_methods_request_and_notification_forms() {
+    use crate::methods::{Method, Server2Client};
+
+    let parse = |message: &str| {
+        let message: Message = serde_json::from_str(message).unwrap();
+        match Method::try_from(message).unwrap() {
+            Method::Server2Client(method) => method,
+            _ => panic!(),
+        }
+    };
+
+    let reconnect = Reconnect {
+        hostname: Some("backup.pool.example".to_string()),
+        port: Some(3333),
+        wait_time: None,
+    };
+    for message in [
+        r#"{"id":null,"method":"client.reconnect","params":["backup.pool.example",3333]}"#,
+        r#"{"id":7,"method":"client.reconnect","params":["backup.pool.example",3333]}"#,
+    ] {
+        assert!(matches!(parse(message), Server2Client::Reconnect(r) if r == reconnect));
+    }
+
+    for message in [
+        r#"{"id":null,"method":"client.show_message","params":["maintenance"]}"#,
+        r#"{"id":"m1","method":"client.show_message","params":["maintenance"]}"#,
+    ] {
+        assert!(
+            matches!(parse(message), Server2Client::ShowMessage(m) if m.message == "maintenance")
+        );
+    }
+
+    let message = r#"{"id":8,"method":"client.get_version","params":[]}"#;
+    assert!(matches!(parse(message), Server2Client::GetVersion(g) if g.id == Id::from(8)));
+    let message = r#"{"id":null,"method":"client.get_version","params":[]}"#;
+    assert!(matches!(parse(message), Server2Client::GetVersion(g) if g.id == Id::Null));
+}
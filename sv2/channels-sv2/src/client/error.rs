@@ -25,6 +25,9 @@ pub enum ExtendedChannelError {
     RequestIdMismatch,
     NoChainTip,
     ChainTipMismatch,
+
+    /// The channel has no active job.
+    NoActiveJob,
 }
 
 /// Errors that can occur within a **standard channel** context.
@@ -37,6 +40,9 @@ pub enum StandardChannelError {
 
     /// The provided extranonce prefix exceeds the maximum allowed size.
     NewExtranoncePrefixTooLarge,
+
+    /// The message is addressed to another channel.
+    ChannelIdMismatch,
 }
 
 /// Errors that can occur within a **group channel** context.
@@ -11,12 +11,14 @@ use crate::{
     client::{
         error::ExtendedChannelError,
         share_accounting::{ShareAccounting, ShareValidationError, ShareValidationResult},
+        work::{WorkGenerator, BIP320_VERSION_MASK},
     },
+    clock::Clock,
     merkle_root::merkle_root_from_path,
     target::{bytes_to_hex, u256_to_block_hash},
     MAX_EXTRANONCE_PREFIX_LEN,
 };
-use alloc::{format, string::String, vec, vec::Vec};
+use alloc::{format, string::String, sync::Arc, vec, vec::Vec};
 use binary_sv2::{self, Sv2Option};
 use bitcoin::{
     absolute::LockTime,
@@ -28,7 +30,8 @@ use bitcoin::{
 };
 use mining_sv2::{
     NewExtendedMiningJob, SetCustomMiningJob, SetCustomMiningJobSuccess,
-    SetNewPrevHash as SetNewPrevHashMp, SubmitSharesExtended,
+    SetNewPrevHash as SetNewPrevHashMp, SubmitSharesError, SubmitSharesExtended,
+    SubmitSharesSuccess,
 };
 use tracing::debug;
 
@@ -58,6 +61,7 @@ pub type ExtendedJob<'a> = (NewExtendedMiningJob<'a>, Vec<u8>);
 ///   `job_id`).
 /// - Share accounting for the channel (as tracked by the client).
 /// - The channel's current chain tip.
+/// - The `sequence_number` of the next share built by the channel.
 #[derive(Clone, Debug)]
 pub struct ExtendedChannel<'a> {
     channel_id: u32,
@@ -76,6 +80,7 @@ pub struct ExtendedChannel<'a> {
     stale_jobs: HashMap<u32, ExtendedJob<'a>>,
     share_accounting: ShareAccounting,
     chain_tip: Option<ChainTip>,
+    next_share_sequence_number: u32,
 }
 
 impl<'a> ExtendedChannel<'a> {
@@ -103,6 +108,7 @@ impl<'a> ExtendedChannel<'a> {
             stale_jobs: HashMap::new(),
             share_accounting: ShareAccounting::new(),
             chain_tip: None,
+            next_share_sequence_number: 0,
         }
     }
 
@@ -206,6 +212,17 @@ impl<'a> ExtendedChannel<'a> {
         &self.share_accounting
     }
 
+    /// Returns the `sequence_number` to be assigned to the next share built by
+    /// [`build_share`](ExtendedChannel::build_share).
+    pub fn get_next_share_sequence_number(&self) -> u32 {
+        self.next_share_sequence_number
+    }
+
+    /// Replaces the time source used by this channel's [`ShareAccounting`].
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.share_accounting.set_clock(clock);
+    }
+
     /// Handles a [`NewExtendedMiningJob`] message received from upstream.
     ///
     /// - If [`NewExtendedMiningJob::min_ntime`] is empty, the job is considered a future job and
@@ -423,15 +440,153 @@ impl<'a> ExtendedChannel<'a> {
         Ok(())
     }
 
+    /// Returns a [`WorkGenerator`] over the active job of the channel.
+    ///
+    /// Version bits are rolled within `version_rolling_mask` (as negotiated with upstream,
+    /// restricted to [`BIP320_VERSION_MASK`]), only if both the channel and the job allow version
+    /// rolling. Headers use the `min_ntime` of the job.
+    ///
+    /// Returns an error if there is no active job or no chain tip.
+    pub fn get_work_generator(
+        &self,
+        version_rolling_mask: u32,
+    ) -> Result<WorkGenerator, ExtendedChannelError> {
+        let (job, extranonce_prefix) = self
+            .active_job
+            .as_ref()
+            .ok_or(ExtendedChannelError::NoActiveJob)?;
+        let chain_tip = self
+            .chain_tip
+            .as_ref()
+            .ok_or(ExtendedChannelError::NoChainTip)?;
+
+        let version_rolling_mask = if self.version_rolling && job.version_rolling_allowed {
+            version_rolling_mask & BIP320_VERSION_MASK
+        } else {
+            0
+        };
+
+        Ok(WorkGenerator::new(
+            job,
+            extranonce_prefix,
+            self.rollable_extranonce_size,
+            chain_tip,
+            version_rolling_mask,
+        ))
+    }
+
     /// Validates a share prior to submission upstream.
     ///
     /// Updates channel state with the share validation result:
     /// - Prevents propagation of stale, duplicate, or low-difficulty shares.
     /// - Indicates whether a block was found from the share.
-    /// - Maintains local share accounting for later reconciliation with upstream acknowledgements.
+    /// - Maintains local share accounting.
+    /// - Records rejected shares in the rejection statistics of the [`ShareAccounting`].
+    ///
+    /// Valid shares are not recorded as pending upstream acknowledgement, as their
+    /// `sequence_number` may not be the one submitted upstream. Shares built with
+    /// [`build_share`](ExtendedChannel::build_share) are.
     pub fn validate_share(
         &mut self,
         share: SubmitSharesExtended,
+    ) -> Result<ShareValidationResult, ShareValidationError> {
+        let result = self.check_share(&share);
+        if let Err(error) = &result {
+            self.share_accounting
+                .update_rejected_share_accounting(error);
+        }
+        result
+    }
+
+    /// Handles a [`SubmitSharesSuccess`] message received from upstream, acknowledging the pending
+    /// shares up to its `last_sequence_number`.
+    pub fn on_submit_shares_success(
+        &mut self,
+        submit_shares_success: SubmitSharesSuccess,
+    ) -> Result<(), ExtendedChannelError> {
+        if submit_shares_success.channel_id != self.channel_id {
+            return Err(ExtendedChannelError::ChannelIdMismatch);
+        }
+        self.share_accounting.acknowledge_shares_accepted(
+            submit_shares_success.last_sequence_number,
+            submit_shares_success.new_submits_accepted_count,
+            submit_shares_success.new_shares_sum,
+        );
+        Ok(())
+    }
+
+    /// Handles a [`SubmitSharesError`] message received from upstream, acknowledging the rejected
+    /// pending share.
+    pub fn on_submit_shares_error(
+        &mut self,
+        submit_shares_error: SubmitSharesError,
+    ) -> Result<(), ExtendedChannelError> {
+        if submit_shares_error.channel_id != self.channel_id {
+            return Err(ExtendedChannelError::ChannelIdMismatch);
+        }
+        debug!(
+            "share {} rejected by upstream: {}",
+            submit_shares_error.sequence_number,
+            submit_shares_error.error_code.as_utf8_or_hex()
+        );
+        self.share_accounting
+            .acknowledge_share_rejected(submit_shares_error.sequence_number);
+        Ok(())
+    }
+
+    /// Builds a [`SubmitSharesExtended`] from the work found on job `job_id`, ready to be
+    /// submitted upstream.
+    ///
+    /// The share is assigned the channel's next `sequence_number` and validated as with
+    /// [`validate_share`](ExtendedChannel::validate_share). If valid, it is recorded as pending
+    /// upstream acknowledgement in the [`ShareAccounting`]. Otherwise, no `sequence_number` is
+    /// consumed.
+    ///
+    /// `extranonce` is the rollable part of the extranonce only.
+    pub fn build_share(
+        &mut self,
+        job_id: u32,
+        nonce: u32,
+        ntime: u32,
+        version: u32,
+        extranonce: Vec<u8>,
+    ) -> Result<(SubmitSharesExtended<'static>, ShareValidationResult), ShareValidationError> {
+        let extranonce = match extranonce.try_into() {
+            Ok(extranonce) => extranonce,
+            Err(_) => {
+                let error = ShareValidationError::BadExtranonceSize;
+                self.share_accounting
+                    .update_rejected_share_accounting(&error);
+                return Err(error);
+            }
+        };
+        let share = SubmitSharesExtended {
+            channel_id: self.channel_id,
+            sequence_number: self.next_share_sequence_number,
+            job_id,
+            nonce,
+            ntime,
+            version,
+            extranonce,
+        };
+
+        let result = self.validate_share(share.clone())?;
+        let (ShareValidationResult::Valid(hash) | ShareValidationResult::BlockFound(hash)) = result;
+        self.share_accounting.record_pending_share(
+            share.sequence_number,
+            share.job_id,
+            hash,
+            self.target.difficulty_float(),
+        );
+        self.next_share_sequence_number = self.next_share_sequence_number.wrapping_add(1);
+
+        Ok((share, result))
+    }
+
+    // Validates a share, updating the channel state for accepted shares only.
+    fn check_share(
+        &mut self,
+        share: &SubmitSharesExtended,
     ) -> Result<ShareValidationResult, ShareValidationError> {
         let job_id = share.job_id;
 
@@ -564,16 +719,22 @@ impl<'a> ExtendedChannel<'a> {
 
 #[cfg(test)]
 mod tests {
-    use crate::client::{
-        extended::ExtendedChannel,
-        share_accounting::{ShareValidationError, ShareValidationResult},
+    use crate::{
+        client::{
+            error::ExtendedChannelError,
+            extended::ExtendedChannel,
+            share_accounting::{ShareValidationError, ShareValidationResult},
+        },
+        clock::MockClock,
+        hashrate::HashrateWindow,
     };
     use binary_sv2::Sv2Option;
     use bitcoin::Target;
     use mining_sv2::{
         NewExtendedMiningJob, SetNewPrevHash as SetNewPrevHashMp, SubmitSharesExtended,
+        SubmitSharesSuccess,
     };
-    use std::convert::TryInto;
+    use std::{convert::TryInto, sync::Arc};
 
     #[test]
     fn test_future_job_activation_flow() {
@@ -913,6 +1074,138 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_build_share() {
+        let channel_id = 1;
+        let extranonce_prefix = [
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1,
+        ]
+        .to_vec();
+        // channel target: 0000ffff00000000000000000000000000000000000000000000000000000000
+        let target = Target::from_le_bytes([
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0xff, 0xff, 0x00, 0x00,
+        ]);
+
+        let mut channel = ExtendedChannel::new(
+            channel_id,
+            "user_identity".to_string(),
+            extranonce_prefix,
+            target,
+            1.0,
+            true,
+            8,
+        );
+        let clock = MockClock::new(1745596971);
+        channel.set_clock(Arc::new(clock.clone()));
+
+        let future_job = NewExtendedMiningJob {
+            channel_id,
+            job_id: 1,
+            min_ntime: Sv2Option::new(None),
+            version: 536870912,
+            version_rolling_allowed: true,
+            coinbase_tx_prefix: vec![
+                2, 0, 0, 0, 0, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+                0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 255, 255, 255, 255, 34, 82, 0,
+            ]
+            .try_into()
+            .unwrap(),
+            coinbase_tx_suffix: vec![
+                255, 255, 255, 255, 2, 0, 242, 5, 42, 1, 0, 0, 0, 22, 0, 20, 235, 225, 183, 220,
+                194, 147, 204, 170, 14, 231, 67, 168, 111, 137, 223, 130, 88, 194, 8, 252, 0, 0, 0,
+                0, 0, 0, 0, 0, 38, 106, 36, 170, 33, 169, 237, 226, 246, 28, 63, 113, 209, 222,
+                253, 63, 169, 153, 223, 163, 105, 83, 117, 92, 105, 6, 137, 121, 153, 98, 180, 139,
+                235, 216, 54, 151, 78, 140, 249, 1, 32, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+                0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            ]
+            .try_into()
+            .unwrap(),
+            merkle_path: vec![].try_into().unwrap(),
+        };
+        channel
+            .on_new_extended_mining_job(future_job.clone())
+            .unwrap();
+        let set_new_prev_hash = SetNewPrevHashMp {
+            channel_id,
+            job_id: future_job.job_id,
+            prev_hash: [
+                200, 53, 253, 129, 214, 31, 43, 84, 179, 58, 58, 76, 128, 213, 24, 53, 38, 144,
+                205, 88, 172, 20, 251, 22, 217, 141, 21, 221, 21, 0, 0, 0,
+            ]
+            .into(),
+            nbits: 453040064,
+            min_ntime: 1746839905,
+        };
+        channel.on_set_new_prev_hash(set_new_prev_hash).unwrap();
+
+        // this share has hash 00005e460def43b0153246e6300ce38d9da1c9abd8ef2157a88b2e9a12a8524a
+        // which does meet the channel target
+        let (share, result) = channel
+            .build_share(
+                1,
+                102103,
+                1745596971,
+                536870912,
+                vec![1, 0, 0, 0, 0, 0, 0, 0],
+            )
+            .unwrap();
+        assert!(matches!(result, ShareValidationResult::Valid(_)));
+        assert_eq!(share.channel_id, channel_id);
+        assert_eq!(share.sequence_number, 0);
+        assert_eq!(share.job_id, 1);
+        assert_eq!(channel.get_next_share_sequence_number(), 1);
+
+        let pending_share = channel.get_share_accounting().get_pending_share(0).unwrap();
+        assert_eq!(pending_share.get_job_id(), 1);
+        assert_eq!(pending_share.get_share_work(), target.difficulty_float());
+        assert_eq!(pending_share.get_submitted_at(), Some(1745596971));
+
+        // invalid shares are not pending and don't consume a sequence number
+        let res = channel.build_share(
+            1,
+            102103,
+            1745596971,
+            536870912,
+            vec![1, 0, 0, 0, 0, 0, 0, 0],
+        );
+        assert_eq!(res.unwrap_err(), ShareValidationError::DuplicateShare);
+        let res = channel.build_share(1, 0, 1745596971, 536870912, vec![1, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(res.unwrap_err(), ShareValidationError::DoesNotMeetTarget);
+        let res = channel.build_share(1, 102103, 1745596971, 536870912, vec![1, 0, 0, 0]);
+        assert_eq!(res.unwrap_err(), ShareValidationError::BadExtranonceSize);
+        let res = channel.build_share(2, 102103, 1745596971, 536870912, vec![0; 8]);
+        assert_eq!(res.unwrap_err(), ShareValidationError::InvalidJobId);
+
+        assert_eq!(channel.get_next_share_sequence_number(), 1);
+        assert_eq!(channel.get_share_accounting().get_pending_shares().len(), 1);
+        assert_eq!(channel.get_share_accounting().get_shares_rejected(), 4);
+
+        // upstream acknowledges the share
+        let submit_shares_success = SubmitSharesSuccess {
+            channel_id: channel_id + 1,
+            last_sequence_number: 0,
+            new_submits_accepted_count: 1,
+            new_shares_sum: 65_536,
+        };
+        assert!(matches!(
+            channel.on_submit_shares_success(submit_shares_success.clone()),
+            Err(ExtendedChannelError::ChannelIdMismatch)
+        ));
+        clock.advance(1);
+        channel
+            .on_submit_shares_success(SubmitSharesSuccess {
+                channel_id,
+                ..submit_shares_success
+            })
+            .unwrap();
+        let share_accounting = channel.get_share_accounting();
+        assert_eq!(share_accounting.get_unacknowledged_shares_count(), 0);
+        assert_eq!(share_accounting.get_upstream_shares_accepted(), 1);
+        assert_eq!(share_accounting.get_last_acknowledgement_latency(), Some(1));
+    }
+
     #[test]
     fn test_share_validation_valid_share() {
         let channel_id = 1;
@@ -1000,10 +1293,32 @@ mod tests {
             extranonce: vec![1, 0, 0, 0, 0, 0, 0, 0].try_into().unwrap(),
         };
 
+        let clock = MockClock::new(1745596971);
+        channel.set_clock(Arc::new(clock.clone()));
+
         let res = channel.validate_share(valid_share);
 
         assert!(matches!(res, Ok(ShareValidationResult::Valid(_))));
 
+        // the accepted work is tracked over the rolling windows
+        let share_accounting = channel.get_share_accounting();
+        assert_eq!(
+            share_accounting.get_window_shares_accepted(HashrateWindow::OneMinute),
+            1
+        );
+        assert_eq!(
+            share_accounting.get_window_share_work_sum(HashrateWindow::OneDay),
+            target.difficulty_float()
+        );
+        assert!(share_accounting.get_window_hashrate(HashrateWindow::FiveMinutes) > 0.0);
+        clock.advance(60);
+        assert_eq!(
+            channel
+                .get_share_accounting()
+                .get_window_shares_accepted(HashrateWindow::OneMinute),
+            0
+        );
+
         // try to cheat by re-submitting the same share
         // with a different sequence number
         let repeated_share = SubmitSharesExtended {
@@ -1022,5 +1337,14 @@ mod tests {
             res.unwrap_err(),
             ShareValidationError::DuplicateShare
         ));
+
+        // the rejection is recorded along with its timestamp
+        let rejection_stats = channel
+            .get_share_accounting()
+            .get_rejection_stats(&ShareValidationError::DuplicateShare)
+            .unwrap();
+        assert_eq!(rejection_stats.get_count(), 1);
+        assert_eq!(rejection_stats.get_last_timestamp(), Some(1745597031));
+        assert_eq!(channel.get_share_accounting().get_shares_rejected(), 1);
     }
 }
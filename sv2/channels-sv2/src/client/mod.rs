@@ -10,6 +10,7 @@ pub mod extended;
 pub mod group;
 pub mod share_accounting;
 pub mod standard;
+pub mod work;
 
 // Type aliases that switch between `std::collections` and `hashbrown`
 // depending on whether the `no_std` feature is enabled.
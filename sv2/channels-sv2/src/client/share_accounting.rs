@@ -3,10 +3,35 @@
 //! This module provides types and logic for validating mining shares, tracking share
 //! statistics, and reporting share validation results and errors. These abstractions
 //! are intended for use in Mining Clients.
+//!
+//! Rejected shares are counted per [`ShareValidationError`] variant, to help diagnose misbehaving
+//! firmware.
+//!
+//! Accepted work is also tracked over rolling windows (1m/5m/1h/24h), see [`RollingHashrate`].
+//! Time is read from a [`Clock`], which defaults to [`crate::clock::SystemClock`]. In `no_std`
+//! environments no default is available, and rolling windows are only updated once a clock is
+//! provided.
+//!
+//! Shares built for submission upstream are kept as [`PendingShare`]s, indexed by their
+//! `sequence_number`, until upstream acknowledges them with `SubmitShares.Success` or
+//! `SubmitShares.Error`. Comparing local and upstream figures (and looking for overdue pending
+//! shares) allows detecting an upstream silently dropping work. At most
+//! [`DEFAULT_MAX_PENDING_SHARES`] shares are kept pending by default, the oldest ones being
+//! evicted first.
 
-use super::HashSet;
+extern crate alloc;
+use super::{HashMap, HashSet};
+use crate::{
+    clock::Clock,
+    hashrate::{HashrateWindow, RollingHashrate},
+};
+use alloc::{collections::BTreeMap, sync::Arc, vec::Vec};
 use bitcoin::hashes::sha256d::Hash;
 
+/// Default maximum number of shares waiting for upstream acknowledgement tracked by a
+/// [`ShareAccounting`].
+pub const DEFAULT_MAX_PENDING_SHARES: usize = 1_024;
+
 /// The outcome of share validation, as seen by a Mining Client.
 ///
 /// - `Valid`: The share is valid and accepted.
@@ -28,7 +53,7 @@ pub enum ShareValidationResult {
 /// - `NoChainTip`: The chain tip is unknown or unavailable.
 /// - `BadExtranonceSize`: The share extranonce size is different from the channel's rollable
 ///   extranonce size.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum ShareValidationError {
     Invalid,
     Stale,
@@ -40,6 +65,59 @@ pub enum ShareValidationError {
     BadExtranonceSize,
 }
 
+/// Statistics of the shares rejected for a single [`ShareValidationError`] reason.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ShareRejectionStats {
+    count: u32,
+    last_timestamp: Option<u64>,
+}
+
+impl ShareRejectionStats {
+    /// Returns the number of shares rejected for this reason.
+    pub fn get_count(&self) -> u32 {
+        self.count
+    }
+
+    /// Returns the unix timestamp (seconds) of the last share rejected for this reason, or
+    /// `None` if no clock was set at the time.
+    pub fn get_last_timestamp(&self) -> Option<u64> {
+        self.last_timestamp
+    }
+}
+
+/// A share submitted upstream that was not acknowledged yet.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PendingShare {
+    job_id: u32,
+    share_hash: Hash,
+    share_work: f64,
+    submitted_at: Option<u64>,
+}
+
+impl PendingShare {
+    /// Returns the `job_id` the share was submitted for.
+    pub fn get_job_id(&self) -> u32 {
+        self.job_id
+    }
+
+    /// Returns the hash of the share.
+    pub fn get_share_hash(&self) -> Hash {
+        self.share_hash
+    }
+
+    /// Returns the work of the share, i.e. the difficulty of the channel target it was built
+    /// under.
+    pub fn get_share_work(&self) -> f64 {
+        self.share_work
+    }
+
+    /// Returns the unix timestamp (seconds) the share was submitted at, or `None` if no clock was
+    /// set at the time.
+    pub fn get_submitted_at(&self) -> Option<u64> {
+        self.submitted_at
+    }
+}
+
 /// Tracks share validation state for a specific channel (Extended or Standard).
 ///
 /// Used only on Mining Clients.
@@ -49,6 +127,10 @@ pub enum ShareValidationError {
 /// - cumulative work from accepted shares
 /// - hashes of seen shares (for duplicate detection)
 /// - highest difficulty seen in accepted shares
+/// - rejected shares, per rejection reason
+/// - work of accepted shares over rolling windows
+/// - shares submitted upstream and waiting for acknowledgement
+/// - shares acknowledged by upstream, and how long acknowledgements took
 #[derive(Clone, Debug)]
 pub struct ShareAccounting {
     last_share_sequence_number: u32,
@@ -56,6 +138,21 @@ pub struct ShareAccounting {
     share_work_sum: f64,
     seen_shares: HashSet<Hash>,
     best_diff: f64,
+    rejections: HashMap<ShareValidationError, ShareRejectionStats>,
+    rolling_hashrate: RollingHashrate,
+    // pending shares are indexed with sequence_number (u32)
+    pending_shares: BTreeMap<u32, PendingShare>,
+    // sequence_number of the last share recorded as pending
+    newest_pending_sequence_number: u32,
+    max_pending_shares: usize,
+    pending_shares_evicted: u32,
+    upstream_shares_accepted: u32,
+    upstream_share_work_sum: u64,
+    upstream_shares_rejected: u32,
+    last_acknowledgement_latency: Option<u64>,
+    acknowledgement_latency_sum: u64,
+    acknowledgement_latency_count: u32,
+    clock: Option<Arc<dyn Clock>>,
 }
 
 impl Default for ShareAccounting {
@@ -66,21 +163,54 @@ impl Default for ShareAccounting {
 
 impl ShareAccounting {
     /// Creates a new [`ShareAccounting`] instance, initializing all statistics to zero.
+    ///
+    /// Time is read from a [`crate::clock::SystemClock`]. In `no_std` environments, no clock is
+    /// set.
     pub fn new() -> Self {
+        #[cfg(not(feature = "no_std"))]
+        let clock: Option<Arc<dyn Clock>> = Some(Arc::new(crate::clock::SystemClock));
+        #[cfg(feature = "no_std")]
+        let clock: Option<Arc<dyn Clock>> = None;
         Self {
             last_share_sequence_number: 0,
             shares_accepted: 0,
             share_work_sum: 0.0,
             seen_shares: HashSet::new(),
             best_diff: 0.0,
+            rejections: HashMap::new(),
+            rolling_hashrate: RollingHashrate::new(),
+            pending_shares: BTreeMap::new(),
+            newest_pending_sequence_number: 0,
+            max_pending_shares: DEFAULT_MAX_PENDING_SHARES,
+            pending_shares_evicted: 0,
+            upstream_shares_accepted: 0,
+            upstream_share_work_sum: 0,
+            upstream_shares_rejected: 0,
+            last_acknowledgement_latency: None,
+            acknowledgement_latency_sum: 0,
+            acknowledgement_latency_count: 0,
+            clock,
         }
     }
 
+    /// Creates a new [`ShareAccounting`] instance, reading time from `clock`.
+    pub fn new_with_clock(clock: Arc<dyn Clock>) -> Self {
+        let mut share_accounting = Self::new();
+        share_accounting.set_clock(clock);
+        share_accounting
+    }
+
+    /// Replaces the time source used by this [`ShareAccounting`].
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = Some(clock);
+    }
+
     /// Updates the accounting state with a newly accepted share.
     ///
     /// - Increments share count and total work.
     /// - Updates last share sequence number.
     /// - Records share hash to detect duplicates.
+    /// - Records share work in the rolling hashrate windows, if a clock is set.
     pub fn update_share_accounting(
         &mut self,
         share_work: f64,
@@ -91,6 +221,243 @@ impl ShareAccounting {
         self.shares_accepted += 1;
         self.share_work_sum += share_work;
         self.seen_shares.insert(share_hash);
+        if let Some(clock) = &self.clock {
+            self.rolling_hashrate.record_share(clock.now(), share_work);
+        }
+    }
+
+    /// Updates the accounting state with a share rejected with `error`.
+    ///
+    /// Increments the rejection count for `error` and, if a clock is set, records the current
+    /// time as its last occurrence.
+    pub fn update_rejected_share_accounting(&mut self, error: &ShareValidationError) {
+        let now = self.clock.as_ref().map(|clock| clock.now());
+        let stats = self.rejections.entry(*error).or_default();
+        stats.count = stats.count.saturating_add(1);
+        stats.last_timestamp = now;
+    }
+
+    /// Returns the statistics of the shares rejected with `error`, if any was.
+    pub fn get_rejection_stats(
+        &self,
+        error: &ShareValidationError,
+    ) -> Option<&ShareRejectionStats> {
+        self.rejections.get(error)
+    }
+
+    /// Returns the statistics of the shares rejected on this channel, for every reason at least
+    /// one share was rejected for.
+    pub fn get_all_rejection_stats(
+        &self,
+    ) -> impl Iterator<Item = (&ShareValidationError, &ShareRejectionStats)> {
+        self.rejections.iter()
+    }
+
+    /// Returns the total number of shares rejected.
+    pub fn get_shares_rejected(&self) -> u32 {
+        self.rejections
+            .values()
+            .fold(0u32, |count, stats| count.saturating_add(stats.count))
+    }
+
+    /// Records a share submitted upstream with `share_sequence_number`, as pending until upstream
+    /// acknowledges it.
+    ///
+    /// Shares are expected to be recorded in `sequence_number` order, which wraps around after
+    /// `u32::MAX`. If a clock is set, the current time is recorded as the submission time. Once
+    /// more than `max_pending_shares` shares are pending, the oldest ones are evicted, see
+    /// [`ShareAccounting::get_evicted_pending_shares_count`].
+    pub fn record_pending_share(
+        &mut self,
+        share_sequence_number: u32,
+        job_id: u32,
+        share_hash: Hash,
+        share_work: f64,
+    ) {
+        let submitted_at = self.clock.as_ref().map(|clock| clock.now());
+        self.pending_shares.insert(
+            share_sequence_number,
+            PendingShare {
+                job_id,
+                share_hash,
+                share_work,
+                submitted_at,
+            },
+        );
+        self.newest_pending_sequence_number = share_sequence_number;
+
+        while self.pending_shares.len() > self.max_pending_shares {
+            let oldest = self
+                .oldest_pending_sequence_number()
+                .expect("pending shares must not be empty");
+            self.pending_shares.remove(&oldest);
+            self.pending_shares_evicted = self.pending_shares_evicted.saturating_add(1);
+        }
+    }
+
+    // Returns the sequence_number of the oldest pending share. Sequence numbers wrap around, so
+    // pending shares numbered above the newest one were submitted before it wrapped.
+    fn oldest_pending_sequence_number(&self) -> Option<u32> {
+        self.newest_pending_sequence_number
+            .checked_add(1)
+            .and_then(|after_newest| self.pending_shares.range(after_newest..).next())
+            .or_else(|| self.pending_shares.iter().next())
+            .map(|(sequence_number, _)| *sequence_number)
+    }
+
+    /// Returns the share submitted with `share_sequence_number`, if it is still pending.
+    pub fn get_pending_share(&self, share_sequence_number: u32) -> Option<&PendingShare> {
+        self.pending_shares.get(&share_sequence_number)
+    }
+
+    /// Returns the shares waiting for acknowledgement, indexed by `sequence_number`.
+    pub fn get_pending_shares(&self) -> &BTreeMap<u32, PendingShare> {
+        &self.pending_shares
+    }
+
+    /// Acknowledges the pending shares up to `last_sequence_number` (included), as reported by a
+    /// `SubmitShares.Success` message.
+    ///
+    /// Pending shares are acknowledged from the oldest one, following `sequence_number`
+    /// wrap-arounds. A `last_sequence_number` outside of the pending shares' range (e.g. repeated
+    /// by upstream for shares already acknowledged) acknowledges no share.
+    ///
+    /// `new_submits_accepted_count` and `new_shares_sum` are added to the upstream accepted share
+    /// count and work sum. Returns the number of pending shares acknowledged.
+    pub fn acknowledge_shares_accepted(
+        &mut self,
+        last_sequence_number: u32,
+        new_submits_accepted_count: u32,
+        new_shares_sum: u64,
+    ) -> usize {
+        self.upstream_shares_accepted = self
+            .upstream_shares_accepted
+            .saturating_add(new_submits_accepted_count);
+        self.upstream_share_work_sum = self.upstream_share_work_sum.saturating_add(new_shares_sum);
+
+        let Some(oldest) = self.oldest_pending_sequence_number() else {
+            return 0;
+        };
+        let newest = self.newest_pending_sequence_number;
+        if last_sequence_number.wrapping_sub(oldest) > newest.wrapping_sub(oldest) {
+            return 0;
+        }
+        let acknowledged: Vec<u32> = if oldest <= last_sequence_number {
+            self.pending_shares
+                .range(oldest..=last_sequence_number)
+                .map(|(sequence_number, _)| *sequence_number)
+                .collect()
+        } else {
+            self.pending_shares
+                .range(oldest..)
+                .chain(self.pending_shares.range(..=last_sequence_number))
+                .map(|(sequence_number, _)| *sequence_number)
+                .collect()
+        };
+        for sequence_number in &acknowledged {
+            if let Some(pending_share) = self.pending_shares.remove(sequence_number) {
+                self.record_acknowledgement_latency(&pending_share);
+            }
+        }
+        acknowledged.len()
+    }
+
+    /// Acknowledges the pending share with `sequence_number` as rejected by upstream, as reported
+    /// by a `SubmitShares.Error` message.
+    ///
+    /// Returns the acknowledged share, or `None` if no share with `sequence_number` was pending.
+    /// The upstream rejected share count is incremented in both cases.
+    pub fn acknowledge_share_rejected(&mut self, sequence_number: u32) -> Option<PendingShare> {
+        self.upstream_shares_rejected = self.upstream_shares_rejected.saturating_add(1);
+        let pending_share = self.pending_shares.remove(&sequence_number)?;
+        self.record_acknowledgement_latency(&pending_share);
+        Some(pending_share)
+    }
+
+    // Records the time elapsed between the submission of `pending_share` and now, if both are
+    // known.
+    fn record_acknowledgement_latency(&mut self, pending_share: &PendingShare) {
+        let (Some(clock), Some(submitted_at)) = (&self.clock, pending_share.submitted_at) else {
+            return;
+        };
+        let latency = clock.now().saturating_sub(submitted_at);
+        self.last_acknowledgement_latency = Some(latency);
+        self.acknowledgement_latency_sum = self.acknowledgement_latency_sum.saturating_add(latency);
+        self.acknowledgement_latency_count = self.acknowledgement_latency_count.saturating_add(1);
+    }
+
+    /// Returns the number of shares accepted by upstream, as reported by `SubmitShares.Success`
+    /// messages.
+    pub fn get_upstream_shares_accepted(&self) -> u32 {
+        self.upstream_shares_accepted
+    }
+
+    /// Returns the work of the shares accepted by upstream, as reported by `SubmitShares.Success`
+    /// messages.
+    pub fn get_upstream_share_work_sum(&self) -> u64 {
+        self.upstream_share_work_sum
+    }
+
+    /// Returns the number of shares rejected by upstream with `SubmitShares.Error` messages.
+    pub fn get_upstream_shares_rejected(&self) -> u32 {
+        self.upstream_shares_rejected
+    }
+
+    /// Returns the number of shares submitted upstream and not acknowledged yet.
+    pub fn get_unacknowledged_shares_count(&self) -> usize {
+        self.pending_shares.len()
+    }
+
+    /// Returns the number of pending shares evicted before upstream acknowledged them, because
+    /// more than `max_pending_shares` shares were pending.
+    pub fn get_evicted_pending_shares_count(&self) -> u32 {
+        self.pending_shares_evicted
+    }
+
+    /// Returns the maximum number of shares kept pending.
+    pub fn get_max_pending_shares(&self) -> usize {
+        self.max_pending_shares
+    }
+
+    /// Sets the maximum number of shares kept pending.
+    ///
+    /// Defaults to [`DEFAULT_MAX_PENDING_SHARES`]. Already pending shares are kept until a new
+    /// share is recorded, see [`ShareAccounting::record_pending_share`].
+    pub fn set_max_pending_shares(&mut self, max_pending_shares: usize) {
+        self.max_pending_shares = max_pending_shares;
+    }
+
+    /// Returns the number of shares not acknowledged by upstream more than `timeout_secs` seconds
+    /// after their submission.
+    ///
+    /// Shares submitted while no clock was set are not counted, and `0` is returned if no clock is
+    /// set.
+    pub fn get_overdue_shares_count(&self, timeout_secs: u64) -> usize {
+        let Some(clock) = &self.clock else {
+            return 0;
+        };
+        let now = clock.now();
+        self.pending_shares
+            .values()
+            .filter(|pending_share| {
+                pending_share
+                    .submitted_at
+                    .is_some_and(|submitted_at| now.saturating_sub(submitted_at) > timeout_secs)
+            })
+            .count()
+    }
+
+    /// Returns the time, in seconds, upstream took to acknowledge the last acknowledged share.
+    pub fn get_last_acknowledgement_latency(&self) -> Option<u64> {
+        self.last_acknowledgement_latency
+    }
+
+    /// Returns the average time, in seconds, upstream took to acknowledge shares.
+    pub fn get_average_acknowledgement_latency(&self) -> Option<f64> {
+        if self.acknowledgement_latency_count == 0 {
+            return None;
+        }
+        Some(self.acknowledgement_latency_sum as f64 / self.acknowledgement_latency_count as f64)
     }
 
     /// Clears the set of seen share hashes.
@@ -116,6 +483,33 @@ impl ShareAccounting {
         self.share_work_sum
     }
 
+    /// Returns the number of shares accepted within `window`, or `0` if no clock is set.
+    pub fn get_window_shares_accepted(&self, window: HashrateWindow) -> u32 {
+        self.clock.as_ref().map_or(0, |clock| {
+            self.rolling_hashrate.get_shares(window, clock.now())
+        })
+    }
+
+    /// Returns the work of the shares accepted within `window`, or `0.0` if no clock is set.
+    pub fn get_window_share_work_sum(&self, window: HashrateWindow) -> f64 {
+        self.clock.as_ref().map_or(0.0, |clock| {
+            self.rolling_hashrate.get_work(window, clock.now())
+        })
+    }
+
+    /// Returns the hashrate (H/s) estimated from the work of the shares accepted within
+    /// `window`, or `0.0` if no clock is set.
+    pub fn get_window_hashrate(&self, window: HashrateWindow) -> f64 {
+        self.clock.as_ref().map_or(0.0, |clock| {
+            self.rolling_hashrate.get_hashrate(window, clock.now())
+        })
+    }
+
+    /// Returns the rolling windows of accepted work.
+    pub fn get_rolling_hashrate(&self) -> &RollingHashrate {
+        &self.rolling_hashrate
+    }
+
     /// Checks if the given share hash has already been seen (duplicate detection).
     pub fn is_share_seen(&self, share_hash: Hash) -> bool {
         self.seen_shares.contains(&share_hash)
@@ -133,3 +527,129 @@ impl ShareAccounting {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+    use bitcoin::hashes::Hash as _;
+
+    #[test]
+    fn test_pending_share_reconciliation() {
+        let clock = MockClock::new(1_000);
+        let mut share_accounting = ShareAccounting::new_with_clock(Arc::new(clock.clone()));
+
+        for sequence_number in 0..4 {
+            share_accounting.record_pending_share(
+                sequence_number,
+                1,
+                Hash::hash(&sequence_number.to_le_bytes()),
+                10.0,
+            );
+            clock.advance(1);
+        }
+        assert_eq!(share_accounting.get_unacknowledged_shares_count(), 4);
+
+        // upstream rejects share 1, then acknowledges shares up to 2
+        clock.advance(2);
+        let rejected = share_accounting.acknowledge_share_rejected(1).unwrap();
+        assert_eq!(rejected.get_submitted_at(), Some(1_001));
+        assert_eq!(share_accounting.get_last_acknowledgement_latency(), Some(5));
+        assert_eq!(share_accounting.acknowledge_shares_accepted(2, 2, 20), 2);
+        assert_eq!(share_accounting.get_upstream_shares_accepted(), 2);
+        assert_eq!(share_accounting.get_upstream_share_work_sum(), 20);
+        assert_eq!(share_accounting.get_upstream_shares_rejected(), 1);
+        assert_eq!(share_accounting.get_last_acknowledgement_latency(), Some(4));
+        assert_eq!(
+            share_accounting.get_average_acknowledgement_latency(),
+            Some(5.0)
+        );
+
+        // share 3 is never acknowledged
+        assert_eq!(share_accounting.get_unacknowledged_shares_count(), 1);
+        assert!(share_accounting.get_pending_share(3).is_some());
+        assert_eq!(share_accounting.get_overdue_shares_count(5), 0);
+        clock.advance(10);
+        assert_eq!(share_accounting.get_overdue_shares_count(5), 1);
+
+        // errors for unknown shares are still counted
+        assert!(share_accounting.acknowledge_share_rejected(42).is_none());
+        assert_eq!(share_accounting.get_upstream_shares_rejected(), 2);
+    }
+
+    #[test]
+    fn test_pending_share_sequence_number_wrap_around() {
+        let mut share_accounting = ShareAccounting::new();
+        let record = |share_accounting: &mut ShareAccounting, sequence_number: u32| {
+            share_accounting.record_pending_share(
+                sequence_number,
+                1,
+                Hash::hash(&sequence_number.to_le_bytes()),
+                10.0,
+            );
+        };
+
+        for sequence_number in [u32::MAX - 2, u32::MAX - 1, u32::MAX, 0, 1] {
+            record(&mut share_accounting, sequence_number);
+        }
+
+        // acknowledging up to u32::MAX leaves the shares submitted after the wrap-around pending
+        assert_eq!(
+            share_accounting.acknowledge_shares_accepted(u32::MAX, 3, 30),
+            3
+        );
+        assert_eq!(
+            share_accounting
+                .get_pending_shares()
+                .keys()
+                .collect::<Vec<_>>(),
+            [&0, &1]
+        );
+
+        // a repeated acknowledgement doesn't acknowledge the shares submitted since
+        assert_eq!(
+            share_accounting.acknowledge_shares_accepted(u32::MAX, 0, 0),
+            0
+        );
+        assert_eq!(share_accounting.get_unacknowledged_shares_count(), 2);
+
+        // acknowledging across the wrap-around
+        let mut share_accounting = ShareAccounting::new();
+        for sequence_number in [u32::MAX - 1, u32::MAX, 0, 1] {
+            record(&mut share_accounting, sequence_number);
+        }
+        assert!(share_accounting
+            .acknowledge_share_rejected(u32::MAX)
+            .is_some());
+        assert_eq!(share_accounting.acknowledge_shares_accepted(0, 2, 20), 2);
+        assert_eq!(
+            share_accounting
+                .get_pending_shares()
+                .keys()
+                .collect::<Vec<_>>(),
+            [&1]
+        );
+    }
+
+    #[test]
+    fn test_pending_share_eviction() {
+        let mut share_accounting = ShareAccounting::new();
+        share_accounting.set_max_pending_shares(2);
+
+        for sequence_number in [u32::MAX - 1, u32::MAX, 0] {
+            share_accounting.record_pending_share(
+                sequence_number,
+                1,
+                Hash::hash(&sequence_number.to_le_bytes()),
+                10.0,
+            );
+        }
+
+        // the oldest share is evicted, even though its sequence_number is not the lowest one
+        assert_eq!(share_accounting.get_unacknowledged_shares_count(), 2);
+        assert_eq!(share_accounting.get_evicted_pending_shares_count(), 1);
+        assert!(share_accounting.get_pending_share(u32::MAX - 1).is_none());
+        assert!(share_accounting.get_pending_share(u32::MAX).is_some());
+        assert!(share_accounting.get_pending_share(0).is_some());
+    }
+}
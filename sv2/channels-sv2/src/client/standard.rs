@@ -12,11 +12,12 @@ use crate::{
         error::StandardChannelError,
         share_accounting::{ShareAccounting, ShareValidationError, ShareValidationResult},
     },
+    clock::Clock,
     merkle_root::merkle_root_from_path,
     target::{bytes_to_hex, u256_to_block_hash},
     MAX_EXTRANONCE_PREFIX_LEN,
 };
-use alloc::{format, string::String, vec::Vec};
+use alloc::{format, string::String, sync::Arc, vec::Vec};
 use binary_sv2::{self, Sv2Option};
 use bitcoin::{
     blockdata::block::{Header, Version},
@@ -24,7 +25,8 @@ use bitcoin::{
     CompactTarget, Target,
 };
 use mining_sv2::{
-    NewExtendedMiningJob, NewMiningJob, SetNewPrevHash as SetNewPrevHashMp, SubmitSharesStandard,
+    NewExtendedMiningJob, NewMiningJob, SetNewPrevHash as SetNewPrevHashMp, SubmitSharesError,
+    SubmitSharesStandard, SubmitSharesSuccess,
 };
 use tracing::debug;
 
@@ -42,6 +44,7 @@ use tracing::debug;
 /// - stale jobs (jobs from previous chain tip, indexed by job_id)
 /// - share accounting state
 /// - chain tip state
+/// - `sequence_number` of the next share built by the channel
 #[derive(Debug, Clone)]
 pub struct StandardChannel<'a> {
     channel_id: u32,
@@ -55,6 +58,7 @@ pub struct StandardChannel<'a> {
     stale_jobs: HashMap<u32, NewMiningJob<'a>>,
     share_accounting: ShareAccounting,
     chain_tip: Option<ChainTip>,
+    next_share_sequence_number: u32,
 }
 
 impl<'a> StandardChannel<'a> {
@@ -78,6 +82,7 @@ impl<'a> StandardChannel<'a> {
             stale_jobs: HashMap::new(),
             share_accounting: ShareAccounting::new(),
             chain_tip: None,
+            next_share_sequence_number: 0,
         }
     }
 
@@ -165,6 +170,17 @@ impl<'a> StandardChannel<'a> {
         &self.share_accounting
     }
 
+    /// Returns the `sequence_number` to be assigned to the next share built by
+    /// [`build_share`](StandardChannel::build_share).
+    pub fn get_next_share_sequence_number(&self) -> u32 {
+        self.next_share_sequence_number
+    }
+
+    /// Replaces the time source used by this channel's [`ShareAccounting`].
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.share_accounting.set_clock(clock);
+    }
+
     /// Handles a new group channel job by converting it into a standard job
     /// and activating it in this channel's context.
     ///
@@ -254,10 +270,100 @@ impl<'a> StandardChannel<'a> {
     /// - Verifies the share meets the channel target, is not a duplicate, and is not stale.
     /// - Updates share accounting state based on validation result.
     /// - Returns whether the share is valid or resulted in a block being found.
-    /// - Returns error describing why share is not valid.
+    /// - Returns error describing why share is not valid, recording it in the rejection statistics
+    ///   of the [`ShareAccounting`].
+    ///
+    /// Valid shares are not recorded as pending upstream acknowledgement, as their
+    /// `sequence_number` may not be the one submitted upstream. Shares built with
+    /// [`build_share`](StandardChannel::build_share) are.
     pub fn validate_share(
         &mut self,
         share: SubmitSharesStandard,
+    ) -> Result<ShareValidationResult, ShareValidationError> {
+        let result = self.check_share(share);
+        if let Err(error) = &result {
+            self.share_accounting
+                .update_rejected_share_accounting(error);
+        }
+        result
+    }
+
+    /// Builds a [`SubmitSharesStandard`] from the work found on job `job_id`, ready to be
+    /// submitted upstream.
+    ///
+    /// The share is assigned the channel's next `sequence_number` and validated as with
+    /// [`validate_share`](StandardChannel::validate_share). If valid, it is recorded as pending
+    /// upstream acknowledgement in the [`ShareAccounting`]. Otherwise, no `sequence_number` is
+    /// consumed.
+    pub fn build_share(
+        &mut self,
+        job_id: u32,
+        nonce: u32,
+        ntime: u32,
+        version: u32,
+    ) -> Result<(SubmitSharesStandard, ShareValidationResult), ShareValidationError> {
+        let share = SubmitSharesStandard {
+            channel_id: self.channel_id,
+            sequence_number: self.next_share_sequence_number,
+            job_id,
+            nonce,
+            ntime,
+            version,
+        };
+
+        let result = self.validate_share(share.clone())?;
+        let (ShareValidationResult::Valid(hash) | ShareValidationResult::BlockFound(hash)) = result;
+        self.share_accounting.record_pending_share(
+            share.sequence_number,
+            share.job_id,
+            hash,
+            self.target.difficulty_float(),
+        );
+        self.next_share_sequence_number = self.next_share_sequence_number.wrapping_add(1);
+
+        Ok((share, result))
+    }
+
+    /// Handles a [`SubmitSharesSuccess`] message received from upstream, acknowledging the pending
+    /// shares up to its `last_sequence_number`.
+    pub fn on_submit_shares_success(
+        &mut self,
+        submit_shares_success: SubmitSharesSuccess,
+    ) -> Result<(), StandardChannelError> {
+        if submit_shares_success.channel_id != self.channel_id {
+            return Err(StandardChannelError::ChannelIdMismatch);
+        }
+        self.share_accounting.acknowledge_shares_accepted(
+            submit_shares_success.last_sequence_number,
+            submit_shares_success.new_submits_accepted_count,
+            submit_shares_success.new_shares_sum,
+        );
+        Ok(())
+    }
+
+    /// Handles a [`SubmitSharesError`] message received from upstream, acknowledging the rejected
+    /// pending share.
+    pub fn on_submit_shares_error(
+        &mut self,
+        submit_shares_error: SubmitSharesError,
+    ) -> Result<(), StandardChannelError> {
+        if submit_shares_error.channel_id != self.channel_id {
+            return Err(StandardChannelError::ChannelIdMismatch);
+        }
+        debug!(
+            "share {} rejected by upstream: {}",
+            submit_shares_error.sequence_number,
+            submit_shares_error.error_code.as_utf8_or_hex()
+        );
+        self.share_accounting
+            .acknowledge_share_rejected(submit_shares_error.sequence_number);
+        Ok(())
+    }
+
+    // Validates a share, updating the channel state for accepted shares only.
+    fn check_share(
+        &mut self,
+        share: SubmitSharesStandard,
     ) -> Result<ShareValidationResult, ShareValidationError> {
         let job_id = share.job_id;
 
@@ -692,5 +798,86 @@ mod tests {
         let res = channel.validate_share(valid_share);
 
         assert!(matches!(res, Ok(ShareValidationResult::Valid(_))));
+        // validated shares are not pending upstream acknowledgement
+        assert_eq!(
+            channel
+                .get_share_accounting()
+                .get_unacknowledged_shares_count(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_build_share() {
+        let channel_id = 1;
+        let extranonce_prefix = [
+            83, 116, 114, 97, 116, 117, 109, 32, 86, 50, 32, 83, 82, 73, 32, 80, 111, 111, 108, 0,
+            0, 0, 0, 0, 0, 0, 1,
+        ]
+        .to_vec();
+        // channel target: 0000ffff00000000000000000000000000000000000000000000000000000000
+        let target = Target::from_le_bytes([
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0xff, 0xff, 0x00, 0x00,
+        ]);
+
+        let mut channel = StandardChannel::new(
+            channel_id,
+            "user_identity".to_string(),
+            extranonce_prefix,
+            target,
+            1.0,
+        );
+
+        let future_job = NewMiningJob {
+            channel_id,
+            job_id: 1,
+            merkle_root: [
+                189, 200, 25, 246, 119, 73, 34, 42, 209, 112, 237, 50, 169, 71, 163, 192, 24, 84,
+                56, 86, 147, 71, 243, 44, 18, 107, 167, 169, 169, 66, 186, 98,
+            ]
+            .into(),
+            version: 536870912,
+            min_ntime: Sv2Option::new(None),
+        };
+        channel.on_new_mining_job(future_job.clone());
+        channel
+            .on_set_new_prev_hash(SetNewPrevHashMp {
+                channel_id,
+                job_id: future_job.job_id,
+                prev_hash: [
+                    200, 53, 253, 129, 214, 31, 43, 84, 179, 58, 58, 76, 128, 213, 24, 53, 38, 144,
+                    205, 88, 172, 20, 251, 22, 217, 141, 21, 221, 21, 0, 0, 0,
+                ]
+                .into(),
+                nbits: 453040064,
+                min_ntime: 1746839905,
+            })
+            .unwrap();
+
+        // this share has hash 0000762e88282a2ed8e7097aef06f413a962a47e32206a80ecbfc1f0b4bd1493
+        // which meets the channel target
+        let (share, result) = channel
+            .build_share(1, 244405, 1745596932, 536870912)
+            .unwrap();
+        assert!(matches!(result, ShareValidationResult::Valid(_)));
+        assert_eq!(share.sequence_number, 0);
+        assert_eq!(channel.get_next_share_sequence_number(), 1);
+        assert!(channel
+            .get_share_accounting()
+            .get_pending_share(0)
+            .is_some());
+
+        // invalid shares are not pending and don't consume a sequence number
+        let res = channel.build_share(1, 244405, 1745596932, 536870912);
+        assert_eq!(res.unwrap_err(), ShareValidationError::DuplicateShare);
+        assert_eq!(channel.get_next_share_sequence_number(), 1);
+        assert_eq!(
+            channel
+                .get_share_accounting()
+                .get_unacknowledged_shares_count(),
+            1
+        );
     }
 }
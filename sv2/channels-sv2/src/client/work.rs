@@ -0,0 +1,355 @@
+//! Local Work Generation - Mining Client Abstraction.
+//!
+//! This module provides [`WorkGenerator`], an iterator over the search space of the active job of
+//! an [`ExtendedChannel`]. Each item is a [`Work`] unit, carrying a ready-to-hash 80-byte block
+//! header, so that device firmware can feed hashing chips directly from channel state, without
+//! rebuilding coinbase transactions or computing merkle roots itself.
+//!
+//! ## Search Space
+//!
+//! The rollable part of the extranonce is rolled as a big-endian counter, starting from zero.
+//! For each extranonce, the version bits allowed by the negotiated version rolling mask (and by
+//! [BIP 320](https://github.com/bitcoin/bips/blob/master/bip-0320.mediawiki)) are rolled
+//! before moving to the next extranonce, so that the merkle root is computed once per extranonce.
+//!
+//! The `nonce` field of the headers is left to zero, and rolled by the hashing chips. Found
+//! nonces are turned into shares with [`ExtendedChannel::build_share`], using the `job_id`,
+//! `extranonce`, `version` and `ntime` of the [`Work`] they were found on.
+//!
+//! This module is `no_std` compatible.
+
+extern crate alloc;
+#[cfg(doc)]
+use crate::client::extended::ExtendedChannel;
+use crate::{chain_tip::ChainTip, merkle_root::merkle_root_from_path, target::u256_to_block_hash};
+use alloc::{vec, vec::Vec};
+use binary_sv2::U256;
+use bitcoin::{
+    blockdata::block::{Header, Version},
+    consensus::serialize,
+    hashes::sha256d::Hash,
+    CompactTarget,
+};
+use mining_sv2::NewExtendedMiningJob;
+
+/// Version bits that can be rolled, as per [BIP 320].
+///
+/// [BIP 320]: https://github.com/bitcoin/bips/blob/master/bip-0320.mediawiki
+pub const BIP320_VERSION_MASK: u32 = 0x1fffe000;
+
+/// A unit of work for hashing chips, ready to be hashed once its `nonce` is rolled.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Work {
+    job_id: u32,
+    extranonce: Vec<u8>,
+    version: u32,
+    ntime: u32,
+    header: [u8; 80],
+}
+
+impl Work {
+    /// Returns the `job_id` this work was generated from.
+    pub fn get_job_id(&self) -> u32 {
+        self.job_id
+    }
+
+    /// Returns the rollable part of the extranonce used in the coinbase transaction.
+    pub fn get_extranonce(&self) -> &[u8] {
+        &self.extranonce
+    }
+
+    /// Returns the block version of the header.
+    pub fn get_version(&self) -> u32 {
+        self.version
+    }
+
+    /// Returns the `ntime` of the header.
+    pub fn get_ntime(&self) -> u32 {
+        self.ntime
+    }
+
+    /// Returns the serialized 80-byte block header, with a zero `nonce`.
+    pub fn get_header(&self) -> &[u8; 80] {
+        &self.header
+    }
+}
+
+/// Iterator over the [`Work`] units of an extended job.
+///
+/// Created with [`ExtendedChannel::get_work_generator`]. The generator keeps a copy of the job, so
+/// it is not affected by later updates of the channel, and must be replaced whenever a new job is
+/// activated.
+#[derive(Clone, Debug)]
+pub struct WorkGenerator {
+    job_id: u32,
+    version: u32,
+    version_rolling_mask: u32,
+    coinbase_tx_prefix: Vec<u8>,
+    coinbase_tx_suffix: Vec<u8>,
+    merkle_path: Vec<Vec<u8>>,
+    extranonce_prefix: Vec<u8>,
+    prev_hash: U256<'static>,
+    nbits: u32,
+    ntime: u32,
+    // next extranonce and version bits, or `None` once the search space is exhausted
+    next: Option<(Vec<u8>, u32)>,
+    // merkle root for the current extranonce
+    merkle_root: Option<[u8; 32]>,
+}
+
+impl WorkGenerator {
+    // Creates a generator over `job`, starting from a zero rollable extranonce.
+    pub(crate) fn new(
+        job: &NewExtendedMiningJob,
+        extranonce_prefix: &[u8],
+        rollable_extranonce_size: u16,
+        chain_tip: &ChainTip,
+        version_rolling_mask: u32,
+    ) -> Self {
+        let ntime = job
+            .min_ntime
+            .clone()
+            .into_inner()
+            .unwrap_or(chain_tip.min_ntime());
+        Self {
+            job_id: job.job_id,
+            version: job.version,
+            version_rolling_mask,
+            coinbase_tx_prefix: job.coinbase_tx_prefix.inner_as_ref().to_vec(),
+            coinbase_tx_suffix: job.coinbase_tx_suffix.inner_as_ref().to_vec(),
+            merkle_path: job
+                .merkle_path
+                .inner_as_ref()
+                .iter()
+                .map(|hash| hash.to_vec())
+                .collect(),
+            extranonce_prefix: extranonce_prefix.to_vec(),
+            prev_hash: chain_tip.prev_hash(),
+            nbits: chain_tip.nbits(),
+            ntime,
+            next: Some((vec![0; rollable_extranonce_size as usize], 0)),
+            merkle_root: None,
+        }
+    }
+
+    /// Returns the `job_id` of the job work is generated from.
+    pub fn get_job_id(&self) -> u32 {
+        self.job_id
+    }
+
+    /// Returns the version bits rolled by this generator.
+    pub fn get_version_rolling_mask(&self) -> u32 {
+        self.version_rolling_mask
+    }
+
+    // Returns the value following `bits` among the subsets of `mask`, or `None` after the last.
+    fn next_version_bits(&self, bits: u32) -> Option<u32> {
+        let next = (bits | !self.version_rolling_mask).wrapping_add(1) & self.version_rolling_mask;
+        (next != 0).then_some(next)
+    }
+
+    // Increments `extranonce` as a big-endian counter, returning `false` if it wrapped around.
+    fn increment_extranonce(extranonce: &mut [u8]) -> bool {
+        for byte in extranonce.iter_mut().rev() {
+            let (incremented, overflow) = byte.overflowing_add(1);
+            *byte = incremented;
+            if !overflow {
+                return true;
+            }
+        }
+        false
+    }
+
+    // Computes the merkle root of the job with the rollable `extranonce`.
+    fn compute_merkle_root(&self, extranonce: &[u8]) -> Option<[u8; 32]> {
+        let mut full_extranonce = self.extranonce_prefix.clone();
+        full_extranonce.extend_from_slice(extranonce);
+        merkle_root_from_path(
+            &self.coinbase_tx_prefix,
+            &self.coinbase_tx_suffix,
+            &full_extranonce,
+            &self.merkle_path,
+        )?
+        .try_into()
+        .ok()
+    }
+}
+
+impl Iterator for WorkGenerator {
+    type Item = Work;
+
+    fn next(&mut self) -> Option<Work> {
+        let (extranonce, version_bits) = self.next.take()?;
+
+        let merkle_root = match self.merkle_root {
+            Some(merkle_root) => merkle_root,
+            // an invalid coinbase can't be fixed by rolling the extranonce
+            None => self.compute_merkle_root(&extranonce)?,
+        };
+
+        // prepare the following work unit, rolling version bits first
+        self.next = match self.next_version_bits(version_bits) {
+            Some(next_version_bits) => {
+                self.merkle_root = Some(merkle_root);
+                Some((extranonce.clone(), next_version_bits))
+            }
+            None => {
+                self.merkle_root = None;
+                let mut next_extranonce = extranonce.clone();
+                Self::increment_extranonce(&mut next_extranonce).then_some((next_extranonce, 0))
+            }
+        };
+
+        let version = (self.version & !self.version_rolling_mask) | version_bits;
+        let header = Header {
+            version: Version::from_consensus(version as i32),
+            prev_blockhash: u256_to_block_hash(self.prev_hash.clone()),
+            merkle_root: (*Hash::from_bytes_ref(&merkle_root)).into(),
+            time: self.ntime,
+            bits: CompactTarget::from_consensus(self.nbits),
+            nonce: 0,
+        };
+
+        Some(Work {
+            job_id: self.job_id,
+            extranonce,
+            version,
+            ntime: self.ntime,
+            header: serialize(&header)
+                .try_into()
+                .expect("block header must be 80 bytes"),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::{
+        error::ExtendedChannelError, extended::ExtendedChannel,
+        share_accounting::ShareValidationResult,
+    };
+    use binary_sv2::Sv2Option;
+    use bitcoin::{hashes::Hash as _, Target};
+    use mining_sv2::SetNewPrevHash as SetNewPrevHashMp;
+    use std::convert::TryInto;
+
+    // Returns a channel with an active job and a chain tip, accepting any share.
+    fn channel_with_active_job(
+        extranonce_prefix: Vec<u8>,
+        rollable_extranonce_size: u16,
+    ) -> ExtendedChannel<'static> {
+        let mut channel = ExtendedChannel::new(
+            1,
+            "user_identity".to_string(),
+            extranonce_prefix,
+            Target::from_le_bytes([0xff; 32]),
+            1.0,
+            true,
+            rollable_extranonce_size,
+        );
+        let job = NewExtendedMiningJob {
+            channel_id: 1,
+            job_id: 1,
+            min_ntime: Sv2Option::new(None),
+            version: 536870912,
+            version_rolling_allowed: true,
+            coinbase_tx_prefix: vec![
+                2, 0, 0, 0, 0, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+                0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 255, 255, 255, 255, 34, 82, 0,
+            ]
+            .try_into()
+            .unwrap(),
+            coinbase_tx_suffix: vec![
+                255, 255, 255, 255, 2, 0, 242, 5, 42, 1, 0, 0, 0, 22, 0, 20, 235, 225, 183, 220,
+                194, 147, 204, 170, 14, 231, 67, 168, 111, 137, 223, 130, 88, 194, 8, 252, 0, 0, 0,
+                0, 0, 0, 0, 0, 38, 106, 36, 170, 33, 169, 237, 226, 246, 28, 63, 113, 209, 222,
+                253, 63, 169, 153, 223, 163, 105, 83, 117, 92, 105, 6, 137, 121, 153, 98, 180, 139,
+                235, 216, 54, 151, 78, 140, 249, 1, 32, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+                0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            ]
+            .try_into()
+            .unwrap(),
+            merkle_path: vec![].try_into().unwrap(),
+        };
+        channel.on_new_extended_mining_job(job).unwrap();
+        let set_new_prev_hash = SetNewPrevHashMp {
+            channel_id: 1,
+            job_id: 1,
+            prev_hash: [
+                200, 53, 253, 129, 214, 31, 43, 84, 179, 58, 58, 76, 128, 213, 24, 53, 38, 144,
+                205, 88, 172, 20, 251, 22, 217, 141, 21, 221, 21, 0, 0, 0,
+            ]
+            .into(),
+            nbits: 453040064,
+            min_ntime: 1746839905,
+        };
+        channel.on_set_new_prev_hash(set_new_prev_hash).unwrap();
+        channel
+    }
+
+    #[test]
+    fn test_work_matches_share_validation() {
+        let mut channel = channel_with_active_job([0; 24].to_vec(), 8);
+        let mut work_generator = channel.get_work_generator(0).unwrap();
+
+        let work = work_generator.next().unwrap();
+        assert_eq!(work.get_job_id(), 1);
+        assert_eq!(work.get_extranonce(), [0; 8]);
+        assert_eq!(work.get_version(), 536870912);
+        assert_eq!(work.get_ntime(), 1746839905);
+
+        // without version rolling, the next work rolls the extranonce
+        let next_work = work_generator.next().unwrap();
+        assert_eq!(next_work.get_extranonce(), [0, 0, 0, 0, 0, 0, 0, 1]);
+        assert_eq!(next_work.get_version(), 536870912);
+        assert_ne!(next_work.get_header(), work.get_header());
+
+        // the header hashes to the share built from the same work
+        let (_, result) = channel
+            .build_share(
+                work.get_job_id(),
+                0,
+                work.get_ntime(),
+                work.get_version(),
+                work.get_extranonce().to_vec(),
+            )
+            .unwrap();
+        let header_hash = Hash::hash(work.get_header());
+        assert!(matches!(result, ShareValidationResult::Valid(hash) if hash == header_hash));
+    }
+
+    #[test]
+    fn test_version_rolling_and_exhaustion() {
+        let mut channel = channel_with_active_job([0; 31].to_vec(), 1);
+
+        // bits outside of BIP 320 are never rolled
+        let work_generator = channel.get_work_generator(0x0000_6001).unwrap();
+        assert_eq!(work_generator.get_version_rolling_mask(), 0x0000_6000);
+        let work: Vec<Work> = work_generator.collect();
+        assert_eq!(work.len(), 256 * 4);
+        let versions: Vec<u32> = work[..4].iter().map(Work::get_version).collect();
+        assert_eq!(
+            versions,
+            vec![0x2000_0000, 0x2000_2000, 0x2000_4000, 0x2000_6000]
+        );
+        assert!(work[..4].iter().all(|work| work.get_extranonce() == [0]));
+        assert_eq!(work[4].get_extranonce(), [1]);
+        assert_eq!(work[1023].get_extranonce(), [255]);
+
+        // no work without an active job
+        channel = ExtendedChannel::new(
+            1,
+            "user_identity".to_string(),
+            [0; 31].to_vec(),
+            Target::from_le_bytes([0xff; 32]),
+            1.0,
+            false,
+            1,
+        );
+        assert!(matches!(
+            channel.get_work_generator(0x1fffe000),
+            Err(ExtendedChannelError::NoActiveJob)
+        ));
+    }
+}
@@ -0,0 +1,289 @@
+//! Rolling hashrate estimation.
+//!
+//! [`RollingHashrate`] keeps the work of accepted shares over rolling windows of time (see
+//! [`HashrateWindow`]) and derives hashrate estimates from it with
+//! [`hash_rate_from_target`]. The share accounting of both Mining Servers and Mining Clients keeps
+//! one, so that dashboards and vardiff logic read hashrate figures computed the same way on both
+//! sides of a channel.
+//!
+//! Each window is split into [`BUCKETS_PER_WINDOW`] buckets, so memory usage is fixed regardless
+//! of the share rate, and windows slide with a granularity of `1 / BUCKETS_PER_WINDOW` of their
+//! length.
+//!
+//! This module is `no_std` compatible. Time is provided by the caller, as unix timestamps in
+//! seconds.
+
+use crate::target::hash_rate_from_target;
+use bitcoin::Target;
+
+/// Number of buckets each [`HashrateWindow`] is split into.
+pub const BUCKETS_PER_WINDOW: usize = 60;
+
+/// Returns the hashrate (H/s) estimated from `work` accepted over `secs` seconds.
+///
+/// Work is expressed as in [`RollingHashrate`]. Returns `0.0` if no work was accepted or no time
+/// elapsed.
+pub fn hashrate_from_work(work: f64, secs: u64) -> f64 {
+    if work <= 0.0 || secs == 0 {
+        return 0.0;
+    }
+    // work is the number of difficulty 1 shares the accepted shares are worth, so the hashrate is
+    // the one producing one difficulty 1 share per minute, scaled by the rate of difficulty 1
+    // shares over `secs`
+    let difficulty_1_shares_per_min = work * 60.0 / secs as f64;
+    hash_rate_from_target(Target::MAX.to_le_bytes().into(), 1.0)
+        .map(|hashrate| hashrate * difficulty_1_shares_per_min)
+        .unwrap_or(0.0)
+}
+
+/// The rolling windows over which accepted work is tracked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HashrateWindow {
+    /// The last minute.
+    OneMinute,
+    /// The last 5 minutes.
+    FiveMinutes,
+    /// The last hour.
+    OneHour,
+    /// The last 24 hours.
+    OneDay,
+}
+
+impl HashrateWindow {
+    /// All windows, from the shortest to the longest.
+    pub const ALL: [HashrateWindow; 4] = [
+        HashrateWindow::OneMinute,
+        HashrateWindow::FiveMinutes,
+        HashrateWindow::OneHour,
+        HashrateWindow::OneDay,
+    ];
+
+    /// Returns the length of the window, in seconds.
+    pub fn as_secs(&self) -> u64 {
+        match self {
+            HashrateWindow::OneMinute => 60,
+            HashrateWindow::FiveMinutes => 300,
+            HashrateWindow::OneHour => 3_600,
+            HashrateWindow::OneDay => 86_400,
+        }
+    }
+
+    fn index(&self) -> usize {
+        match self {
+            HashrateWindow::OneMinute => 0,
+            HashrateWindow::FiveMinutes => 1,
+            HashrateWindow::OneHour => 2,
+            HashrateWindow::OneDay => 3,
+        }
+    }
+
+    fn bucket_secs(&self) -> u64 {
+        self.as_secs() / BUCKETS_PER_WINDOW as u64
+    }
+}
+
+// Work accepted during one bucket of a window. `epoch` is the bucket's start timestamp divided by
+// the window's bucket length.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct Bucket {
+    epoch: u64,
+    shares: u32,
+    work: f64,
+}
+
+/// Accepted work over each [`HashrateWindow`], and the hashrate derived from it.
+///
+/// Work is expressed in the same unit as the share accounting's work sum, i.e. the sum of the
+/// difficulties of the channel targets the shares were accepted under.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RollingHashrate {
+    buckets: [[Bucket; BUCKETS_PER_WINDOW]; HashrateWindow::ALL.len()],
+}
+
+impl Default for RollingHashrate {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RollingHashrate {
+    /// Creates a new [`RollingHashrate`], with no work recorded.
+    pub fn new() -> Self {
+        Self {
+            buckets: [[Bucket::default(); BUCKETS_PER_WINDOW]; HashrateWindow::ALL.len()],
+        }
+    }
+
+    /// Records a share of work `share_work` accepted at the `now` unix timestamp.
+    pub fn record_share(&mut self, now: u64, share_work: f64) {
+        for window in HashrateWindow::ALL {
+            let epoch = now / window.bucket_secs();
+            let bucket =
+                &mut self.buckets[window.index()][(epoch % BUCKETS_PER_WINDOW as u64) as usize];
+            if bucket.epoch != epoch {
+                *bucket = Bucket {
+                    epoch,
+                    ..Default::default()
+                };
+            }
+            bucket.shares = bucket.shares.saturating_add(1);
+            bucket.work += share_work;
+        }
+    }
+
+    /// Returns the number of shares accepted within `window`, as of the `now` unix timestamp.
+    pub fn get_shares(&self, window: HashrateWindow, now: u64) -> u32 {
+        self.live_buckets(window, now)
+            .fold(0u32, |shares, bucket| shares.saturating_add(bucket.shares))
+    }
+
+    /// Returns the work accepted within `window`, as of the `now` unix timestamp.
+    pub fn get_work(&self, window: HashrateWindow, now: u64) -> f64 {
+        self.live_buckets(window, now)
+            .map(|bucket| bucket.work)
+            .sum()
+    }
+
+    /// Returns the hashrate (H/s) estimated from the work accepted within `window`, as of the
+    /// `now` unix timestamp.
+    ///
+    /// The work is averaged over the full length of the window, so estimates of windows longer
+    /// than the time since the first share are lower than the actual hashrate.
+    pub fn get_hashrate(&self, window: HashrateWindow, now: u64) -> f64 {
+        hashrate_from_work(self.get_work(window, now), window.as_secs())
+    }
+
+    // Returns the buckets of `window` that fall within the window as of `now`.
+    fn live_buckets(&self, window: HashrateWindow, now: u64) -> impl Iterator<Item = &Bucket> {
+        let current = now / window.bucket_secs();
+        let oldest = current.saturating_sub(BUCKETS_PER_WINDOW as u64 - 1);
+        self.buckets[window.index()]
+            .iter()
+            .filter(move |bucket| bucket.shares > 0 && (oldest..=current).contains(&bucket.epoch))
+    }
+
+    // Writes the recorded work as part of a channel snapshot. Only non empty buckets are written.
+    #[cfg(not(feature = "no_std"))]
+    pub(crate) fn write_snapshot(
+        &self,
+        writer: &mut crate::server::snapshot::SnapshotWriter,
+    ) -> Result<(), crate::server::error::SnapshotError> {
+        let buckets: Vec<_> = self
+            .buckets
+            .iter()
+            .enumerate()
+            .flat_map(|(window, buckets)| {
+                buckets
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, bucket)| bucket.shares > 0)
+                    .map(move |(slot, bucket)| (window, slot, bucket))
+            })
+            .collect();
+        writer.write_len(buckets.len())?;
+        for (window, slot, bucket) in buckets {
+            writer.write_u8(window as u8);
+            writer.write_u8(slot as u8);
+            writer.write_u64(bucket.epoch);
+            writer.write_u32(bucket.shares);
+            writer.write_f64(bucket.work);
+        }
+        Ok(())
+    }
+
+    // Reads the recorded work written by `write_snapshot`.
+    #[cfg(not(feature = "no_std"))]
+    pub(crate) fn read_snapshot(
+        reader: &mut crate::server::snapshot::SnapshotReader,
+    ) -> Result<Self, crate::server::error::SnapshotError> {
+        let mut rolling_hashrate = Self::new();
+        for _ in 0..reader.read_len()? {
+            let window = reader.read_u8()? as usize;
+            let slot = reader.read_u8()? as usize;
+            let bucket = rolling_hashrate
+                .buckets
+                .get_mut(window)
+                .and_then(|buckets| buckets.get_mut(slot))
+                .ok_or(crate::server::error::SnapshotError::InvalidHashrateWindow)?;
+            bucket.epoch = reader.read_u64()?;
+            bucket.shares = reader.read_u32()?;
+            bucket.work = reader.read_f64()?;
+        }
+        Ok(rolling_hashrate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rolling_windows() {
+        let mut rolling_hashrate = RollingHashrate::new();
+        let start = 1_000_020;
+
+        rolling_hashrate.record_share(start, 2.0);
+        rolling_hashrate.record_share(start + 30, 3.0);
+        for window in HashrateWindow::ALL {
+            assert_eq!(rolling_hashrate.get_shares(window, start + 30), 2);
+            assert_eq!(rolling_hashrate.get_work(window, start + 30), 5.0);
+        }
+
+        // the first share leaves the 1 minute window, but not the longer ones
+        let now = start + 65;
+        assert_eq!(
+            rolling_hashrate.get_shares(HashrateWindow::OneMinute, now),
+            1
+        );
+        assert_eq!(
+            rolling_hashrate.get_work(HashrateWindow::OneMinute, now),
+            3.0
+        );
+        assert_eq!(
+            rolling_hashrate.get_work(HashrateWindow::FiveMinutes, now),
+            5.0
+        );
+
+        // both shares leave every window but the 24 hours one
+        let now = start + 3_700;
+        assert_eq!(
+            rolling_hashrate.get_work(HashrateWindow::OneMinute, now),
+            0.0
+        );
+        assert_eq!(
+            rolling_hashrate.get_work(HashrateWindow::FiveMinutes, now),
+            0.0
+        );
+        assert_eq!(rolling_hashrate.get_work(HashrateWindow::OneHour, now), 0.0);
+        assert_eq!(rolling_hashrate.get_work(HashrateWindow::OneDay, now), 5.0);
+        assert_eq!(
+            rolling_hashrate.get_hashrate(HashrateWindow::OneHour, now),
+            0.0
+        );
+
+        // the bucket of the first share is reused without keeping its work
+        rolling_hashrate.record_share(start + 60, 1.0);
+        assert_eq!(
+            rolling_hashrate.get_work(HashrateWindow::OneMinute, start + 60),
+            4.0
+        );
+    }
+
+    #[test]
+    fn test_hashrate_estimate() {
+        let mut rolling_hashrate = RollingHashrate::new();
+        let start = 1_000_020;
+
+        // 60 shares of difficulty 1_000 in a minute, about 71.6 TH/s
+        for i in 0..60 {
+            rolling_hashrate.record_share(start + i, 1_000.0);
+        }
+        let hashrate = rolling_hashrate.get_hashrate(HashrateWindow::OneMinute, start + 59);
+        let expected = 60_000.0 * 4_294_967_296.0 / 60.0;
+        assert!((hashrate - expected).abs() / expected < 0.001);
+
+        // the same work averaged over 5 minutes
+        let hashrate = rolling_hashrate.get_hashrate(HashrateWindow::FiveMinutes, start + 59);
+        assert!((hashrate - expected / 5.0).abs() / expected < 0.001);
+    }
+}
@@ -10,8 +10,13 @@
 //! - Channel primitives for SV2 mining protocol
 //! - Channel management for mining servers and clients
 //! - Standard, extended, and group channel support
-//! - Share accounting
-//! - Job store abstractions
+//! - Extranonce prefix allocation for server channels
+//! - Share accounting, with optional share deduplication across channels
+//! - Rolling hashrate estimates (1m/5m/1h/24h) from accepted work
+//! - Block reconstruction from shares that solve a block
+//! - Merged mining (AuxPoW) commitments and proofs
+//! - Injectable [`clock`] for vardiff, channels and share accounting
+//! - Job store abstractions, with serializable snapshots of job stores and channels
 //! - [`client`] module is `no_std` compatible. To enable it build the crate with `no_std` feature.
 #![cfg_attr(feature = "no_std", no_std)]
 
@@ -27,6 +32,8 @@ pub mod outputs;
 pub mod bip141;
 pub mod chain_tip;
 pub mod client;
+pub mod clock;
+pub mod hashrate;
 pub mod merkle_root;
 pub mod target;
 
@@ -34,4 +41,4 @@ pub mod target;
 pub mod vardiff;
 
 #[cfg(not(feature = "no_std"))]
-pub use vardiff::{classic::VardiffState, Vardiff};
+pub use vardiff::{classic::VardiffState, ewma::EwmaVardiff, poisson::PoissonVardiff, Vardiff};
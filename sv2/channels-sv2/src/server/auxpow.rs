@@ -0,0 +1,659 @@
+//! Merged mining (AuxPoW) - Mining Server Abstraction.
+//!
+//! Merged mining allows the proof of work of a parent chain (Bitcoin) to be reused on auxiliary
+//! chains, as long as the parent block's coinbase commits to the auxiliary blocks. This module
+//! provides the building blocks for a pool to merge-mine auxiliary chains on top of the jobs
+//! created by [`JobFactory`].
+//!
+//! ## Responsibilities
+//!
+//! - **Merged Mining Tree**: [`MergedMiningTree`] places the auxiliary block hashes in the slots
+//!   of a merkle tree, following the slot assignment rule of Namecoin-style merged mining.
+//! - **Commitment**: [`MergedMiningTree::commitment`] builds the `fabe6d6d` commitment to be
+//!   pushed in the coinbase scriptSig, see [`JobFactory::set_merged_mining_commitment`].
+//! - **Proofs**: When a share meets the target of some auxiliary chain, an [`AuxPowSolution`] is
+//!   reported, carrying the [`AuxPow`] proof (parent coinbase, coinbase merkle branch, chain
+//!   merkle branch and parent header) to be submitted to the auxiliary chain.
+//!
+//! ## Usage
+//!
+//! Build a [`MergedMiningTree`] from the current auxiliary blocks and set it on a channel with
+//! [`ExtendedChannel::set_merged_mining_tree`] (or [`StandardChannel::set_merged_mining_tree`]).
+//! Jobs created afterwards commit to the tree, and
+//! [`ExtendedChannel::validate_share_with_aux_pow`] (or
+//! [`StandardChannel::validate_share_with_aux_pow`]) reports the auxiliary chains whose targets
+//! were met.
+//!
+//! [`JobFactory`]: crate::server::jobs::factory::JobFactory
+//! [`JobFactory::set_merged_mining_commitment`]: crate::server::jobs::factory::JobFactory::set_merged_mining_commitment
+//! [`ExtendedChannel::set_merged_mining_tree`]: crate::server::extended::ExtendedChannel::set_merged_mining_tree
+//! [`ExtendedChannel::validate_share_with_aux_pow`]: crate::server::extended::ExtendedChannel::validate_share_with_aux_pow
+//! [`StandardChannel::set_merged_mining_tree`]: crate::server::standard::StandardChannel::set_merged_mining_tree
+//! [`StandardChannel::validate_share_with_aux_pow`]: crate::server::standard::StandardChannel::validate_share_with_aux_pow
+
+use crate::server::{
+    error::{AuxPowError, SnapshotError},
+    snapshot::{SnapshotReader, SnapshotWriter},
+};
+use bitcoin::{
+    blockdata::block::Header,
+    consensus::{self, Encodable},
+    hashes::{sha256d::Hash, Hash as _},
+    Target, Transaction, VarInt,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    convert::TryInto,
+};
+use tracing::error;
+
+/// Magic bytes preceding the merged mining commitment in the coinbase scriptSig.
+pub const MERGED_MINING_HEADER: [u8; 4] = [0xfa, 0xbe, 0x6d, 0x6d];
+
+/// Size (in bytes) of the merged mining commitment: the magic bytes, the merkle root, the merkle
+/// tree size and the merkle nonce.
+pub const MERGED_MINING_COMMITMENT_SIZE: usize = 44;
+
+/// Maximum height of the merged mining merkle tree.
+///
+/// Auxiliary chains accept chain merkle branches of up to 30 hashes, but the whole tree is kept in
+/// memory, so it is capped to `2^10` leaves.
+pub const MAX_MERGED_MINING_TREE_HEIGHT: u32 = 10;
+
+// Number of merkle nonces tried for each tree height before growing the tree.
+const MAX_MERGED_MINING_NONCE_ATTEMPTS: u32 = 1_000;
+
+/// Returns the slot of the merged mining merkle tree reserved for `chain_id`, for a tree of
+/// `2^height` leaves built with `nonce`.
+///
+/// This is the slot assignment rule enforced by auxiliary chains when validating an [`AuxPow`].
+pub fn merged_mining_slot(chain_id: u32, nonce: u32, height: u32) -> u32 {
+    let mut rand = nonce;
+    rand = rand.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+    rand = rand.wrapping_add(chain_id);
+    rand = rand.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+    rand % (1u32 << height)
+}
+
+/// Returns the merged mining merkle root committed to by a coinbase transaction prefix, if any.
+///
+/// The root is returned in internal byte order.
+pub fn merged_mining_root(coinbase_tx_prefix: &[u8]) -> Option<[u8; 32]> {
+    let start = coinbase_tx_prefix
+        .windows(MERGED_MINING_HEADER.len())
+        .position(|window| window == MERGED_MINING_HEADER)?
+        + MERGED_MINING_HEADER.len();
+    let mut root: [u8; 32] = coinbase_tx_prefix.get(start..start + 32)?.try_into().ok()?;
+    root.reverse();
+    Some(root)
+}
+
+/// An auxiliary chain to be merge-mined.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuxChain {
+    chain_id: u32,
+    aux_block_hash: [u8; 32],
+    target: Target,
+}
+
+impl AuxChain {
+    /// Creates a new [`AuxChain`] from its chain ID, the hash of the auxiliary block to be mined
+    /// (in internal byte order) and the auxiliary chain's target.
+    pub fn new(chain_id: u32, aux_block_hash: [u8; 32], target: Target) -> Self {
+        Self {
+            chain_id,
+            aux_block_hash,
+            target,
+        }
+    }
+
+    /// Returns the chain ID of the auxiliary chain.
+    pub fn get_chain_id(&self) -> u32 {
+        self.chain_id
+    }
+
+    /// Returns the hash of the auxiliary block to be mined.
+    pub fn get_aux_block_hash(&self) -> [u8; 32] {
+        self.aux_block_hash
+    }
+
+    /// Returns the target of the auxiliary chain.
+    pub fn get_target(&self) -> Target {
+        self.target
+    }
+}
+
+/// A merkle tree committing to the blocks of one or more auxiliary chains.
+///
+/// Each auxiliary block hash is placed in the slot given by [`merged_mining_slot`]. The tree is
+/// the smallest one (and, for that size, uses the smallest merkle nonce) where no two auxiliary
+/// chains share a slot. Empty slots are filled with zeros.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergedMiningTree {
+    aux_chains: Vec<AuxChain>,
+    height: u32,
+    nonce: u32,
+    slots: Vec<u32>,
+    // the levels of the tree, from the leaves up to the root
+    levels: Vec<Vec<[u8; 32]>>,
+}
+
+impl MergedMiningTree {
+    /// Creates a new [`MergedMiningTree`] for the given auxiliary chains.
+    ///
+    /// Returns an error if no auxiliary chain is provided, if two auxiliary chains share the same
+    /// chain ID, or if no slot assignment is found within [`MAX_MERGED_MINING_TREE_HEIGHT`].
+    pub fn new(aux_chains: Vec<AuxChain>) -> Result<Self, AuxPowError> {
+        if aux_chains.is_empty() {
+            return Err(AuxPowError::NoAuxChains);
+        }
+
+        let mut chain_ids = HashSet::new();
+        for aux_chain in &aux_chains {
+            if !chain_ids.insert(aux_chain.chain_id) {
+                return Err(AuxPowError::DuplicateChainId(aux_chain.chain_id));
+            }
+        }
+
+        // smallest height fitting all auxiliary chains
+        let min_height = aux_chains.len().next_power_of_two().trailing_zeros();
+
+        for height in min_height..=MAX_MERGED_MINING_TREE_HEIGHT {
+            for nonce in 0..MAX_MERGED_MINING_NONCE_ATTEMPTS {
+                let slots: Vec<u32> = aux_chains
+                    .iter()
+                    .map(|aux_chain| merged_mining_slot(aux_chain.chain_id, nonce, height))
+                    .collect();
+                if slots.iter().collect::<HashSet<_>>().len() != slots.len() {
+                    continue;
+                }
+
+                let mut leaves = vec![[0u8; 32]; 1 << height];
+                for (aux_chain, slot) in aux_chains.iter().zip(&slots) {
+                    leaves[*slot as usize] = aux_chain.aux_block_hash;
+                }
+                let mut levels = vec![leaves];
+                while let Some(level) = levels.last().filter(|level| level.len() > 1) {
+                    let next_level = level
+                        .chunks(2)
+                        .map(|pair| hash_pair(&pair[0], &pair[1]))
+                        .collect();
+                    levels.push(next_level);
+                }
+
+                return Ok(Self {
+                    aux_chains,
+                    height,
+                    nonce,
+                    slots,
+                    levels,
+                });
+            }
+        }
+
+        Err(AuxPowError::NoSlotAssignment)
+    }
+
+    /// Returns the auxiliary chains committed to by this tree.
+    pub fn get_aux_chains(&self) -> &[AuxChain] {
+        &self.aux_chains
+    }
+
+    /// Returns the number of leaves of this tree.
+    pub fn get_size(&self) -> u32 {
+        1 << self.height
+    }
+
+    /// Returns the merkle nonce used for the slot assignment of this tree.
+    pub fn get_nonce(&self) -> u32 {
+        self.nonce
+    }
+
+    /// Returns the slot of the given auxiliary chain, if committed to by this tree.
+    pub fn get_slot(&self, chain_id: u32) -> Option<u32> {
+        self.aux_chains
+            .iter()
+            .position(|aux_chain| aux_chain.chain_id == chain_id)
+            .map(|index| self.slots[index])
+    }
+
+    /// Returns the merkle root of this tree, in internal byte order.
+    pub fn merkle_root(&self) -> [u8; 32] {
+        self.levels.last().expect("tree must have a root")[0]
+    }
+
+    /// Returns the merkle branch linking the leaf at `slot` to the merkle root.
+    pub fn merkle_branch(&self, slot: u32) -> Vec<[u8; 32]> {
+        let height = self.height as usize;
+        self.levels[..height]
+            .iter()
+            .enumerate()
+            .map(|(depth, level)| level[(slot as usize >> depth) ^ 1])
+            .collect()
+    }
+
+    /// Returns the merged mining commitment to be pushed in the coinbase scriptSig.
+    ///
+    /// The commitment is made of [`MERGED_MINING_HEADER`], the merkle root (in reversed byte
+    /// order), the tree size and the merkle nonce (both as little-endian `u32`).
+    pub fn commitment(&self) -> [u8; MERGED_MINING_COMMITMENT_SIZE] {
+        let mut root = self.merkle_root();
+        root.reverse();
+
+        let mut commitment = [0u8; MERGED_MINING_COMMITMENT_SIZE];
+        commitment[..4].copy_from_slice(&MERGED_MINING_HEADER);
+        commitment[4..36].copy_from_slice(&root);
+        commitment[36..40].copy_from_slice(&self.get_size().to_le_bytes());
+        commitment[40..44].copy_from_slice(&self.nonce.to_le_bytes());
+        commitment
+    }
+
+    /// Returns the solutions for every auxiliary chain whose target is met by `parent_header`.
+    ///
+    /// `coinbase_tx` is the serialized parent coinbase (without BIP141 data), and
+    /// `coinbase_branch` the merkle path linking it to the parent header's merkle root.
+    ///
+    /// The proofs are built for shares already accepted on the parent chain, so failing to build
+    /// them doesn't affect the share: the error is logged and no solution is returned.
+    pub(crate) fn aux_pow_solutions(
+        &self,
+        parent_header: &Header,
+        coinbase_tx: &[u8],
+        coinbase_branch: &[[u8; 32]],
+    ) -> Vec<AuxPowSolution> {
+        self.try_aux_pow_solutions(parent_header, coinbase_tx, coinbase_branch)
+            .unwrap_or_else(|e| {
+                error!(
+                    "failed to build the AuxPoW proofs of share {}: {:?}",
+                    parent_header.block_hash(),
+                    e
+                );
+                vec![]
+            })
+    }
+
+    fn try_aux_pow_solutions(
+        &self,
+        parent_header: &Header,
+        coinbase_tx: &[u8],
+        coinbase_branch: &[[u8; 32]],
+    ) -> Result<Vec<AuxPowSolution>, AuxPowError> {
+        let hash = parent_header.block_hash();
+        let met: Vec<(&AuxChain, u32)> = self
+            .aux_chains
+            .iter()
+            .zip(self.slots.iter().copied())
+            .filter(|(aux_chain, _)| aux_chain.target.is_met_by(hash))
+            .collect();
+        if met.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let coinbase_tx: Transaction =
+            consensus::deserialize(coinbase_tx).map_err(|_| AuxPowError::InvalidCoinbase)?;
+
+        Ok(met
+            .into_iter()
+            .map(|(aux_chain, slot)| AuxPowSolution {
+                chain_id: aux_chain.chain_id,
+                aux_block_hash: aux_chain.aux_block_hash,
+                aux_pow: AuxPow {
+                    coinbase_tx: coinbase_tx.clone(),
+                    coinbase_branch: coinbase_branch.to_vec(),
+                    chain_branch: self.merkle_branch(slot),
+                    chain_index: slot,
+                    parent_header: *parent_header,
+                },
+            })
+            .collect())
+    }
+}
+
+/// The proof that a parent block commits to an auxiliary block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuxPow {
+    coinbase_tx: Transaction,
+    coinbase_branch: Vec<[u8; 32]>,
+    chain_branch: Vec<[u8; 32]>,
+    chain_index: u32,
+    parent_header: Header,
+}
+
+impl AuxPow {
+    /// Returns the parent block's coinbase transaction.
+    pub fn get_coinbase_tx(&self) -> &Transaction {
+        &self.coinbase_tx
+    }
+
+    /// Returns the merkle branch linking the parent coinbase to the parent header's merkle root.
+    pub fn get_coinbase_branch(&self) -> &[[u8; 32]] {
+        &self.coinbase_branch
+    }
+
+    /// Returns the merkle branch linking the auxiliary block hash to the merged mining merkle root.
+    pub fn get_chain_branch(&self) -> &[[u8; 32]] {
+        &self.chain_branch
+    }
+
+    /// Returns the slot of the auxiliary block hash in the merged mining merkle tree.
+    pub fn get_chain_index(&self) -> u32 {
+        self.chain_index
+    }
+
+    /// Returns the parent block header.
+    pub fn get_parent_header(&self) -> &Header {
+        &self.parent_header
+    }
+
+    /// Serializes the proof in the `auxpow` format expected by auxiliary chains.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = consensus::serialize(&self.coinbase_tx);
+        bytes.extend_from_slice(self.parent_header.block_hash().as_ref());
+        write_branch(&mut bytes, &self.coinbase_branch, 0);
+        write_branch(&mut bytes, &self.chain_branch, self.chain_index);
+        bytes.extend(consensus::serialize(&self.parent_header));
+        bytes
+    }
+}
+
+/// An auxiliary chain whose target was met by a share, along with the proof to be submitted to
+/// it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuxPowSolution {
+    chain_id: u32,
+    aux_block_hash: [u8; 32],
+    aux_pow: AuxPow,
+}
+
+impl AuxPowSolution {
+    /// Returns the chain ID of the auxiliary chain.
+    pub fn get_chain_id(&self) -> u32 {
+        self.chain_id
+    }
+
+    /// Returns the hash of the solved auxiliary block.
+    pub fn get_aux_block_hash(&self) -> [u8; 32] {
+        self.aux_block_hash
+    }
+
+    /// Returns the proof of work of the auxiliary block.
+    pub fn get_aux_pow(&self) -> &AuxPow {
+        &self.aux_pow
+    }
+}
+
+/// The merged mining trees committed to by the jobs of a channel.
+///
+/// Trees are looked up by the merkle root committed to in a job's coinbase, so that shares for
+/// jobs created before the current tree was set are still checked against the right auxiliary
+/// blocks.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct MergedMiningTrees {
+    current: Option<MergedMiningTree>,
+    committed: HashMap<[u8; 32], MergedMiningTree>,
+}
+
+impl MergedMiningTrees {
+    pub(crate) fn get_current(&self) -> Option<&MergedMiningTree> {
+        self.current.as_ref()
+    }
+
+    pub(crate) fn set_current(&mut self, tree: Option<MergedMiningTree>) {
+        if let Some(tree) = &tree {
+            self.committed.insert(tree.merkle_root(), tree.clone());
+        }
+        self.current = tree;
+    }
+
+    // Returns the tree committed to by the given coinbase transaction prefix (or merged mining
+    // commitment), if known.
+    pub(crate) fn find(&self, coinbase_tx_prefix: &[u8]) -> Option<&MergedMiningTree> {
+        if self.committed.is_empty() {
+            return None;
+        }
+        self.committed.get(&merged_mining_root(coinbase_tx_prefix)?)
+    }
+
+    // Drops the trees that are neither current nor committed to by the given coinbase transaction
+    // prefixes (or merged mining commitments). To be called upon a chain tip change, with the
+    // prefixes of the jobs still valid.
+    pub(crate) fn retain<'p>(&mut self, coinbase_tx_prefixes: impl Iterator<Item = &'p [u8]>) {
+        let mut roots: HashSet<[u8; 32]> = coinbase_tx_prefixes
+            .filter_map(merged_mining_root)
+            .collect();
+        if let Some(current) = &self.current {
+            roots.insert(current.merkle_root());
+        }
+        self.committed.retain(|root, _| roots.contains(root));
+    }
+
+    pub(crate) fn write_snapshot(&self, writer: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+        match &self.current {
+            Some(tree) => {
+                writer.write_bool(true);
+                write_aux_chains(writer, &tree.aux_chains)?;
+            }
+            None => writer.write_bool(false),
+        }
+        // sorted by merkle root, so that serialization is deterministic
+        let mut committed: Vec<_> = self.committed.iter().collect();
+        committed.sort_by_key(|(root, _)| **root);
+        writer.write_len(committed.len())?;
+        for (_, tree) in committed {
+            write_aux_chains(writer, &tree.aux_chains)?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn read_snapshot(reader: &mut SnapshotReader) -> Result<Self, SnapshotError> {
+        let current = match reader.read_bool()? {
+            true => Some(read_tree(reader)?),
+            false => None,
+        };
+        let mut committed = HashMap::new();
+        for _ in 0..reader.read_len()? {
+            let tree = read_tree(reader)?;
+            committed.insert(tree.merkle_root(), tree);
+        }
+        Ok(Self { current, committed })
+    }
+}
+
+fn hash_pair(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut data = [0u8; 64];
+    data[..32].copy_from_slice(left);
+    data[32..].copy_from_slice(right);
+    Hash::hash(&data).to_byte_array()
+}
+
+fn write_branch(bytes: &mut Vec<u8>, branch: &[[u8; 32]], index: u32) {
+    VarInt(branch.len() as u64)
+        .consensus_encode(bytes)
+        .expect("writing to a Vec can't fail");
+    for hash in branch {
+        bytes.extend_from_slice(hash);
+    }
+    bytes.extend_from_slice(&index.to_le_bytes());
+}
+
+fn write_aux_chains(
+    writer: &mut SnapshotWriter,
+    aux_chains: &[AuxChain],
+) -> Result<(), SnapshotError> {
+    writer.write_len(aux_chains.len())?;
+    for aux_chain in aux_chains {
+        writer.write_u32(aux_chain.chain_id);
+        writer.write_bytes(&aux_chain.aux_block_hash)?;
+        writer.write_target(&aux_chain.target)?;
+    }
+    Ok(())
+}
+
+// Trees are rebuilt from their auxiliary chains, as the slot assignment is deterministic.
+fn read_tree(reader: &mut SnapshotReader) -> Result<MergedMiningTree, SnapshotError> {
+    let mut aux_chains = Vec::new();
+    for _ in 0..reader.read_len()? {
+        let chain_id = reader.read_u32()?;
+        let aux_block_hash = reader
+            .read_bytes()?
+            .try_into()
+            .map_err(|_| SnapshotError::InvalidMergedMining)?;
+        let target = reader.read_target()?;
+        aux_chains.push(AuxChain::new(chain_id, aux_block_hash, target));
+    }
+    MergedMiningTree::new(aux_chains).map_err(|_| SnapshotError::InvalidMergedMining)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn aux_chain(chain_id: u32, byte: u8) -> AuxChain {
+        AuxChain::new(chain_id, [byte; 32], Target::from_le_bytes([0xff; 32]))
+    }
+
+    // recomputes the merkle root from a leaf and its branch
+    fn root_from_branch(leaf: [u8; 32], branch: &[[u8; 32]], mut index: u32) -> [u8; 32] {
+        let mut hash = leaf;
+        for sibling in branch {
+            hash = match index & 1 {
+                0 => hash_pair(&hash, sibling),
+                _ => hash_pair(sibling, &hash),
+            };
+            index >>= 1;
+        }
+        hash
+    }
+
+    #[test]
+    fn test_single_chain_tree() {
+        let tree = MergedMiningTree::new(vec![aux_chain(1, 0xaa)]).unwrap();
+        assert_eq!(tree.get_size(), 1);
+        assert_eq!(tree.get_nonce(), 0);
+        assert_eq!(tree.get_slot(1), Some(0));
+        assert_eq!(tree.merkle_root(), [0xaa; 32]);
+        assert!(tree.merkle_branch(0).is_empty());
+
+        let commitment = tree.commitment();
+        assert_eq!(commitment[..4], MERGED_MINING_HEADER);
+        assert_eq!(commitment[4..36], [0xaa; 32]);
+        assert_eq!(commitment[36..40], 1u32.to_le_bytes());
+        assert_eq!(commitment[40..44], 0u32.to_le_bytes());
+    }
+
+    #[test]
+    fn test_multiple_chains_tree() {
+        let aux_chains = vec![aux_chain(1, 0xaa), aux_chain(2, 0xbb), aux_chain(7, 0xcc)];
+        let tree = MergedMiningTree::new(aux_chains.clone()).unwrap();
+        assert!(tree.get_size() >= 4);
+
+        let root = tree.merkle_root();
+        for aux_chain in &aux_chains {
+            let slot = tree.get_slot(aux_chain.get_chain_id()).unwrap();
+            assert_eq!(
+                slot,
+                merged_mining_slot(
+                    aux_chain.get_chain_id(),
+                    tree.get_nonce(),
+                    tree.get_size().trailing_zeros()
+                )
+            );
+            let branch = tree.merkle_branch(slot);
+            assert_eq!(
+                root_from_branch(aux_chain.get_aux_block_hash(), &branch, slot),
+                root
+            );
+        }
+
+        // the root can be found back from a coinbase prefix embedding the commitment
+        let mut coinbase_tx_prefix = vec![0x01, 0x02, 0x2c];
+        coinbase_tx_prefix.extend(tree.commitment());
+        assert_eq!(merged_mining_root(&coinbase_tx_prefix), Some(root));
+        assert_eq!(merged_mining_root(&[0x01, 0x02]), None);
+    }
+
+    #[test]
+    fn test_invalid_tree() {
+        assert!(matches!(
+            MergedMiningTree::new(vec![]),
+            Err(AuxPowError::NoAuxChains)
+        ));
+        assert!(matches!(
+            MergedMiningTree::new(vec![aux_chain(3, 0xaa), aux_chain(3, 0xbb)]),
+            Err(AuxPowError::DuplicateChainId(3))
+        ));
+
+        // more auxiliary chains than leaves in the largest tree
+        let max_size = 1 << MAX_MERGED_MINING_TREE_HEIGHT;
+        let aux_chains = (0..=max_size)
+            .map(|chain_id| aux_chain(chain_id, 0xaa))
+            .collect();
+        assert!(matches!(
+            MergedMiningTree::new(aux_chains),
+            Err(AuxPowError::NoSlotAssignment)
+        ));
+    }
+
+    #[test]
+    fn test_aux_pow_solutions() {
+        let tree = MergedMiningTree::new(vec![aux_chain(1, 0xaa), aux_chain(2, 0xbb)]).unwrap();
+        let coinbase_tx = Transaction {
+            version: bitcoin::transaction::Version::TWO,
+            lock_time: bitcoin::absolute::LockTime::ZERO,
+            input: vec![bitcoin::TxIn::default()],
+            output: vec![],
+        };
+        let parent_header = Header {
+            version: bitcoin::block::Version::TWO,
+            prev_blockhash: bitcoin::BlockHash::all_zeros(),
+            merkle_root: bitcoin::TxMerkleNode::from_raw_hash(
+                coinbase_tx.compute_txid().to_raw_hash(),
+            ),
+            time: 0,
+            bits: bitcoin::CompactTarget::from_consensus(0),
+            nonce: 0,
+        };
+
+        let solutions =
+            tree.aux_pow_solutions(&parent_header, &consensus::serialize(&coinbase_tx), &[]);
+        assert_eq!(solutions.len(), 2);
+        for solution in &solutions {
+            let aux_pow = solution.get_aux_pow();
+            assert_eq!(aux_pow.get_coinbase_tx(), &coinbase_tx);
+            assert_eq!(
+                aux_pow.get_chain_index(),
+                tree.get_slot(solution.get_chain_id()).unwrap()
+            );
+        }
+
+        // a proof that can't be built is dropped rather than reported as an error
+        let solutions = tree.aux_pow_solutions(&parent_header, &[0x01, 0x02], &[]);
+        assert!(solutions.is_empty());
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let mut trees = MergedMiningTrees::default();
+        let past_tree = MergedMiningTree::new(vec![aux_chain(1, 0xaa)]).unwrap();
+        let current_tree = MergedMiningTree::new(vec![aux_chain(1, 0xbb)]).unwrap();
+        trees.set_current(Some(past_tree.clone()));
+        trees.set_current(Some(current_tree.clone()));
+
+        let mut writer = SnapshotWriter::new();
+        trees.write_snapshot(&mut writer).unwrap();
+        let bytes = writer.into_bytes();
+        let mut reader = SnapshotReader::new(&bytes).unwrap();
+        let mut restored = MergedMiningTrees::read_snapshot(&mut reader).unwrap();
+        reader.finish().unwrap();
+
+        assert_eq!(restored.get_current(), Some(&current_tree));
+        let mut past_prefix = MERGED_MINING_HEADER.to_vec();
+        past_prefix.extend(past_tree.commitment()[4..].iter());
+        assert_eq!(restored.find(&past_prefix), Some(&past_tree));
+
+        // the past tree is dropped once no job commits to it
+        restored.retain(std::iter::empty());
+        assert_eq!(restored.find(&past_prefix), None);
+        assert_eq!(restored.get_current(), Some(&current_tree));
+    }
+}
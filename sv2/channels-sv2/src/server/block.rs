@@ -0,0 +1,377 @@
+//! Block reconstruction - Mining Server Abstraction.
+//!
+//! When share validation returns [`ShareValidationResult::BlockFound`], the caller still needs to
+//! assemble the full block before submitting it to a Bitcoin node. This module provides helpers
+//! that rebuild a [`bitcoin::Block`] from the job, the share and the template's transaction list.
+//!
+//! ## Responsibilities
+//!
+//! - **Header Reconstruction**: Rebuilds the block header from the chain tip, the job and the
+//!   share's `version`, `ntime` and `nonce`.
+//! - **Coinbase Reconstruction**: Rebuilds the coinbase transaction (with BIP141 data) of extended
+//!   jobs from the job's coinbase prefix/suffix and the full extranonce.
+//! - **Validation**: Asserts that the transaction list commits to the header's merkle root and, for
+//!   SegWit blocks, to the coinbase witness commitment.
+//!
+//! ## Usage
+//!
+//! The transaction list is the one carried by `RequestTransactionData.Success` (Template
+//! Distribution) or `ProvideMissingTransactions.Success` (Job Declaration), excluding the coinbase.
+//! It can be passed as `transaction_list.inner_as_ref()`.
+//!
+//! Proof of work is not checked here, as that is already done by share validation. The returned
+//! block can be serialized with [`bitcoin::consensus::serialize`].
+//!
+//! [`ShareValidationResult::BlockFound`]: crate::server::share_accounting::ShareValidationResult::BlockFound
+
+use crate::{
+    chain_tip::ChainTip,
+    merkle_root::merkle_root_from_path,
+    server::{
+        error::BlockReconstructionError,
+        jobs::{extended::ExtendedJob, standard::StandardJob},
+    },
+    target::u256_to_block_hash,
+};
+use bitcoin::{
+    blockdata::block::{Header, Version},
+    consensus,
+    hashes::{sha256d::Hash, Hash as _},
+    Block, CompactTarget, Transaction, TxMerkleNode,
+};
+use mining_sv2::{SubmitSharesExtended, SubmitSharesStandard};
+use std::convert::TryInto;
+
+/// Reconstructs the block solved by a share submitted for an extended job.
+///
+/// The coinbase is rebuilt from the job and the share's extranonce, and `transactions` must be the
+/// serialized non-coinbase transactions of the job's template, in block order.
+pub fn block_from_extended_share<T: AsRef<[u8]>>(
+    job: &ExtendedJob<'_>,
+    share: &SubmitSharesExtended<'_>,
+    chain_tip: &ChainTip,
+    transactions: &[T],
+) -> Result<Block, BlockReconstructionError> {
+    let mut full_extranonce = job.get_extranonce_prefix().clone();
+    full_extranonce.extend(share.extranonce.inner_as_ref());
+
+    let merkle_root: [u8; 32] = merkle_root_from_path(
+        &job.get_coinbase_tx_prefix_without_bip141(),
+        &job.get_coinbase_tx_suffix_without_bip141(),
+        &full_extranonce,
+        &job.get_merkle_path().inner_as_ref(),
+    )
+    .ok_or(BlockReconstructionError::InvalidCoinbase)?
+    .try_into()
+    .expect("merkle root must be 32 bytes");
+
+    let mut coinbase = job.get_coinbase_tx_prefix_with_bip141();
+    coinbase.extend(full_extranonce);
+    coinbase.extend(job.get_coinbase_tx_suffix_with_bip141());
+
+    let header = header(
+        chain_tip,
+        merkle_root,
+        share.version,
+        share.ntime,
+        share.nonce,
+    );
+
+    assemble_block(header, &coinbase, transactions)
+}
+
+/// Reconstructs the block solved by a share submitted for a standard job.
+///
+/// `coinbase` is the serialized coinbase carried by [`ShareValidationResult::BlockFound`], and
+/// `transactions` must be the serialized non-coinbase transactions of the job's template, in block
+/// order.
+///
+/// [`ShareValidationResult::BlockFound`]: crate::server::share_accounting::ShareValidationResult::BlockFound
+pub fn block_from_standard_share<T: AsRef<[u8]>>(
+    job: &StandardJob<'_>,
+    share: &SubmitSharesStandard,
+    chain_tip: &ChainTip,
+    coinbase: &[u8],
+    transactions: &[T],
+) -> Result<Block, BlockReconstructionError> {
+    let merkle_root: [u8; 32] = job
+        .get_merkle_root()
+        .inner_as_ref()
+        .try_into()
+        .expect("merkle root must be 32 bytes");
+
+    let header = header(
+        chain_tip,
+        merkle_root,
+        share.version,
+        share.ntime,
+        share.nonce,
+    );
+
+    assemble_block(header, coinbase, transactions)
+}
+
+fn header(
+    chain_tip: &ChainTip,
+    merkle_root: [u8; 32],
+    version: u32,
+    ntime: u32,
+    nonce: u32,
+) -> Header {
+    Header {
+        version: Version::from_consensus(version as i32),
+        prev_blockhash: u256_to_block_hash(chain_tip.prev_hash()),
+        merkle_root: TxMerkleNode::from_raw_hash(Hash::from_byte_array(merkle_root)),
+        time: ntime,
+        bits: CompactTarget::from_consensus(chain_tip.nbits()),
+        nonce,
+    }
+}
+
+// Deserializes the coinbase and the transaction list, and asserts they commit to the header.
+fn assemble_block<T: AsRef<[u8]>>(
+    header: Header,
+    coinbase: &[u8],
+    transactions: &[T],
+) -> Result<Block, BlockReconstructionError> {
+    let coinbase: Transaction =
+        consensus::deserialize(coinbase).map_err(|_| BlockReconstructionError::InvalidCoinbase)?;
+    if !coinbase.is_coinbase() {
+        return Err(BlockReconstructionError::InvalidCoinbase);
+    }
+
+    let mut txdata = Vec::with_capacity(transactions.len() + 1);
+    txdata.push(coinbase);
+    for (index, transaction) in transactions.iter().enumerate() {
+        let transaction: Transaction = consensus::deserialize(transaction.as_ref())
+            .map_err(|_| BlockReconstructionError::InvalidTransaction(index))?;
+        txdata.push(transaction);
+    }
+
+    let block = Block { header, txdata };
+
+    if !block.check_merkle_root() {
+        return Err(BlockReconstructionError::MerkleRootMismatch);
+    }
+    if !block.check_witness_commitment() {
+        return Err(BlockReconstructionError::WitnessCommitmentMismatch);
+    }
+
+    Ok(block)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::{
+        extended::ExtendedChannel, jobs::job_store::DefaultJobStore,
+        share_accounting::ShareValidationResult, standard::StandardChannel,
+    };
+    use bitcoin::{
+        absolute::LockTime, transaction, Amount, OutPoint, ScriptBuf, Sequence, Target, TxIn,
+        TxOut, Witness,
+    };
+    use template_distribution_sv2::{NewTemplate, SetNewPrevHash};
+
+    fn dummy_transaction(value: u64) -> Transaction {
+        Transaction {
+            version: transaction::Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: OutPoint::new(bitcoin::Txid::from_byte_array([1; 32]), 0),
+                script_sig: ScriptBuf::new(),
+                sequence: Sequence::MAX,
+                witness: Witness::new(),
+            }],
+            output: vec![TxOut {
+                value: Amount::from_sat(value),
+                script_pubkey: ScriptBuf::new(),
+            }],
+        }
+    }
+
+    // Template with `transaction` as its only non-coinbase transaction, and coinbase outputs
+    // claiming the template's whole value.
+    fn segwit_template(transaction: &Transaction) -> (NewTemplate<'static>, Vec<TxOut>) {
+        let txid: [u8; 32] = *transaction.compute_txid().as_ref();
+
+        // the template's witness commitment, computed from the witness root of a block made of the
+        // coinbase (whose wtxid is all zeros) and `transaction` (whose wtxid is its txid)
+        let witness_root = Hash::hash(&[[0; 32], txid].concat());
+        let commitment = Hash::hash(&[witness_root.to_byte_array(), [0; 32]].concat());
+        let mut commitment_script = vec![0x6a, 0x24, 0xaa, 0x21, 0xa9, 0xed];
+        commitment_script.extend(commitment.to_byte_array());
+        let commitment_output = TxOut {
+            value: Amount::ZERO,
+            script_pubkey: ScriptBuf::from(commitment_script),
+        };
+
+        let template = NewTemplate {
+            template_id: 1,
+            future_template: true,
+            version: 536870912,
+            coinbase_tx_version: 2,
+            coinbase_prefix: vec![82, 0].try_into().unwrap(),
+            coinbase_tx_input_sequence: 4294967295,
+            coinbase_tx_value_remaining: 5_000_000_000,
+            coinbase_tx_outputs_count: 1,
+            coinbase_tx_outputs: consensus::serialize(&commitment_output).try_into().unwrap(),
+            coinbase_tx_locktime: 0,
+            merkle_path: vec![txid.into()].try_into().unwrap(),
+        };
+        let coinbase_reward_outputs = vec![TxOut {
+            value: Amount::from_sat(5_000_000_000),
+            script_pubkey: ScriptBuf::new(),
+        }];
+        (template, coinbase_reward_outputs)
+    }
+
+    // Regtest difficulty, met by about half of the shares.
+    fn set_new_prev_hash() -> SetNewPrevHash<'static> {
+        SetNewPrevHash {
+            template_id: 1,
+            prev_hash: [0xaa; 32].into(),
+            header_timestamp: 1745596910,
+            n_bits: 545259519,
+            target: [0xff; 32].into(),
+        }
+    }
+
+    #[test]
+    fn test_block_from_extended_share() {
+        let transaction = dummy_transaction(1_000);
+
+        let mut channel = ExtendedChannel::new_for_pool(
+            1,
+            "user_identity".to_string(),
+            vec![0; 4],
+            Target::from_le_bytes([0xff; 32]),
+            1.0,
+            true,
+            8,
+            100,
+            1.0,
+            DefaultJobStore::new(),
+            "pool".to_string(),
+        )
+        .unwrap();
+
+        let (template, coinbase_reward_outputs) = segwit_template(&transaction);
+        channel
+            .on_new_template(template, coinbase_reward_outputs)
+            .unwrap();
+        channel.on_set_new_prev_hash(set_new_prev_hash()).unwrap();
+        let chain_tip = channel.get_chain_tip().unwrap().clone();
+        let job = channel.get_active_job().unwrap();
+
+        let share = SubmitSharesExtended {
+            channel_id: 1,
+            sequence_number: 0,
+            job_id: job.get_job_id(),
+            nonce: 8,
+            ntime: 1745596971,
+            version: 536870912,
+            extranonce: vec![1, 0, 0, 0, 0, 0, 0, 0].try_into().unwrap(),
+        };
+
+        let transactions = vec![consensus::serialize(&transaction)];
+        let block = block_from_extended_share(job, &share, &chain_tip, &transactions).unwrap();
+        assert_eq!(block.txdata.len(), 2);
+        assert!(block.txdata[0].is_coinbase());
+        assert_eq!(block.header.nonce, 8);
+        assert_eq!(block.header.time, 1745596971);
+        assert!(block.check_witness_commitment());
+
+        // the transaction list doesn't match the job's merkle path
+        let other_transactions = vec![consensus::serialize(&dummy_transaction(2_000))];
+        let res = block_from_extended_share(job, &share, &chain_tip, &other_transactions);
+        assert!(matches!(
+            res,
+            Err(BlockReconstructionError::MerkleRootMismatch)
+        ));
+
+        // the transaction list can't be deserialized
+        let res = block_from_extended_share(job, &share, &chain_tip, &[vec![0u8; 3]]);
+        assert!(matches!(
+            res,
+            Err(BlockReconstructionError::InvalidTransaction(0))
+        ));
+    }
+
+    #[test]
+    fn test_block_from_standard_share() {
+        let transaction = dummy_transaction(1_000);
+
+        let mut channel = StandardChannel::new_for_pool(
+            1,
+            "user_identity".to_string(),
+            vec![0; 32],
+            Target::from_le_bytes([0xff; 32]),
+            1.0,
+            100,
+            1.0,
+            DefaultJobStore::new(),
+            "pool".to_string(),
+        )
+        .unwrap();
+
+        let (template, coinbase_reward_outputs) = segwit_template(&transaction);
+        channel
+            .on_new_template(template, coinbase_reward_outputs)
+            .unwrap();
+        channel.on_set_new_prev_hash(set_new_prev_hash()).unwrap();
+        let chain_tip = channel.get_chain_tip().unwrap().clone();
+        let job = channel.get_active_job().unwrap().clone();
+
+        // roll the nonce until a share finds a block, to get the coinbase from share validation
+        let (share, coinbase) = (0..)
+            .find_map(|nonce| {
+                let share = SubmitSharesStandard {
+                    channel_id: 1,
+                    sequence_number: nonce,
+                    job_id: job.get_job_id(),
+                    nonce,
+                    ntime: 1745596971,
+                    version: 536870912,
+                };
+                match channel.validate_share(share.clone()) {
+                    Ok(ShareValidationResult::BlockFound(_, _, coinbase)) => {
+                        Some((share, coinbase))
+                    }
+                    _ => None,
+                }
+            })
+            .unwrap();
+
+        let transactions = vec![consensus::serialize(&transaction)];
+        let block =
+            block_from_standard_share(&job, &share, &chain_tip, &coinbase, &transactions).unwrap();
+        assert_eq!(block.txdata.len(), 2);
+        assert!(block.txdata[0].is_coinbase());
+        assert_eq!(
+            block.header.merkle_root.to_byte_array().to_vec(),
+            job.get_merkle_root().inner_as_ref().to_vec()
+        );
+        assert_eq!(block.compute_merkle_root(), Some(block.header.merkle_root));
+        assert_eq!(block.header.nonce, share.nonce);
+        assert!(block.check_witness_commitment());
+
+        // the transaction list doesn't match the job's merkle root
+        let other_transactions = vec![consensus::serialize(&dummy_transaction(2_000))];
+        let res =
+            block_from_standard_share(&job, &share, &chain_tip, &coinbase, &other_transactions);
+        assert!(matches!(
+            res,
+            Err(BlockReconstructionError::MerkleRootMismatch)
+        ));
+
+        // the coinbase isn't the one the job commits to
+        let other_coinbase = consensus::serialize(&dummy_transaction(3_000));
+        let res =
+            block_from_standard_share(&job, &share, &chain_tip, &other_coinbase, &transactions);
+        assert!(matches!(
+            res,
+            Err(BlockReconstructionError::InvalidCoinbase)
+        ));
+    }
+}
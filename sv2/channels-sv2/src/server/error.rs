@@ -1,6 +1,9 @@
 //! # Channel Error Types
 
-use crate::server::jobs::error::JobFactoryError;
+use crate::{
+    server::{jobs::error::JobFactoryError, retarget::RetargetError},
+    vardiff::error::VardiffError,
+};
 
 #[derive(Debug)]
 pub enum ExtendedChannelError {
@@ -13,6 +16,16 @@ pub enum ExtendedChannelError {
     RequestedMinExtranonceSizeTooLarge,
     ExtranoncePrefixTooLarge,
     ScriptSigSizeTooLarge,
+    VardiffError(VardiffError),
+}
+
+impl From<RetargetError> for ExtendedChannelError {
+    fn from(error: RetargetError) -> Self {
+        match error {
+            RetargetError::InvalidNominalHashrate => ExtendedChannelError::InvalidNominalHashrate,
+            RetargetError::VardiffError(error) => ExtendedChannelError::VardiffError(error),
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -33,4 +46,54 @@ pub enum StandardChannelError {
     ChainTipNotSet,
     FailedToConvertToStandardJob,
     ScriptSigSizeTooLarge,
+    VardiffError(VardiffError),
+}
+
+impl From<RetargetError> for StandardChannelError {
+    fn from(error: RetargetError) -> Self {
+        match error {
+            RetargetError::InvalidNominalHashrate => StandardChannelError::InvalidNominalHashrate,
+            RetargetError::VardiffError(error) => StandardChannelError::VardiffError(error),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    UnsupportedVersion(u8),
+    UnexpectedEnd,
+    TrailingBytes,
+    TooLarge,
+    InvalidTag(u8),
+    InvalidUtf8,
+    FailedToEncodeMessage,
+    FailedToDecodeMessage,
+    FailedToDecodeCoinbaseOutputs,
+    InvalidMergedMining,
+    InvalidHashrateWindow,
+    InvalidRejectionReason,
+    InvalidUserIdentity,
+}
+
+#[derive(Debug)]
+pub enum BlockReconstructionError {
+    InvalidCoinbase,
+    InvalidTransaction(usize),
+    MerkleRootMismatch,
+    WitnessCommitmentMismatch,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ExtranoncePrefixAllocatorError {
+    InvalidPrefixLength,
+    PrefixSpaceExhausted,
+    UnknownPrefix,
+}
+
+#[derive(Debug)]
+pub enum AuxPowError {
+    NoAuxChains,
+    DuplicateChainId(u32),
+    NoSlotAssignment,
+    InvalidCoinbase,
 }
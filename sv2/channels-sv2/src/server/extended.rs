@@ -19,11 +19,14 @@
 //!   - Past and stale jobs (for share validation over time)
 //! - **Share Validation and Accounting**: Validates shares submitted by the miner, updating
 //!   internal accounting and detecting duplicates or stale submissions. Determines if a share meets
-//!   the channel or network target and responds accordingly.
+//!   the channel or network target and responds accordingly. Shares carrying a worker identity are
+//!   also accounted for per worker.
 //! - **Chain Tip Management**: Tracks the latest known chain tip (previous hash, timestamp, and
 //!   target) for constructing headers and validating shares.
 //! - **Version Rolling**: Honors server configuration on whether version rolling is permitted,
 //!   validating submitted BIP320 header versions accordingly.
+//! - **Merged Mining**: Commits jobs to a [`MergedMiningTree`] and reports the auxiliary chains
+//!   whose targets are met by valid shares.
 //!
 //! ## Usage
 //!
@@ -41,13 +44,28 @@
 
 use crate::{
     chain_tip::ChainTip,
+    clock::{Clock, SystemClock},
     merkle_root::merkle_root_from_path,
     server::{
-        error::ExtendedChannelError,
-        jobs::{extended::ExtendedJob, factory::JobFactory, job_store::JobStore, JobOrigin},
-        share_accounting::{ShareAccounting, ShareValidationError, ShareValidationResult},
+        auxpow::{AuxPowSolution, MergedMiningTree, MergedMiningTrees},
+        error::{ExtendedChannelError, SnapshotError},
+        jobs::{
+            extended::ExtendedJob,
+            factory::JobFactory,
+            job_store::{JobStore, JobStoreSnapshot},
+            script_sig::ScriptSigLayout,
+            JobOrigin,
+        },
+        retarget::Retarget,
+        share_accounting::{
+            ShareAccounting, ShareValidationError, ShareValidationResult,
+            DEFAULT_MAX_NTIME_DRIFT_SECS,
+        },
+        share_dedup::ShareDeduplicator,
+        snapshot::{SnapshotReader, SnapshotWriter},
     },
     target::{bytes_to_hex, hash_rate_to_target, u256_to_block_hash},
+    vardiff::Vardiff,
     MAX_EXTRANONCE_PREFIX_LEN,
 };
 use bitcoin::{
@@ -56,9 +74,12 @@ use bitcoin::{
     transaction::TxOut,
     CompactTarget, Target,
 };
-use mining_sv2::{SetCustomMiningJob, SubmitSharesExtended};
-use std::{collections::HashMap, convert::TryInto, marker::PhantomData};
-use template_distribution_sv2::{NewTemplate, SetNewPrevHash as SetNewPrevHashTdp};
+use extensions_sv2::worker_specific_hashrate_tracking::UserIdentity;
+use mining_sv2::{SetCustomMiningJob, SetTarget, SubmitSharesExtended, UpdateChannel};
+use std::{collections::HashMap, convert::TryInto, marker::PhantomData, sync::Arc};
+use template_distribution_sv2::{
+    CoinbaseOutputConstraints, NewTemplate, SetNewPrevHash as SetNewPrevHashTdp,
+};
 use tracing::debug;
 
 /// Mining Server abstraction of a Sv2 Extended Channel.
@@ -82,6 +103,9 @@ use tracing::debug;
 /// - the channel's share validation state
 /// - the channel's job factory
 /// - the channel's chain tip
+/// - the channel's maximum forward ntime drift
+/// - the channel's share deduplicator (shared with other channels)
+/// - the channel's merged mining trees
 #[derive(Debug)]
 pub struct ExtendedChannel<'a, J>
 where
@@ -99,6 +123,10 @@ where
     share_accounting: ShareAccounting,
     expected_share_per_minute: f32,
     chain_tip: Option<ChainTip>,
+    max_ntime_drift_secs: u64,
+    share_deduplicator: Option<Arc<dyn ShareDeduplicator>>,
+    merged_mining_trees: MergedMiningTrees,
+    clock: Arc<dyn Clock>,
     phantom: PhantomData<&'a ()>,
 }
 
@@ -234,6 +262,8 @@ where
             return Err(ExtendedChannelError::ScriptSigSizeTooLarge);
         }
 
+        let clock: Arc<dyn Clock> = Arc::new(SystemClock);
+
         Ok(Self {
             channel_id,
             user_identity,
@@ -244,9 +274,13 @@ where
             nominal_hashrate,
             job_store,
             job_factory: JobFactory::new(version_rolling_allowed, pool_tag, miner_tag),
-            share_accounting: ShareAccounting::new(share_batch_size),
+            share_accounting: ShareAccounting::new_with_clock(share_batch_size, clock.clone()),
             expected_share_per_minute,
             chain_tip: None,
+            max_ntime_drift_secs: DEFAULT_MAX_NTIME_DRIFT_SECS,
+            share_deduplicator: None,
+            merged_mining_trees: MergedMiningTrees::default(),
+            clock,
             phantom: PhantomData,
         })
     }
@@ -353,6 +387,10 @@ where
     /// - vardiff algorithm estimated a new nominal hashrate, in which case `requested_max_target`
     ///   is `None` and we use the value from the channel state (that was set either during channel
     ///   opening or some previous `UpdateChannel` message).
+    ///
+    /// Unlike [`ExtendedChannel::on_update_channel`] and [`ExtendedChannel::try_vardiff`], a
+    /// target above the requested max target is rejected with
+    /// [`ExtendedChannelError::RequestedMaxTargetOutOfRange`], leaving the channel state unchanged.
     pub fn update_channel(
         &mut self,
         new_nominal_hashrate: f32,
@@ -405,6 +443,53 @@ where
 
         Ok(())
     }
+
+    /// Runs `vardiff` against the channel state, retargeting the channel if it estimates a new
+    /// hashrate.
+    ///
+    /// `vardiff` is expected to be dedicated to this channel, and to be notified of every accepted
+    /// share via [`Vardiff::increment_shares_since_last_update`]. The new target is derived from
+    /// the estimated hashrate and the channel's expected share rate, and clamped to the channel's
+    /// requested max target.
+    ///
+    /// Returns the `SetTarget` message to send downstream if the channel's target changed, or
+    /// `None` otherwise.
+    pub fn try_vardiff(
+        &mut self,
+        vardiff: &mut dyn Vardiff,
+    ) -> Result<Option<SetTarget<'static>>, ExtendedChannelError> {
+        Ok(self.retarget().try_vardiff(vardiff)?)
+    }
+
+    /// Handles an `UpdateChannel` message, retargeting the channel from its nominal hashrate and
+    /// maximum target.
+    ///
+    /// Unlike [`ExtendedChannel::update_channel`], a target above the requested max target is
+    /// clamped to it instead of rejected, as the downstream must be able to keep mining. The
+    /// `vardiff` cycle of the channel is restarted, since the shares counted so far were found
+    /// under the previous target.
+    ///
+    /// Returns the `SetTarget` message to send downstream if the channel's target changed, or
+    /// `None` otherwise.
+    pub fn on_update_channel(
+        &mut self,
+        update_channel: UpdateChannel,
+        vardiff: &mut dyn Vardiff,
+    ) -> Result<Option<SetTarget<'static>>, ExtendedChannelError> {
+        Ok(self.retarget().on_update_channel(update_channel, vardiff)?)
+    }
+
+    // Lends the target state of the channel for retargeting.
+    fn retarget(&mut self) -> Retarget<'_> {
+        Retarget {
+            channel_id: self.channel_id,
+            expected_share_per_minute: self.expected_share_per_minute,
+            nominal_hashrate: &mut self.nominal_hashrate,
+            target: &mut self.target,
+            requested_max_target: &mut self.requested_max_target,
+        }
+    }
+
     /// Returns the currently active job, if any.
     pub fn get_active_job(&self) -> Option<&ExtendedJob<'a>> {
         self.job_store.get_active_job()
@@ -422,6 +507,165 @@ where
         &self.share_accounting
     }
 
+    /// Captures the current state of the channel, including its job store, share accounting and
+    /// job factory.
+    ///
+    /// The snapshot can be serialized with [`ExtendedChannelSnapshot::to_bytes`] and used to
+    /// restore the channel with [`ExtendedChannel::from_snapshot`], so that shares submitted for
+    /// jobs sent before a restart are still accepted.
+    pub fn snapshot(&self) -> ExtendedChannelSnapshot<'a> {
+        ExtendedChannelSnapshot {
+            channel_id: self.channel_id,
+            user_identity: self.user_identity.clone(),
+            extranonce_prefix: self.extranonce_prefix.clone(),
+            rollable_extranonce_size: self.rollable_extranonce_size,
+            requested_max_target: self.requested_max_target,
+            target: self.target,
+            nominal_hashrate: self.nominal_hashrate,
+            job_store: self.job_store.snapshot(),
+            job_factory: self.job_factory.clone(),
+            share_accounting: self.share_accounting.clone(),
+            expected_share_per_minute: self.expected_share_per_minute,
+            chain_tip: self.chain_tip.clone(),
+            max_ntime_drift_secs: self.max_ntime_drift_secs,
+            merged_mining_trees: self.merged_mining_trees.clone(),
+        }
+    }
+
+    /// Restores a channel from a snapshot.
+    ///
+    /// The restored channel has no share deduplicator, see
+    /// [`ExtendedChannel::set_share_deduplicator`].
+    ///
+    /// The restored channel reads time from a [`SystemClock`], see [`ExtendedChannel::set_clock`].
+    pub fn from_snapshot(snapshot: ExtendedChannelSnapshot<'a>) -> Self
+    where
+        J: From<JobStoreSnapshot<ExtendedJob<'a>>>,
+    {
+        let clock: Arc<dyn Clock> = Arc::new(SystemClock);
+        let mut share_accounting = snapshot.share_accounting;
+        share_accounting.set_clock(clock.clone());
+
+        Self {
+            channel_id: snapshot.channel_id,
+            user_identity: snapshot.user_identity,
+            extranonce_prefix: snapshot.extranonce_prefix,
+            rollable_extranonce_size: snapshot.rollable_extranonce_size,
+            requested_max_target: snapshot.requested_max_target,
+            target: snapshot.target,
+            nominal_hashrate: snapshot.nominal_hashrate,
+            job_store: J::from(snapshot.job_store),
+            job_factory: snapshot.job_factory,
+            share_accounting,
+            expected_share_per_minute: snapshot.expected_share_per_minute,
+            chain_tip: snapshot.chain_tip,
+            max_ntime_drift_secs: snapshot.max_ntime_drift_secs,
+            share_deduplicator: None,
+            merged_mining_trees: snapshot.merged_mining_trees,
+            clock,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Returns how far ahead of the current time (in seconds) a share's `ntime` may be.
+    pub fn get_max_ntime_drift_secs(&self) -> u64 {
+        self.max_ntime_drift_secs
+    }
+
+    /// Sets how far ahead of the current time (in seconds) a share's `ntime` may be.
+    ///
+    /// Defaults to [`DEFAULT_MAX_NTIME_DRIFT_SECS`].
+    pub fn set_max_ntime_drift_secs(&mut self, max_ntime_drift_secs: u64) {
+        self.max_ntime_drift_secs = max_ntime_drift_secs;
+    }
+
+    /// Sets the layout of the coinbase scriptSig pushes placed between the BIP34 block height and
+    /// the extranonce, replacing the pool and miner tags.
+    ///
+    /// See [`JobFactory::set_script_sig_layout`].
+    pub fn set_script_sig_layout(&mut self, script_sig_layout: ScriptSigLayout) {
+        self.job_factory.set_script_sig_layout(script_sig_layout);
+    }
+
+    /// Sets the `CoinbaseOutputConstraints` declared to the Template Provider, which jobs created
+    /// from templates must comply with.
+    ///
+    /// See [`JobFactory::set_coinbase_output_constraints`].
+    pub fn set_coinbase_output_constraints(
+        &mut self,
+        coinbase_output_constraints: Option<CoinbaseOutputConstraints>,
+    ) {
+        self.job_factory
+            .set_coinbase_output_constraints(coinbase_output_constraints);
+    }
+
+    /// Returns the merged mining tree committed to by new jobs, if any.
+    pub fn get_merged_mining_tree(&self) -> Option<&MergedMiningTree> {
+        self.merged_mining_trees.get_current()
+    }
+
+    /// Sets the merged mining tree committed to by new jobs, or stops merge-mining if `None`.
+    ///
+    /// Only affects jobs created after this call, see [`JobFactory::set_merged_mining_commitment`].
+    /// Shares for jobs committing to a previous tree are still checked against that tree's
+    /// auxiliary chains, until the next chain tip change.
+    pub fn set_merged_mining_tree(&mut self, merged_mining_tree: Option<MergedMiningTree>) {
+        self.job_factory.set_merged_mining_commitment(
+            merged_mining_tree
+                .as_ref()
+                .map(|merged_mining_tree| merged_mining_tree.commitment()),
+        );
+        self.merged_mining_trees.set_current(merged_mining_tree);
+    }
+
+    /// Returns the share deduplicator shared with other channels, if any.
+    pub fn get_share_deduplicator(&self) -> Option<Arc<dyn ShareDeduplicator>> {
+        self.share_deduplicator.clone()
+    }
+
+    /// Sets the share deduplicator shared with other channels, so that a share accepted on any of
+    /// them is rejected as a duplicate on the others.
+    ///
+    /// The share deduplicator is notified of the channel's chain tip, if any. It is not part of
+    /// the channel snapshot.
+    pub fn set_share_deduplicator(
+        &mut self,
+        share_deduplicator: Option<Arc<dyn ShareDeduplicator>>,
+    ) {
+        self.share_deduplicator = share_deduplicator;
+        self.notify_share_deduplicator();
+    }
+
+    // Notifies the share deduplicator (if any) of the channel's chain tip.
+    fn notify_share_deduplicator(&self) {
+        if let (Some(share_deduplicator), Some(chain_tip)) =
+            (&self.share_deduplicator, &self.chain_tip)
+        {
+            share_deduplicator.on_new_chain_tip(
+                chain_tip
+                    .prev_hash()
+                    .inner_as_ref()
+                    .try_into()
+                    .expect("prev hash must be 32 bytes"),
+            );
+        }
+    }
+
+    /// Returns the time source used by this channel.
+    pub fn get_clock(&self) -> Arc<dyn Clock> {
+        self.clock.clone()
+    }
+
+    /// Replaces the time source used by this channel, its share accounting and its job store.
+    ///
+    /// Channels are created with a [`SystemClock`]. Simulations and tests can inject a
+    /// [`crate::clock::MockClock`] to drive time explicitly.
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.share_accounting.set_clock(clock.clone());
+        self.job_store.set_clock(clock.clone());
+        self.clock = clock;
+    }
+
     /// Updates the channel state with a new template.
     ///
     /// If the template is a future template, the chain tip is not used.
@@ -504,8 +748,20 @@ where
         // clear seen shares, as shares for past chain tip will be rejected as stale
         self.share_accounting.flush_seen_shares();
 
+        // drop the merged mining trees only committed to by stale jobs
+        let coinbase_tx_prefixes: Vec<Vec<u8>> = self
+            .job_store
+            .get_active_job()
+            .into_iter()
+            .chain(self.job_store.get_future_jobs().values())
+            .map(|job| job.get_coinbase_tx_prefix_without_bip141())
+            .collect();
+        self.merged_mining_trees
+            .retain(coinbase_tx_prefixes.iter().map(|prefix| prefix.as_slice()));
+
         // update the chain tip
         self.chain_tip = Some(set_new_prev_hash.into());
+        self.notify_share_deduplicator();
 
         Ok(())
     }
@@ -545,19 +801,82 @@ where
         let min_ntime = set_custom_mining_job_static.min_ntime;
         let new_chain_tip = ChainTip::new(prev_hash, nbits, min_ntime);
         self.chain_tip = Some(new_chain_tip);
+        self.notify_share_deduplicator();
 
         Ok(job_id)
     }
 
     /// Validates a share.
     ///
-    /// Updates the channel state with the result of the share validation.
+    /// Updates the channel state with the result of the share validation. Rejected shares are
+    /// accounted for in the rejection statistics of the channel's [`ShareAccounting`].
+    ///
+    /// Auxiliary chains whose targets are met by the share are not reported, see
+    /// [`ExtendedChannel::validate_share_with_aux_pow`].
     pub fn validate_share(
         &mut self,
         share: SubmitSharesExtended,
     ) -> Result<ShareValidationResult, ShareValidationError> {
+        self.validate_and_account_share(share, None)
+            .map(|(result, _)| result)
+    }
+
+    /// Validates a share submitted on behalf of the worker identified by `user_identity` (as
+    /// carried by the share's Worker-Specific Hashrate Tracking TLV), if any, also reporting the
+    /// auxiliary chains whose targets are met by it as in [`ExtendedChannel::validate_share_with_aux_pow`].
+    ///
+    /// Accepted shares are also accounted for in the worker's statistics, see
+    /// [`ShareAccounting::get_worker_share_accounting`].
+    pub fn validate_share_with_user_identity(
+        &mut self,
+        share: SubmitSharesExtended,
+        user_identity: Option<&UserIdentity>,
+    ) -> Result<(ShareValidationResult, Vec<AuxPowSolution>), ShareValidationError> {
+        self.validate_and_account_share(share, user_identity)
+    }
+
+    /// Validates a share, also reporting the auxiliary chains whose targets are met by it.
+    ///
+    /// Along with the [`ShareValidationResult`], returns an [`AuxPowSolution`] for every auxiliary
+    /// chain committed to by the share's job (see [`ExtendedChannel::set_merged_mining_tree`])
+    /// whose target is met. Only accepted shares are checked against auxiliary targets, and
+    /// proofs that can't be built are logged and left out.
+    ///
+    /// Updates the channel state with the result of the share validation.
+    pub fn validate_share_with_aux_pow(
+        &mut self,
+        share: SubmitSharesExtended,
+    ) -> Result<(ShareValidationResult, Vec<AuxPowSolution>), ShareValidationError> {
+        self.validate_and_account_share(share, None)
+    }
+
+    // Validates a share on behalf of the public `validate_share*` methods, accounting for
+    // rejected shares in the rejection statistics of the channel's `ShareAccounting`, and for
+    // accepted shares in the statistics of the worker identified by `user_identity`, if provided.
+    fn validate_and_account_share(
+        &mut self,
+        share: SubmitSharesExtended,
+        user_identity: Option<&UserIdentity>,
+    ) -> Result<(ShareValidationResult, Vec<AuxPowSolution>), ShareValidationError> {
+        let result = self.check_share(share, user_identity);
+        if let Err(error) = &result {
+            self.share_accounting
+                .update_rejected_share_accounting(error);
+        }
+        result
+    }
+
+    // Validates a share, updating the channel state for accepted shares only.
+    fn check_share(
+        &mut self,
+        share: SubmitSharesExtended,
+        user_identity: Option<&UserIdentity>,
+    ) -> Result<(ShareValidationResult, Vec<AuxPowSolution>), ShareValidationError> {
         let job_id = share.job_id;
 
+        // drop past jobs that exceed the job store's retention policy
+        self.job_store.evict_expired_jobs();
+
         // check if job_id is active job
         let is_active_job = self
             .job_store
@@ -576,6 +895,9 @@ where
 
         // if job_id is not active, past or stale, return error
         if !is_active_job && !is_past_job && !is_stale_job {
+            if self.job_store.is_job_evicted(job_id) {
+                return Err(ShareValidationError::JobEvicted);
+            }
             return Err(ShareValidationError::InvalidJobId);
         }
 
@@ -628,6 +950,14 @@ where
         let prev_hash = chain_tip.prev_hash();
         let nbits = CompactTarget::from_consensus(chain_tip.nbits());
 
+        // validate ntime: it can't be earlier than the chain tip's min_ntime, nor too far ahead
+        // of the current time
+        if share.ntime < chain_tip.min_ntime()
+            || share.ntime as u64 > self.clock.now().saturating_add(self.max_ntime_drift_secs)
+        {
+            return Err(ShareValidationError::InvalidNtime);
+        }
+
         // validate when version rolling is not allowed
         if !job.version_rolling_allowed() {
             // If version rolling is not allowed, ensure bits 13-28 are 0
@@ -638,6 +968,12 @@ where
             }
         }
 
+        // validate version: only the BIP320 general purpose bits (13-28) may differ from the
+        // job's version
+        if (share.version & !0x1fffe000) != (job.get_version() & !0x1fffe000) {
+            return Err(ShareValidationError::InvalidVersion);
+        }
+
         // create the header for validation
         let header = Header {
             version: Version::from_consensus(share.version as i32),
@@ -668,43 +1004,47 @@ where
         );
 
         // check if a block was found
-        if network_target.is_met_by(hash) {
+        let result = if network_target.is_met_by(hash) {
             self.share_accounting.update_share_accounting(
                 self.target.difficulty_float(),
                 share.sequence_number,
                 hash.to_raw_hash(),
             );
+            if let Some(user_identity) = user_identity {
+                self.share_accounting.update_worker_share_accounting(
+                    user_identity,
+                    self.target.difficulty_float(),
+                    hash_as_diff,
+                );
+            }
 
             let mut coinbase = vec![];
             coinbase.extend(job.get_coinbase_tx_prefix_with_bip141());
             coinbase.extend(full_extranonce.clone());
             coinbase.extend(job.get_coinbase_tx_suffix_with_bip141());
 
-            match job.get_origin() {
-                JobOrigin::NewTemplate(template) => {
-                    let template_id = template.template_id;
-                    return Ok(ShareValidationResult::BlockFound(
-                        hash.to_raw_hash(),
-                        Some(template_id),
-                        coinbase,
-                    ));
-                }
-                JobOrigin::SetCustomMiningJob(_set_custom_mining_job) => {
-                    return Ok(ShareValidationResult::BlockFound(
-                        hash.to_raw_hash(),
-                        None,
-                        coinbase,
-                    ));
-                }
-            }
-        }
-
-        // check if the share hash meets the channel target
-        if block_hash_target <= self.target {
+            let template_id = match job.get_origin() {
+                JobOrigin::NewTemplate(template) => Some(template.template_id),
+                JobOrigin::SetCustomMiningJob(_set_custom_mining_job) => None,
+            };
+            ShareValidationResult::BlockFound(hash.to_raw_hash(), template_id, coinbase)
+        } else if block_hash_target <= self.target {
+            // the share hash meets the channel target
             if self.share_accounting.is_share_seen(hash.to_raw_hash()) {
                 return Err(ShareValidationError::DuplicateShare);
             }
 
+            // check if the share was already accepted on another channel
+            if let Some(share_deduplicator) = &self.share_deduplicator {
+                let prev_hash: [u8; 32] = prev_hash
+                    .inner_as_ref()
+                    .try_into()
+                    .expect("prev hash must be 32 bytes");
+                if !share_deduplicator.insert(prev_hash, hash.to_raw_hash()) {
+                    return Err(ShareValidationError::DuplicateShare);
+                }
+            }
+
             self.share_accounting.update_share_accounting(
                 self.target.difficulty_float(),
                 share.sequence_number,
@@ -714,10 +1054,116 @@ where
             // update the best diff
             self.share_accounting.update_best_diff(hash_as_diff);
 
-            Ok(ShareValidationResult::Valid(hash.to_raw_hash()))
+            if let Some(user_identity) = user_identity {
+                self.share_accounting.update_worker_share_accounting(
+                    user_identity,
+                    self.target.difficulty_float(),
+                    hash_as_diff,
+                );
+            }
+
+            ShareValidationResult::Valid(hash.to_raw_hash())
         } else {
-            Err(ShareValidationError::DoesNotMeetTarget)
-        }
+            return Err(ShareValidationError::DoesNotMeetTarget);
+        };
+
+        // check the accepted share against the targets of the auxiliary chains committed to by
+        // the job
+        let aux_pow_solutions = match self
+            .merged_mining_trees
+            .find(&job.get_coinbase_tx_prefix_without_bip141())
+        {
+            Some(merged_mining_tree) => {
+                let mut coinbase_tx = job.get_coinbase_tx_prefix_without_bip141();
+                coinbase_tx.extend(full_extranonce.iter());
+                coinbase_tx.extend(job.get_coinbase_tx_suffix_without_bip141());
+                let coinbase_branch: Vec<[u8; 32]> = job
+                    .get_merkle_path()
+                    .inner_as_ref()
+                    .iter()
+                    .map(|hash| {
+                        (*hash)
+                            .try_into()
+                            .expect("merkle path hashes must be 32 bytes")
+                    })
+                    .collect();
+                merged_mining_tree.aux_pow_solutions(&header, &coinbase_tx, &coinbase_branch)
+            }
+            None => vec![],
+        };
+
+        Ok((result, aux_pow_solutions))
+    }
+}
+
+/// A point-in-time copy of the state of an [`ExtendedChannel`].
+///
+/// Created with [`ExtendedChannel::snapshot`], restored with [`ExtendedChannel::from_snapshot`].
+#[derive(Debug, Clone)]
+pub struct ExtendedChannelSnapshot<'a> {
+    channel_id: u32,
+    user_identity: String,
+    extranonce_prefix: Vec<u8>,
+    rollable_extranonce_size: u16,
+    requested_max_target: Target,
+    target: Target,
+    nominal_hashrate: f32,
+    job_store: JobStoreSnapshot<ExtendedJob<'a>>,
+    job_factory: JobFactory,
+    share_accounting: ShareAccounting,
+    expected_share_per_minute: f32,
+    chain_tip: Option<ChainTip>,
+    max_ntime_drift_secs: u64,
+    merged_mining_trees: MergedMiningTrees,
+}
+
+impl ExtendedChannelSnapshot<'_> {
+    /// Returns the channel ID of the snapshotted channel.
+    pub fn get_channel_id(&self) -> u32 {
+        self.channel_id
+    }
+
+    /// Serializes the snapshot into bytes.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SnapshotError> {
+        let mut writer = SnapshotWriter::new();
+        writer.write_u32(self.channel_id);
+        writer.write_string(&self.user_identity)?;
+        writer.write_bytes(&self.extranonce_prefix)?;
+        writer.write_u16(self.rollable_extranonce_size);
+        writer.write_target(&self.requested_max_target)?;
+        writer.write_target(&self.target)?;
+        writer.write_f32(self.nominal_hashrate);
+        writer.write_bytes(&self.job_store.to_bytes()?)?;
+        self.job_factory.write_snapshot(&mut writer)?;
+        self.share_accounting.write_snapshot(&mut writer)?;
+        writer.write_f32(self.expected_share_per_minute);
+        writer.write_option_chain_tip(self.chain_tip.as_ref())?;
+        writer.write_u64(self.max_ntime_drift_secs);
+        self.merged_mining_trees.write_snapshot(&mut writer)?;
+        Ok(writer.into_bytes())
+    }
+
+    /// Deserializes a snapshot previously serialized with [`ExtendedChannelSnapshot::to_bytes`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<ExtendedChannelSnapshot<'static>, SnapshotError> {
+        let mut reader = SnapshotReader::new(bytes)?;
+        let snapshot = ExtendedChannelSnapshot {
+            channel_id: reader.read_u32()?,
+            user_identity: reader.read_string()?,
+            extranonce_prefix: reader.read_bytes()?.to_vec(),
+            rollable_extranonce_size: reader.read_u16()?,
+            requested_max_target: reader.read_target()?,
+            target: reader.read_target()?,
+            nominal_hashrate: reader.read_f32()?,
+            job_store: JobStoreSnapshot::from_bytes(reader.read_bytes()?)?,
+            job_factory: JobFactory::read_snapshot(&mut reader)?,
+            share_accounting: ShareAccounting::read_snapshot(&mut reader, Arc::new(SystemClock))?,
+            expected_share_per_minute: reader.read_f32()?,
+            chain_tip: reader.read_option_chain_tip()?,
+            max_ntime_drift_secs: reader.read_u64()?,
+            merged_mining_trees: MergedMiningTrees::read_snapshot(&mut reader)?,
+        };
+        reader.finish()?;
+        Ok(snapshot)
     }
 }
 
@@ -725,17 +1171,27 @@ where
 mod tests {
     use crate::{
         chain_tip::ChainTip,
+        clock::MockClock,
         server::{
+            auxpow::{AuxChain, MergedMiningTree},
             error::ExtendedChannelError,
-            extended::ExtendedChannel,
+            extended::{ExtendedChannel, ExtendedChannelSnapshot},
             jobs::job_store::DefaultJobStore,
             share_accounting::{ShareValidationError, ShareValidationResult},
+            share_dedup::{DefaultShareDeduplicator, ShareDeduplicator},
+            test::{
+                coinbase_reward_outputs, easiest_target, empty_template, extended_share,
+                test_chain_tip, TEST_MIN_NTIME, TEST_VERSION,
+            },
         },
+        target::hash_rate_to_target,
+        vardiff::{classic::VardiffState, Vardiff},
     };
     use binary_sv2::Sv2Option;
     use bitcoin::{transaction::TxOut, Amount, ScriptBuf, Target};
-    use mining_sv2::{NewExtendedMiningJob, SubmitSharesExtended};
-    use std::convert::TryInto;
+    use extensions_sv2::worker_specific_hashrate_tracking::UserIdentity;
+    use mining_sv2::{NewExtendedMiningJob, SubmitSharesExtended, UpdateChannel};
+    use std::{convert::TryInto, sync::Arc};
     use template_distribution_sv2::{NewTemplate, SetNewPrevHash};
 
     const SATS_AVAILABLE_IN_TEMPLATE: u64 = 5000000000;
@@ -1440,6 +1896,608 @@ mod tests {
         assert!(matches!(res, Err(ShareValidationError::DuplicateShare)));
     }
 
+    #[test]
+    fn test_snapshot_and_restore() {
+        // note:
+        // the messages on this test were collected from a sane message flow
+        // we use them as test vectors to assert correct behavior of job creation and share
+        // validation
+
+        let channel_id = 1;
+        let user_identity = "user_identity".to_string();
+        let extranonce_prefix = [
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1,
+        ]
+        .to_vec();
+        let max_target = Target::from_le_bytes([0xff; 32]);
+        let expected_share_per_minute = 1.0;
+        let nominal_hashrate = 1_000.0; // bigger hashrate to get higher difficulty
+        let version_rolling_allowed = true;
+        let rollable_extranonce_size = 8u16;
+        let share_batch_size = 100;
+        let job_store = DefaultJobStore::new();
+
+        let mut channel = ExtendedChannel::new(
+            channel_id,
+            user_identity,
+            extranonce_prefix,
+            max_target,
+            nominal_hashrate,
+            version_rolling_allowed,
+            rollable_extranonce_size,
+            share_batch_size,
+            expected_share_per_minute,
+            job_store,
+            None,
+            None,
+        )
+        .unwrap();
+
+        // channel target is:
+        // 0001179d9861a761ffdadd11c307c4fc04eea3a418f7d687584e4434af158205
+
+        let template_id = 1;
+        let template = NewTemplate {
+            template_id,
+            future_template: false,
+            version: 536870912,
+            coinbase_tx_version: 2,
+            coinbase_prefix: vec![82, 0].try_into().unwrap(),
+            coinbase_tx_input_sequence: 4294967295,
+            coinbase_tx_value_remaining: SATS_AVAILABLE_IN_TEMPLATE,
+            coinbase_tx_outputs_count: 1,
+            coinbase_tx_outputs: vec![
+                0, 0, 0, 0, 0, 0, 0, 0, 38, 106, 36, 170, 33, 169, 237, 226, 246, 28, 63, 113, 209,
+                222, 253, 63, 169, 153, 223, 163, 105, 83, 117, 92, 105, 6, 137, 121, 153, 98, 180,
+                139, 235, 216, 54, 151, 78, 140, 249,
+            ]
+            .try_into()
+            .unwrap(),
+            coinbase_tx_locktime: 0,
+            merkle_path: vec![].try_into().unwrap(),
+        };
+
+        // match the original script format used to generate the coinbase_reward_outputs for the
+        // expected job
+        let pubkey_hash = [
+            235, 225, 183, 220, 194, 147, 204, 170, 14, 231, 67, 168, 111, 137, 223, 130, 88, 194,
+            8, 252,
+        ];
+        let mut script_bytes = vec![0]; // SegWit version 0
+        script_bytes.push(20); // Push 20 bytes (length of pubkey hash)
+        script_bytes.extend_from_slice(&pubkey_hash);
+        let script = ScriptBuf::from(script_bytes);
+        let coinbase_reward_outputs = vec![TxOut {
+            value: Amount::from_sat(SATS_AVAILABLE_IN_TEMPLATE),
+            script_pubkey: script,
+        }];
+
+        // network tarkget is: 000000000000d7c0000000000000000000000000000000000000000000000000
+        let n_bits = 453040064;
+        let ntime = 1745611105;
+        let prev_hash = [
+            23, 205, 72, 134, 153, 86, 220, 153, 224, 28, 216, 146, 228, 120, 227, 157, 213, 99,
+            160, 163, 128, 59, 139, 190, 158, 62, 0, 0, 0, 0, 0, 0,
+        ]
+        .into();
+        let chain_tip = ChainTip::new(prev_hash, n_bits, ntime);
+        channel.set_chain_tip(chain_tip);
+
+        // prepare channel with non-future job
+        channel
+            .on_new_template(template.clone(), coinbase_reward_outputs.clone())
+            .unwrap();
+
+        // this share has hash 000004f9d35777e4d56eedc20b1d05d251a7c0ed0b4e3013b5a809852844e218
+        // which does meet the channel target
+        // 0001179d9861a761ffdadd11c307c4fc04eea3a418f7d687584e4434af158205
+        // but does not meet network target
+        // 000000000000d7c0000000000000000000000000000000000000000000000000
+        let valid_share = SubmitSharesExtended {
+            channel_id,
+            sequence_number: 1,
+            job_id: 1,
+            nonce: 51208,
+            ntime: 1745611105,
+            version: 536870912,
+            extranonce: vec![1, 0, 0, 0, 0, 0, 0, 0].try_into().unwrap(),
+        };
+
+        let res = channel.validate_share(valid_share.clone());
+        assert!(matches!(res, Ok(ShareValidationResult::Valid(_))));
+
+        let bytes = channel.snapshot().to_bytes().unwrap();
+        let snapshot = ExtendedChannelSnapshot::from_bytes(&bytes).unwrap();
+        assert_eq!(snapshot.get_channel_id(), channel_id);
+        // serialization is deterministic
+        assert_eq!(snapshot.to_bytes().unwrap(), bytes);
+
+        let mut restored_channel: ExtendedChannel<'static, DefaultJobStore<_>> =
+            ExtendedChannel::from_snapshot(snapshot);
+
+        assert_eq!(restored_channel.get_target(), channel.get_target());
+        assert_eq!(
+            restored_channel.get_active_job().unwrap().get_job_message(),
+            channel.get_active_job().unwrap().get_job_message()
+        );
+        assert_eq!(
+            restored_channel
+                .get_share_accounting()
+                .get_shares_accepted(),
+            1
+        );
+
+        // seen shares survive the restore
+        let repeated_share = SubmitSharesExtended {
+            sequence_number: 2,
+            ..valid_share
+        };
+        let res = restored_channel.validate_share(repeated_share);
+        assert!(matches!(res, Err(ShareValidationError::DuplicateShare)));
+
+        // job ids keep being unique after the restore
+        restored_channel
+            .on_new_template(template, coinbase_reward_outputs)
+            .unwrap();
+        assert_eq!(restored_channel.get_active_job().unwrap().get_job_id(), 2);
+        assert!(restored_channel.get_past_jobs().contains_key(&1));
+    }
+
+    #[test]
+    fn test_share_validation_job_evicted() {
+        let channel_id = 1;
+        let extranonce_prefix = [
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1,
+        ]
+        .to_vec();
+        let max_target = Target::from_le_bytes([0xff; 32]);
+        // keep at most two past jobs, for at most one minute
+        let job_store = DefaultJobStore::new_with_retention(Some(2), Some(60));
+
+        let mut channel = ExtendedChannel::new(
+            channel_id,
+            "user_identity".to_string(),
+            extranonce_prefix,
+            max_target,
+            1_000.0,
+            true,
+            8,
+            100,
+            1.0,
+            job_store,
+            None,
+            None,
+        )
+        .unwrap();
+        let clock = MockClock::new(1745611105);
+        channel.set_clock(Arc::new(clock.clone()));
+
+        channel.set_chain_tip(test_chain_tip());
+
+        // jobs 1, 2, 3 and 4 are created, only jobs 2 and 3 fit in the past job retention
+        for _ in 0..4 {
+            channel
+                .on_new_template(empty_template(1, false), coinbase_reward_outputs())
+                .unwrap();
+            clock.advance(10);
+        }
+        assert_eq!(channel.get_active_job().unwrap().get_job_id(), 4);
+        assert_eq!(channel.get_past_jobs().len(), 2);
+        assert!(channel.get_past_jobs().contains_key(&2));
+        assert!(channel.get_past_jobs().contains_key(&3));
+
+        let share = extended_share(channel_id, 1, 0);
+        let res = channel.validate_share(share.clone());
+        assert!(matches!(res, Err(ShareValidationError::JobEvicted)));
+
+        // unknown jobs are still reported as such
+        let res = channel.validate_share(SubmitSharesExtended {
+            job_id: 42,
+            ..share.clone()
+        });
+        assert!(matches!(res, Err(ShareValidationError::InvalidJobId)));
+
+        // job 2 was replaced 20 seconds ago, so it expires after another 41 seconds
+        clock.advance(41);
+        let res = channel.validate_share(SubmitSharesExtended { job_id: 2, ..share });
+        assert!(matches!(res, Err(ShareValidationError::JobEvicted)));
+        assert!(!channel.get_past_jobs().contains_key(&2));
+        assert!(channel.get_past_jobs().contains_key(&3));
+    }
+
+    #[test]
+    fn test_share_validation_ntime_and_version_bounds() {
+        let channel_id = 1;
+        let extranonce_prefix = [
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1,
+        ]
+        .to_vec();
+        let max_target = Target::from_le_bytes([0xff; 32]);
+
+        let mut channel = ExtendedChannel::new(
+            channel_id,
+            "user_identity".to_string(),
+            extranonce_prefix,
+            max_target,
+            1_000.0,
+            true,
+            8,
+            100,
+            1.0,
+            DefaultJobStore::new(),
+            None,
+            None,
+        )
+        .unwrap();
+        let min_ntime = TEST_MIN_NTIME;
+        let clock = MockClock::new(min_ntime as u64);
+        channel.set_clock(Arc::new(clock.clone()));
+        channel.set_max_ntime_drift_secs(600);
+
+        channel.set_chain_tip(test_chain_tip());
+        channel
+            .on_new_template(empty_template(1, false), coinbase_reward_outputs())
+            .unwrap();
+
+        let share = extended_share(channel_id, 1, 0);
+
+        // ntime earlier than the chain tip's min_ntime
+        let res = channel.validate_share(SubmitSharesExtended {
+            ntime: min_ntime - 1,
+            ..share.clone()
+        });
+        assert!(matches!(res, Err(ShareValidationError::InvalidNtime)));
+
+        // ntime too far ahead of the current time
+        let res = channel.validate_share(SubmitSharesExtended {
+            ntime: min_ntime + 601,
+            ..share.clone()
+        });
+        assert!(matches!(res, Err(ShareValidationError::InvalidNtime)));
+
+        // the drift window moves with the clock
+        clock.advance(1);
+        let res = channel.validate_share(SubmitSharesExtended {
+            ntime: min_ntime + 601,
+            ..share.clone()
+        });
+        assert!(!matches!(res, Err(ShareValidationError::InvalidNtime)));
+
+        // version bits outside of the BIP320 mask can't be rolled
+        let res = channel.validate_share(SubmitSharesExtended {
+            version: TEST_VERSION | 0x1,
+            ..share.clone()
+        });
+        assert!(matches!(res, Err(ShareValidationError::InvalidVersion)));
+        assert_eq!(
+            ShareValidationError::InvalidVersion.as_error_code(),
+            "invalid-version"
+        );
+
+        // version bits inside of the BIP320 mask can be rolled
+        let res = channel.validate_share(SubmitSharesExtended {
+            version: TEST_VERSION | 0x1fffe000,
+            ..share
+        });
+        assert!(!matches!(res, Err(ShareValidationError::InvalidVersion)));
+    }
+
+    #[test]
+    fn test_share_validation_aux_pow() {
+        let channel_id = 1;
+        let mut channel = ExtendedChannel::new_for_pool(
+            channel_id,
+            "user_identity".to_string(),
+            vec![0; 4],
+            Target::from_le_bytes([0xff; 32]),
+            1.0,
+            true,
+            8,
+            100,
+            1.0,
+            DefaultJobStore::new(),
+            "pool".to_string(),
+        )
+        .unwrap();
+        channel.set_target(easiest_target());
+
+        // the share meets the target of chain 1, but not the target of chain 2
+        let merged_mining_tree = MergedMiningTree::new(vec![
+            AuxChain::new(1, [0xaa; 32], Target::from_le_bytes([0xff; 32])),
+            AuxChain::new(2, [0xbb; 32], Target::from_le_bytes([0; 32])),
+        ])
+        .unwrap();
+        channel.set_merged_mining_tree(Some(merged_mining_tree.clone()));
+
+        channel.set_chain_tip(test_chain_tip());
+        channel
+            .on_new_template(empty_template(1, false), coinbase_reward_outputs())
+            .unwrap();
+
+        // the job's coinbase commits to the merged mining tree
+        let commitment = merged_mining_tree.commitment();
+        let coinbase_tx_prefix = channel
+            .get_active_job()
+            .unwrap()
+            .get_coinbase_tx_prefix_without_bip141();
+        assert!(coinbase_tx_prefix
+            .windows(commitment.len())
+            .any(|window| window == commitment));
+
+        let share = extended_share(channel_id, 1, 1);
+        let (res, aux_pow_solutions) = channel.validate_share_with_aux_pow(share).unwrap();
+        assert!(matches!(res, ShareValidationResult::Valid(_)));
+        assert_eq!(aux_pow_solutions.len(), 1);
+
+        let solution = &aux_pow_solutions[0];
+        assert_eq!(solution.get_chain_id(), 1);
+        assert_eq!(solution.get_aux_block_hash(), [0xaa; 32]);
+        let aux_pow = solution.get_aux_pow();
+        assert_eq!(
+            aux_pow.get_chain_index(),
+            merged_mining_tree.get_slot(1).unwrap()
+        );
+        assert_eq!(
+            aux_pow.get_chain_branch(),
+            merged_mining_tree
+                .merkle_branch(aux_pow.get_chain_index())
+                .as_slice()
+        );
+        assert!(aux_pow.get_coinbase_branch().is_empty());
+        // with an empty merkle path, the parent merkle root is the coinbase txid
+        assert_eq!(
+            aux_pow.get_parent_header().merkle_root.to_raw_hash(),
+            aux_pow.get_coinbase_tx().compute_txid().to_raw_hash()
+        );
+
+        // shares for jobs committing to a previous tree are checked against that tree
+        let new_merged_mining_tree = MergedMiningTree::new(vec![AuxChain::new(
+            1,
+            [0xcc; 32],
+            Target::from_le_bytes([0xff; 32]),
+        )])
+        .unwrap();
+        channel.set_merged_mining_tree(Some(new_merged_mining_tree.clone()));
+        let share = extended_share(channel_id, 2, 2);
+        let (_, aux_pow_solutions) = channel.validate_share_with_aux_pow(share).unwrap();
+        assert_eq!(aux_pow_solutions[0].get_aux_block_hash(), [0xaa; 32]);
+
+        // shares of tracked workers are checked against auxiliary targets too
+        let alice = UserIdentity::new("alice").unwrap();
+        let share = extended_share(channel_id, 3, 3);
+        let (_, aux_pow_solutions) = channel
+            .validate_share_with_user_identity(share, Some(&alice))
+            .unwrap();
+        assert_eq!(aux_pow_solutions.len(), 1);
+        assert!(channel
+            .get_share_accounting()
+            .get_worker_share_accounting(&alice)
+            .is_some());
+
+        // the merged mining trees are restored from a snapshot
+        let bytes = channel.snapshot().to_bytes().unwrap();
+        let snapshot = ExtendedChannelSnapshot::from_bytes(&bytes).unwrap();
+        assert_eq!(snapshot.to_bytes().unwrap(), bytes);
+        let mut restored_channel: ExtendedChannel<'static, DefaultJobStore<_>> =
+            ExtendedChannel::from_snapshot(snapshot);
+        assert_eq!(
+            restored_channel.get_merged_mining_tree(),
+            Some(&new_merged_mining_tree)
+        );
+        let share = extended_share(channel_id, 4, 4);
+        let (_, aux_pow_solutions) = restored_channel.validate_share_with_aux_pow(share).unwrap();
+        assert_eq!(aux_pow_solutions[0].get_aux_block_hash(), [0xaa; 32]);
+    }
+
+    #[test]
+    fn test_share_validation_cross_channel_duplicate() {
+        // two channels sharing the same extranonce prefix (and thus the same jobs)
+        let new_channel = |channel_id| {
+            let mut channel = ExtendedChannel::new(
+                channel_id,
+                "user_identity".to_string(),
+                [0; 24].to_vec(),
+                easiest_target(),
+                1_000.0,
+                true,
+                8,
+                100,
+                1.0,
+                DefaultJobStore::new(),
+                None,
+                None,
+            )
+            .unwrap();
+
+            channel.set_target(easiest_target());
+            channel.set_chain_tip(test_chain_tip());
+            channel
+                .on_new_template(empty_template(1, false), coinbase_reward_outputs())
+                .unwrap();
+            channel
+        };
+
+        let share_deduplicator: Arc<dyn ShareDeduplicator> =
+            Arc::new(DefaultShareDeduplicator::new());
+        let mut channel_a = new_channel(1);
+        let mut channel_b = new_channel(2);
+        channel_a.set_share_deduplicator(Some(share_deduplicator.clone()));
+        channel_b.set_share_deduplicator(Some(share_deduplicator.clone()));
+
+        let share = |channel_id, nonce| extended_share(channel_id, 1, nonce);
+
+        let res = channel_a.validate_share(share(1, 1));
+        assert!(matches!(res, Ok(ShareValidationResult::Valid(_))));
+
+        // the same header replayed on the other channel is rejected
+        let res = channel_b.validate_share(share(2, 1));
+        assert!(matches!(res, Err(ShareValidationError::DuplicateShare)));
+
+        let res = channel_b.validate_share(share(2, 2));
+        assert!(matches!(res, Ok(ShareValidationResult::Valid(_))));
+
+        // without the deduplicator, the replayed share would be accepted
+        channel_b.set_share_deduplicator(None);
+        let res = channel_b.validate_share(share(2, 1));
+        assert!(matches!(res, Ok(ShareValidationResult::Valid(_))));
+    }
+
+    #[test]
+    fn test_share_validation_with_user_identity() {
+        let channel_id = 1;
+        let mut channel = ExtendedChannel::new_for_pool(
+            channel_id,
+            "user_identity".to_string(),
+            vec![0; 4],
+            Target::from_le_bytes([0xff; 32]),
+            1.0,
+            true,
+            8,
+            100,
+            1.0,
+            DefaultJobStore::new(),
+            "pool".to_string(),
+        )
+        .unwrap();
+        channel.set_target(easiest_target());
+        channel.set_chain_tip(test_chain_tip());
+        channel
+            .on_new_template(empty_template(1, false), coinbase_reward_outputs())
+            .unwrap();
+
+        let share = |sequence_number, nonce| extended_share(channel_id, sequence_number, nonce);
+        let alice = UserIdentity::new("alice").unwrap();
+        let bob = UserIdentity::new("bob").unwrap();
+
+        for nonce in 1..=3 {
+            let res = channel.validate_share_with_user_identity(share(nonce, nonce), Some(&alice));
+            assert!(matches!(res, Ok((ShareValidationResult::Valid(_), _))));
+        }
+        let res = channel.validate_share_with_user_identity(share(4, 4), Some(&bob));
+        assert!(matches!(res, Ok((ShareValidationResult::Valid(_), _))));
+        // shares without a worker identity are only accounted for at the channel level
+        let res = channel.validate_share(share(5, 5));
+        assert!(matches!(res, Ok(ShareValidationResult::Valid(_))));
+
+        // duplicates are not accounted for
+        let res = channel.validate_share_with_user_identity(share(6, 4), Some(&alice));
+        assert!(matches!(res, Err(ShareValidationError::DuplicateShare)));
+
+        let share_accounting = channel.get_share_accounting();
+        assert_eq!(share_accounting.get_shares_accepted(), 5);
+        let alice_share_accounting = share_accounting
+            .get_worker_share_accounting(&alice)
+            .unwrap();
+        assert_eq!(alice_share_accounting.get_shares_accepted(), 3);
+        assert_eq!(
+            alice_share_accounting.get_share_work_sum(),
+            3.0 * channel.get_target().difficulty_float()
+        );
+        assert!(alice_share_accounting.get_best_diff() > 0.0);
+        assert_eq!(
+            share_accounting
+                .get_worker_share_accounting(&bob)
+                .unwrap()
+                .get_shares_accepted(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_vardiff_retarget() {
+        let extranonce_prefix = [0; 27].to_vec();
+        let max_target = Target::from_le_bytes([0xff; 32]);
+        let mut channel = ExtendedChannel::new(
+            1,
+            "user_identity".to_string(),
+            extranonce_prefix,
+            max_target,
+            10.0,
+            true,
+            4,
+            100,
+            1.0,
+            DefaultJobStore::new(),
+            None,
+            None,
+        )
+        .unwrap();
+
+        let clock = MockClock::new(1_000);
+        let mut vardiff = VardiffState::new_with_clock(1.0, Arc::new(clock.clone())).unwrap();
+
+        // too early to retarget
+        assert!(channel.try_vardiff(&mut vardiff).unwrap().is_none());
+
+        // 100 shares per minute instead of 1
+        for _ in 0..100 {
+            vardiff.increment_shares_since_last_update();
+        }
+        clock.advance(60);
+        let set_target = channel.try_vardiff(&mut vardiff).unwrap().unwrap();
+        assert_eq!(set_target.channel_id, 1);
+        assert_eq!(
+            set_target.maximum_target.inner_as_ref(),
+            channel.get_target().to_le_bytes()
+        );
+        assert_eq!(channel.get_nominal_hashrate(), 30.0);
+        assert_eq!(
+            *channel.get_target(),
+            hash_rate_to_target(30.0, 1.0).unwrap()
+        );
+
+        // the vardiff cycle was restarted
+        assert!(channel.try_vardiff(&mut vardiff).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_on_update_channel_clamps_to_maximum_target() {
+        let extranonce_prefix = [0; 27].to_vec();
+        let mut channel = ExtendedChannel::new(
+            1,
+            "user_identity".to_string(),
+            extranonce_prefix,
+            Target::from_le_bytes([0xff; 32]),
+            10.0,
+            true,
+            4,
+            100,
+            1.0,
+            DefaultJobStore::new(),
+            None,
+            None,
+        )
+        .unwrap();
+        let mut vardiff =
+            VardiffState::new_with_clock(1.0, Arc::new(MockClock::new(1_000))).unwrap();
+        vardiff.increment_shares_since_last_update();
+
+        // a proxy without devices sends a zero hashrate, whose target exceeds maximum_target
+        let maximum_target = hash_rate_to_target(1_000.0, 1.0).unwrap();
+        let update_channel = UpdateChannel {
+            channel_id: 1,
+            nominal_hash_rate: 0.0,
+            maximum_target: maximum_target.to_le_bytes().into(),
+        };
+        let set_target = channel
+            .on_update_channel(update_channel.clone(), &mut vardiff)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            set_target.maximum_target.inner_as_ref(),
+            maximum_target.to_le_bytes()
+        );
+        assert_eq!(*channel.get_target(), maximum_target);
+        assert_eq!(*channel.get_requested_max_target(), maximum_target);
+        assert_eq!(vardiff.shares_since_last_update(), 0);
+
+        // nothing to send if the target doesn't change
+        assert!(channel
+            .on_update_channel(update_channel, &mut vardiff)
+            .unwrap()
+            .is_none());
+    }
+
     #[test]
     fn test_update_channel() {
         let channel_id = 1;
@@ -0,0 +1,336 @@
+//! Extranonce Prefix Allocation - Mining Server Abstraction.
+//!
+//! Every channel of a pool must be assigned a unique extranonce prefix, otherwise miners on
+//! different channels could end up searching the same space and submit identical shares. This
+//! module provides [`ExtranoncePrefixAllocator`], which manages the extranonce prefix space across
+//! all channels of a server, so that prefix uniqueness is guaranteed by the allocator instead of by
+//! convention.
+//!
+//! ## Responsibilities
+//!
+//! - **Allocation**: Hands out unique extranonce prefixes, made of a static prefix (e.g. a server
+//!   identifier, or the extranonce prefix assigned by an upstream) followed by a per-channel part.
+//! - **Reclaiming**: Prefixes released when channels close are handed out again.
+//! - **Exhaustion**: Reports when no prefix is left, instead of wrapping around.
+//! - **Sub-Range Reservation**: Reserves a block of per-channel prefixes as a single shorter
+//!   prefix, leaving more rollable extranonce bytes to a downstream proxy that allocates prefixes
+//!   of its own.
+//!
+//! ## Usage
+//!
+//! Create one allocator per prefix space, allocate a prefix with
+//! [`ExtranoncePrefixAllocator::allocate_prefix`] before opening a channel (e.g. with
+//! [`ExtendedChannel::new_for_pool`]), and release it with
+//! [`ExtranoncePrefixAllocator::release_prefix`] once the channel is closed.
+//!
+//! [`ExtendedChannel::new_for_pool`]: crate::server::extended::ExtendedChannel::new_for_pool
+
+use crate::{server::error::ExtranoncePrefixAllocatorError, MAX_EXTRANONCE_PREFIX_LEN};
+use std::collections::BTreeMap;
+
+/// Maximum length in bytes of the per-channel part of the prefixes handed out by an
+/// [`ExtranoncePrefixAllocator`].
+pub const MAX_CHANNEL_PREFIX_LEN: usize = 8;
+
+/// Allocator of unique extranonce prefixes.
+///
+/// Prefixes are `static_prefix || channel_prefix`, where `channel_prefix` is a big-endian index of
+/// `channel_prefix_len` bytes. The channels using them can roll the remaining
+/// `full_extranonce_size - static_prefix.len() - channel_prefix_len` bytes of the extranonce.
+///
+/// Allocations always take the lowest free index, so released prefixes are reused first. Free
+/// indexes are tracked as ranges, so allocating a prefix doesn't scan the allocated ones.
+#[derive(Debug, Clone)]
+pub struct ExtranoncePrefixAllocator {
+    static_prefix: Vec<u8>,
+    channel_prefix_len: usize,
+    full_extranonce_size: usize,
+    // allocated index ranges, as `first index -> last index`
+    allocations: BTreeMap<u64, u64>,
+    // free index ranges, as `first index -> last index`, never adjacent to each other
+    free_ranges: BTreeMap<u64, u64>,
+}
+
+impl ExtranoncePrefixAllocator {
+    /// Creates a new [`ExtranoncePrefixAllocator`] handing out prefixes made of `static_prefix`
+    /// followed by `channel_prefix_len` bytes, for extranonces of `full_extranonce_size` bytes.
+    ///
+    /// Returns an error if `channel_prefix_len` is `0` or greater than
+    /// [`MAX_CHANNEL_PREFIX_LEN`], or if the prefixes don't fit in `full_extranonce_size`, which
+    /// can't exceed 32 bytes.
+    pub fn new(
+        static_prefix: Vec<u8>,
+        channel_prefix_len: usize,
+        full_extranonce_size: usize,
+    ) -> Result<Self, ExtranoncePrefixAllocatorError> {
+        if channel_prefix_len == 0
+            || channel_prefix_len > MAX_CHANNEL_PREFIX_LEN
+            || full_extranonce_size > MAX_EXTRANONCE_PREFIX_LEN
+            || static_prefix.len() + channel_prefix_len > full_extranonce_size
+        {
+            return Err(ExtranoncePrefixAllocatorError::InvalidPrefixLength);
+        }
+        let last_index = u64::MAX >> (8 * (MAX_CHANNEL_PREFIX_LEN - channel_prefix_len));
+        Ok(Self {
+            static_prefix,
+            channel_prefix_len,
+            full_extranonce_size,
+            allocations: BTreeMap::new(),
+            free_ranges: BTreeMap::from([(0, last_index)]),
+        })
+    }
+
+    /// Returns the static part of every prefix handed out by this allocator.
+    pub fn get_static_prefix(&self) -> &[u8] {
+        &self.static_prefix
+    }
+
+    /// Returns the length of the per-channel part of the prefixes.
+    pub fn get_channel_prefix_len(&self) -> usize {
+        self.channel_prefix_len
+    }
+
+    /// Returns the full extranonce size of the channels using the prefixes.
+    pub fn get_full_extranonce_size(&self) -> usize {
+        self.full_extranonce_size
+    }
+
+    /// Returns the number of extranonce bytes the channels using `prefix` can roll.
+    pub fn get_rollable_extranonce_size(&self, prefix: &[u8]) -> usize {
+        self.full_extranonce_size.saturating_sub(prefix.len())
+    }
+
+    /// Returns the number of allocated prefixes, counting each reserved sub-range once.
+    pub fn get_allocated_count(&self) -> usize {
+        self.allocations.len()
+    }
+
+    /// Returns `true` if `prefix` is currently allocated, either as a channel prefix or as a
+    /// reserved sub-range.
+    pub fn is_allocated(&self, prefix: &[u8]) -> bool {
+        self.parse_prefix(prefix)
+            .is_some_and(|(first, last)| self.allocations.get(&first) == Some(&last))
+    }
+
+    /// Allocates a new extranonce prefix, of `static_prefix.len() + channel_prefix_len` bytes.
+    ///
+    /// Returns [`ExtranoncePrefixAllocatorError::PrefixSpaceExhausted`] if every prefix is
+    /// allocated.
+    pub fn allocate_prefix(&mut self) -> Result<Vec<u8>, ExtranoncePrefixAllocatorError> {
+        self.allocate(0)
+    }
+
+    /// Reserves a sub-range of `256^reserved_bytes` consecutive channel prefixes, returned as a
+    /// single prefix `reserved_bytes` shorter than the ones returned by
+    /// [`ExtranoncePrefixAllocator::allocate_prefix`].
+    ///
+    /// Meant for downstream proxies, which get `reserved_bytes` more rollable extranonce bytes to
+    /// allocate prefixes of their own (e.g. with an [`ExtranoncePrefixAllocator`] using the
+    /// reserved prefix as static prefix). The sub-range is released with
+    /// [`ExtranoncePrefixAllocator::release_prefix`].
+    ///
+    /// `reserved_bytes` must be at least `1` and lower than `channel_prefix_len`.
+    pub fn reserve_sub_range(
+        &mut self,
+        reserved_bytes: usize,
+    ) -> Result<Vec<u8>, ExtranoncePrefixAllocatorError> {
+        if reserved_bytes == 0 || reserved_bytes >= self.channel_prefix_len {
+            return Err(ExtranoncePrefixAllocatorError::InvalidPrefixLength);
+        }
+        self.allocate(reserved_bytes)
+    }
+
+    /// Releases a prefix previously returned by [`ExtranoncePrefixAllocator::allocate_prefix`] or
+    /// [`ExtranoncePrefixAllocator::reserve_sub_range`], so it can be allocated again.
+    ///
+    /// Returns [`ExtranoncePrefixAllocatorError::UnknownPrefix`] if `prefix` is not allocated.
+    pub fn release_prefix(&mut self, prefix: &[u8]) -> Result<(), ExtranoncePrefixAllocatorError> {
+        if !self.is_allocated(prefix) {
+            return Err(ExtranoncePrefixAllocatorError::UnknownPrefix);
+        }
+        let (mut first, mut last) = self
+            .parse_prefix(prefix)
+            .expect("allocated prefixes must be valid");
+        self.allocations.remove(&first);
+
+        // merge the released range with the adjacent free ranges
+        if let Some((&previous_first, &previous_last)) = self.free_ranges.range(..first).next_back()
+        {
+            if previous_last.checked_add(1) == Some(first) {
+                self.free_ranges.remove(&previous_first);
+                first = previous_first;
+            }
+        }
+        if let Some(next_first) = last.checked_add(1) {
+            if let Some(next_last) = self.free_ranges.remove(&next_first) {
+                last = next_last;
+            }
+        }
+        self.free_ranges.insert(first, last);
+        Ok(())
+    }
+
+    // Allocates the lowest free range of `256^reserved_bytes` indexes, aligned to its size.
+    fn allocate(
+        &mut self,
+        reserved_bytes: usize,
+    ) -> Result<Vec<u8>, ExtranoncePrefixAllocatorError> {
+        let size = 1u128 << (8 * reserved_bytes);
+
+        // single prefixes always fit in the first free range, only sub-ranges may need to skip
+        // free ranges too small to hold an aligned block
+        let (free_first, free_last, first) = self
+            .free_ranges
+            .iter()
+            .find_map(|(&free_first, &free_last)| {
+                let first = (free_first as u128).div_ceil(size) * size;
+                (first + size - 1 <= free_last as u128).then_some((free_first, free_last, first))
+            })
+            .ok_or(ExtranoncePrefixAllocatorError::PrefixSpaceExhausted)?;
+        let first = first as u64;
+        let last = first + (size - 1) as u64;
+
+        self.free_ranges.remove(&free_first);
+        if free_first < first {
+            self.free_ranges.insert(free_first, first - 1);
+        }
+        if last < free_last {
+            self.free_ranges.insert(last + 1, free_last);
+        }
+        self.allocations.insert(first, last);
+        let channel_prefix = &first.to_be_bytes()[8 - self.channel_prefix_len..];
+        let mut prefix = self.static_prefix.clone();
+        prefix.extend_from_slice(&channel_prefix[..self.channel_prefix_len - reserved_bytes]);
+        Ok(prefix)
+    }
+
+    // Returns the index range covered by `prefix`, if it belongs to this allocator's space.
+    fn parse_prefix(&self, prefix: &[u8]) -> Option<(u64, u64)> {
+        let channel_prefix = prefix.strip_prefix(self.static_prefix.as_slice())?;
+        if channel_prefix.is_empty() || channel_prefix.len() > self.channel_prefix_len {
+            return None;
+        }
+        let reserved_bytes = self.channel_prefix_len - channel_prefix.len();
+        let mut index_bytes = [0u8; 8];
+        index_bytes[8 - self.channel_prefix_len..8 - reserved_bytes]
+            .copy_from_slice(channel_prefix);
+        let first = u64::from_be_bytes(index_bytes);
+        let size = 1u64 << (8 * reserved_bytes);
+        Some((first, first + (size - 1)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allocate_and_reclaim() {
+        let mut allocator = ExtranoncePrefixAllocator::new(vec![0xaa], 1, 8).unwrap();
+
+        assert_eq!(allocator.allocate_prefix().unwrap(), vec![0xaa, 0]);
+        assert_eq!(allocator.allocate_prefix().unwrap(), vec![0xaa, 1]);
+        assert_eq!(allocator.allocate_prefix().unwrap(), vec![0xaa, 2]);
+        assert_eq!(allocator.get_rollable_extranonce_size(&[0xaa, 2]), 6);
+
+        // released prefixes are reused first
+        allocator.release_prefix(&[0xaa, 1]).unwrap();
+        assert!(!allocator.is_allocated(&[0xaa, 1]));
+        assert_eq!(allocator.allocate_prefix().unwrap(), vec![0xaa, 1]);
+
+        // prefixes that were never allocated can't be released
+        assert_eq!(
+            allocator.release_prefix(&[0xaa, 3]),
+            Err(ExtranoncePrefixAllocatorError::UnknownPrefix)
+        );
+        assert_eq!(
+            allocator.release_prefix(&[0xbb, 0]),
+            Err(ExtranoncePrefixAllocatorError::UnknownPrefix)
+        );
+    }
+
+    #[test]
+    fn test_exhaustion() {
+        let mut allocator = ExtranoncePrefixAllocator::new(vec![], 1, 4).unwrap();
+        for i in 0..=255u8 {
+            assert_eq!(allocator.allocate_prefix().unwrap(), vec![i]);
+        }
+        assert_eq!(
+            allocator.allocate_prefix(),
+            Err(ExtranoncePrefixAllocatorError::PrefixSpaceExhausted)
+        );
+
+        allocator.release_prefix(&[42]).unwrap();
+        assert_eq!(allocator.allocate_prefix().unwrap(), vec![42]);
+    }
+
+    #[test]
+    fn test_reserve_sub_range() {
+        let mut allocator = ExtranoncePrefixAllocator::new(vec![0xaa], 2, 16).unwrap();
+
+        assert_eq!(allocator.allocate_prefix().unwrap(), vec![0xaa, 0, 0]);
+
+        // the sub-range can't overlap with the allocated prefix, so it starts at the next
+        // aligned index
+        let reserved = allocator.reserve_sub_range(1).unwrap();
+        assert_eq!(reserved, vec![0xaa, 1]);
+        assert_eq!(allocator.get_rollable_extranonce_size(&reserved), 14);
+        assert!(allocator.is_allocated(&reserved));
+
+        // single prefixes fill the gap before the sub-range, then continue after it
+        for i in 1..=255u8 {
+            assert_eq!(allocator.allocate_prefix().unwrap(), vec![0xaa, 0, i]);
+        }
+        assert_eq!(allocator.allocate_prefix().unwrap(), vec![0xaa, 2, 0]);
+
+        // prefixes within a reserved sub-range are not allocated individually
+        assert!(!allocator.is_allocated(&[0xaa, 1, 0]));
+        assert_eq!(
+            allocator.release_prefix(&[0xaa, 1, 0]),
+            Err(ExtranoncePrefixAllocatorError::UnknownPrefix)
+        );
+        allocator.release_prefix(&reserved).unwrap();
+        assert_eq!(allocator.allocate_prefix().unwrap(), vec![0xaa, 1, 0]);
+
+        assert_eq!(
+            allocator.reserve_sub_range(2),
+            Err(ExtranoncePrefixAllocatorError::InvalidPrefixLength)
+        );
+    }
+
+    #[test]
+    fn test_released_ranges_are_merged() {
+        let mut allocator = ExtranoncePrefixAllocator::new(vec![], 2, 8).unwrap();
+        for i in 0..=255u8 {
+            assert_eq!(allocator.allocate_prefix().unwrap(), vec![0, i]);
+        }
+        assert_eq!(allocator.reserve_sub_range(1).unwrap(), vec![1]);
+
+        // the released prefixes only fit a sub-range once they are merged back together
+        for i in (0..=255u8).step_by(2) {
+            allocator.release_prefix(&[0, i]).unwrap();
+        }
+        assert_eq!(allocator.reserve_sub_range(1).unwrap(), vec![2]);
+        for i in (1..=255u8).step_by(2) {
+            allocator.release_prefix(&[0, i]).unwrap();
+        }
+        assert_eq!(allocator.reserve_sub_range(1).unwrap(), vec![0]);
+        assert_eq!(allocator.get_allocated_count(), 3);
+
+        // the whole space of the widest channel prefixes can be handed out
+        let mut allocator = ExtranoncePrefixAllocator::new(vec![], 8, 8).unwrap();
+        assert_eq!(allocator.allocate_prefix().unwrap(), vec![0; 8]);
+        assert_eq!(allocator.reserve_sub_range(7).unwrap(), vec![1]);
+        allocator.release_prefix(&[0; 8]).unwrap();
+        allocator.release_prefix(&[1]).unwrap();
+        assert_eq!(allocator.reserve_sub_range(7).unwrap(), vec![0]);
+    }
+
+    #[test]
+    fn test_invalid_layout() {
+        assert!(ExtranoncePrefixAllocator::new(vec![], 0, 8).is_err());
+        assert!(ExtranoncePrefixAllocator::new(vec![], 9, 16).is_err());
+        assert!(ExtranoncePrefixAllocator::new(vec![0; 4], 4, 7).is_err());
+        assert!(ExtranoncePrefixAllocator::new(vec![], 4, 33).is_err());
+    }
+}
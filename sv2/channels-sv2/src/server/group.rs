@@ -4,9 +4,9 @@
 //! (SV2) group channel as maintained by a mining server.
 //!
 //! A group channel represents a logical grouping of standard channels, allowing multiple mining
-//! entities to share jobs. It manages job distribution and activation for all
-//! associated standard channels, but delegates share validation and accounting to those standard
-//! channels.
+//! entities to share jobs. It owns the lifecycle of the extended jobs broadcast to all associated
+//! standard channels, and validates their shares by deriving each member's standard job on demand,
+//! so standard channels don't need to keep their own copy of every group job.
 //!
 //! ## Responsibilities
 //!
@@ -21,29 +21,44 @@
 //! - **Job Lifecycle Management**: Stores jobs received from new templates, including:
 //!   - Future jobs (indexed by `template_id`)
 //!   - Active job (currently being mined)
+//!   - Past jobs (which were active jobs under the current chain tip)
+//!   - Stale jobs (which were past and active jobs under the previous chain tip)
+//! - **Share Validation**: Validates shares submitted on member standard channels for group jobs,
+//!   see [`GroupChannel::validate_share`].
 //! - **Chain Tip Management**: Tracks the latest known chain tip (block height, previous hash,
-//!   timestamp, and target) for constructing headers and activating jobs.
+//!   timestamp, and target) for constructing headers, activating jobs and validating shares.
 //!
 //! ## Notes
 //!
-//! - Share validation and accounting is handled at the standard channel level, not in the group
-//!   channel.
-//! - Past and stale jobs are not tracked in this abstraction.
-//! - Extranonce prefix management is deferred to standard channels; group jobs use an empty prefix.
+//! - Share accounting is handled at the standard channel level, not in the group channel.
+//! - Extranonce prefix management is deferred to standard channels; group jobs use an empty prefix,
+//!   replaced by the member's extranonce prefix when deriving its standard job.
 
 use crate::{
     chain_tip::ChainTip,
     server::{
-        error::GroupChannelError,
-        jobs::{extended::ExtendedJob, factory::JobFactory, job_store::JobStore},
+        error::{GroupChannelError, SnapshotError},
+        jobs::{
+            extended::ExtendedJob,
+            factory::JobFactory,
+            job_store::{JobStore, JobStoreSnapshot},
+            script_sig::ScriptSigLayout,
+            standard::StandardJob,
+        },
+        share_accounting::{ShareValidationError, ShareValidationResult},
+        snapshot::{SnapshotReader, SnapshotWriter},
+        standard::StandardChannel,
     },
 };
 use bitcoin::transaction::TxOut;
+use mining_sv2::SubmitSharesStandard;
 use std::{
     collections::{HashMap, HashSet},
     marker::PhantomData,
 };
-use template_distribution_sv2::{NewTemplate, SetNewPrevHash as SetNewPrevHashTdp};
+use template_distribution_sv2::{
+    CoinbaseOutputConstraints, NewTemplate, SetNewPrevHash as SetNewPrevHashTdp,
+};
 
 /// Abstraction of a Group Channel.
 ///
@@ -54,12 +69,14 @@ use template_distribution_sv2::{NewTemplate, SetNewPrevHash as SetNewPrevHashTdp
 /// - the group channel's future jobs (indexed by `template_id`, to be activated upon receipt of a
 ///   `SetNewPrevHash` message)
 /// - the group channel's active job
+/// - the group channel's past jobs (which were active jobs under the current chain tip, indexed by
+///   `job_id`)
+/// - the group channel's stale jobs (which were past and active jobs under the previous chain tip,
+///   indexed by `job_id`)
 /// - the group channel's chain tip
 ///
-/// Since share validation happens at the Standard Channel level, we don't really keep track of:
-/// - the group channel's past jobs
-/// - the group channel's stale jobs
-/// - the group channel's share validation state
+/// Since share accounting happens at the Standard Channel level, we don't keep track of the group
+/// channel's share validation state.
 #[derive(Debug)]
 pub struct GroupChannel<'a, J>
 where
@@ -189,6 +206,57 @@ where
         self.chain_tip.as_ref()
     }
 
+    /// Sets the layout of the coinbase scriptSig pushes placed between the BIP34 block height and
+    /// the extranonce, replacing the pool and miner tags.
+    ///
+    /// See [`JobFactory::set_script_sig_layout`].
+    pub fn set_script_sig_layout(&mut self, script_sig_layout: ScriptSigLayout) {
+        self.job_factory.set_script_sig_layout(script_sig_layout);
+    }
+
+    /// Sets the `CoinbaseOutputConstraints` declared to the Template Provider, which jobs created
+    /// from templates must comply with.
+    ///
+    /// See [`JobFactory::set_coinbase_output_constraints`].
+    pub fn set_coinbase_output_constraints(
+        &mut self,
+        coinbase_output_constraints: Option<CoinbaseOutputConstraints>,
+    ) {
+        self.job_factory
+            .set_coinbase_output_constraints(coinbase_output_constraints);
+    }
+
+    /// Captures the current state of the group channel, including its job store and job factory.
+    pub fn snapshot(&self) -> GroupChannelSnapshot<'a> {
+        GroupChannelSnapshot {
+            group_channel_id: self.group_channel_id,
+            standard_channel_ids: self.standard_channel_ids.clone(),
+            job_factory: self.job_factory.clone(),
+            job_store: self.job_store.snapshot(),
+            chain_tip: self.chain_tip.clone(),
+            full_extranonce_size: self.full_extranonce_size,
+        }
+    }
+
+    /// Restores a group channel from a snapshot.
+    ///
+    /// A restored [`DefaultJobStore`](crate::server::jobs::job_store::DefaultJobStore) reads time
+    /// from a [`SystemClock`](crate::clock::SystemClock), whatever clock the snapshotted one used.
+    pub fn from_snapshot(snapshot: GroupChannelSnapshot<'a>) -> Self
+    where
+        J: From<JobStoreSnapshot<ExtendedJob<'a>>>,
+    {
+        Self {
+            group_channel_id: snapshot.group_channel_id,
+            standard_channel_ids: snapshot.standard_channel_ids,
+            job_factory: snapshot.job_factory,
+            job_store: J::from(snapshot.job_store),
+            chain_tip: snapshot.chain_tip,
+            full_extranonce_size: snapshot.full_extranonce_size,
+            phantom: PhantomData,
+        }
+    }
+
     /// Only for testing purposes, not meant to be used in real apps.
     #[cfg(test)]
     pub fn set_chain_tip(&mut self, chain_tip: ChainTip) {
@@ -210,6 +278,16 @@ where
         self.job_store.get_future_jobs()
     }
 
+    /// Returns all past jobs for this group channel.
+    pub fn get_past_jobs(&self) -> &HashMap<u32, ExtendedJob<'a>> {
+        self.job_store.get_past_jobs()
+    }
+
+    /// Returns all stale jobs for this group channel.
+    pub fn get_stale_jobs(&self) -> &HashMap<u32, ExtendedJob<'a>> {
+        self.job_store.get_stale_jobs()
+    }
+
     /// Updates the group channel state with a new template.
     ///
     /// If the template is a future template, the chain tip is not used.
@@ -269,7 +347,8 @@ where
     /// If there is a future job matching the `template_id` specified in `SetNewPrevHash`,
     /// this future job is "activated" and set as the active job.
     ///
-    /// Updates the chain tip for the group channel.
+    /// Activating the future job marks past jobs as stale, and the chain tip for the group channel
+    /// is updated.
     /// Returns an error if no matching future job is found.
     pub fn on_set_new_prev_hash(
         &mut self,
@@ -292,17 +371,149 @@ where
 
         Ok(())
     }
+
+    /// Validates a share submitted on `standard_channel`, a member of this group channel, for a
+    /// job broadcast to the group channel.
+    ///
+    /// The member's standard job is derived on demand from the group job, so standard channels
+    /// don't need to receive group jobs via [`StandardChannel::on_group_channel_job`]. The share is
+    /// checked against the member's target, and accounted for in the member's share accounting.
+    /// The member also follows the group channel's chain tip.
+    ///
+    /// Shares submitted on standard channels that are not members of this group channel are
+    /// rejected with [`ShareValidationError::NotGroupMember`], without being accounted for in the
+    /// standard channel's share accounting.
+    pub fn validate_share<S>(
+        &mut self,
+        standard_channel: &mut StandardChannel<'a, S>,
+        share: SubmitSharesStandard,
+    ) -> Result<ShareValidationResult, ShareValidationError>
+    where
+        S: JobStore<StandardJob<'a>>,
+    {
+        if !self
+            .standard_channel_ids
+            .contains(&standard_channel.get_channel_id())
+        {
+            return Err(ShareValidationError::NotGroupMember);
+        }
+
+        let job = self.derive_standard_job(
+            standard_channel.get_channel_id(),
+            standard_channel.get_extranonce_prefix(),
+            share.job_id,
+        );
+        standard_channel.validate_group_share(
+            share,
+            job,
+            self.chain_tip.as_ref(),
+            &self.job_factory,
+        )
+    }
+
+    // Derives the standard job of the member standard channel with `standard_channel_id` and
+    // `extranonce_prefix`, from the group job with `job_id`.
+    fn derive_standard_job(
+        &mut self,
+        standard_channel_id: u32,
+        extranonce_prefix: &[u8],
+        job_id: u32,
+    ) -> Result<StandardJob<'a>, ShareValidationError> {
+        // drop past jobs that exceed the job store's retention policy
+        self.job_store.evict_expired_jobs();
+
+        if self.job_store.get_stale_jobs().contains_key(&job_id) {
+            return Err(ShareValidationError::Stale);
+        }
+
+        let job = match self.job_store.get_active_job() {
+            Some(active_job) if active_job.get_job_id() == job_id => active_job,
+            _ => match self.job_store.get_past_jobs().get(&job_id) {
+                Some(past_job) => past_job,
+                None if self.job_store.is_job_evicted(job_id) => {
+                    return Err(ShareValidationError::JobEvicted);
+                }
+                None => return Err(ShareValidationError::InvalidJobId),
+            },
+        };
+
+        job.to_standard_job(standard_channel_id, extranonce_prefix.to_vec())
+            .map_err(|_| ShareValidationError::Invalid)
+    }
+}
+
+/// A point-in-time copy of the state of a [`GroupChannel`].
+///
+/// Created with [`GroupChannel::snapshot`], restored with [`GroupChannel::from_snapshot`].
+#[derive(Debug, Clone)]
+pub struct GroupChannelSnapshot<'a> {
+    group_channel_id: u32,
+    standard_channel_ids: HashSet<u32>,
+    job_factory: JobFactory,
+    job_store: JobStoreSnapshot<ExtendedJob<'a>>,
+    chain_tip: Option<ChainTip>,
+    full_extranonce_size: usize,
+}
+
+impl GroupChannelSnapshot<'_> {
+    /// Returns the group channel ID of the snapshotted group channel.
+    pub fn get_group_channel_id(&self) -> u32 {
+        self.group_channel_id
+    }
+
+    /// Serializes the snapshot into bytes.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SnapshotError> {
+        let mut writer = SnapshotWriter::new();
+        writer.write_u32(self.group_channel_id);
+        let mut standard_channel_ids: Vec<_> = self.standard_channel_ids.iter().collect();
+        standard_channel_ids.sort();
+        writer.write_len(standard_channel_ids.len())?;
+        for standard_channel_id in standard_channel_ids {
+            writer.write_u32(*standard_channel_id);
+        }
+        self.job_factory.write_snapshot(&mut writer)?;
+        writer.write_bytes(&self.job_store.to_bytes()?)?;
+        writer.write_option_chain_tip(self.chain_tip.as_ref())?;
+        writer.write_u64(self.full_extranonce_size as u64);
+        Ok(writer.into_bytes())
+    }
+
+    /// Deserializes a snapshot previously serialized with [`GroupChannelSnapshot::to_bytes`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<GroupChannelSnapshot<'static>, SnapshotError> {
+        let mut reader = SnapshotReader::new(bytes)?;
+        let group_channel_id = reader.read_u32()?;
+        let mut standard_channel_ids = HashSet::new();
+        for _ in 0..reader.read_len()? {
+            standard_channel_ids.insert(reader.read_u32()?);
+        }
+        let snapshot = GroupChannelSnapshot {
+            group_channel_id,
+            standard_channel_ids,
+            job_factory: JobFactory::read_snapshot(&mut reader)?,
+            job_store: JobStoreSnapshot::from_bytes(reader.read_bytes()?)?,
+            chain_tip: reader.read_option_chain_tip()?,
+            full_extranonce_size: reader.read_u64()? as usize,
+        };
+        reader.finish()?;
+        Ok(snapshot)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::{
         chain_tip::ChainTip,
-        server::{group::GroupChannel, jobs::job_store::DefaultJobStore},
+        server::{
+            group::{GroupChannel, GroupChannelSnapshot},
+            jobs::job_store::DefaultJobStore,
+            share_accounting::{ShareValidationError, ShareValidationResult},
+            standard::StandardChannel,
+            test::{coinbase_reward_outputs, empty_template},
+        },
     };
     use binary_sv2::Sv2Option;
-    use bitcoin::{transaction::TxOut, Amount, ScriptBuf};
-    use mining_sv2::NewExtendedMiningJob;
+    use bitcoin::{transaction::TxOut, Amount, ScriptBuf, Target};
+    use mining_sv2::{NewExtendedMiningJob, SubmitSharesStandard};
     use std::convert::TryInto;
     use template_distribution_sv2::{NewTemplate, SetNewPrevHash};
 
@@ -599,4 +810,222 @@ mod tests {
 
         assert!(group_channel.get_future_jobs().is_empty());
     }
+
+    #[test]
+    fn test_snapshot_and_restore() {
+        let mut group_channel =
+            GroupChannel::new(1, DefaultJobStore::new(), 32, None, None).unwrap();
+        group_channel.add_standard_channel_id(2);
+        group_channel.add_standard_channel_id(3);
+
+        group_channel
+            .on_new_template(empty_template(1, true), coinbase_reward_outputs())
+            .unwrap();
+        group_channel
+            .on_set_new_prev_hash(SetNewPrevHash {
+                template_id: 1,
+                prev_hash: [1; 32].into(),
+                header_timestamp: 1746839905,
+                n_bits: 503543726,
+                target: [0; 32].into(),
+            })
+            .unwrap();
+        group_channel
+            .on_new_template(empty_template(2, false), coinbase_reward_outputs())
+            .unwrap();
+
+        let bytes = group_channel.snapshot().to_bytes().unwrap();
+        let snapshot = GroupChannelSnapshot::from_bytes(&bytes).unwrap();
+        assert_eq!(snapshot.get_group_channel_id(), 1);
+        // serialization is deterministic
+        assert_eq!(snapshot.to_bytes().unwrap(), bytes);
+
+        let mut restored_group_channel: GroupChannel<'static, DefaultJobStore<_>> =
+            GroupChannel::from_snapshot(snapshot);
+        assert_eq!(restored_group_channel.snapshot().to_bytes().unwrap(), bytes);
+        assert_eq!(
+            restored_group_channel.get_standard_channel_ids(),
+            group_channel.get_standard_channel_ids()
+        );
+        assert_eq!(
+            restored_group_channel
+                .get_chain_tip()
+                .map(|chain_tip| chain_tip.prev_hash()),
+            group_channel
+                .get_chain_tip()
+                .map(|chain_tip| chain_tip.prev_hash())
+        );
+        assert_eq!(
+            restored_group_channel
+                .get_active_job()
+                .unwrap()
+                .get_job_message(),
+            group_channel.get_active_job().unwrap().get_job_message()
+        );
+        assert_eq!(
+            restored_group_channel
+                .get_past_jobs()
+                .keys()
+                .collect::<Vec<_>>(),
+            group_channel.get_past_jobs().keys().collect::<Vec<_>>()
+        );
+
+        // job ids keep being unique after the restore
+        restored_group_channel
+            .on_new_template(empty_template(3, false), coinbase_reward_outputs())
+            .unwrap();
+        assert_eq!(
+            restored_group_channel
+                .get_active_job()
+                .unwrap()
+                .get_job_id(),
+            group_channel.get_active_job().unwrap().get_job_id() + 1
+        );
+    }
+
+    #[test]
+    fn test_share_validation_for_member_channels() {
+        let mut group_channel =
+            GroupChannel::new(1, DefaultJobStore::new(), 32, None, None).unwrap();
+
+        let new_template = empty_template;
+        let coinbase_reward_outputs = coinbase_reward_outputs();
+        let new_prev_hash = |template_id: u64, prev_hash: [u8; 32]| SetNewPrevHash {
+            template_id,
+            prev_hash: prev_hash.into(),
+            header_timestamp: 1746839905,
+            n_bits: 503543726,
+            target: [0; 32].into(),
+        };
+
+        // the member channel accepts any share meeting the network's difficulty
+        let mut standard_channel = StandardChannel::new_for_pool(
+            2,
+            "user_identity".to_string(),
+            [[0; 31].as_slice(), &[1]].concat(),
+            Target::from_le_bytes([0xff; 32]),
+            1.0,
+            10,
+            1.0,
+            DefaultJobStore::new(),
+            String::new(),
+        )
+        .unwrap();
+        standard_channel.set_target(Target::from_le_bytes([0xff; 32]));
+        group_channel.add_standard_channel_id(2);
+
+        group_channel
+            .on_new_template(new_template(1, true), coinbase_reward_outputs.clone())
+            .unwrap();
+        group_channel
+            .on_set_new_prev_hash(new_prev_hash(1, [1; 32]))
+            .unwrap();
+        let job_id = group_channel.get_active_job().unwrap().get_job_id();
+
+        let share = |job_id: u32, nonce: u32| SubmitSharesStandard {
+            channel_id: 2,
+            sequence_number: nonce,
+            job_id,
+            nonce,
+            ntime: 1746839905,
+            version: 536870912,
+        };
+
+        let res = group_channel.validate_share(&mut standard_channel, share(job_id, 1));
+        assert!(matches!(res, Ok(ShareValidationResult::Valid(_))));
+        // the member follows the group's chain tip, but doesn't hold the group's jobs
+        assert!(standard_channel.get_chain_tip().is_some());
+        assert!(standard_channel.get_active_job().is_none());
+
+        let res = group_channel.validate_share(&mut standard_channel, share(job_id, 1));
+        assert!(matches!(res, Err(ShareValidationError::DuplicateShare)));
+
+        let res = group_channel.validate_share(&mut standard_channel, share(job_id + 10, 2));
+        assert!(matches!(res, Err(ShareValidationError::InvalidJobId)));
+
+        // a new non-future job turns the active job into a past job, still valid
+        group_channel
+            .on_new_template(new_template(2, false), coinbase_reward_outputs.clone())
+            .unwrap();
+        assert!(group_channel.get_past_jobs().contains_key(&job_id));
+        let res = group_channel.validate_share(&mut standard_channel, share(job_id, 3));
+        assert!(matches!(res, Ok(ShareValidationResult::Valid(_))));
+
+        // a new chain tip turns past jobs into stale jobs
+        group_channel
+            .on_new_template(new_template(3, true), coinbase_reward_outputs)
+            .unwrap();
+        group_channel
+            .on_set_new_prev_hash(new_prev_hash(3, [2; 32]))
+            .unwrap();
+        assert!(group_channel.get_stale_jobs().contains_key(&job_id));
+        let res = group_channel.validate_share(&mut standard_channel, share(job_id, 4));
+        assert!(matches!(res, Err(ShareValidationError::Stale)));
+
+        // channels outside the group are rejected
+        group_channel.remove_standard_channel_id(2);
+        let active_job_id = group_channel.get_active_job().unwrap().get_job_id();
+        let res = group_channel.validate_share(&mut standard_channel, share(active_job_id, 5));
+        assert!(matches!(res, Err(ShareValidationError::NotGroupMember)));
+
+        let share_accounting = standard_channel.get_share_accounting();
+        assert_eq!(share_accounting.get_shares_accepted(), 2);
+        assert_eq!(share_accounting.get_shares_rejected(), 3);
+    }
+
+    #[test]
+    fn test_share_validation_rejects_non_member_channels() {
+        let mut group_channel =
+            GroupChannel::new(1, DefaultJobStore::new(), 32, None, None).unwrap();
+
+        let mut standard_channel = StandardChannel::new_for_pool(
+            2,
+            "user_identity".to_string(),
+            [[0; 31].as_slice(), &[1]].concat(),
+            Target::from_le_bytes([0xff; 32]),
+            1.0,
+            10,
+            1.0,
+            DefaultJobStore::new(),
+            String::new(),
+        )
+        .unwrap();
+
+        group_channel
+            .on_new_template(empty_template(1, true), coinbase_reward_outputs())
+            .unwrap();
+        group_channel
+            .on_set_new_prev_hash(SetNewPrevHash {
+                template_id: 1,
+                prev_hash: [1; 32].into(),
+                header_timestamp: 1746839905,
+                n_bits: 503543726,
+                target: [0; 32].into(),
+            })
+            .unwrap();
+        let job_id = group_channel.get_active_job().unwrap().get_job_id();
+
+        let share = SubmitSharesStandard {
+            channel_id: 2,
+            sequence_number: 1,
+            job_id,
+            nonce: 1,
+            ntime: 1746839905,
+            version: 536870912,
+        };
+
+        // the channel was never added to the group, so its share is rejected before it follows
+        // the group's chain tip, and isn't accounted for
+        let res = group_channel.validate_share(&mut standard_channel, share);
+        assert!(matches!(res, Err(ShareValidationError::NotGroupMember)));
+        assert_eq!(
+            ShareValidationError::NotGroupMember.as_error_code(),
+            "invalid-channel-id"
+        );
+        assert!(standard_channel.get_chain_tip().is_none());
+
+        let share_accounting = standard_channel.get_share_accounting();
+        assert_eq!(share_accounting.get_shares_accepted(), 0);
+        assert_eq!(share_accounting.get_shares_rejected(), 0);
+    }
 }
@@ -29,4 +29,13 @@ pub enum JobFactoryError {
     CoinbaseOutputsSumOverflow,
     InvalidCoinbaseOutputsSum,
     ChainTipRequired,
+    ScriptSigSizeTooLarge,
+    CoinbaseOutputsMaxAdditionalSizeExceeded,
+    CoinbaseOutputsMaxAdditionalSigopsExceeded,
+}
+
+#[derive(Debug)]
+pub enum PayoutPolicyError {
+    NoPayouts,
+    ZeroWeight,
 }
@@ -2,7 +2,11 @@ use super::Job;
 use crate::{
     merkle_root::merkle_root_from_path,
     outputs::deserialize_template_outputs,
-    server::jobs::{error::ExtendedJobError, standard::StandardJob, JobOrigin},
+    server::{
+        error::SnapshotError,
+        jobs::{error::ExtendedJobError, standard::StandardJob, JobOrigin},
+        snapshot::{decode_sv2, SnapshotJob, SnapshotReader, SnapshotWriter},
+    },
 };
 use binary_sv2::{Seq0255, Sv2Option, U256};
 use bitcoin::transaction::TxOut;
@@ -44,6 +48,61 @@ impl Job for ExtendedJob<'_> {
     }
 }
 
+impl SnapshotJob for ExtendedJob<'_> {
+    fn to_snapshot_bytes(&self) -> Result<Vec<u8>, SnapshotError> {
+        let mut writer = SnapshotWriter::new();
+        match &self.origin {
+            JobOrigin::NewTemplate(template) => {
+                writer.write_u8(0);
+                writer.write_sv2(template.clone())?;
+            }
+            JobOrigin::SetCustomMiningJob(custom_job) => {
+                writer.write_u8(1);
+                writer.write_sv2(custom_job.clone())?;
+            }
+        }
+        writer.write_bytes(&self.extranonce_prefix)?;
+        writer.write_tx_outs(&self.coinbase_outputs)?;
+        writer.write_bytes(&self.coinbase_tx_prefix_with_bip141)?;
+        writer.write_bytes(&self.coinbase_tx_suffix_with_bip141)?;
+        writer.write_sv2(self.job_message.clone())?;
+        Ok(writer.into_bytes())
+    }
+
+    fn from_snapshot_bytes(bytes: &[u8]) -> Result<Self, SnapshotError> {
+        let mut reader = SnapshotReader::new(bytes)?;
+        let origin = match reader.read_u8()? {
+            0 => {
+                let mut raw = reader.read_sv2()?;
+                let template: NewTemplate = decode_sv2(&mut raw)?;
+                JobOrigin::NewTemplate(template.into_static())
+            }
+            1 => {
+                let mut raw = reader.read_sv2()?;
+                let custom_job: SetCustomMiningJob = decode_sv2(&mut raw)?;
+                JobOrigin::SetCustomMiningJob(custom_job.into_static())
+            }
+            tag => return Err(SnapshotError::InvalidTag(tag)),
+        };
+        let extranonce_prefix = reader.read_bytes()?.to_vec();
+        let coinbase_outputs = reader.read_tx_outs()?;
+        let coinbase_tx_prefix_with_bip141 = reader.read_bytes()?.to_vec();
+        let coinbase_tx_suffix_with_bip141 = reader.read_bytes()?.to_vec();
+        let mut raw = reader.read_sv2()?;
+        let job_message: NewExtendedMiningJob = decode_sv2(&mut raw)?;
+        reader.finish()?;
+
+        Ok(ExtendedJob {
+            origin,
+            extranonce_prefix,
+            coinbase_outputs,
+            coinbase_tx_prefix_with_bip141,
+            coinbase_tx_suffix_with_bip141,
+            job_message: job_message.into_static(),
+        })
+    }
+}
+
 impl<'a> ExtendedJob<'a> {
     /// Creates a new job from a template.
     ///
@@ -104,6 +163,18 @@ impl<'a> ExtendedJob<'a> {
         self,
         channel_id: u32,
         extranonce_prefix: Vec<u8>,
+    ) -> Result<StandardJob<'a>, ExtendedJobError> {
+        self.to_standard_job(channel_id, extranonce_prefix)
+    }
+
+    /// Creates a `StandardJob` from the `ExtendedJob`, without consuming it.
+    ///
+    /// Same as [`ExtendedJob::into_standard_job`], for callers that need to keep the
+    /// `ExtendedJob` around.
+    pub fn to_standard_job(
+        &self,
+        channel_id: u32,
+        extranonce_prefix: Vec<u8>,
     ) -> Result<StandardJob<'a>, ExtendedJobError> {
         // here we can only convert extended jobs that were created from a template
         let template = match self.get_origin() {
@@ -13,19 +13,34 @@
 //!   messages, assembling all required coinbase transaction data and metadata.
 //! - **Coinbase Output Validation**: Verifies that coinbase outputs match SV2 template constraints
 //!   and protocol rules.
+//! - **Coinbase Budget**: Verifies that the additional coinbase outputs and the coinbase scriptSig
+//!   fit in the size and sigops budget declared to the Template Provider via
+//!   `CoinbaseOutputConstraints`.
 //! - **Version Rolling**: Tracks version rolling allowance for created jobs.
+//! - **ScriptSig Layout**: Composes the coinbase scriptSig from the [`ScriptSigLayout`] pushes.
+//! - **Merged Mining**: Appends the AuxPoW merged mining commitment (see [`auxpow`]) to the
+//!   coinbase scriptSig.
 //!
 //! ## Usage
 //!
 //! Designed for mining server implementations. Use `JobFactory` to generate jobs in response to
 //! incoming SV2 messages (`NewTemplate`, `SetCustomMiningJob`), ensuring protocol correctness and
 //! uniqueness of job IDs.
+//!
+//! [`auxpow`]: crate::server::auxpow
 use crate::{
     bip141::try_strip_bip141,
     chain_tip::ChainTip,
     merkle_root::merkle_root_from_path,
     outputs::deserialize_template_outputs,
-    server::jobs::{error::*, extended::ExtendedJob, standard::StandardJob},
+    server::{
+        auxpow::MERGED_MINING_COMMITMENT_SIZE,
+        error::SnapshotError,
+        jobs::{
+            error::*, extended::ExtendedJob, script_sig::ScriptSigLayout, standard::StandardJob,
+        },
+        snapshot::{SnapshotReader, SnapshotWriter},
+    },
 };
 use binary_sv2::{Sv2Option, B0255};
 use bitcoin::{
@@ -33,11 +48,32 @@ use bitcoin::{
     blockdata::witness::Witness,
     consensus::{serialize, Decodable},
     transaction::{OutPoint, Transaction, TxIn, TxOut, Version},
-    Amount, Sequence,
+    Amount, Script, ScriptBuf, Sequence,
 };
 use mining_sv2::{NewExtendedMiningJob, NewMiningJob, SetCustomMiningJob};
 use std::convert::TryInto;
-use template_distribution_sv2::NewTemplate;
+use template_distribution_sv2::{CoinbaseOutputConstraints, NewTemplate};
+
+/// Maximum size (in bytes) of the coinbase scriptSig, as enforced by consensus.
+pub const MAX_COINBASE_SCRIPT_SIG_SIZE: usize = 100;
+
+// Scale factor between legacy sigops and sigops cost (BIP141).
+const WITNESS_SCALE_FACTOR: usize = 4;
+
+/// Returns the serialized size (in bytes) of the additional coinbase outputs, as accounted for by
+/// `CoinbaseOutputConstraints.coinbase_output_max_additional_size`.
+pub fn coinbase_outputs_serialized_size(outputs: &[TxOut]) -> usize {
+    outputs.iter().map(|output| output.size()).sum()
+}
+
+/// Returns the sigops cost (BIP141) of a script placed in the coinbase, as accounted for by
+/// `CoinbaseOutputConstraints.coinbase_output_max_additional_sigops`.
+///
+/// Coinbase scripts are never executed as witness programs, so their legacy sigops are counted
+/// and scaled by the witness scale factor.
+pub fn coinbase_script_sigops_cost(script: &Script) -> usize {
+    script.count_sigops_legacy() * WITNESS_SCALE_FACTOR
+}
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 struct JobIdFactory {
@@ -68,15 +104,16 @@ impl JobIdFactory {
 pub struct JobFactory {
     job_id_factory: JobIdFactory,
     version_rolling_allowed: bool,
-    pool_tag_string: Option<String>,
-    miner_tag_string: Option<String>,
+    script_sig_layout: ScriptSigLayout,
+    coinbase_output_constraints: Option<CoinbaseOutputConstraints>,
+    merged_mining_commitment: Option<[u8; MERGED_MINING_COMMITMENT_SIZE]>,
 }
 
 impl JobFactory {
     /// Creates a new [`JobFactory`] instance.
     ///
     /// The `pool_tag_string` and `miner_tag_string` are optional and will be added to the coinbase
-    /// scriptSig.
+    /// scriptSig, see [`ScriptSigLayout::pool_miner_tag`].
     ///
     /// Version rolling is always allowed for standard jobs, so the `version_rolling_allowed`
     /// parameter is only relevant for creating extended jobs.
@@ -84,47 +121,176 @@ impl JobFactory {
         version_rolling_allowed: bool,
         pool_tag_string: Option<String>,
         miner_tag_string: Option<String>,
+    ) -> Self {
+        Self::new_with_script_sig_layout(
+            version_rolling_allowed,
+            ScriptSigLayout::pool_miner_tag(
+                pool_tag_string.as_deref(),
+                miner_tag_string.as_deref(),
+            ),
+        )
+    }
+
+    /// Creates a new [`JobFactory`] instance with a custom coinbase scriptSig layout.
+    ///
+    /// See [`ScriptSigLayoutBuilder`](crate::server::jobs::script_sig::ScriptSigLayoutBuilder).
+    pub fn new_with_script_sig_layout(
+        version_rolling_allowed: bool,
+        script_sig_layout: ScriptSigLayout,
     ) -> Self {
         Self {
             job_id_factory: JobIdFactory::new(),
             version_rolling_allowed,
-            pool_tag_string,
-            miner_tag_string,
+            script_sig_layout,
+            coinbase_output_constraints: None,
+            merged_mining_commitment: None,
         }
     }
 
-    /// Returns a byte vector with the OP_PUSHBYTES opcode and the pool+miner tag.
+    /// Returns the layout of the coinbase scriptSig pushes placed between the BIP34 block height
+    /// and the extranonce.
+    pub fn get_script_sig_layout(&self) -> &ScriptSigLayout {
+        &self.script_sig_layout
+    }
+
+    /// Sets the layout of the coinbase scriptSig pushes placed between the BIP34 block height and
+    /// the extranonce.
+    ///
+    /// Only affects jobs created after this call. Job creation fails if the resulting scriptSig
+    /// exceeds [`MAX_COINBASE_SCRIPT_SIG_SIZE`] bytes.
+    pub fn set_script_sig_layout(&mut self, script_sig_layout: ScriptSigLayout) {
+        self.script_sig_layout = script_sig_layout;
+    }
+
+    /// Returns the `CoinbaseOutputConstraints` enforced on template jobs, if any.
+    pub fn get_coinbase_output_constraints(&self) -> Option<CoinbaseOutputConstraints> {
+        self.coinbase_output_constraints
+    }
+
+    /// Sets the `CoinbaseOutputConstraints` declared to the Template Provider.
     ///
-    /// The character `/` is used as a delimiter.
+    /// Once set, creating a job from a template fails if the serialized size of the additional
+    /// coinbase outputs exceeds `coinbase_output_max_additional_size`, or if the sigops cost of the
+    /// additional coinbase outputs and the coinbase scriptSig exceeds
+    /// `coinbase_output_max_additional_sigops`.
     ///
-    /// If no pool or miner tag is provided, the delimiters are still added.
-    pub fn op_pushbytes_pool_miner_tag(&self) -> Result<Vec<u8>, JobFactoryError> {
-        let mut pool_miner_tag = vec![];
-        pool_miner_tag.extend_from_slice(b"/");
-        if let Some(pool_tag_string) = &self.pool_tag_string {
-            pool_miner_tag.extend_from_slice(pool_tag_string.as_bytes());
+    /// The coinbase scriptSig is always limited to [`MAX_COINBASE_SCRIPT_SIG_SIZE`] bytes,
+    /// regardless of the constraints.
+    pub fn set_coinbase_output_constraints(
+        &mut self,
+        coinbase_output_constraints: Option<CoinbaseOutputConstraints>,
+    ) {
+        self.coinbase_output_constraints = coinbase_output_constraints;
+    }
+
+    /// Returns the merged mining commitment pushed in the coinbase scriptSig, if any.
+    pub fn get_merged_mining_commitment(&self) -> Option<[u8; MERGED_MINING_COMMITMENT_SIZE]> {
+        self.merged_mining_commitment
+    }
+
+    /// Sets the merged mining commitment to be pushed in the coinbase scriptSig, after the
+    /// [`ScriptSigLayout`] pushes.
+    ///
+    /// See [`MergedMiningTree::commitment`](crate::server::auxpow::MergedMiningTree::commitment).
+    /// Only affects jobs created after this call. Job creation fails if the resulting scriptSig
+    /// exceeds [`MAX_COINBASE_SCRIPT_SIG_SIZE`] bytes, layouts meant for merged mining should be
+    /// built with [`ScriptSigLayoutBuilder::reserve_merged_mining_commitment`].
+    ///
+    /// [`ScriptSigLayoutBuilder::reserve_merged_mining_commitment`]:
+    /// crate::server::jobs::script_sig::ScriptSigLayoutBuilder::reserve_merged_mining_commitment
+    pub fn set_merged_mining_commitment(
+        &mut self,
+        merged_mining_commitment: Option<[u8; MERGED_MINING_COMMITMENT_SIZE]>,
+    ) {
+        self.merged_mining_commitment = merged_mining_commitment;
+    }
+
+    // Writes the job factory state as part of a channel snapshot, so that job IDs keep being
+    // unique after a restore.
+    pub(crate) fn write_snapshot(&self, writer: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+        writer.write_u32(self.job_id_factory.state);
+        writer.write_bool(self.version_rolling_allowed);
+        writer.write_len(self.script_sig_layout.get_pushes().len())?;
+        for push in self.script_sig_layout.get_pushes() {
+            writer.write_bytes(push)?;
+        }
+        match &self.coinbase_output_constraints {
+            Some(constraints) => {
+                writer.write_bool(true);
+                writer.write_u32(constraints.coinbase_output_max_additional_size);
+                writer.write_u16(constraints.coinbase_output_max_additional_sigops);
+            }
+            None => writer.write_bool(false),
         }
-        pool_miner_tag.extend_from_slice(b"/");
-        if let Some(miner_tag_string) = &self.miner_tag_string {
-            pool_miner_tag.extend_from_slice(miner_tag_string.as_bytes());
+        match &self.merged_mining_commitment {
+            Some(commitment) => {
+                writer.write_bool(true);
+                writer.write_bytes(commitment)?;
+            }
+            None => writer.write_bool(false),
         }
-        pool_miner_tag.extend_from_slice(b"/");
-
-        // Create the proper OP_PUSHBYTES opcode based on data length
-        let op_pushbytes = match pool_miner_tag.len() {
-            // 100 bytes are available for scriptSig
-            // subtract 5 for BIP34
-            // subtract 1 for OP_PUSHBYTES before pool/miner tag
-            // subtract 1+32 for extranonce (OP_PUSHBYTES + 32 bytes max)
-            len @ 1..=61 => len as u8,
-            _ => return Err(JobFactoryError::CoinbaseTxPrefixError),
+        Ok(())
+    }
+
+    // Reads the job factory state written by `write_snapshot`.
+    pub(crate) fn read_snapshot(reader: &mut SnapshotReader) -> Result<Self, SnapshotError> {
+        let job_id_factory = JobIdFactory {
+            state: reader.read_u32()?,
         };
+        let version_rolling_allowed = reader.read_bool()?;
+        let pushes_len = reader.read_len()?;
+        let mut pushes = Vec::new();
+        for _ in 0..pushes_len {
+            pushes.push(reader.read_bytes()?.to_vec());
+        }
+        Ok(Self {
+            job_id_factory,
+            version_rolling_allowed,
+            script_sig_layout: ScriptSigLayout::from_pushes(pushes),
+            coinbase_output_constraints: match reader.read_bool()? {
+                true => Some(CoinbaseOutputConstraints {
+                    coinbase_output_max_additional_size: reader.read_u32()?,
+                    coinbase_output_max_additional_sigops: reader.read_u16()?,
+                }),
+                false => None,
+            },
+            merged_mining_commitment: match reader.read_bool()? {
+                true => Some(
+                    reader
+                        .read_bytes()?
+                        .try_into()
+                        .map_err(|_| SnapshotError::InvalidMergedMining)?,
+                ),
+                false => None,
+            },
+        })
+    }
 
-        let mut op_pushbytes_pool_miner_tag = vec![];
-        op_pushbytes_pool_miner_tag.push(op_pushbytes);
-        op_pushbytes_pool_miner_tag.extend_from_slice(&pool_miner_tag);
+    /// Returns the serialized coinbase scriptSig pushes placed between the BIP34 block height and
+    /// the extranonce (push opcodes included).
+    ///
+    /// With the default layout, this is the OP_PUSHBYTES opcode followed by the pool+miner tag,
+    /// with the character `/` used as a delimiter. The merged mining commitment, if any, is
+    /// pushed last.
+    pub fn op_pushbytes_pool_miner_tag(&self) -> Result<Vec<u8>, JobFactoryError> {
+        self.op_pushbytes_pool_miner_tag_with_commitment(self.merged_mining_commitment)
+    }
 
-        Ok(op_pushbytes_pool_miner_tag)
+    // Same as `op_pushbytes_pool_miner_tag`, with the given merged mining commitment instead of the
+    // current one, so that the scriptSig of a job created with a previous commitment can be
+    // rebuilt.
+    pub(crate) fn op_pushbytes_pool_miner_tag_with_commitment(
+        &self,
+        merged_mining_commitment: Option<[u8; MERGED_MINING_COMMITMENT_SIZE]>,
+    ) -> Result<Vec<u8>, JobFactoryError> {
+        match &merged_mining_commitment {
+            Some(commitment) => {
+                let mut pushes = self.script_sig_layout.get_pushes().to_vec();
+                pushes.push(commitment.to_vec());
+                Ok(ScriptSigLayout::from_pushes(pushes).to_bytes())
+            }
+            None => Ok(self.script_sig_layout.to_bytes()),
+        }
     }
 
     /// Creates a new job from a template.
@@ -141,6 +307,7 @@ impl JobFactory {
     ///
     /// It's up to the caller to ensure that the sum of `additional_coinbase_outputs` is equal to
     /// available template revenue. Returns an error otherwise.
+    /// A [`PayoutPolicy`](crate::server::jobs::payout::PayoutPolicy) can be used to derive them.
     pub fn new_standard_job<'a>(
         &mut self,
         channel_id: u32,
@@ -206,13 +373,14 @@ impl JobFactory {
             }
         };
 
-        let job = StandardJob::from_template(
+        let mut job = StandardJob::from_template(
             template,
             extranonce_prefix,
             additional_coinbase_outputs,
             job_message,
         )
         .map_err(|_| JobFactoryError::DeserializeCoinbaseOutputsError)?;
+        job.set_merged_mining_commitment(self.merged_mining_commitment);
 
         Ok(job)
     }
@@ -228,6 +396,7 @@ impl JobFactory {
     ///
     /// It's up to the caller to ensure that the sum of `additional_coinbase_outputs` is equal to
     /// available template revenue. Returns an error otherwise.
+    /// A [`PayoutPolicy`](crate::server::jobs::payout::PayoutPolicy) can be used to derive them.
     pub fn new_extended_job<'a>(
         &mut self,
         channel_id: u32,
@@ -354,6 +523,9 @@ impl JobFactory {
     ///
     /// It's up to the caller to ensure that the sum of the additional coinbase outputs is equal to
     /// available template revenue.
+    ///
+    /// As for template jobs, the additional coinbase outputs and the coinbase scriptSig are checked
+    /// against the `CoinbaseOutputConstraints` and [`MAX_COINBASE_SCRIPT_SIG_SIZE`].
     #[allow(clippy::too_many_arguments)]
     pub fn new_custom_job<'a>(
         &self,
@@ -390,6 +562,10 @@ impl JobFactory {
         coinbase_prefix.extend_from_slice(&self.op_pushbytes_pool_miner_tag()?);
         coinbase_prefix.push(full_extranonce_size as u8); // OP_PUSHBYTES_X (for the full extranonce)
 
+        let mut script_sig = coinbase_prefix.clone();
+        script_sig.extend_from_slice(&vec![0; full_extranonce_size]);
+        self.check_coinbase_budget(&additional_coinbase_outputs, &ScriptBuf::from(script_sig))?;
+
         let set_custom_mining_job = SetCustomMiningJob {
             channel_id,
             request_id,
@@ -417,6 +593,10 @@ impl JobFactory {
     ///
     /// Assumes that the SetCustomMiningJob message has already been validated.
     ///
+    /// Fails if the coinbase scriptSig exceeds [`MAX_COINBASE_SCRIPT_SIG_SIZE`] bytes. The
+    /// `CoinbaseOutputConstraints` are not enforced, since they were declared to the Template
+    /// Provider of this factory, not to the one the custom job was built from.
+    ///
     /// To be used by Extended Channels on a Sv2 Pool Server.
     pub fn new_extended_job_from_custom_job<'a>(
         &mut self,
@@ -432,8 +612,6 @@ impl JobFactory {
         let coinbase_outputs = Vec::<TxOut>::consensus_decode(&mut serialized_outputs.as_slice())
             .map_err(|_| JobFactoryError::DeserializeCoinbaseOutputsError)?;
 
-        let job_id = self.job_id_factory.next();
-
         let version = set_custom_mining_job.version;
 
         let coinbase_tx_prefix =
@@ -441,6 +619,8 @@ impl JobFactory {
         let coinbase_tx_suffix =
             self.custom_coinbase_tx_suffix(set_custom_mining_job.clone(), full_extranonce_size)?;
 
+        let job_id = self.job_id_factory.next();
+
         // strip bip141 bytes from coinbase_tx_prefix and coinbase_tx_suffix
         let (coinbase_tx_prefix_stripped_bip141, coinbase_tx_suffix_stripped_bip141) =
             try_strip_bip141(&coinbase_tx_prefix, &coinbase_tx_suffix)
@@ -481,6 +661,38 @@ impl JobFactory {
 
 // impl block with private methods
 impl JobFactory {
+    // check that the additional coinbase outputs and the coinbase scriptSig fit in the budget
+    // declared to the Template Provider
+    fn check_coinbase_budget(
+        &self,
+        additional_coinbase_outputs: &[TxOut],
+        script_sig: &Script,
+    ) -> Result<(), JobFactoryError> {
+        if script_sig.len() > MAX_COINBASE_SCRIPT_SIG_SIZE {
+            return Err(JobFactoryError::ScriptSigSizeTooLarge);
+        }
+
+        let Some(constraints) = &self.coinbase_output_constraints else {
+            return Ok(());
+        };
+
+        let size = coinbase_outputs_serialized_size(additional_coinbase_outputs);
+        if size > constraints.coinbase_output_max_additional_size as usize {
+            return Err(JobFactoryError::CoinbaseOutputsMaxAdditionalSizeExceeded);
+        }
+
+        let sigops_cost = coinbase_script_sigops_cost(script_sig)
+            + additional_coinbase_outputs
+                .iter()
+                .map(|output| coinbase_script_sigops_cost(&output.script_pubkey))
+                .sum::<usize>();
+        if sigops_cost > constraints.coinbase_output_max_additional_sigops as usize {
+            return Err(JobFactoryError::CoinbaseOutputsMaxAdditionalSigopsExceeded);
+        }
+
+        Ok(())
+    }
+
     // build a coinbase transaction from a SetCustomMiningJob
     // this is only used to extract coinbase_tx_prefix and coinbase_tx_suffix from the custom
     // coinbase
@@ -497,6 +709,9 @@ impl JobFactory {
         let mut script_sig = vec![];
         script_sig.extend_from_slice(m.coinbase_prefix.inner_as_ref());
         script_sig.extend_from_slice(&vec![0; full_extranonce_size]);
+        if script_sig.len() > MAX_COINBASE_SCRIPT_SIG_SIZE {
+            return Err(JobFactoryError::ScriptSigSizeTooLarge);
+        }
 
         // Create transaction input
         let tx_in = TxIn {
@@ -600,10 +815,13 @@ impl JobFactory {
         script_sig.extend_from_slice(&op_pushbytes_pool_miner_tag);
         script_sig.push(full_extranonce_size as u8); // OP_PUSHBYTES_X (for the full extranonce)
         script_sig.extend_from_slice(&vec![0; full_extranonce_size]);
+        let script_sig = ScriptBuf::from(script_sig);
+
+        self.check_coinbase_budget(&coinbase_reward_outputs, &script_sig)?;
 
         let tx_in = TxIn {
             previous_output: OutPoint::null(),
-            script_sig: script_sig.into(),
+            script_sig,
             sequence: Sequence(template.coinbase_tx_input_sequence),
             witness: Witness::from(vec![vec![0; 32]]), /* note: 32 bytes of zeros is only safe to
                                                         * assume now, this could change in future
@@ -631,11 +849,8 @@ impl JobFactory {
         )?;
         let serialized_coinbase = serialize(&coinbase);
 
-        // Calculate the full pool/miner tag length including delimiters and OP_PUSHBYTES opcode
-        let pool_miner_tag_len = 1 // OP_PUSHBYTES opcode
-            + 3 // three "/" delimiters
-            + self.pool_tag_string.as_ref().map_or(0, |s| s.len())
-            + self.miner_tag_string.as_ref().map_or(0, |s| s.len());
+        // Length of the scriptSig pushes in between the BIP34 height and the extranonce
+        let script_sig_layout_len = self.op_pushbytes_pool_miner_tag()?.len();
 
         let index = 4 // tx version
             + 2 // segwit bytes
@@ -644,7 +859,7 @@ impl JobFactory {
             + 4 // index
             + 1 // bytes in script
             + template.coinbase_prefix.len()
-            + pool_miner_tag_len
+            + script_sig_layout_len
             + 1; // OP_PUSHBYTES_X (for the extranonce)
 
         let coinbase_tx_prefix = serialized_coinbase[0..index].to_vec();
@@ -665,11 +880,8 @@ impl JobFactory {
         )?;
         let serialized_coinbase = serialize(&coinbase);
 
-        // Calculate the full pool/miner tag length including delimiters and OP_PUSHBYTES opcode
-        let pool_miner_tag_len = 1 // OP_PUSHBYTES opcode
-            + 3 // three "/" delimiters
-            + self.pool_tag_string.as_ref().map_or(0, |s| s.len())
-            + self.miner_tag_string.as_ref().map_or(0, |s| s.len());
+        // Length of the scriptSig pushes in between the BIP34 height and the extranonce
+        let script_sig_layout_len = self.op_pushbytes_pool_miner_tag()?.len();
 
         let coinbase_tx_suffix = serialized_coinbase[4 // tx version
             + 2 // segwit bytes
@@ -678,7 +890,7 @@ impl JobFactory {
             + 4 // index
             + 1 // bytes in script
             + template.coinbase_prefix.len()
-            + pool_miner_tag_len
+            + script_sig_layout_len
             + 1 // OP_PUSHBYTES_X (for the full extranonce)
             + full_extranonce_size..]
             .to_vec();
@@ -690,7 +902,8 @@ impl JobFactory {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use bitcoin::ScriptBuf;
+    use crate::server::jobs::script_sig::ScriptSigLayoutBuilder;
+    use bitcoin::{hashes::Hash as _, ScriptBuf};
     use template_distribution_sv2::NewTemplate;
 
     #[test]
@@ -784,6 +997,122 @@ mod tests {
         assert_eq!(job.get_job_message(), &expected_job);
     }
 
+    #[test]
+    fn test_new_job_with_script_sig_layout() {
+        let script_sig_layout = ScriptSigLayoutBuilder::new(32)
+            .push_pool_miner_tag(Some("pool"), None)
+            .unwrap()
+            .push(&[0xfa, 0xbe, 0x6d, 0x6d])
+            .unwrap()
+            .build();
+        let mut job_factory = JobFactory::new_with_script_sig_layout(true, script_sig_layout);
+
+        let template = NewTemplate {
+            template_id: 1,
+            future_template: true,
+            version: 536870912,
+            coinbase_tx_version: 2,
+            coinbase_prefix: vec![82, 0].try_into().unwrap(),
+            coinbase_tx_input_sequence: 4294967295,
+            coinbase_tx_value_remaining: 5000000000,
+            coinbase_tx_outputs_count: 0,
+            coinbase_tx_outputs: vec![].try_into().unwrap(),
+            coinbase_tx_locktime: 0,
+            merkle_path: vec![].try_into().unwrap(),
+        };
+        let coinbase_reward_outputs = vec![TxOut {
+            value: Amount::from_sat(5000000000),
+            script_pubkey: ScriptBuf::new(),
+        }];
+
+        let job = job_factory
+            .new_extended_job(1, None, vec![0; 4], template, coinbase_reward_outputs, 32)
+            .unwrap();
+
+        // the prefix ends with the BIP34 height, the layout pushes and the extranonce push opcode
+        let mut expected_script_sig_start = vec![82, 0];
+        expected_script_sig_start.extend_from_slice(b"\x07/pool//");
+        expected_script_sig_start.extend_from_slice(&[4, 0xfa, 0xbe, 0x6d, 0x6d]);
+        expected_script_sig_start.push(32);
+        let coinbase_tx_prefix = job.get_coinbase_tx_prefix_without_bip141();
+        assert!(coinbase_tx_prefix.ends_with(&expected_script_sig_start));
+    }
+
+    #[test]
+    fn test_coinbase_output_constraints() {
+        let mut job_factory = JobFactory::new(true, Some("pool".to_string()), None);
+
+        let template = NewTemplate {
+            template_id: 1,
+            future_template: true,
+            version: 536870912,
+            coinbase_tx_version: 2,
+            coinbase_prefix: vec![82, 0].try_into().unwrap(),
+            coinbase_tx_input_sequence: 4294967295,
+            coinbase_tx_value_remaining: 5000000000,
+            coinbase_tx_outputs_count: 0,
+            coinbase_tx_outputs: vec![].try_into().unwrap(),
+            coinbase_tx_locktime: 0,
+            merkle_path: vec![].try_into().unwrap(),
+        };
+
+        // P2PKH: 34 bytes serialized, 1 legacy sigop (OP_CHECKSIG)
+        let coinbase_reward_outputs = vec![TxOut {
+            value: Amount::from_sat(5000000000),
+            script_pubkey: ScriptBuf::new_p2pkh(&bitcoin::PubkeyHash::from_byte_array([1; 20])),
+        }];
+        assert_eq!(
+            coinbase_outputs_serialized_size(&coinbase_reward_outputs),
+            34
+        );
+        assert_eq!(
+            coinbase_script_sigops_cost(&coinbase_reward_outputs[0].script_pubkey),
+            4
+        );
+
+        let new_job = |job_factory: &mut JobFactory, full_extranonce_size| {
+            job_factory.new_extended_job(
+                1,
+                None,
+                vec![0; 4],
+                template.clone(),
+                coinbase_reward_outputs.clone(),
+                full_extranonce_size,
+            )
+        };
+
+        job_factory.set_coinbase_output_constraints(Some(CoinbaseOutputConstraints {
+            coinbase_output_max_additional_size: 34,
+            coinbase_output_max_additional_sigops: 4,
+        }));
+        assert!(new_job(&mut job_factory, 32).is_ok());
+
+        job_factory.set_coinbase_output_constraints(Some(CoinbaseOutputConstraints {
+            coinbase_output_max_additional_size: 33,
+            coinbase_output_max_additional_sigops: 4,
+        }));
+        assert!(matches!(
+            new_job(&mut job_factory, 32),
+            Err(JobFactoryError::CoinbaseOutputsMaxAdditionalSizeExceeded)
+        ));
+
+        job_factory.set_coinbase_output_constraints(Some(CoinbaseOutputConstraints {
+            coinbase_output_max_additional_size: 34,
+            coinbase_output_max_additional_sigops: 3,
+        }));
+        assert!(matches!(
+            new_job(&mut job_factory, 32),
+            Err(JobFactoryError::CoinbaseOutputsMaxAdditionalSigopsExceeded)
+        ));
+
+        // the scriptSig size is limited regardless of the constraints
+        job_factory.set_coinbase_output_constraints(None);
+        assert!(matches!(
+            new_job(&mut job_factory, 90),
+            Err(JobFactoryError::ScriptSigSizeTooLarge)
+        ));
+    }
+
     #[test]
     fn test_new_extended_job_from_custom_job() {
         let jdc_job_factory = JobFactory::new(
@@ -843,6 +1172,20 @@ mod tests {
             1746839905,
         );
 
+        // the coinbase scriptSig of custom jobs is limited too
+        assert!(matches!(
+            jdc_job_factory.new_custom_job(
+                1,
+                1,
+                vec![0].try_into().unwrap(),
+                chain_tip.clone(),
+                template.clone(),
+                coinbase_reward_outputs.clone(),
+                60,
+            ),
+            Err(JobFactoryError::ScriptSigSizeTooLarge)
+        ));
+
         let set_custom_mining_job = jdc_job_factory
             .new_custom_job(
                 1,
@@ -858,6 +1201,15 @@ mod tests {
         let mut pool_job_factory =
             JobFactory::new(true, Some("Stratum V2 SRI Pool".to_string()), None);
 
+        assert!(matches!(
+            pool_job_factory.new_extended_job_from_custom_job(
+                set_custom_mining_job.clone(),
+                extranonce_prefix.clone(),
+                60
+            ),
+            Err(JobFactoryError::ScriptSigSizeTooLarge)
+        ));
+
         let custom_job = pool_job_factory
             .new_extended_job_from_custom_job(set_custom_mining_job, extranonce_prefix, 32)
             .unwrap();
@@ -10,15 +10,26 @@
 //! - **Template Mapping**: Tracks mappings from template IDs to job IDs for future jobs.
 //! - **Lifecycle Management**: Ensures correct state transitions when activating jobs or updating
 //!   chain tips.
+//! - **Retention**: Optionally bounds the number and age of past jobs kept for share validation,
+//!   remembering evicted job IDs so that shares referencing them can be told apart.
+//! - **Snapshots**: Captures the whole job store state into a serializable [`JobStoreSnapshot`],
+//!   which can be used to restore a [`DefaultJobStore`] after a restart.
 //!
 //! ## Usage
 //!
 //! Use the [`JobStore`] trait for custom job store implementations, or the [`DefaultJobStore`]
 //! for standard job lifecycle management in mining channel abstractions.
 
-use std::{collections::HashMap, fmt::Debug};
+use std::{collections::HashMap, fmt::Debug, ops::RangeInclusive, sync::Arc};
 
 use super::Job;
+use crate::{
+    clock::{Clock, SystemClock},
+    server::{
+        error::SnapshotError,
+        snapshot::{SnapshotJob, SnapshotReader, SnapshotWriter},
+    },
+};
 
 /// Trait for job lifecycle management in mining channels.
 ///
@@ -54,12 +65,145 @@ pub trait JobStore<T: Job>: Send + Sync + Debug {
 
     /// Returns all stale jobs (jobs from previous chain tip), indexed by job ID.
     fn get_stale_jobs(&self) -> &HashMap<u32, T>;
+
+    /// Evicts past jobs that exceed the store's retention policy.
+    ///
+    /// Channels call this before validating a share. The default implementation never evicts.
+    fn evict_expired_jobs(&mut self) {}
+
+    /// Returns `true` if `job_id` refers to a past job that was evicted by the store's retention
+    /// policy under the current chain tip.
+    ///
+    /// The default implementation never evicts, so it always returns `false`.
+    fn is_job_evicted(&self, _job_id: u32) -> bool {
+        false
+    }
+
+    /// Replaces the time source used to measure the age of past jobs.
+    ///
+    /// The default implementation ignores the clock.
+    fn set_clock(&mut self, _clock: Arc<dyn Clock>) {}
+
+    /// Captures the current state of the job store.
+    fn snapshot(&self) -> JobStoreSnapshot<T>
+    where
+        T: Clone,
+    {
+        JobStoreSnapshot {
+            future_template_to_job_id: self.get_future_template_to_job_id().clone(),
+            future_jobs: self.get_future_jobs().clone(),
+            active_job: self.get_active_job().cloned(),
+            past_jobs: self.get_past_jobs().clone(),
+            stale_jobs: self.get_stale_jobs().clone(),
+        }
+    }
+}
+
+/// A point-in-time copy of the state of a [`JobStore`].
+///
+/// Can be serialized with [`JobStoreSnapshot::to_bytes`] and used to restore a
+/// [`DefaultJobStore`] (see [`DefaultJobStore::from_snapshot`]), so that shares submitted for
+/// jobs sent before a restart can still be validated.
+#[derive(Debug, Clone)]
+pub struct JobStoreSnapshot<T> {
+    /// Mapping from future template IDs to job IDs.
+    pub future_template_to_job_id: HashMap<u64, u32>,
+    /// Future jobs, indexed by job ID.
+    pub future_jobs: HashMap<u32, T>,
+    /// The active job, if any.
+    pub active_job: Option<T>,
+    /// Past jobs, indexed by job ID.
+    pub past_jobs: HashMap<u32, T>,
+    /// Stale jobs, indexed by job ID.
+    pub stale_jobs: HashMap<u32, T>,
+}
+
+impl<T: SnapshotJob> JobStoreSnapshot<T> {
+    /// Serializes the snapshot into bytes.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SnapshotError> {
+        let mut writer = SnapshotWriter::new();
+
+        let mut future_template_to_job_id: Vec<_> = self.future_template_to_job_id.iter().collect();
+        future_template_to_job_id.sort();
+        writer.write_len(future_template_to_job_id.len())?;
+        for (template_id, job_id) in future_template_to_job_id {
+            writer.write_u64(*template_id);
+            writer.write_u32(*job_id);
+        }
+
+        Self::write_jobs(&mut writer, &self.future_jobs)?;
+        writer.write_bool(self.active_job.is_some());
+        if let Some(active_job) = &self.active_job {
+            writer.write_job(active_job)?;
+        }
+        Self::write_jobs(&mut writer, &self.past_jobs)?;
+        Self::write_jobs(&mut writer, &self.stale_jobs)?;
+
+        Ok(writer.into_bytes())
+    }
+
+    /// Deserializes a snapshot previously serialized with [`JobStoreSnapshot::to_bytes`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SnapshotError> {
+        let mut reader = SnapshotReader::new(bytes)?;
+
+        let mut future_template_to_job_id = HashMap::new();
+        for _ in 0..reader.read_len()? {
+            let template_id = reader.read_u64()?;
+            let job_id = reader.read_u32()?;
+            future_template_to_job_id.insert(template_id, job_id);
+        }
+
+        let future_jobs = Self::read_jobs(&mut reader)?;
+        let active_job = match reader.read_bool()? {
+            true => Some(reader.read_job()?),
+            false => None,
+        };
+        let past_jobs = Self::read_jobs(&mut reader)?;
+        let stale_jobs = Self::read_jobs(&mut reader)?;
+        reader.finish()?;
+
+        Ok(Self {
+            future_template_to_job_id,
+            future_jobs,
+            active_job,
+            past_jobs,
+            stale_jobs,
+        })
+    }
+
+    // jobs are written sorted by job ID, so that equal stores produce equal bytes
+    fn write_jobs(
+        writer: &mut SnapshotWriter,
+        jobs: &HashMap<u32, T>,
+    ) -> Result<(), SnapshotError> {
+        let mut jobs: Vec<_> = jobs.iter().collect();
+        jobs.sort_by_key(|(job_id, _)| **job_id);
+        writer.write_len(jobs.len())?;
+        for (_, job) in jobs {
+            writer.write_job(job)?;
+        }
+        Ok(())
+    }
+
+    fn read_jobs(reader: &mut SnapshotReader) -> Result<HashMap<u32, T>, SnapshotError> {
+        let mut jobs = HashMap::new();
+        for _ in 0..reader.read_len()? {
+            let job: T = reader.read_job()?;
+            jobs.insert(job.get_job_id(), job);
+        }
+        Ok(jobs)
+    }
 }
 
 /// Default implementation of [`JobStore`] for tracking mining job states in SV2 channels.
 ///
 /// Maintains collections for future, active, past, and stale jobs, and tracks template-to-job ID
 /// mappings for future job activation.
+///
+/// By default, past jobs are kept until the next chain tip. A retention policy can bound how many
+/// past jobs are kept and for how long (see [`DefaultJobStore::new_with_retention`]). The age of a
+/// past job is measured from the moment it was replaced by a newer active job. When the limit is
+/// exceeded, the oldest past jobs are evicted first.
 #[derive(Debug)]
 pub struct DefaultJobStore<T: Job + Clone> {
     future_template_to_job_id: HashMap<u64, u32>,
@@ -70,10 +214,18 @@ pub struct DefaultJobStore<T: Job + Clone> {
     past_jobs: HashMap<u32, T>,
     // Stale jobs are indexed with job_id (u32)
     stale_jobs: HashMap<u32, T>,
+    // Unix timestamp (seconds) at which each past job stopped being the active job
+    past_job_timestamps: HashMap<u32, u64>,
+    // Ids of the past jobs evicted under the current chain tip. Job ids are monotonic and the
+    // oldest past jobs are evicted first, so the evicted ids form a range.
+    evicted_job_ids: Option<RangeInclusive<u32>>,
+    max_past_jobs: Option<usize>,
+    max_past_job_age_secs: Option<u64>,
+    clock: Arc<dyn Clock>,
 }
 
 impl<T: Job + Clone> DefaultJobStore<T> {
-    /// Creates a new empty job store.
+    /// Creates a new empty job store, retaining past jobs until the next chain tip.
     pub fn new() -> Self {
         Self {
             future_template_to_job_id: HashMap::new(),
@@ -81,7 +233,112 @@ impl<T: Job + Clone> DefaultJobStore<T> {
             active_job: None,
             past_jobs: HashMap::new(),
             stale_jobs: HashMap::new(),
+            past_job_timestamps: HashMap::new(),
+            evicted_job_ids: None,
+            max_past_jobs: None,
+            max_past_job_age_secs: None,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Creates a new empty job store with a bounded past job retention.
+    ///
+    /// - `max_past_jobs`: maximum number of past jobs kept, `None` for no limit.
+    /// - `max_past_job_age_secs`: maximum number of seconds a past job is kept after being
+    ///   replaced by a newer active job, `None` for no limit.
+    pub fn new_with_retention(
+        max_past_jobs: Option<usize>,
+        max_past_job_age_secs: Option<u64>,
+    ) -> Self {
+        let mut job_store = Self::new();
+        job_store.set_retention(max_past_jobs, max_past_job_age_secs);
+        job_store
+    }
+
+    /// Restores a job store from a snapshot.
+    ///
+    /// The restored store retains past jobs until the next chain tip, see
+    /// [`DefaultJobStore::set_retention`]. Past jobs are considered replaced at restore time.
+    ///
+    /// The restored store reads time from a [`SystemClock`], see [`JobStore::set_clock`].
+    pub fn from_snapshot(snapshot: JobStoreSnapshot<T>) -> Self {
+        let mut job_store = Self::new();
+        let now = job_store.clock.now();
+        job_store.past_job_timestamps = snapshot.past_jobs.keys().map(|id| (*id, now)).collect();
+        job_store.future_template_to_job_id = snapshot.future_template_to_job_id;
+        job_store.future_jobs = snapshot.future_jobs;
+        job_store.active_job = snapshot.active_job;
+        job_store.past_jobs = snapshot.past_jobs;
+        job_store.stale_jobs = snapshot.stale_jobs;
+        job_store
+    }
+
+    /// Sets the past job retention policy, evicting past jobs that already exceed it.
+    ///
+    /// See [`DefaultJobStore::new_with_retention`].
+    pub fn set_retention(
+        &mut self,
+        max_past_jobs: Option<usize>,
+        max_past_job_age_secs: Option<u64>,
+    ) {
+        self.max_past_jobs = max_past_jobs;
+        self.max_past_job_age_secs = max_past_job_age_secs;
+        self.evict_past_jobs();
+    }
+
+    // Moves the currently active job (if any) to past jobs, enforcing the retention policy.
+    fn retire_active_job(&mut self) {
+        if let Some(active_job) = self.active_job.take() {
+            let job_id = active_job.get_job_id();
+            self.past_jobs.insert(job_id, active_job);
+            self.past_job_timestamps.insert(job_id, self.clock.now());
+            self.evict_past_jobs();
+        }
+    }
+
+    fn evict_past_jobs(&mut self) {
+        if let Some(max_age) = self.max_past_job_age_secs {
+            let now = self.clock.now();
+            let expired: Vec<u32> = self
+                .past_job_timestamps
+                .iter()
+                .filter(|(_, retired_at)| now.saturating_sub(**retired_at) > max_age)
+                .map(|(job_id, _)| *job_id)
+                .collect();
+            for job_id in expired {
+                self.evict_past_job(job_id);
+            }
         }
+
+        if let Some(max_past_jobs) = self.max_past_jobs {
+            if self.past_jobs.len() > max_past_jobs {
+                let mut by_age: Vec<(u64, u32)> = self
+                    .past_job_timestamps
+                    .iter()
+                    .map(|(job_id, retired_at)| (*retired_at, *job_id))
+                    .collect();
+                by_age.sort();
+                let excess = self.past_jobs.len() - max_past_jobs;
+                for (_, job_id) in by_age.into_iter().take(excess) {
+                    self.evict_past_job(job_id);
+                }
+            }
+        }
+    }
+
+    fn evict_past_job(&mut self, job_id: u32) {
+        self.past_jobs.remove(&job_id);
+        self.past_job_timestamps.remove(&job_id);
+        self.evicted_job_ids = Some(match self.evicted_job_ids.take() {
+            Some(evicted) => (*evicted.start()).min(job_id)..=(*evicted.end()).max(job_id),
+            None => job_id..=job_id,
+        });
+    }
+}
+
+impl<T: Job + Clone> From<JobStoreSnapshot<T>> for DefaultJobStore<T> {
+    fn from(snapshot: JobStoreSnapshot<T>) -> Self {
+        Self::from_snapshot(snapshot)
     }
 }
 
@@ -102,9 +359,7 @@ impl<T: Job + Clone + Debug> JobStore<T> for DefaultJobStore<T> {
 
     fn add_active_job(&mut self, job: T) {
         // Move currently active job to past jobs (so it can be marked as stale)
-        if let Some(active_job) = self.active_job.take() {
-            self.past_jobs.insert(active_job.get_job_id(), active_job);
-        }
+        self.retire_active_job();
         // Set the new active job
         self.active_job = Some(job);
     }
@@ -122,9 +377,7 @@ impl<T: Job + Clone + Debug> JobStore<T> for DefaultJobStore<T> {
             };
 
         // Move currently active job to past jobs (so it can be marked as stale)
-        if let Some(active_job) = self.active_job.take() {
-            self.past_jobs.insert(active_job.get_job_id(), active_job);
-        }
+        self.retire_active_job();
 
         // Activate the future job
         future_job.activate(prev_hash_header_timestamp);
@@ -144,6 +397,8 @@ impl<T: Job + Clone + Debug> JobStore<T> for DefaultJobStore<T> {
 
         // Clear past jobs, as we're no longer going to validate shares for them
         self.past_jobs.clear();
+        self.past_job_timestamps.clear();
+        self.evicted_job_ids = None;
     }
 
     fn get_future_template_to_job_id(&self) -> &HashMap<u64, u32> {
@@ -165,4 +420,102 @@ impl<T: Job + Clone + Debug> JobStore<T> for DefaultJobStore<T> {
     fn get_stale_jobs(&self) -> &HashMap<u32, T> {
         &self.stale_jobs
     }
+
+    fn evict_expired_jobs(&mut self) {
+        self.evict_past_jobs();
+    }
+
+    fn is_job_evicted(&self, job_id: u32) -> bool {
+        self.evicted_job_ids
+            .as_ref()
+            .is_some_and(|evicted| evicted.contains(&job_id))
+            && !self.past_jobs.contains_key(&job_id)
+    }
+
+    fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::{
+        extended::ExtendedChannel,
+        jobs::extended::ExtendedJob,
+        test::{coinbase_reward_outputs, easiest_target, empty_template},
+    };
+    use template_distribution_sv2::SetNewPrevHash;
+
+    #[test]
+    fn test_snapshot_and_restore() {
+        // jobs are created by a channel, and then handed over to the job store under test
+        let mut channel = ExtendedChannel::new_for_pool(
+            1,
+            "user_identity".to_string(),
+            vec![0; 24],
+            easiest_target(),
+            1_000.0,
+            true,
+            8,
+            100,
+            1.0,
+            DefaultJobStore::new(),
+            "pool".to_string(),
+        )
+        .unwrap();
+        channel
+            .on_new_template(empty_template(1, true), coinbase_reward_outputs())
+            .unwrap();
+        let future_job = channel.get_future_jobs().values().next().unwrap().clone();
+        channel
+            .on_set_new_prev_hash(SetNewPrevHash {
+                template_id: 1,
+                prev_hash: [1; 32].into(),
+                header_timestamp: 1746839905,
+                n_bits: 503543726,
+                target: [0; 32].into(),
+            })
+            .unwrap();
+        let mut active_jobs = vec![];
+        for template_id in 2..=4 {
+            channel
+                .on_new_template(
+                    empty_template(template_id, false),
+                    coinbase_reward_outputs(),
+                )
+                .unwrap();
+            active_jobs.push(channel.get_active_job().unwrap().clone());
+        }
+
+        let mut job_store: DefaultJobStore<ExtendedJob> = DefaultJobStore::new();
+        job_store.add_future_job(1, future_job);
+        job_store.add_active_job(active_jobs[0].clone());
+        job_store.add_active_job(active_jobs[1].clone());
+        job_store.mark_past_jobs_as_stale();
+        job_store.add_active_job(active_jobs[2].clone());
+
+        let bytes = job_store.snapshot().to_bytes().unwrap();
+        let snapshot = JobStoreSnapshot::from_bytes(&bytes).unwrap();
+        // serialization is deterministic
+        assert_eq!(snapshot.to_bytes().unwrap(), bytes);
+
+        let restored_job_store: DefaultJobStore<ExtendedJob> =
+            DefaultJobStore::from_snapshot(snapshot);
+        assert_eq!(restored_job_store.snapshot().to_bytes().unwrap(), bytes);
+        assert_eq!(
+            restored_job_store.get_future_template_to_job_id(),
+            job_store.get_future_template_to_job_id()
+        );
+        assert_eq!(
+            restored_job_store.get_active_job().unwrap().get_job_id(),
+            active_jobs[2].get_job_id()
+        );
+        assert!(restored_job_store
+            .get_past_jobs()
+            .contains_key(&active_jobs[1].get_job_id()));
+        assert!(restored_job_store
+            .get_stale_jobs()
+            .contains_key(&active_jobs[0].get_job_id()));
+    }
 }
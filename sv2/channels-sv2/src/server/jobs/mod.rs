@@ -10,6 +10,8 @@
 //! - **Standard Jobs**: See [`standard`] submodule for SV2 standard job implementation.
 //! - **Job Factories**: See [`factory`] for job creation logic and unique job ID assignment.
 //! - **Job Storage**: See [`job_store`] for job lifecycle management and storage abstractions.
+//! - **Payouts**: See [`payout`] for deriving coinbase outputs from weighted payout scripts.
+//! - **ScriptSig Layout**: See [`script_sig`] for composing the coinbase scriptSig.
 //! - **Job Origin Tracking**: Tracks job origin (template or custom job message).
 //! - **Job Trait**: Unified trait for all mining job types, supporting activation and job ID
 //!   retrieval.
@@ -22,6 +24,8 @@ pub mod error;
 pub mod extended;
 pub mod factory;
 pub mod job_store;
+pub mod payout;
+pub mod script_sig;
 pub mod standard;
 
 use mining_sv2::SetCustomMiningJob;
@@ -0,0 +1,240 @@
+//! Payout policies for splitting coinbase rewards.
+//!
+//! [`JobFactory`] requires the additional coinbase outputs of template jobs to sum exactly to the
+//! template's `coinbase_tx_value_remaining`. This module provides the [`PayoutPolicy`] trait,
+//! which derives those outputs from the available value, and [`WeightedPayoutPolicy`], which
+//! splits it across weighted payout scripts.
+//!
+//! ## Responsibilities
+//!
+//! - **Weighted Splits**: Pays each script proportionally to its weight, which covers solo mining
+//!   (a single script), pool fee splits and PPLNS-on-coinbase payouts.
+//! - **Rounding**: Distributes the satoshis lost to integer division with the largest remainder
+//!   method, so that outputs always sum exactly to the available value.
+//! - **Dust**: Drops outputs below a dust limit and redistributes their value across the remaining
+//!   scripts.
+//!
+//! [`JobFactory`]: crate::server::jobs::factory::JobFactory
+
+use crate::server::jobs::error::PayoutPolicyError;
+use bitcoin::{transaction::TxOut, Amount, ScriptBuf};
+use std::fmt::Debug;
+
+/// Default dust limit (in satoshis), matching the dust threshold of P2PKH outputs.
+pub const DEFAULT_DUST_LIMIT_SATS: u64 = 546;
+
+/// Trait for deriving the additional coinbase outputs of a job from the value made available by
+/// the template.
+pub trait PayoutPolicy: Debug + Send + Sync {
+    /// Returns the coinbase outputs paying out `coinbase_tx_value_remaining` satoshis.
+    ///
+    /// The values of the returned outputs must sum exactly to `coinbase_tx_value_remaining`.
+    fn coinbase_outputs(
+        &self,
+        coinbase_tx_value_remaining: u64,
+    ) -> Result<Vec<TxOut>, PayoutPolicyError>;
+}
+
+/// A [`PayoutPolicy`] splitting the coinbase value across weighted payout scripts.
+///
+/// Each script receives a share of the value proportional to its weight. Outputs are returned in
+/// the same order as the payout scripts were provided.
+///
+/// Outputs worth less than the dust limit are dropped and their value is redistributed across the
+/// remaining scripts. If every output would be dust, the whole value is paid to the script with the
+/// highest weight (the first one, in case of ties).
+#[derive(Debug, Clone)]
+pub struct WeightedPayoutPolicy {
+    payouts: Vec<(ScriptBuf, u64)>,
+    dust_limit_sats: u64,
+}
+
+impl WeightedPayoutPolicy {
+    /// Creates a new [`WeightedPayoutPolicy`] from `(script_pubkey, weight)` pairs.
+    ///
+    /// Returns an error if no payout is provided or if any weight is zero.
+    pub fn new(payouts: Vec<(ScriptBuf, u64)>) -> Result<Self, PayoutPolicyError> {
+        if payouts.is_empty() {
+            return Err(PayoutPolicyError::NoPayouts);
+        }
+        if payouts.iter().any(|(_, weight)| *weight == 0) {
+            return Err(PayoutPolicyError::ZeroWeight);
+        }
+        Ok(Self {
+            payouts,
+            dust_limit_sats: DEFAULT_DUST_LIMIT_SATS,
+        })
+    }
+
+    /// Creates a new [`WeightedPayoutPolicy`] paying the whole coinbase value to a single script.
+    pub fn solo(script_pubkey: ScriptBuf) -> Self {
+        Self {
+            payouts: vec![(script_pubkey, 1)],
+            dust_limit_sats: DEFAULT_DUST_LIMIT_SATS,
+        }
+    }
+
+    /// Returns the weighted payout scripts.
+    pub fn get_payouts(&self) -> &[(ScriptBuf, u64)] {
+        &self.payouts
+    }
+
+    /// Returns the dust limit (in satoshis).
+    pub fn get_dust_limit_sats(&self) -> u64 {
+        self.dust_limit_sats
+    }
+
+    /// Sets the dust limit (in satoshis).
+    ///
+    /// Defaults to [`DEFAULT_DUST_LIMIT_SATS`].
+    pub fn set_dust_limit_sats(&mut self, dust_limit_sats: u64) {
+        self.dust_limit_sats = dust_limit_sats;
+    }
+
+    // Splits `value` across the payouts flagged in `included`, proportionally to their weights.
+    //
+    // Each payout first receives the floor of its exact share, then the satoshis left over are
+    // handed out one by one to the payouts with the largest remainders (lowest index first on
+    // ties).
+    fn split(&self, value: u64, included: &[bool]) -> Vec<u64> {
+        let total_weight: u128 = self
+            .payouts
+            .iter()
+            .zip(included)
+            .filter(|(_, included)| **included)
+            .map(|((_, weight), _)| *weight as u128)
+            .sum();
+
+        let mut amounts = vec![0u64; self.payouts.len()];
+        let mut remainders = Vec::new();
+        let mut distributed = 0u64;
+        for (index, ((_, weight), included)) in self.payouts.iter().zip(included).enumerate() {
+            if !included {
+                continue;
+            }
+            let numerator = value as u128 * *weight as u128;
+            // never larger than `value`, as `weight <= total_weight`
+            amounts[index] = (numerator / total_weight) as u64;
+            distributed += amounts[index];
+            remainders.push((numerator % total_weight, index));
+        }
+
+        // largest remainders first, lowest index first on ties
+        remainders.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+        for (_, index) in remainders.into_iter().take((value - distributed) as usize) {
+            amounts[index] += 1;
+        }
+
+        amounts
+    }
+}
+
+impl PayoutPolicy for WeightedPayoutPolicy {
+    fn coinbase_outputs(
+        &self,
+        coinbase_tx_value_remaining: u64,
+    ) -> Result<Vec<TxOut>, PayoutPolicyError> {
+        let mut included = vec![true; self.payouts.len()];
+        let mut amounts = self.split(coinbase_tx_value_remaining, &included);
+
+        // drop dust outputs, lowest weight first (last one first on ties), until all remaining
+        // outputs are above the dust limit or a single one is left
+        while included.iter().filter(|included| **included).count() > 1 {
+            let dust_index = (0..amounts.len())
+                .filter(|index| included[*index] && amounts[*index] < self.dust_limit_sats)
+                .min_by_key(|index| (self.payouts[*index].1, usize::MAX - index));
+            match dust_index {
+                Some(dust_index) => {
+                    included[dust_index] = false;
+                    amounts = self.split(coinbase_tx_value_remaining, &included);
+                }
+                None => break,
+            }
+        }
+
+        Ok(self
+            .payouts
+            .iter()
+            .zip(amounts)
+            .zip(included)
+            .filter(|(_, included)| *included)
+            .map(|((payout, amount), _)| TxOut {
+                value: Amount::from_sat(amount),
+                script_pubkey: payout.0.clone(),
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn script(byte: u8) -> ScriptBuf {
+        ScriptBuf::from(vec![0, 20, byte, byte, byte])
+    }
+
+    fn values(outputs: &[TxOut]) -> Vec<u64> {
+        outputs.iter().map(|o| o.value.to_sat()).collect()
+    }
+
+    #[test]
+    fn test_solo_payout() {
+        let policy = WeightedPayoutPolicy::solo(script(1));
+        let outputs = policy.coinbase_outputs(312_500_000).unwrap();
+        assert_eq!(outputs.len(), 1);
+        assert_eq!(outputs[0].value.to_sat(), 312_500_000);
+        assert_eq!(outputs[0].script_pubkey, script(1));
+    }
+
+    #[test]
+    fn test_weighted_payout_rounding() {
+        // 1% pool fee, the rest split in thirds
+        let policy = WeightedPayoutPolicy::new(vec![
+            (script(1), 1),
+            (script(2), 33),
+            (script(3), 33),
+            (script(4), 33),
+        ])
+        .unwrap();
+
+        let outputs = policy.coinbase_outputs(1_000_001).unwrap();
+        assert_eq!(values(&outputs), vec![10_000, 330_001, 330_000, 330_000]);
+        assert_eq!(values(&outputs).iter().sum::<u64>(), 1_000_001);
+        assert_eq!(outputs[1].script_pubkey, script(2));
+    }
+
+    #[test]
+    fn test_weighted_payout_dust() {
+        let mut policy =
+            WeightedPayoutPolicy::new(vec![(script(1), 1), (script(2), 1_000), (script(3), 1)])
+                .unwrap();
+
+        // 100_200 sats: the weight 1 outputs would get 100 sats each
+        let outputs = policy.coinbase_outputs(100_200).unwrap();
+        assert_eq!(values(&outputs), vec![100_200]);
+        assert_eq!(outputs[0].script_pubkey, script(2));
+
+        policy.set_dust_limit_sats(100);
+        let outputs = policy.coinbase_outputs(100_200).unwrap();
+        assert_eq!(values(&outputs), vec![100, 100_000, 100]);
+
+        // everything is dust, so the highest weight takes it all
+        policy.set_dust_limit_sats(1_000_000);
+        let outputs = policy.coinbase_outputs(100_200).unwrap();
+        assert_eq!(values(&outputs), vec![100_200]);
+        assert_eq!(outputs[0].script_pubkey, script(2));
+    }
+
+    #[test]
+    fn test_weighted_payout_invalid() {
+        assert!(matches!(
+            WeightedPayoutPolicy::new(vec![]),
+            Err(PayoutPolicyError::NoPayouts)
+        ));
+        assert!(matches!(
+            WeightedPayoutPolicy::new(vec![(script(1), 1), (script(2), 0)]),
+            Err(PayoutPolicyError::ZeroWeight)
+        ));
+    }
+}
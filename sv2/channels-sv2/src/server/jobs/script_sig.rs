@@ -0,0 +1,258 @@
+//! Layout of the coinbase scriptSig for SV2 mining servers.
+//!
+//! The coinbase scriptSig of jobs created by a [`JobFactory`] is made of:
+//! - the BIP34 block height (the template's `coinbase_prefix`)
+//! - an ordered list of data pushes (the [`ScriptSigLayout`])
+//! - a push of the full extranonce (extranonce prefix + rollable extranonce)
+//!
+//! By default, the layout is a single push of the `/pool_tag/miner_tag/` tag. The
+//! [`ScriptSigLayoutBuilder`] allows composing other layouts (e.g. merged mining commitments or
+//! custom identifiers), keeping track of how many bytes are left before the scriptSig exceeds the
+//! 100 bytes consensus limit.
+//!
+//! [`JobFactory`]: crate::server::jobs::factory::JobFactory
+
+use crate::server::{
+    auxpow::MERGED_MINING_COMMITMENT_SIZE,
+    jobs::{error::JobFactoryError, factory::MAX_COINBASE_SCRIPT_SIG_SIZE},
+};
+
+/// Number of scriptSig bytes reserved for the BIP34 block height push.
+pub const BIP34_RESERVED_SIZE: usize = 5;
+
+// Script opcodes used to push data.
+const OP_0: u8 = 0x00;
+const OP_PUSHDATA1: u8 = 0x4c;
+const OP_PUSHDATA2: u8 = 0x4d;
+const MAX_OP_PUSHBYTES: usize = 75;
+
+/// An ordered list of data pushes placed in the coinbase scriptSig, between the BIP34 block
+/// height and the extranonce.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScriptSigLayout {
+    pushes: Vec<Vec<u8>>,
+}
+
+impl ScriptSigLayout {
+    /// Creates a layout with a single push of the `/pool_tag/miner_tag/` tag.
+    ///
+    /// The character `/` is used as a delimiter. If no pool or miner tag is provided, the
+    /// delimiters are still added.
+    pub fn pool_miner_tag(pool_tag: Option<&str>, miner_tag: Option<&str>) -> Self {
+        Self {
+            pushes: vec![pool_miner_tag(pool_tag, miner_tag)],
+        }
+    }
+
+    /// Creates a layout from raw pushes, without any budget accounting.
+    ///
+    /// Prefer [`ScriptSigLayoutBuilder`], which rejects pushes exceeding the scriptSig budget.
+    pub fn from_pushes(pushes: Vec<Vec<u8>>) -> Self {
+        Self { pushes }
+    }
+
+    /// Returns the data pushes of this layout, in order.
+    pub fn get_pushes(&self) -> &[Vec<u8>] {
+        &self.pushes
+    }
+
+    /// Returns the serialized size of this layout (push opcodes included).
+    pub fn serialized_len(&self) -> usize {
+        self.pushes
+            .iter()
+            .map(|push| push_opcode(push.len()).len() + push.len())
+            .sum()
+    }
+
+    /// Serializes this layout as script bytes, each push preceded by its push opcode.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.serialized_len());
+        for push in &self.pushes {
+            bytes.extend(push_opcode(push.len()));
+            bytes.extend_from_slice(push);
+        }
+        bytes
+    }
+}
+
+/// Builder for a [`ScriptSigLayout`], accounting for the scriptSig budget.
+///
+/// The budget is the 100 bytes consensus limit, minus [`BIP34_RESERVED_SIZE`] bytes for the block
+/// height and the bytes reserved for the extranonce push.
+///
+/// ```
+/// use channels_sv2::server::jobs::script_sig::ScriptSigLayoutBuilder;
+///
+/// let layout = ScriptSigLayoutBuilder::new(32)
+///     .push_pool_miner_tag(Some("pool"), None)
+///     .unwrap()
+///     .push(&[0xfa, 0xbe, 0x6d, 0x6d])
+///     .unwrap()
+///     .build();
+///
+/// assert_eq!(layout.get_pushes().len(), 2);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ScriptSigLayoutBuilder {
+    pushes: Vec<Vec<u8>>,
+    remaining_budget: usize,
+}
+
+impl ScriptSigLayoutBuilder {
+    /// Creates a new builder, reserving space for a `full_extranonce_size` bytes extranonce.
+    pub fn new(full_extranonce_size: usize) -> Self {
+        let extranonce_push_size = push_opcode(full_extranonce_size).len() + full_extranonce_size;
+        Self {
+            pushes: vec![],
+            remaining_budget: MAX_COINBASE_SCRIPT_SIG_SIZE
+                .saturating_sub(BIP34_RESERVED_SIZE)
+                .saturating_sub(extranonce_push_size),
+        }
+    }
+
+    /// Returns how many bytes (push opcodes included) can still be added to the layout.
+    pub fn remaining_budget(&self) -> usize {
+        self.remaining_budget
+    }
+
+    /// Appends a data push to the layout.
+    ///
+    /// Returns an error if the push (opcode included) doesn't fit in the remaining budget.
+    pub fn push(mut self, data: &[u8]) -> Result<Self, JobFactoryError> {
+        let size = push_opcode(data.len()).len() + data.len();
+        if size > self.remaining_budget {
+            return Err(JobFactoryError::ScriptSigSizeTooLarge);
+        }
+        self.remaining_budget -= size;
+        self.pushes.push(data.to_vec());
+        Ok(self)
+    }
+
+    /// Reserves space for the merged mining commitment push, which a [`JobFactory`] with a merged
+    /// mining commitment appends after the layout.
+    ///
+    /// Returns an error if the push (opcode included) doesn't fit in the remaining budget.
+    ///
+    /// [`JobFactory`]: crate::server::jobs::factory::JobFactory
+    pub fn reserve_merged_mining_commitment(mut self) -> Result<Self, JobFactoryError> {
+        let size = push_opcode(MERGED_MINING_COMMITMENT_SIZE).len() + MERGED_MINING_COMMITMENT_SIZE;
+        if size > self.remaining_budget {
+            return Err(JobFactoryError::ScriptSigSizeTooLarge);
+        }
+        self.remaining_budget -= size;
+        Ok(self)
+    }
+
+    /// Appends a push of the `/pool_tag/miner_tag/` tag to the layout.
+    ///
+    /// See [`ScriptSigLayout::pool_miner_tag`].
+    pub fn push_pool_miner_tag(
+        self,
+        pool_tag: Option<&str>,
+        miner_tag: Option<&str>,
+    ) -> Result<Self, JobFactoryError> {
+        self.push(&pool_miner_tag(pool_tag, miner_tag))
+    }
+
+    /// Builds the layout.
+    pub fn build(self) -> ScriptSigLayout {
+        ScriptSigLayout {
+            pushes: self.pushes,
+        }
+    }
+}
+
+fn pool_miner_tag(pool_tag: Option<&str>, miner_tag: Option<&str>) -> Vec<u8> {
+    let mut tag = vec![];
+    tag.extend_from_slice(b"/");
+    if let Some(pool_tag) = pool_tag {
+        tag.extend_from_slice(pool_tag.as_bytes());
+    }
+    tag.extend_from_slice(b"/");
+    if let Some(miner_tag) = miner_tag {
+        tag.extend_from_slice(miner_tag.as_bytes());
+    }
+    tag.extend_from_slice(b"/");
+    tag
+}
+
+// Returns the opcode (and length bytes) pushing `len` bytes of data.
+fn push_opcode(len: usize) -> Vec<u8> {
+    match len {
+        0 => vec![OP_0],
+        1..=MAX_OP_PUSHBYTES => vec![len as u8],
+        76..=0xff => vec![OP_PUSHDATA1, len as u8],
+        _ => {
+            let mut opcode = vec![OP_PUSHDATA2];
+            opcode.extend_from_slice(&(len as u16).to_le_bytes());
+            opcode
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pool_miner_tag_layout() {
+        let layout = ScriptSigLayout::pool_miner_tag(Some("pool"), Some("miner"));
+        assert_eq!(layout.to_bytes(), b"\x0c/pool/miner/".to_vec());
+        assert_eq!(layout.serialized_len(), 13);
+
+        let layout = ScriptSigLayout::pool_miner_tag(None, None);
+        assert_eq!(layout.to_bytes(), b"\x03///".to_vec());
+    }
+
+    #[test]
+    fn test_builder_budget() {
+        // 100 - 5 (BIP34) - 33 (extranonce push)
+        let builder = ScriptSigLayoutBuilder::new(32);
+        assert_eq!(builder.remaining_budget(), 62);
+
+        let builder = builder
+            .push_pool_miner_tag(Some("pool"), None)
+            .unwrap()
+            .push(&[0xaa; 32])
+            .unwrap();
+        assert_eq!(builder.remaining_budget(), 62 - 8 - 33);
+
+        // 21 bytes left, a 21 bytes push needs 22
+        let res = builder.clone().push(&[0xbb; 21]);
+        assert!(matches!(res, Err(JobFactoryError::ScriptSigSizeTooLarge)));
+
+        let layout = builder.push(&[0xbb; 20]).unwrap().build();
+        assert_eq!(layout.get_pushes().len(), 3);
+        assert_eq!(layout.serialized_len(), 62);
+        assert_eq!(layout.to_bytes().len(), 62);
+    }
+
+    #[test]
+    fn test_builder_merged_mining_reservation() {
+        // 62 - 45 (merged mining commitment push)
+        let builder = ScriptSigLayoutBuilder::new(32)
+            .reserve_merged_mining_commitment()
+            .unwrap();
+        assert_eq!(builder.remaining_budget(), 17);
+
+        // a layout accepted without the reservation no longer fits
+        let res = builder.push_pool_miner_tag(Some("Stratum V2 SRI Pool"), None);
+        assert!(matches!(res, Err(JobFactoryError::ScriptSigSizeTooLarge)));
+
+        let res = ScriptSigLayoutBuilder::new(32)
+            .push(&[0xaa; 20])
+            .unwrap()
+            .reserve_merged_mining_commitment();
+        assert!(matches!(res, Err(JobFactoryError::ScriptSigSizeTooLarge)));
+    }
+
+    #[test]
+    fn test_push_opcodes() {
+        let layout = ScriptSigLayout::from_pushes(vec![vec![], vec![1; 75], vec![2; 76]]);
+        let bytes = layout.to_bytes();
+        assert_eq!(bytes[0], OP_0);
+        assert_eq!(bytes[1], 75);
+        assert_eq!(bytes[77..79], [OP_PUSHDATA1, 76]);
+        assert_eq!(bytes.len(), layout.serialized_len());
+    }
+}
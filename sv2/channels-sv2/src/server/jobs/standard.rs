@@ -21,7 +21,12 @@
 
 use crate::{
     outputs::deserialize_template_outputs,
-    server::jobs::{error::StandardJobError, Job},
+    server::{
+        auxpow::MERGED_MINING_COMMITMENT_SIZE,
+        error::SnapshotError,
+        jobs::{error::StandardJobError, Job},
+        snapshot::{decode_sv2, SnapshotJob, SnapshotReader, SnapshotWriter},
+    },
 };
 use binary_sv2::{Sv2Option, U256};
 use bitcoin::transaction::TxOut;
@@ -39,6 +44,7 @@ pub struct StandardJob<'a> {
     extranonce_prefix: Vec<u8>,
     coinbase_outputs: Vec<TxOut>,
     job_message: NewMiningJob<'a>,
+    merged_mining_commitment: Option<[u8; MERGED_MINING_COMMITMENT_SIZE]>,
 }
 
 impl Job for StandardJob<'_> {
@@ -53,6 +59,52 @@ impl Job for StandardJob<'_> {
     }
 }
 
+impl SnapshotJob for StandardJob<'_> {
+    fn to_snapshot_bytes(&self) -> Result<Vec<u8>, SnapshotError> {
+        let mut writer = SnapshotWriter::new();
+        writer.write_sv2(self.template.clone())?;
+        writer.write_bytes(&self.extranonce_prefix)?;
+        writer.write_tx_outs(&self.coinbase_outputs)?;
+        writer.write_sv2(self.job_message.clone())?;
+        match &self.merged_mining_commitment {
+            Some(commitment) => {
+                writer.write_bool(true);
+                writer.write_bytes(commitment)?;
+            }
+            None => writer.write_bool(false),
+        }
+        Ok(writer.into_bytes())
+    }
+
+    fn from_snapshot_bytes(bytes: &[u8]) -> Result<Self, SnapshotError> {
+        let mut reader = SnapshotReader::new(bytes)?;
+        let mut raw = reader.read_sv2()?;
+        let template: NewTemplate = decode_sv2(&mut raw)?;
+        let extranonce_prefix = reader.read_bytes()?.to_vec();
+        let coinbase_outputs = reader.read_tx_outs()?;
+        let mut raw = reader.read_sv2()?;
+        let job_message: NewMiningJob = decode_sv2(&mut raw)?;
+        let merged_mining_commitment = match reader.read_bool()? {
+            true => Some(
+                reader
+                    .read_bytes()?
+                    .try_into()
+                    .map_err(|_| SnapshotError::InvalidMergedMining)?,
+            ),
+            false => None,
+        };
+        reader.finish()?;
+
+        Ok(StandardJob {
+            template: template.into_static(),
+            extranonce_prefix,
+            coinbase_outputs,
+            job_message: job_message.into_static(),
+            merged_mining_commitment,
+        })
+    }
+}
+
 impl<'a> StandardJob<'a> {
     /// Creates a new standard job from a template.
     ///
@@ -79,6 +131,7 @@ impl<'a> StandardJob<'a> {
             extranonce_prefix,
             coinbase_outputs,
             job_message,
+            merged_mining_commitment: None,
         })
     }
     /// Returns the job ID for this job.
@@ -105,6 +158,17 @@ impl<'a> StandardJob<'a> {
     pub fn get_merkle_root(&self) -> &U256<'a> {
         &self.job_message.merkle_root
     }
+    /// Returns the merged mining commitment pushed in the coinbase scriptSig of this job, if any.
+    pub fn get_merged_mining_commitment(&self) -> Option<[u8; MERGED_MINING_COMMITMENT_SIZE]> {
+        self.merged_mining_commitment
+    }
+    // Records the merged mining commitment the job factory pushed in the coinbase scriptSig.
+    pub(crate) fn set_merged_mining_commitment(
+        &mut self,
+        merged_mining_commitment: Option<[u8; MERGED_MINING_COMMITMENT_SIZE]>,
+    ) {
+        self.merged_mining_commitment = merged_mining_commitment;
+    }
     /// Returns true if the job is a future job (not yet activated).
     pub fn is_future(&self) -> bool {
         self.job_message.min_ntime.clone().into_inner().is_none()
@@ -1,8 +1,16 @@
 //! Sv2 channels - Mining Servers Abstraction.
 
+pub mod auxpow;
+pub mod block;
 pub mod error;
 pub mod extended;
+pub mod extranonce;
 pub mod group;
 pub mod jobs;
+pub(crate) mod retarget;
 pub mod share_accounting;
+pub mod share_dedup;
+pub mod snapshot;
 pub mod standard;
+#[cfg(test)]
+pub mod test;
@@ -0,0 +1,113 @@
+//! Channel Retargeting - Mining Server Abstraction.
+//!
+//! Retargeting logic shared by [`ExtendedChannel`] and [`StandardChannel`], driven either by a
+//! [`Vardiff`] estimation or by an `UpdateChannel` message. Both channels lend their target state
+//! to a [`Retarget`], and map [`RetargetError`] into their own error types.
+//!
+//! [`ExtendedChannel`]: crate::server::extended::ExtendedChannel
+//! [`StandardChannel`]: crate::server::standard::StandardChannel
+
+use crate::{
+    target::{bytes_to_hex, hash_rate_to_target},
+    vardiff::{error::VardiffError, Vardiff},
+};
+use bitcoin::Target;
+use mining_sv2::{SetTarget, UpdateChannel};
+use std::convert::TryInto;
+use tracing::debug;
+
+/// Errors that can occur while retargeting a channel.
+#[derive(Debug)]
+pub(crate) enum RetargetError {
+    InvalidNominalHashrate,
+    VardiffError(VardiffError),
+}
+
+/// The target state of a channel, borrowed for retargeting.
+pub(crate) struct Retarget<'c> {
+    pub(crate) channel_id: u32,
+    pub(crate) expected_share_per_minute: f32,
+    pub(crate) nominal_hashrate: &'c mut f32,
+    pub(crate) target: &'c mut Target,
+    pub(crate) requested_max_target: &'c mut Target,
+}
+
+impl Retarget<'_> {
+    /// Runs `vardiff` against the channel state, retargeting the channel if it estimates a new
+    /// hashrate. The new target is clamped to the channel's requested max target.
+    pub(crate) fn try_vardiff(
+        self,
+        vardiff: &mut dyn Vardiff,
+    ) -> Result<Option<SetTarget<'static>>, RetargetError> {
+        let new_nominal_hashrate = vardiff
+            .try_vardiff(
+                *self.nominal_hashrate,
+                self.target,
+                self.expected_share_per_minute,
+            )
+            .map_err(RetargetError::VardiffError)?;
+        match new_nominal_hashrate {
+            Some(new_nominal_hashrate) => {
+                let requested_max_target = *self.requested_max_target;
+                self.retarget(new_nominal_hashrate, requested_max_target)
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Retargets the channel from the nominal hashrate and maximum target of `update_channel`,
+    /// clamping the new target to the maximum target, and restarts the `vardiff` cycle.
+    pub(crate) fn on_update_channel(
+        self,
+        update_channel: UpdateChannel,
+        vardiff: &mut dyn Vardiff,
+    ) -> Result<Option<SetTarget<'static>>, RetargetError> {
+        let requested_max_target = Target::from_le_bytes(
+            update_channel
+                .maximum_target
+                .inner_as_ref()
+                .try_into()
+                .expect("maximum target must be 32 bytes"),
+        );
+        let set_target = self.retarget(update_channel.nominal_hash_rate, requested_max_target)?;
+        vardiff
+            .reset_counter()
+            .map_err(RetargetError::VardiffError)?;
+        Ok(set_target)
+    }
+
+    // Sets the nominal hashrate and requested max target, and the target derived from them
+    // (clamped to the requested max target). Returns the `SetTarget` message for the new target,
+    // if it changed.
+    fn retarget(
+        self,
+        nominal_hashrate: f32,
+        requested_max_target: Target,
+    ) -> Result<Option<SetTarget<'static>>, RetargetError> {
+        let target = hash_rate_to_target(
+            nominal_hashrate.into(),
+            self.expected_share_per_minute.into(),
+        )
+        .map_err(|_| RetargetError::InvalidNominalHashrate)?
+        .min(requested_max_target);
+
+        *self.nominal_hashrate = nominal_hashrate;
+        *self.requested_max_target = requested_max_target;
+        if target == *self.target {
+            return Ok(None);
+        }
+
+        debug!(
+            "retargeting channel {} \nold target:\t{}\nnew target:\t{}",
+            self.channel_id,
+            bytes_to_hex(&self.target.to_be_bytes()),
+            bytes_to_hex(&target.to_be_bytes())
+        );
+        *self.target = target;
+
+        Ok(Some(SetTarget {
+            channel_id: self.channel_id,
+            maximum_target: target.to_le_bytes().into(),
+        }))
+    }
+}
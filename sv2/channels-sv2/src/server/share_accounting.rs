@@ -11,15 +11,45 @@
 //!   success, batch acknowledgment, and block discovery.
 //! - **Share Validation Error**: Enumerates possible failure reasons when validating a share.
 //! - **Share Accounting**: Tracks per-channel share statistics, acknowledges batches, detects
-//!   duplicate shares, and maintains best difficulty found.
+//!   duplicate shares, and maintains best difficulty found. Time is read from an injectable
+//!   [`Clock`].
+//! - **Per-Worker Accounting**: Tracks share statistics per worker, as identified by the
+//!   [`UserIdentity`] TLV of the Worker-Specific Hashrate Tracking extension, so that extended
+//!   channels aggregating many miners still provide per-worker statistics.
+//! - **Rejection Statistics**: Counts rejected shares per [`ShareValidationError`] variant, along
+//!   with the timestamp of the last rejection, to help diagnose misbehaving firmware.
+//! - **Rolling Hashrate**: Tracks accepted work over rolling windows (1m/5m/1h/24h) and derives
+//!   hashrate estimates from it, see [`RollingHashrate`].
 //!
 //! ## Usage
 //!
 //! Intended for use within mining server implementations that process SV2 share submissions and
 //! issue `SubmitShares.Success` messages. Not intended for use by mining clients.
 
-use bitcoin::hashes::sha256d::Hash;
-use std::collections::HashSet;
+use crate::{
+    clock::{Clock, SystemClock},
+    hashrate::{hashrate_from_work, HashrateWindow, RollingHashrate},
+    server::{
+        error::SnapshotError,
+        snapshot::{SnapshotReader, SnapshotWriter},
+    },
+};
+use bitcoin::hashes::{sha256d::Hash, Hash as _};
+use extensions_sv2::worker_specific_hashrate_tracking::UserIdentity;
+use mining_sv2::SubmitSharesError;
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    sync::Arc,
+};
+
+/// Default maximum number of seconds a share's `ntime` may be ahead of the current time.
+///
+/// Matches the Bitcoin consensus rule rejecting blocks timestamped more than two hours in the
+/// future.
+pub const DEFAULT_MAX_NTIME_DRIFT_SECS: u64 = 7_200;
+
+/// Default maximum number of workers tracked by a [`ShareAccounting`].
+pub const DEFAULT_MAX_WORKERS: usize = 10_000;
 
 /// The outcome of share validation, from the perspective of a Mining Server.
 ///
@@ -42,7 +72,7 @@ pub enum ShareValidationResult {
 }
 
 /// The error variants that can occur during share validation.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum ShareValidationError {
     /// The share is invalid for unspecified reasons.
     Invalid,
@@ -50,10 +80,19 @@ pub enum ShareValidationError {
     Stale,
     /// The submitted job ID does not refer to any known job for this channel.
     InvalidJobId,
+    /// The submitted job ID refers to a past job that was evicted by the job store's retention
+    /// policy.
+    JobEvicted,
     /// The share does not meet the required target difficulty.
     DoesNotMeetTarget,
     /// The submitted share attempts version rolling when not allowed.
     VersionRollingNotAllowed,
+    /// The share's version differs from the job's version outside of the BIP320 general purpose
+    /// bits.
+    InvalidVersion,
+    /// The share's `ntime` is earlier than the chain tip's `min_ntime`, or too far ahead of the
+    /// current time.
+    InvalidNtime,
     /// The share is a duplicate of a previously accepted share.
     DuplicateShare,
     /// The coinbase transaction was invalid or malformed.
@@ -62,6 +101,182 @@ pub enum ShareValidationError {
     NoChainTip,
     /// The share extranonce size is different from the channel's rollable extranonce size.
     BadExtranonceSize,
+    /// The share was submitted to a group channel on a standard channel that isn't a member of
+    /// it.
+    NotGroupMember,
+}
+
+/// Non-standard `SubmitShares.Error` code for shares with an `ntime` out of bounds.
+///
+/// The Sv2 spec only defines `invalid-channel-id`, `stale-share`, `difficulty-too-low` and
+/// `invalid-job-id`, the codes below are specific to this crate.
+pub const INVALID_NTIME_ERROR_CODE: &str = "invalid-ntime";
+/// Non-standard `SubmitShares.Error` code for shares with disallowed version bits.
+pub const INVALID_VERSION_ERROR_CODE: &str = "invalid-version";
+/// Non-standard `SubmitShares.Error` code for duplicate shares.
+pub const DUPLICATE_SHARE_ERROR_CODE: &str = "duplicate-share";
+/// Non-standard `SubmitShares.Error` code for otherwise invalid shares.
+pub const INVALID_SHARE_ERROR_CODE: &str = "invalid-share";
+
+impl ShareValidationError {
+    /// Returns the `error_code` to be sent in a `SubmitShares.Error` message for this error.
+    ///
+    /// Errors without an equivalent in the Sv2 spec are mapped to the non-standard codes above
+    /// (e.g. [`INVALID_NTIME_ERROR_CODE`]).
+    pub fn as_error_code(&self) -> &'static str {
+        match self {
+            ShareValidationError::NotGroupMember => SubmitSharesError::invalid_channel_error_code(),
+            ShareValidationError::Stale | ShareValidationError::JobEvicted => {
+                SubmitSharesError::stale_share_error_code()
+            }
+            ShareValidationError::InvalidJobId => SubmitSharesError::invalid_job_id_error_code(),
+            ShareValidationError::DoesNotMeetTarget => {
+                SubmitSharesError::difficulty_too_low_error_code()
+            }
+            ShareValidationError::VersionRollingNotAllowed
+            | ShareValidationError::InvalidVersion => INVALID_VERSION_ERROR_CODE,
+            ShareValidationError::InvalidNtime => INVALID_NTIME_ERROR_CODE,
+            ShareValidationError::DuplicateShare => DUPLICATE_SHARE_ERROR_CODE,
+            ShareValidationError::Invalid
+            | ShareValidationError::InvalidCoinbase
+            | ShareValidationError::NoChainTip
+            | ShareValidationError::BadExtranonceSize => INVALID_SHARE_ERROR_CODE,
+        }
+    }
+
+    // Identifies the variant in snapshots.
+    fn as_snapshot_id(&self) -> u8 {
+        match self {
+            ShareValidationError::Invalid => 0,
+            ShareValidationError::Stale => 1,
+            ShareValidationError::InvalidJobId => 2,
+            ShareValidationError::JobEvicted => 3,
+            ShareValidationError::DoesNotMeetTarget => 4,
+            ShareValidationError::VersionRollingNotAllowed => 5,
+            ShareValidationError::InvalidVersion => 6,
+            ShareValidationError::InvalidNtime => 7,
+            ShareValidationError::DuplicateShare => 8,
+            ShareValidationError::InvalidCoinbase => 9,
+            ShareValidationError::NoChainTip => 10,
+            ShareValidationError::BadExtranonceSize => 11,
+            ShareValidationError::NotGroupMember => 12,
+        }
+    }
+
+    fn from_snapshot_id(id: u8) -> Option<Self> {
+        Some(match id {
+            0 => ShareValidationError::Invalid,
+            1 => ShareValidationError::Stale,
+            2 => ShareValidationError::InvalidJobId,
+            3 => ShareValidationError::JobEvicted,
+            4 => ShareValidationError::DoesNotMeetTarget,
+            5 => ShareValidationError::VersionRollingNotAllowed,
+            6 => ShareValidationError::InvalidVersion,
+            7 => ShareValidationError::InvalidNtime,
+            8 => ShareValidationError::DuplicateShare,
+            9 => ShareValidationError::InvalidCoinbase,
+            10 => ShareValidationError::NoChainTip,
+            11 => ShareValidationError::BadExtranonceSize,
+            12 => ShareValidationError::NotGroupMember,
+            _ => return None,
+        })
+    }
+}
+
+/// Statistics of the shares rejected for a single [`ShareValidationError`] reason.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ShareRejectionStats {
+    count: u32,
+    last_timestamp: u64,
+}
+
+impl ShareRejectionStats {
+    /// Returns the number of shares rejected for this reason.
+    pub fn get_count(&self) -> u32 {
+        self.count
+    }
+
+    /// Returns the unix timestamp (seconds) of the last share rejected for this reason.
+    pub fn get_last_timestamp(&self) -> u64 {
+        self.last_timestamp
+    }
+}
+
+/// Share statistics of a single worker, as identified by its [`UserIdentity`].
+#[derive(Clone, Debug)]
+pub struct WorkerShareAccounting {
+    user_identity: UserIdentity,
+    shares_accepted: u32,
+    share_work_sum: f64,
+    best_diff: f64,
+    first_share_timestamp: u64,
+    last_share_timestamp: u64,
+}
+
+impl WorkerShareAccounting {
+    /// Returns the identity of the worker.
+    pub fn get_user_identity(&self) -> &UserIdentity {
+        &self.user_identity
+    }
+
+    /// Returns the number of shares accepted from this worker.
+    pub fn get_shares_accepted(&self) -> u32 {
+        self.shares_accepted
+    }
+
+    /// Returns the sum of work contributed by the shares accepted from this worker.
+    pub fn get_share_work_sum(&self) -> f64 {
+        self.share_work_sum
+    }
+
+    /// Returns the highest difficulty found among the shares accepted from this worker.
+    pub fn get_best_diff(&self) -> f64 {
+        self.best_diff
+    }
+
+    /// Returns the unix timestamp (seconds) of the first share accepted from this worker.
+    pub fn get_first_share_timestamp(&self) -> u64 {
+        self.first_share_timestamp
+    }
+
+    /// Returns the unix timestamp (seconds) of the last share accepted from this worker.
+    pub fn get_last_share_timestamp(&self) -> u64 {
+        self.last_share_timestamp
+    }
+
+    /// Returns the estimated hashrate (in hashes per second) of this worker at the unix timestamp
+    /// `now`, from the work accepted since its first share, see [`hashrate_from_work`].
+    ///
+    /// Returns `None` if no time elapsed since the first share.
+    pub fn get_estimated_hashrate(&self, now: u64) -> Option<f64> {
+        let elapsed_secs = now.checked_sub(self.first_share_timestamp)?;
+        if elapsed_secs == 0 {
+            return None;
+        }
+        Some(hashrate_from_work(self.share_work_sum, elapsed_secs))
+    }
+
+    fn write_snapshot(&self, writer: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+        writer.write_bytes(self.user_identity.as_bytes())?;
+        writer.write_u32(self.shares_accepted);
+        writer.write_f64(self.share_work_sum);
+        writer.write_f64(self.best_diff);
+        writer.write_u64(self.first_share_timestamp);
+        writer.write_u64(self.last_share_timestamp);
+        Ok(())
+    }
+
+    fn read_snapshot(reader: &mut SnapshotReader) -> Result<Self, SnapshotError> {
+        Ok(Self {
+            user_identity: UserIdentity::from_bytes(reader.read_bytes()?)
+                .map_err(|_| SnapshotError::InvalidUserIdentity)?,
+            shares_accepted: reader.read_u32()?,
+            share_work_sum: reader.read_f64()?,
+            best_diff: reader.read_f64()?,
+            first_share_timestamp: reader.read_u64()?,
+            last_share_timestamp: reader.read_u64()?,
+        })
+    }
 }
 
 /// The state of share validation in the context of some specific channel (either Extended or
@@ -79,6 +294,14 @@ pub struct ShareAccounting {
     share_batch_size: usize,
     seen_shares: HashSet<Hash>,
     best_diff: f64,
+    last_share_timestamp: Option<u64>,
+    workers: HashMap<Vec<u8>, WorkerShareAccounting>,
+    // Tracked workers ordered by the timestamp of their last share, oldest first
+    workers_by_activity: BTreeSet<(u64, Vec<u8>)>,
+    max_workers: usize,
+    rejections: HashMap<ShareValidationError, ShareRejectionStats>,
+    rolling_hashrate: RollingHashrate,
+    clock: Arc<dyn Clock>,
 }
 
 impl ShareAccounting {
@@ -86,6 +309,11 @@ impl ShareAccounting {
     ///
     /// `share_batch_size` controls how many accepted shares trigger a batch acknowledgment.
     pub fn new(share_batch_size: usize) -> Self {
+        Self::new_with_clock(share_batch_size, Arc::new(SystemClock))
+    }
+
+    /// Constructs a new `ShareAccounting` instance for a channel, reading time from `clock`.
+    pub fn new_with_clock(share_batch_size: usize, clock: Arc<dyn Clock>) -> Self {
         Self {
             last_share_sequence_number: 0,
             shares_accepted: 0,
@@ -95,25 +323,41 @@ impl ShareAccounting {
             share_batch_size,
             seen_shares: HashSet::new(),
             best_diff: 0.0,
+            last_share_timestamp: None,
+            workers: HashMap::new(),
+            workers_by_activity: BTreeSet::new(),
+            max_workers: DEFAULT_MAX_WORKERS,
+            rejections: HashMap::new(),
+            rolling_hashrate: RollingHashrate::new(),
+            clock,
         }
     }
 
+    /// Replaces the time source used by this `ShareAccounting`.
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+
     /// Updates internal accounting for a newly accepted share.
     ///
     /// - Increments total shares accepted and work sum.
     /// - Increments last batch accepted and work sum if the share batch size is reached.
-    /// - Updates last accepted sequence number.
+    /// - Updates last accepted sequence number and timestamp.
     /// - Records the share hash to detect duplicates.
+    /// - Records the share work in the rolling hashrate windows.
     pub fn update_share_accounting(
         &mut self,
         share_work: f64,
         share_sequence_number: u32,
         share_hash: Hash,
     ) {
+        let now = self.clock.now();
         self.last_share_sequence_number = share_sequence_number;
+        self.last_share_timestamp = Some(now);
         self.shares_accepted += 1;
         self.share_work_sum += share_work;
         self.seen_shares.insert(share_hash);
+        self.rolling_hashrate.record_share(now, share_work);
 
         if self.should_acknowledge() {
             let current_batch_accepted = self.shares_accepted - self.last_batch_accepted;
@@ -123,6 +367,130 @@ impl ShareAccounting {
         }
     }
 
+    /// Updates internal accounting for a share rejected with `error`.
+    ///
+    /// Increments the rejection count for `error` and records the current time as its last
+    /// occurrence.
+    pub fn update_rejected_share_accounting(&mut self, error: &ShareValidationError) {
+        let now = self.clock.now();
+        let stats = self.rejections.entry(*error).or_default();
+        stats.count = stats.count.saturating_add(1);
+        stats.last_timestamp = now;
+    }
+
+    /// Returns the statistics of the shares rejected with `error`, if any was.
+    pub fn get_rejection_stats(
+        &self,
+        error: &ShareValidationError,
+    ) -> Option<&ShareRejectionStats> {
+        self.rejections.get(error)
+    }
+
+    /// Returns the statistics of the shares rejected on this channel, for every reason at least
+    /// one share was rejected for.
+    pub fn get_all_rejection_stats(
+        &self,
+    ) -> impl Iterator<Item = (&ShareValidationError, &ShareRejectionStats)> {
+        self.rejections.iter()
+    }
+
+    /// Returns the total number of shares rejected on this channel.
+    pub fn get_shares_rejected(&self) -> u32 {
+        self.rejections
+            .values()
+            .fold(0u32, |count, stats| count.saturating_add(stats.count))
+    }
+
+    /// Updates the accounting of the worker identified by `user_identity` for a newly accepted
+    /// share, of work `share_work` and hash difficulty `share_diff`.
+    ///
+    /// To be called alongside [`ShareAccounting::update_share_accounting`]. Once `max_workers`
+    /// workers are tracked, a new worker replaces the least recently active one.
+    pub fn update_worker_share_accounting(
+        &mut self,
+        user_identity: &UserIdentity,
+        share_work: f64,
+        share_diff: f64,
+    ) {
+        let now = self.clock.now();
+        if !self.workers.contains_key(user_identity.as_bytes()) {
+            if self.max_workers == 0 {
+                return;
+            }
+            while self.workers.len() >= self.max_workers {
+                self.evict_least_recently_active_worker();
+            }
+            self.workers.insert(
+                user_identity.as_bytes().to_vec(),
+                WorkerShareAccounting {
+                    user_identity: user_identity.clone(),
+                    shares_accepted: 0,
+                    share_work_sum: 0.0,
+                    best_diff: 0.0,
+                    first_share_timestamp: now,
+                    last_share_timestamp: now,
+                },
+            );
+        }
+
+        let worker = self
+            .workers
+            .get_mut(user_identity.as_bytes())
+            .expect("worker must be tracked");
+        self.workers_by_activity.remove(&(
+            worker.last_share_timestamp,
+            user_identity.as_bytes().to_vec(),
+        ));
+        self.workers_by_activity
+            .insert((now, user_identity.as_bytes().to_vec()));
+        worker.shares_accepted += 1;
+        worker.share_work_sum += share_work;
+        worker.last_share_timestamp = now;
+        if share_diff > worker.best_diff {
+            worker.best_diff = share_diff;
+        }
+    }
+
+    // Stops tracking the worker whose last share is the oldest.
+    fn evict_least_recently_active_worker(&mut self) {
+        if let Some((_, key)) = self.workers_by_activity.pop_first() {
+            self.workers.remove(&key);
+        }
+    }
+
+    /// Returns the share statistics of the worker identified by `user_identity`, if tracked.
+    pub fn get_worker_share_accounting(
+        &self,
+        user_identity: &UserIdentity,
+    ) -> Option<&WorkerShareAccounting> {
+        self.workers.get(user_identity.as_bytes())
+    }
+
+    /// Returns the share statistics of all tracked workers.
+    pub fn get_workers_share_accounting(&self) -> impl Iterator<Item = &WorkerShareAccounting> {
+        self.workers.values()
+    }
+
+    /// Returns the estimated hashrate (in hashes per second) of the worker identified by
+    /// `user_identity`, see [`WorkerShareAccounting::get_estimated_hashrate`].
+    pub fn get_worker_estimated_hashrate(&self, user_identity: &UserIdentity) -> Option<f64> {
+        self.get_worker_share_accounting(user_identity)?
+            .get_estimated_hashrate(self.clock.now())
+    }
+
+    /// Returns the maximum number of tracked workers.
+    pub fn get_max_workers(&self) -> usize {
+        self.max_workers
+    }
+
+    /// Sets the maximum number of tracked workers.
+    ///
+    /// Defaults to [`DEFAULT_MAX_WORKERS`]. Already tracked workers are kept until a new worker is
+    /// tracked, see [`ShareAccounting::update_worker_share_accounting`].
+    pub fn set_max_workers(&mut self, max_workers: usize) {
+        self.max_workers = max_workers;
+    }
+
     /// Clears the set of seen share hashes.
     ///
     /// Should be called on every chain tip update to avoid unbounded growth of memory
@@ -136,6 +504,11 @@ impl ShareAccounting {
         self.last_share_sequence_number
     }
 
+    /// Returns the unix timestamp (seconds) of the last accepted share, if any.
+    pub fn get_last_share_timestamp(&self) -> Option<u64> {
+        self.last_share_timestamp
+    }
+
     /// Returns the number of shares accepted in the last batch.
     pub fn get_last_batch_accepted(&self) -> u32 {
         self.last_batch_accepted
@@ -146,6 +519,27 @@ impl ShareAccounting {
         self.last_batch_work_sum
     }
 
+    /// Returns the number of shares accepted within `window`.
+    pub fn get_window_shares_accepted(&self, window: HashrateWindow) -> u32 {
+        self.rolling_hashrate.get_shares(window, self.clock.now())
+    }
+
+    /// Returns the work of the shares accepted within `window`.
+    pub fn get_window_share_work_sum(&self, window: HashrateWindow) -> f64 {
+        self.rolling_hashrate.get_work(window, self.clock.now())
+    }
+
+    /// Returns the hashrate (H/s) estimated from the work of the shares accepted within
+    /// `window`.
+    pub fn get_window_hashrate(&self, window: HashrateWindow) -> f64 {
+        self.rolling_hashrate.get_hashrate(window, self.clock.now())
+    }
+
+    /// Returns the rolling windows of accepted work.
+    pub fn get_rolling_hashrate(&self) -> &RollingHashrate {
+        &self.rolling_hashrate
+    }
+
     /// Returns the total number of shares accepted on this channel.
     ///
     /// Note: this is not what we use for `SubmitShares.Success` messages.
@@ -188,4 +582,262 @@ impl ShareAccounting {
             self.best_diff = diff;
         }
     }
+
+    // Writes the share accounting state as part of a channel snapshot.
+    pub(crate) fn write_snapshot(&self, writer: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+        writer.write_u32(self.last_share_sequence_number);
+        writer.write_u32(self.shares_accepted);
+        writer.write_f64(self.share_work_sum);
+        writer.write_u32(self.last_batch_accepted);
+        writer.write_f64(self.last_batch_work_sum);
+        writer.write_u64(self.share_batch_size as u64);
+        let mut seen_shares: Vec<_> = self.seen_shares.iter().collect();
+        seen_shares.sort();
+        writer.write_len(seen_shares.len())?;
+        for share_hash in seen_shares {
+            writer.write_bytes(share_hash.as_byte_array())?;
+        }
+        writer.write_f64(self.best_diff);
+        writer.write_bool(self.last_share_timestamp.is_some());
+        writer.write_u64(self.last_share_timestamp.unwrap_or_default());
+        let mut workers: Vec<_> = self.workers.iter().collect();
+        workers.sort_by(|a, b| a.0.cmp(b.0));
+        writer.write_len(workers.len())?;
+        for (_, worker) in workers {
+            worker.write_snapshot(writer)?;
+        }
+        writer.write_u64(self.max_workers as u64);
+        let mut rejections: Vec<_> = self
+            .rejections
+            .iter()
+            .map(|(error, stats)| (error.as_snapshot_id(), stats))
+            .collect();
+        rejections.sort_by_key(|(id, _)| *id);
+        writer.write_len(rejections.len())?;
+        for (id, stats) in rejections {
+            writer.write_u8(id);
+            writer.write_u32(stats.count);
+            writer.write_u64(stats.last_timestamp);
+        }
+        self.rolling_hashrate.write_snapshot(writer)?;
+        Ok(())
+    }
+
+    // Reads the share accounting state written by `write_snapshot`, reading time from `clock`.
+    pub(crate) fn read_snapshot(
+        reader: &mut SnapshotReader,
+        clock: Arc<dyn Clock>,
+    ) -> Result<Self, SnapshotError> {
+        let last_share_sequence_number = reader.read_u32()?;
+        let shares_accepted = reader.read_u32()?;
+        let share_work_sum = reader.read_f64()?;
+        let last_batch_accepted = reader.read_u32()?;
+        let last_batch_work_sum = reader.read_f64()?;
+        let share_batch_size = reader.read_u64()? as usize;
+        let mut seen_shares = HashSet::new();
+        for _ in 0..reader.read_len()? {
+            let share_hash =
+                Hash::from_slice(reader.read_bytes()?).map_err(|_| SnapshotError::UnexpectedEnd)?;
+            seen_shares.insert(share_hash);
+        }
+        let best_diff = reader.read_f64()?;
+        let has_last_share_timestamp = reader.read_bool()?;
+        let last_share_timestamp = reader.read_u64()?;
+        let mut workers = HashMap::new();
+        let mut workers_by_activity = BTreeSet::new();
+        for _ in 0..reader.read_len()? {
+            let worker = WorkerShareAccounting::read_snapshot(reader)?;
+            let key = worker.user_identity.as_bytes().to_vec();
+            workers_by_activity.insert((worker.last_share_timestamp, key.clone()));
+            workers.insert(key, worker);
+        }
+        let max_workers = reader.read_u64()? as usize;
+        let mut rejections = HashMap::new();
+        for _ in 0..reader.read_len()? {
+            let error = ShareValidationError::from_snapshot_id(reader.read_u8()?)
+                .ok_or(SnapshotError::InvalidRejectionReason)?;
+            let stats = ShareRejectionStats {
+                count: reader.read_u32()?,
+                last_timestamp: reader.read_u64()?,
+            };
+            rejections.insert(error, stats);
+        }
+        let rolling_hashrate = RollingHashrate::read_snapshot(reader)?;
+
+        Ok(Self {
+            last_share_sequence_number,
+            shares_accepted,
+            share_work_sum,
+            last_batch_accepted,
+            last_batch_work_sum,
+            share_batch_size,
+            seen_shares,
+            best_diff,
+            last_share_timestamp: has_last_share_timestamp.then_some(last_share_timestamp),
+            workers,
+            workers_by_activity,
+            max_workers,
+            rejections,
+            rolling_hashrate,
+            clock,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+
+    #[test]
+    fn test_last_share_timestamp_follows_clock() {
+        let clock = MockClock::new(1_000);
+        let mut share_accounting = ShareAccounting::new_with_clock(10, Arc::new(clock.clone()));
+        assert_eq!(share_accounting.get_last_share_timestamp(), None);
+
+        share_accounting.update_share_accounting(1.0, 1, Hash::from_byte_array([1; 32]));
+        assert_eq!(share_accounting.get_last_share_timestamp(), Some(1_000));
+
+        clock.advance(30);
+        share_accounting.update_share_accounting(1.0, 2, Hash::from_byte_array([2; 32]));
+        assert_eq!(share_accounting.get_last_share_timestamp(), Some(1_030));
+    }
+
+    #[test]
+    fn test_worker_share_accounting() {
+        let clock = MockClock::new(1_000);
+        let mut share_accounting = ShareAccounting::new_with_clock(10, Arc::new(clock.clone()));
+        share_accounting.set_max_workers(2);
+        let alice = UserIdentity::new("alice").unwrap();
+        let bob = UserIdentity::new("bob").unwrap();
+        let carol = UserIdentity::new("carol").unwrap();
+
+        share_accounting.update_worker_share_accounting(&alice, 2.0, 10.0);
+        share_accounting.update_worker_share_accounting(&bob, 2.0, 3.0);
+        clock.advance(10);
+        share_accounting.update_worker_share_accounting(&alice, 2.0, 5.0);
+        // once `max_workers` is reached, a new worker replaces the least recently active one
+        share_accounting.update_worker_share_accounting(&carol, 2.0, 3.0);
+
+        let worker = share_accounting
+            .get_worker_share_accounting(&alice)
+            .unwrap();
+        assert_eq!(worker.get_user_identity(), &alice);
+        assert_eq!(worker.get_shares_accepted(), 2);
+        assert_eq!(worker.get_share_work_sum(), 4.0);
+        assert_eq!(worker.get_best_diff(), 10.0);
+        assert_eq!(worker.get_first_share_timestamp(), 1_000);
+        assert_eq!(worker.get_last_share_timestamp(), 1_010);
+        assert!(share_accounting.get_worker_share_accounting(&bob).is_none());
+        assert!(share_accounting
+            .get_worker_share_accounting(&carol)
+            .is_some());
+        assert_eq!(share_accounting.get_workers_share_accounting().count(), 2);
+
+        // 4 difficulty-1 shares in 10 seconds
+        assert_eq!(
+            share_accounting.get_worker_estimated_hashrate(&alice),
+            Some(hashrate_from_work(4.0, 10))
+        );
+        // no time elapsed since carol's first share
+        assert_eq!(share_accounting.get_worker_estimated_hashrate(&carol), None);
+
+        let mut writer = SnapshotWriter::new();
+        share_accounting.write_snapshot(&mut writer).unwrap();
+        let bytes = writer.into_bytes();
+        let mut reader = SnapshotReader::new(&bytes).unwrap();
+        let mut restored =
+            ShareAccounting::read_snapshot(&mut reader, Arc::new(clock.clone())).unwrap();
+        reader.finish().unwrap();
+        let worker = restored.get_worker_share_accounting(&alice).unwrap();
+        assert_eq!(worker.get_shares_accepted(), 2);
+        assert_eq!(worker.get_best_diff(), 10.0);
+        assert_eq!(restored.get_max_workers(), 2);
+
+        // the activity order of the workers is restored too
+        clock.advance(10);
+        restored.update_worker_share_accounting(&alice, 2.0, 3.0);
+        restored.update_worker_share_accounting(&bob, 2.0, 3.0);
+        assert!(restored.get_worker_share_accounting(&alice).is_some());
+        assert!(restored.get_worker_share_accounting(&carol).is_none());
+    }
+
+    #[test]
+    fn test_rejection_stats() {
+        let clock = MockClock::new(1_000);
+        let mut share_accounting = ShareAccounting::new_with_clock(10, Arc::new(clock.clone()));
+
+        share_accounting.update_rejected_share_accounting(&ShareValidationError::Stale);
+        clock.advance(5);
+        share_accounting.update_rejected_share_accounting(&ShareValidationError::Stale);
+        share_accounting.update_rejected_share_accounting(&ShareValidationError::DuplicateShare);
+
+        let stale = share_accounting
+            .get_rejection_stats(&ShareValidationError::Stale)
+            .unwrap();
+        assert_eq!(stale.get_count(), 2);
+        assert_eq!(stale.get_last_timestamp(), 1_005);
+        assert!(share_accounting
+            .get_rejection_stats(&ShareValidationError::DoesNotMeetTarget)
+            .is_none());
+        assert_eq!(share_accounting.get_shares_rejected(), 3);
+        assert_eq!(share_accounting.get_all_rejection_stats().count(), 2);
+
+        let mut writer = SnapshotWriter::new();
+        share_accounting.write_snapshot(&mut writer).unwrap();
+        let bytes = writer.into_bytes();
+        let mut reader = SnapshotReader::new(&bytes).unwrap();
+        let restored = ShareAccounting::read_snapshot(&mut reader, Arc::new(clock)).unwrap();
+        reader.finish().unwrap();
+        assert_eq!(
+            restored.get_rejection_stats(&ShareValidationError::DuplicateShare),
+            share_accounting.get_rejection_stats(&ShareValidationError::DuplicateShare)
+        );
+        assert_eq!(restored.get_shares_rejected(), 3);
+    }
+
+    #[test]
+    fn test_window_hashrate() {
+        let clock = MockClock::new(1_000_020);
+        let mut share_accounting = ShareAccounting::new_with_clock(10, Arc::new(clock.clone()));
+
+        for i in 0..30 {
+            clock.advance(2);
+            share_accounting.update_share_accounting(2.0, i, Hash::from_byte_array([i as u8; 32]));
+        }
+        assert_eq!(
+            share_accounting.get_window_shares_accepted(HashrateWindow::OneMinute),
+            30
+        );
+        assert_eq!(
+            share_accounting.get_window_share_work_sum(HashrateWindow::OneMinute),
+            60.0
+        );
+        // 60 difficulty-1 shares in a minute
+        let hashrate = share_accounting.get_window_hashrate(HashrateWindow::OneMinute);
+        assert!((hashrate - 4_294_967_296.0).abs() / 4_294_967_296.0 < 0.001);
+
+        let mut writer = SnapshotWriter::new();
+        share_accounting.write_snapshot(&mut writer).unwrap();
+        let bytes = writer.into_bytes();
+        let mut reader = SnapshotReader::new(&bytes).unwrap();
+        let restored =
+            ShareAccounting::read_snapshot(&mut reader, Arc::new(clock.clone())).unwrap();
+        reader.finish().unwrap();
+        assert_eq!(
+            restored.get_rolling_hashrate(),
+            share_accounting.get_rolling_hashrate()
+        );
+
+        // the shares leave the 1 minute window, but not the 5 minutes one
+        clock.advance(120);
+        assert_eq!(
+            share_accounting.get_window_hashrate(HashrateWindow::OneMinute),
+            0.0
+        );
+        assert_eq!(
+            share_accounting.get_window_shares_accepted(HashrateWindow::FiveMinutes),
+            30
+        );
+    }
 }
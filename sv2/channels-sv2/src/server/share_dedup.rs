@@ -0,0 +1,232 @@
+//! Share Deduplication - Mining Server Abstraction.
+//!
+//! [`ShareAccounting`] detects duplicate shares within a single channel. However, channels can
+//! share an extranonce space (e.g. after `SetExtranoncePrefix`, or standard channels belonging to
+//! the same group channel), which allows the same header to be submitted on several channels and
+//! be accounted for more than once.
+//!
+//! This module provides the [`ShareDeduplicator`] trait, a connection-wide or pool-wide service
+//! that channels consult before accepting a share, and [`DefaultShareDeduplicator`], an in-memory
+//! implementation.
+//!
+//! ## Responsibilities
+//!
+//! - **Cross-Channel Deduplication**: Rejects shares whose hash was already accepted on any
+//!   channel using the same deduplicator.
+//! - **Bounded Memory**: Share hashes are recorded per chain tip, and the sets of old chain tips
+//!   are dropped as new chain tips are notified.
+//!
+//! ## Usage
+//!
+//! Create a deduplicator, wrap it in an [`Arc`] and set it on every channel that should be
+//! checked against each other with `set_share_deduplicator`. Channels notify the deduplicator of
+//! their chain tip when it is set on them, and of every chain tip change.
+//!
+//! [`ShareAccounting`]: crate::server::share_accounting::ShareAccounting
+//! [`Arc`]: std::sync::Arc
+
+use bitcoin::hashes::sha256d::Hash;
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt::Debug,
+    sync::{Mutex, PoisonError},
+};
+
+/// Default number of chain tips for which share hashes are kept by a [`DefaultShareDeduplicator`].
+///
+/// Shares for jobs of a previous chain tip are rejected as stale by channels, so only the current
+/// chain tip and the one before it (to cover channels that didn't process the new chain tip yet)
+/// are kept.
+pub const DEFAULT_MAX_CHAIN_TIPS: usize = 2;
+
+// Number of dropped chain tips remembered by a `DefaultShareDeduplicator`, so that late
+// notifications of old chain tips don't replace the current ones.
+const MAX_DROPPED_CHAIN_TIPS: usize = 64;
+
+/// Trait for services detecting shares submitted more than once across channels.
+///
+/// Shares are identified by their hash, and grouped by the `prev_hash` of the chain tip they were
+/// submitted under. Implementations are shared across channels, so they must be safe to use
+/// concurrently.
+pub trait ShareDeduplicator: Debug + Send + Sync {
+    /// Records a share accepted under the chain tip with `prev_hash`.
+    ///
+    /// Returns `false` if the share was already recorded, in which case it must be rejected as a
+    /// duplicate. Shares for a chain tip that isn't tracked (never notified, or already dropped)
+    /// may be left unrecorded, in which case `true` is returned.
+    fn insert(&self, prev_hash: [u8; 32], share_hash: Hash) -> bool;
+
+    /// Returns `true` if the share was already recorded under the chain tip with `prev_hash`.
+    fn contains(&self, prev_hash: [u8; 32], share_hash: Hash) -> bool;
+
+    /// Notifies a new chain tip, allowing the shares recorded under older chain tips to be
+    /// dropped.
+    ///
+    /// As every channel notifies its chain tip, the same chain tip may be notified several times,
+    /// and a lagging channel may notify a chain tip older than the current one.
+    fn on_new_chain_tip(&self, prev_hash: [u8; 32]);
+}
+
+#[derive(Debug, Default)]
+struct DedupState {
+    // tracked chain tips, from the oldest to the newest
+    chain_tips: VecDeque<[u8; 32]>,
+    // chain tips no longer tracked, from the oldest to the newest
+    dropped_chain_tips: VecDeque<[u8; 32]>,
+    seen_shares: HashMap<[u8; 32], HashSet<Hash>>,
+}
+
+/// In-memory [`ShareDeduplicator`].
+///
+/// Keeps the share hashes of the last `max_chain_tips` chain tips notified with
+/// [`ShareDeduplicator::on_new_chain_tip`]. Notifications of a chain tip that was already dropped
+/// are ignored, so a lagging channel can't evict the current chain tip. Shares for a chain tip
+/// that isn't tracked are not recorded.
+#[derive(Debug)]
+pub struct DefaultShareDeduplicator {
+    state: Mutex<DedupState>,
+    max_chain_tips: usize,
+}
+
+impl Default for DefaultShareDeduplicator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DefaultShareDeduplicator {
+    /// Creates a new [`DefaultShareDeduplicator`], keeping the share hashes of the last
+    /// [`DEFAULT_MAX_CHAIN_TIPS`] chain tips.
+    pub fn new() -> Self {
+        Self::new_with_max_chain_tips(DEFAULT_MAX_CHAIN_TIPS)
+    }
+
+    /// Creates a new [`DefaultShareDeduplicator`], keeping the share hashes of the last
+    /// `max_chain_tips` chain tips (at least one).
+    pub fn new_with_max_chain_tips(max_chain_tips: usize) -> Self {
+        Self {
+            state: Mutex::new(DedupState::default()),
+            max_chain_tips: max_chain_tips.max(1),
+        }
+    }
+
+    /// Returns the number of chain tips for which share hashes are kept.
+    pub fn get_max_chain_tips(&self) -> usize {
+        self.max_chain_tips
+    }
+
+    /// Returns the number of share hashes currently recorded, across all kept chain tips.
+    pub fn get_share_count(&self) -> usize {
+        let state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        state.seen_shares.values().map(HashSet::len).sum()
+    }
+
+    /// Returns the tracked chain tips, from the oldest to the newest.
+    pub fn get_chain_tips(&self) -> Vec<[u8; 32]> {
+        let state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        state.chain_tips.iter().copied().collect()
+    }
+}
+
+impl ShareDeduplicator for DefaultShareDeduplicator {
+    fn insert(&self, prev_hash: [u8; 32], share_hash: Hash) -> bool {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        match state.seen_shares.get_mut(&prev_hash) {
+            Some(seen_shares) => seen_shares.insert(share_hash),
+            None => true,
+        }
+    }
+
+    fn contains(&self, prev_hash: [u8; 32], share_hash: Hash) -> bool {
+        let state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        state
+            .seen_shares
+            .get(&prev_hash)
+            .is_some_and(|seen_shares| seen_shares.contains(&share_hash))
+    }
+
+    fn on_new_chain_tip(&self, prev_hash: [u8; 32]) {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        if state.seen_shares.contains_key(&prev_hash)
+            || state.dropped_chain_tips.contains(&prev_hash)
+        {
+            return;
+        }
+        state.chain_tips.push_back(prev_hash);
+        state.seen_shares.insert(prev_hash, HashSet::new());
+        while state.chain_tips.len() > self.max_chain_tips {
+            if let Some(oldest) = state.chain_tips.pop_front() {
+                state.seen_shares.remove(&oldest);
+                state.dropped_chain_tips.push_back(oldest);
+            }
+        }
+        while state.dropped_chain_tips.len() > MAX_DROPPED_CHAIN_TIPS {
+            state.dropped_chain_tips.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::hashes::Hash as _;
+
+    #[test]
+    fn test_dedup_per_chain_tip() {
+        let dedup = DefaultShareDeduplicator::new();
+        let share_hash = Hash::hash(&[1]);
+        dedup.on_new_chain_tip([1; 32]);
+        dedup.on_new_chain_tip([2; 32]);
+
+        assert!(dedup.insert([1; 32], share_hash));
+        assert!(!dedup.insert([1; 32], share_hash));
+        assert!(dedup.contains([1; 32], share_hash));
+
+        // the same hash under another chain tip is a different share
+        assert!(!dedup.contains([2; 32], share_hash));
+        assert!(dedup.insert([2; 32], share_hash));
+        assert_eq!(dedup.get_share_count(), 2);
+    }
+
+    #[test]
+    fn test_dedup_bounded_chain_tips() {
+        let dedup = DefaultShareDeduplicator::new_with_max_chain_tips(2);
+        let share_hash = Hash::hash(&[1]);
+
+        dedup.on_new_chain_tip([1; 32]);
+        dedup.insert([1; 32], share_hash);
+        dedup.on_new_chain_tip([2; 32]);
+        // notifying a known chain tip doesn't drop anything
+        dedup.on_new_chain_tip([2; 32]);
+        assert!(dedup.contains([1; 32], share_hash));
+
+        // the oldest chain tip is dropped
+        dedup.on_new_chain_tip([3; 32]);
+        assert!(!dedup.contains([1; 32], share_hash));
+        assert_eq!(dedup.get_share_count(), 0);
+    }
+
+    #[test]
+    fn test_dedup_stale_chain_tips() {
+        let dedup = DefaultShareDeduplicator::new_with_max_chain_tips(2);
+        let share_hash = Hash::hash(&[1]);
+
+        dedup.on_new_chain_tip([1; 32]);
+        dedup.on_new_chain_tip([2; 32]);
+        dedup.on_new_chain_tip([3; 32]);
+        assert!(dedup.insert([3; 32], share_hash));
+
+        // a lagging channel notifying a dropped chain tip doesn't evict the current one
+        dedup.on_new_chain_tip([1; 32]);
+        assert_eq!(dedup.get_chain_tips(), vec![[2; 32], [3; 32]]);
+        assert!(dedup.contains([3; 32], share_hash));
+
+        // shares for a chain tip that isn't tracked are not recorded, and don't register it
+        assert!(dedup.insert([1; 32], share_hash));
+        assert!(dedup.insert([1; 32], share_hash));
+        assert!(dedup.insert([4; 32], share_hash));
+        assert_eq!(dedup.get_chain_tips(), vec![[2; 32], [3; 32]]);
+        assert!(!dedup.insert([3; 32], share_hash));
+        assert_eq!(dedup.get_share_count(), 1);
+    }
+}
@@ -0,0 +1,319 @@
+//! Snapshots - Mining Server Abstraction.
+//!
+//! This module provides the building blocks for taking serializable snapshots of job stores and
+//! channels, so that a pool process can be restarted or handed over without invalidating
+//! in-flight work.
+//!
+//! ## Responsibilities
+//!
+//! - **Snapshot Jobs**: The [`SnapshotJob`] trait is implemented by every job type that can be
+//!   persisted as part of a [`JobStoreSnapshot`](crate::server::jobs::job_store::JobStoreSnapshot).
+//! - **Encoding**: Crate-private helpers encoding snapshots into a compact, versioned,
+//!   length-prefixed binary format. Sv2 messages are encoded with `binary_sv2`, and coinbase
+//!   outputs with Bitcoin consensus encoding.
+//!
+//! ## Usage
+//!
+//! Call `snapshot()` on a job store or channel, persist the result of `to_bytes()`, and rebuild
+//! the state with `from_bytes()` followed by `from_snapshot()` after a restart.
+
+use crate::{
+    chain_tip::ChainTip,
+    server::{error::SnapshotError, jobs::Job},
+};
+use binary_sv2::{Decodable, Encodable, GetSize};
+use bitcoin::{
+    consensus::{deserialize, serialize},
+    transaction::TxOut,
+    Target,
+};
+use std::convert::TryInto;
+
+/// Version of the snapshot binary format.
+///
+/// Snapshots of any other version are rejected with [`SnapshotError::UnsupportedVersion`].
+pub(crate) const SNAPSHOT_VERSION: u8 = 1;
+
+/// Trait for job types that can be persisted as part of a snapshot.
+pub trait SnapshotJob: Job + Sized {
+    /// Serializes the job into bytes.
+    fn to_snapshot_bytes(&self) -> Result<Vec<u8>, SnapshotError>;
+
+    /// Deserializes a job previously serialized with [`SnapshotJob::to_snapshot_bytes`].
+    fn from_snapshot_bytes(bytes: &[u8]) -> Result<Self, SnapshotError>;
+}
+
+/// Decodes a Sv2 message read with [`SnapshotReader::read_sv2`].
+pub(crate) fn decode_sv2<'d, T: Decodable<'d>>(raw: &'d mut [u8]) -> Result<T, SnapshotError> {
+    binary_sv2::from_bytes(raw).map_err(|_| SnapshotError::FailedToDecodeMessage)
+}
+
+/// Appends snapshot fields to a byte buffer.
+///
+/// Variable-length fields are prefixed with their length as a little-endian `u32`.
+#[derive(Debug, Default)]
+pub(crate) struct SnapshotWriter {
+    buf: Vec<u8>,
+}
+
+impl SnapshotWriter {
+    /// Creates a new writer, starting with the snapshot format version.
+    pub(crate) fn new() -> Self {
+        let mut writer = Self::default();
+        writer.write_u8(SNAPSHOT_VERSION);
+        writer
+    }
+
+    pub(crate) fn write_u8(&mut self, value: u8) {
+        self.buf.push(value);
+    }
+
+    pub(crate) fn write_bool(&mut self, value: bool) {
+        self.write_u8(value as u8);
+    }
+
+    pub(crate) fn write_u16(&mut self, value: u16) {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub(crate) fn write_u32(&mut self, value: u32) {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub(crate) fn write_u64(&mut self, value: u64) {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub(crate) fn write_f32(&mut self, value: f32) {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub(crate) fn write_f64(&mut self, value: f64) {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub(crate) fn write_len(&mut self, len: usize) -> Result<(), SnapshotError> {
+        let len: u32 = len.try_into().map_err(|_| SnapshotError::TooLarge)?;
+        self.write_u32(len);
+        Ok(())
+    }
+
+    pub(crate) fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), SnapshotError> {
+        self.write_len(bytes.len())?;
+        self.buf.extend_from_slice(bytes);
+        Ok(())
+    }
+
+    pub(crate) fn write_string(&mut self, value: &str) -> Result<(), SnapshotError> {
+        self.write_bytes(value.as_bytes())
+    }
+
+    /// Writes a Sv2 message, encoded with `binary_sv2`.
+    pub(crate) fn write_sv2<T: Encodable + GetSize>(
+        &mut self,
+        message: T,
+    ) -> Result<(), SnapshotError> {
+        let bytes =
+            binary_sv2::to_bytes(message).map_err(|_| SnapshotError::FailedToEncodeMessage)?;
+        self.write_bytes(&bytes)
+    }
+
+    /// Writes coinbase outputs, with Bitcoin consensus encoding.
+    pub(crate) fn write_tx_outs(&mut self, tx_outs: &Vec<TxOut>) -> Result<(), SnapshotError> {
+        self.write_bytes(&serialize(tx_outs))
+    }
+
+    pub(crate) fn write_target(&mut self, target: &Target) -> Result<(), SnapshotError> {
+        self.write_bytes(&target.to_le_bytes())
+    }
+
+    pub(crate) fn write_option_chain_tip(
+        &mut self,
+        chain_tip: Option<&ChainTip>,
+    ) -> Result<(), SnapshotError> {
+        self.write_bool(chain_tip.is_some());
+        if let Some(chain_tip) = chain_tip {
+            self.write_bytes(chain_tip.prev_hash().inner_as_ref())?;
+            self.write_u32(chain_tip.nbits());
+            self.write_u32(chain_tip.min_ntime());
+        }
+        Ok(())
+    }
+
+    /// Writes a job, serialized with [`SnapshotJob::to_snapshot_bytes`].
+    pub(crate) fn write_job<T: SnapshotJob>(&mut self, job: &T) -> Result<(), SnapshotError> {
+        self.write_bytes(&job.to_snapshot_bytes()?)
+    }
+
+    pub(crate) fn into_bytes(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+/// Reads snapshot fields written by a [`SnapshotWriter`].
+#[derive(Debug)]
+pub(crate) struct SnapshotReader<'b> {
+    buf: &'b [u8],
+}
+
+impl<'b> SnapshotReader<'b> {
+    /// Creates a new reader, checking the snapshot format version.
+    pub(crate) fn new(buf: &'b [u8]) -> Result<Self, SnapshotError> {
+        let mut reader = Self { buf };
+        let version = reader.read_u8()?;
+        if version != SNAPSHOT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion(version));
+        }
+        Ok(reader)
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'b [u8], SnapshotError> {
+        if self.buf.len() < len {
+            return Err(SnapshotError::UnexpectedEnd);
+        }
+        let (head, tail) = self.buf.split_at(len);
+        self.buf = tail;
+        Ok(head)
+    }
+
+    fn take_array<const N: usize>(&mut self) -> Result<[u8; N], SnapshotError> {
+        // infallible, `take` returns exactly N bytes
+        Ok(self.take(N)?.try_into().expect("slice has length N"))
+    }
+
+    pub(crate) fn read_u8(&mut self) -> Result<u8, SnapshotError> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub(crate) fn read_bool(&mut self) -> Result<bool, SnapshotError> {
+        match self.read_u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            tag => Err(SnapshotError::InvalidTag(tag)),
+        }
+    }
+
+    pub(crate) fn read_u16(&mut self) -> Result<u16, SnapshotError> {
+        Ok(u16::from_le_bytes(self.take_array()?))
+    }
+
+    pub(crate) fn read_u32(&mut self) -> Result<u32, SnapshotError> {
+        Ok(u32::from_le_bytes(self.take_array()?))
+    }
+
+    pub(crate) fn read_u64(&mut self) -> Result<u64, SnapshotError> {
+        Ok(u64::from_le_bytes(self.take_array()?))
+    }
+
+    pub(crate) fn read_f32(&mut self) -> Result<f32, SnapshotError> {
+        Ok(f32::from_le_bytes(self.take_array()?))
+    }
+
+    pub(crate) fn read_f64(&mut self) -> Result<f64, SnapshotError> {
+        Ok(f64::from_le_bytes(self.take_array()?))
+    }
+
+    pub(crate) fn read_len(&mut self) -> Result<usize, SnapshotError> {
+        Ok(self.read_u32()? as usize)
+    }
+
+    pub(crate) fn read_bytes(&mut self) -> Result<&'b [u8], SnapshotError> {
+        let len = self.read_len()?;
+        self.take(len)
+    }
+
+    pub(crate) fn read_string(&mut self) -> Result<String, SnapshotError> {
+        String::from_utf8(self.read_bytes()?.to_vec()).map_err(|_| SnapshotError::InvalidUtf8)
+    }
+
+    /// Reads the raw bytes of a Sv2 message.
+    ///
+    /// The returned buffer must be decoded with `binary_sv2::from_bytes` and made `'static`.
+    pub(crate) fn read_sv2(&mut self) -> Result<Vec<u8>, SnapshotError> {
+        Ok(self.read_bytes()?.to_vec())
+    }
+
+    /// Reads coinbase outputs written with [`SnapshotWriter::write_tx_outs`].
+    pub(crate) fn read_tx_outs(&mut self) -> Result<Vec<TxOut>, SnapshotError> {
+        deserialize(self.read_bytes()?).map_err(|_| SnapshotError::FailedToDecodeCoinbaseOutputs)
+    }
+
+    pub(crate) fn read_target(&mut self) -> Result<Target, SnapshotError> {
+        let target: [u8; 32] = self
+            .read_bytes()?
+            .try_into()
+            .map_err(|_| SnapshotError::UnexpectedEnd)?;
+        Ok(Target::from_le_bytes(target))
+    }
+
+    pub(crate) fn read_option_chain_tip(&mut self) -> Result<Option<ChainTip>, SnapshotError> {
+        if !self.read_bool()? {
+            return Ok(None);
+        }
+        let prev_hash: [u8; 32] = self
+            .read_bytes()?
+            .try_into()
+            .map_err(|_| SnapshotError::UnexpectedEnd)?;
+        let nbits = self.read_u32()?;
+        let min_ntime = self.read_u32()?;
+        Ok(Some(ChainTip::new(prev_hash.into(), nbits, min_ntime)))
+    }
+
+    /// Reads a job written with [`SnapshotWriter::write_job`].
+    pub(crate) fn read_job<T: SnapshotJob>(&mut self) -> Result<T, SnapshotError> {
+        T::from_snapshot_bytes(self.read_bytes()?)
+    }
+
+    /// Ensures the whole buffer was consumed.
+    pub(crate) fn finish(self) -> Result<(), SnapshotError> {
+        if !self.buf.is_empty() {
+            return Err(SnapshotError::TrailingBytes);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_fields() {
+        let mut writer = SnapshotWriter::new();
+        writer.write_bool(true);
+        writer.write_u16(7);
+        writer.write_u32(42);
+        writer.write_u64(u64::MAX);
+        writer.write_f32(1.5);
+        writer.write_f64(-2.25);
+        writer.write_string("pool").unwrap();
+        let bytes = writer.into_bytes();
+
+        let mut reader = SnapshotReader::new(&bytes).unwrap();
+        assert!(reader.read_bool().unwrap());
+        assert_eq!(reader.read_u16().unwrap(), 7);
+        assert_eq!(reader.read_u32().unwrap(), 42);
+        assert_eq!(reader.read_u64().unwrap(), u64::MAX);
+        assert_eq!(reader.read_f32().unwrap(), 1.5);
+        assert_eq!(reader.read_f64().unwrap(), -2.25);
+        assert_eq!(reader.read_string().unwrap(), "pool");
+        reader.finish().unwrap();
+    }
+
+    #[test]
+    fn test_rejects_malformed_snapshots() {
+        assert!(matches!(
+            SnapshotReader::new(&[SNAPSHOT_VERSION + 1]),
+            Err(SnapshotError::UnsupportedVersion(_))
+        ));
+
+        let mut reader = SnapshotReader::new(&[SNAPSHOT_VERSION, 0xff]).unwrap();
+        assert!(matches!(
+            reader.read_u32(),
+            Err(SnapshotError::UnexpectedEnd)
+        ));
+
+        let reader = SnapshotReader::new(&[SNAPSHOT_VERSION, 0]).unwrap();
+        assert!(matches!(reader.finish(), Err(SnapshotError::TrailingBytes)));
+    }
+}
@@ -35,14 +35,27 @@
 //! - Job lifecycle and share accounting are managed on a per-channel basis.
 use crate::{
     chain_tip::ChainTip,
+    clock::{Clock, SystemClock},
     server::{
-        error::StandardChannelError,
+        auxpow::{AuxPowSolution, MergedMiningTree, MergedMiningTrees},
+        error::{SnapshotError, StandardChannelError},
         jobs::{
-            extended::ExtendedJob, factory::JobFactory, job_store::JobStore, standard::StandardJob,
+            extended::ExtendedJob,
+            factory::JobFactory,
+            job_store::{JobStore, JobStoreSnapshot},
+            script_sig::ScriptSigLayout,
+            standard::StandardJob,
         },
-        share_accounting::{ShareAccounting, ShareValidationError, ShareValidationResult},
+        retarget::Retarget,
+        share_accounting::{
+            ShareAccounting, ShareValidationError, ShareValidationResult,
+            DEFAULT_MAX_NTIME_DRIFT_SECS,
+        },
+        share_dedup::ShareDeduplicator,
+        snapshot::{SnapshotReader, SnapshotWriter},
     },
     target::{bytes_to_hex, hash_rate_to_target, u256_to_block_hash},
+    vardiff::Vardiff,
     MAX_EXTRANONCE_PREFIX_LEN,
 };
 use bitcoin::{
@@ -51,15 +64,16 @@ use bitcoin::{
         block::{Header, Version},
         witness::Witness,
     },
-    consensus::Encodable,
+    consensus::{self, Encodable},
     hashes::sha256d::Hash,
     transaction::{OutPoint, Transaction, TxIn, TxOut, Version as TxVersion},
     CompactTarget, Sequence, Target,
 };
-use mining_sv2::SubmitSharesStandard;
-use std::{collections::HashMap, convert::TryInto, marker::PhantomData};
-use template_distribution_sv2::{NewTemplate, SetNewPrevHash};
-use tracing::debug;
+use extensions_sv2::worker_specific_hashrate_tracking::UserIdentity;
+use mining_sv2::{SetTarget, SubmitSharesStandard, UpdateChannel};
+use std::{collections::HashMap, convert::TryInto, marker::PhantomData, sync::Arc};
+use template_distribution_sv2::{CoinbaseOutputConstraints, NewTemplate, SetNewPrevHash};
+use tracing::{debug, error};
 
 /// Abstraction of a Sv2 Standard Channel.
 ///
@@ -79,6 +93,9 @@ use tracing::debug;
 ///   indexed by `job_id`)
 /// - the channel's job factory
 /// - the channel's chain tip
+/// - the channel's maximum forward ntime drift
+/// - the channel's share deduplicator (shared with other channels)
+/// - the channel's merged mining trees
 #[derive(Debug)]
 pub struct StandardChannel<'a, J>
 where
@@ -95,6 +112,10 @@ where
     job_store: J,
     job_factory: JobFactory,
     chain_tip: Option<ChainTip>,
+    max_ntime_drift_secs: u64,
+    share_deduplicator: Option<Arc<dyn ShareDeduplicator>>,
+    merged_mining_trees: MergedMiningTrees,
+    clock: Arc<dyn Clock>,
     phantom: PhantomData<&'a ()>,
 }
 
@@ -219,6 +240,8 @@ where
             return Err(StandardChannelError::ScriptSigSizeTooLarge);
         }
 
+        let clock: Arc<dyn Clock> = Arc::new(SystemClock);
+
         Ok(Self {
             channel_id,
             user_identity,
@@ -226,11 +249,15 @@ where
             requested_max_target,
             target,
             nominal_hashrate,
-            share_accounting: ShareAccounting::new(share_batch_size),
+            share_accounting: ShareAccounting::new_with_clock(share_batch_size, clock.clone()),
             expected_share_per_minute,
             job_factory: JobFactory::new(true, pool_tag_string, miner_tag_string),
             chain_tip: None,
             job_store,
+            max_ntime_drift_secs: DEFAULT_MAX_NTIME_DRIFT_SECS,
+            share_deduplicator: None,
+            merged_mining_trees: MergedMiningTrees::default(),
+            clock,
             phantom: PhantomData,
         })
     }
@@ -297,6 +324,10 @@ where
     /// - vardiff algorithm estimated a new nominal hashrate, in which case `requested_max_target`
     ///   is `None` and we use the value from the channel state (that was set either during channel
     ///   opening or some previous `UpdateChannel` message).
+    ///
+    /// Unlike [`StandardChannel::on_update_channel`] and [`StandardChannel::try_vardiff`], a
+    /// target above the requested max target is rejected with
+    /// [`StandardChannelError::RequestedMaxTargetOutOfRange`], leaving the channel state unchanged.
     pub fn update_channel(
         &mut self,
         nominal_hashrate: f32,
@@ -347,6 +378,53 @@ where
         self.requested_max_target = requested_max_target;
         Ok(())
     }
+
+    /// Runs `vardiff` against the channel state, retargeting the channel if it estimates a new
+    /// hashrate.
+    ///
+    /// `vardiff` is expected to be dedicated to this channel, and to be notified of every accepted
+    /// share via [`Vardiff::increment_shares_since_last_update`]. The new target is derived from
+    /// the estimated hashrate and the channel's expected share rate, and clamped to the channel's
+    /// requested max target.
+    ///
+    /// Returns the `SetTarget` message to send downstream if the channel's target changed, or
+    /// `None` otherwise.
+    pub fn try_vardiff(
+        &mut self,
+        vardiff: &mut dyn Vardiff,
+    ) -> Result<Option<SetTarget<'static>>, StandardChannelError> {
+        Ok(self.retarget().try_vardiff(vardiff)?)
+    }
+
+    /// Handles an `UpdateChannel` message, retargeting the channel from its nominal hashrate and
+    /// maximum target.
+    ///
+    /// Unlike [`StandardChannel::update_channel`], a target above the requested max target is
+    /// clamped to it instead of rejected, as the downstream must be able to keep mining. The
+    /// `vardiff` cycle of the channel is restarted, since the shares counted so far were found
+    /// under the previous target.
+    ///
+    /// Returns the `SetTarget` message to send downstream if the channel's target changed, or
+    /// `None` otherwise.
+    pub fn on_update_channel(
+        &mut self,
+        update_channel: UpdateChannel,
+        vardiff: &mut dyn Vardiff,
+    ) -> Result<Option<SetTarget<'static>>, StandardChannelError> {
+        Ok(self.retarget().on_update_channel(update_channel, vardiff)?)
+    }
+
+    // Lends the target state of the channel for retargeting.
+    fn retarget(&mut self) -> Retarget<'_> {
+        Retarget {
+            channel_id: self.channel_id,
+            expected_share_per_minute: self.expected_share_per_minute,
+            nominal_hashrate: &mut self.nominal_hashrate,
+            target: &mut self.target,
+            requested_max_target: &mut self.requested_max_target,
+        }
+    }
+
     /// Returns the currently active job, if any.
     pub fn get_active_job(&self) -> Option<&StandardJob<'a>> {
         self.job_store.get_active_job()
@@ -392,6 +470,163 @@ where
         &self.share_accounting
     }
 
+    /// Captures the current state of the channel, including its job store, share accounting and
+    /// job factory.
+    ///
+    /// The snapshot can be serialized with [`StandardChannelSnapshot::to_bytes`] and used to
+    /// restore the channel with [`StandardChannel::from_snapshot`], so that shares submitted for
+    /// jobs sent before a restart are still accepted.
+    pub fn snapshot(&self) -> StandardChannelSnapshot<'a> {
+        StandardChannelSnapshot {
+            channel_id: self.channel_id,
+            user_identity: self.user_identity.clone(),
+            extranonce_prefix: self.extranonce_prefix.clone(),
+            requested_max_target: self.requested_max_target,
+            target: self.target,
+            nominal_hashrate: self.nominal_hashrate,
+            share_accounting: self.share_accounting.clone(),
+            expected_share_per_minute: self.expected_share_per_minute,
+            job_store: self.job_store.snapshot(),
+            job_factory: self.job_factory.clone(),
+            chain_tip: self.chain_tip.clone(),
+            max_ntime_drift_secs: self.max_ntime_drift_secs,
+            merged_mining_trees: self.merged_mining_trees.clone(),
+        }
+    }
+
+    /// Restores a channel from a snapshot.
+    ///
+    /// The restored channel has no share deduplicator, see
+    /// [`StandardChannel::set_share_deduplicator`].
+    ///
+    /// The restored channel reads time from a [`SystemClock`], see [`StandardChannel::set_clock`].
+    pub fn from_snapshot(snapshot: StandardChannelSnapshot<'a>) -> Self
+    where
+        J: From<JobStoreSnapshot<StandardJob<'a>>>,
+    {
+        let clock: Arc<dyn Clock> = Arc::new(SystemClock);
+        let mut share_accounting = snapshot.share_accounting;
+        share_accounting.set_clock(clock.clone());
+
+        Self {
+            channel_id: snapshot.channel_id,
+            user_identity: snapshot.user_identity,
+            extranonce_prefix: snapshot.extranonce_prefix,
+            requested_max_target: snapshot.requested_max_target,
+            target: snapshot.target,
+            nominal_hashrate: snapshot.nominal_hashrate,
+            share_accounting,
+            expected_share_per_minute: snapshot.expected_share_per_minute,
+            job_store: J::from(snapshot.job_store),
+            job_factory: snapshot.job_factory,
+            chain_tip: snapshot.chain_tip,
+            max_ntime_drift_secs: snapshot.max_ntime_drift_secs,
+            share_deduplicator: None,
+            merged_mining_trees: snapshot.merged_mining_trees,
+            clock,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Returns how far ahead of the current time (in seconds) a share's `ntime` may be.
+    pub fn get_max_ntime_drift_secs(&self) -> u64 {
+        self.max_ntime_drift_secs
+    }
+
+    /// Sets how far ahead of the current time (in seconds) a share's `ntime` may be.
+    ///
+    /// Defaults to [`DEFAULT_MAX_NTIME_DRIFT_SECS`].
+    pub fn set_max_ntime_drift_secs(&mut self, max_ntime_drift_secs: u64) {
+        self.max_ntime_drift_secs = max_ntime_drift_secs;
+    }
+
+    /// Sets the layout of the coinbase scriptSig pushes placed between the BIP34 block height and
+    /// the extranonce, replacing the pool and miner tags.
+    ///
+    /// See [`JobFactory::set_script_sig_layout`].
+    pub fn set_script_sig_layout(&mut self, script_sig_layout: ScriptSigLayout) {
+        self.job_factory.set_script_sig_layout(script_sig_layout);
+    }
+
+    /// Sets the `CoinbaseOutputConstraints` declared to the Template Provider, which jobs created
+    /// from templates must comply with.
+    ///
+    /// See [`JobFactory::set_coinbase_output_constraints`].
+    pub fn set_coinbase_output_constraints(
+        &mut self,
+        coinbase_output_constraints: Option<CoinbaseOutputConstraints>,
+    ) {
+        self.job_factory
+            .set_coinbase_output_constraints(coinbase_output_constraints);
+    }
+
+    /// Returns the merged mining tree committed to by new jobs, if any.
+    pub fn get_merged_mining_tree(&self) -> Option<&MergedMiningTree> {
+        self.merged_mining_trees.get_current()
+    }
+
+    /// Sets the merged mining tree committed to by new jobs, or stops merge-mining if `None`.
+    ///
+    /// Only affects jobs created with [`StandardChannel::on_new_template`] after this call, see
+    /// [`JobFactory::set_merged_mining_commitment`]. Shares for jobs committing to a previous tree
+    /// are still checked against that tree's auxiliary chains, until the next chain tip change.
+    pub fn set_merged_mining_tree(&mut self, merged_mining_tree: Option<MergedMiningTree>) {
+        self.job_factory.set_merged_mining_commitment(
+            merged_mining_tree
+                .as_ref()
+                .map(|merged_mining_tree| merged_mining_tree.commitment()),
+        );
+        self.merged_mining_trees.set_current(merged_mining_tree);
+    }
+
+    /// Returns the share deduplicator shared with other channels, if any.
+    pub fn get_share_deduplicator(&self) -> Option<Arc<dyn ShareDeduplicator>> {
+        self.share_deduplicator.clone()
+    }
+
+    /// Sets the share deduplicator shared with other channels, so that a share accepted on any of
+    /// them is rejected as a duplicate on the others.
+    ///
+    /// The share deduplicator is notified of the channel's chain tip, if any. It is not part of
+    /// the channel snapshot.
+    pub fn set_share_deduplicator(
+        &mut self,
+        share_deduplicator: Option<Arc<dyn ShareDeduplicator>>,
+    ) {
+        self.share_deduplicator = share_deduplicator;
+        self.notify_share_deduplicator();
+    }
+
+    // Notifies the share deduplicator (if any) of the channel's chain tip.
+    fn notify_share_deduplicator(&self) {
+        if let (Some(share_deduplicator), Some(chain_tip)) =
+            (&self.share_deduplicator, &self.chain_tip)
+        {
+            share_deduplicator.on_new_chain_tip(
+                chain_tip
+                    .prev_hash()
+                    .inner_as_ref()
+                    .try_into()
+                    .expect("prev hash must be 32 bytes"),
+            );
+        }
+    }
+
+    /// Returns the time source used by this channel.
+    pub fn get_clock(&self) -> Arc<dyn Clock> {
+        self.clock.clone()
+    }
+
+    /// Replaces the time source used by this channel, its share accounting and its job store.
+    ///
+    /// Channels are created with a [`SystemClock`]. Simulations and tests can inject a
+    /// [`crate::clock::MockClock`] to drive time explicitly.
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.share_accounting.set_clock(clock.clone());
+        self.job_store.set_clock(clock.clone());
+        self.clock = clock;
+    }
+
     /// Updates the channel state with a new job.
     ///
     /// If the template is a future template, the chain tip is not used.
@@ -502,8 +737,23 @@ where
         // clear seen shares, as shares for past chain tip will be rejected as stale
         self.share_accounting.flush_seen_shares();
 
+        // drop the merged mining trees only committed to by stale jobs
+        let merged_mining_commitments: Vec<_> = self
+            .job_store
+            .get_active_job()
+            .into_iter()
+            .chain(self.job_store.get_future_jobs().values())
+            .filter_map(|job| job.get_merged_mining_commitment())
+            .collect();
+        self.merged_mining_trees.retain(
+            merged_mining_commitments
+                .iter()
+                .map(|commitment| commitment.as_slice()),
+        );
+
         // update the chain tip
         self.chain_tip = Some(set_new_prev_hash.into());
+        self.notify_share_deduplicator();
 
         Ok(())
     }
@@ -511,48 +761,168 @@ where
     /// Validates a submitted share and updates accounting state.
     ///
     /// Returns the result of share validation, including block found, valid share, duplicate, or
-    /// error if the share is stale or does not meet target.
+    /// error if the share is stale or does not meet target. Rejected shares are accounted for in
+    /// the rejection statistics of the channel's [`ShareAccounting`].
+    ///
+    /// Auxiliary chains whose targets are met by the share are not reported, see
+    /// [`StandardChannel::validate_share_with_aux_pow`].
     pub fn validate_share(
         &mut self,
         share: SubmitSharesStandard,
     ) -> Result<ShareValidationResult, ShareValidationError> {
-        let job_id = share.job_id;
+        self.validate_and_account_share(share, None)
+            .map(|(result, _)| result)
+    }
 
-        // check if job_id is active job
-        let is_active_job = self
-            .job_store
-            .get_active_job()
-            .is_some_and(|job| job.get_job_id() == job_id);
+    /// Validates a share submitted on behalf of the worker identified by `user_identity` (as
+    /// carried by the share's Worker-Specific Hashrate Tracking TLV), if any, also reporting the
+    /// auxiliary chains whose targets are met by it as in [`StandardChannel::validate_share_with_aux_pow`].
+    ///
+    /// Accepted shares are also accounted for in the worker's statistics, see
+    /// [`ShareAccounting::get_worker_share_accounting`].
+    pub fn validate_share_with_user_identity(
+        &mut self,
+        share: SubmitSharesStandard,
+        user_identity: Option<&UserIdentity>,
+    ) -> Result<(ShareValidationResult, Vec<AuxPowSolution>), ShareValidationError> {
+        self.validate_and_account_share(share, user_identity)
+    }
 
-        // check if job_id is past job
-        let is_past_job = self.job_store.get_past_jobs().contains_key(&job_id);
+    /// Validates a share, also reporting the auxiliary chains whose targets are met by it.
+    ///
+    /// Along with the [`ShareValidationResult`], returns an [`AuxPowSolution`] for every auxiliary
+    /// chain committed to by the share's job (see [`StandardChannel::set_merged_mining_tree`])
+    /// whose target is met. Only accepted shares are checked against auxiliary targets, and
+    /// proofs that can't be built are logged and left out.
+    ///
+    /// Updates the channel state with the result of the share validation.
+    pub fn validate_share_with_aux_pow(
+        &mut self,
+        share: SubmitSharesStandard,
+    ) -> Result<(ShareValidationResult, Vec<AuxPowSolution>), ShareValidationError> {
+        self.validate_and_account_share(share, None)
+    }
 
-        // check if job_id is stale job
-        let is_stale_job = self.job_store.get_stale_jobs().contains_key(&job_id);
+    // Validates a share on behalf of the public `validate_share*` methods, accounting for
+    // rejected shares in the rejection statistics of the channel's `ShareAccounting`, and for
+    // accepted shares in the statistics of the worker identified by `user_identity`, if provided.
+    fn validate_and_account_share(
+        &mut self,
+        share: SubmitSharesStandard,
+        user_identity: Option<&UserIdentity>,
+    ) -> Result<(ShareValidationResult, Vec<AuxPowSolution>), ShareValidationError> {
+        let result = self.check_share(share, None, user_identity);
+        if let Err(error) = &result {
+            self.share_accounting
+                .update_rejected_share_accounting(error);
+        }
+        result
+    }
 
-        if is_stale_job {
-            return Err(ShareValidationError::Stale);
+    // Validates a share for a job of the group channel this channel belongs to, on behalf of
+    // `GroupChannel::validate_share`.
+    //
+    // `job` is the group job converted for this channel (or the reason it couldn't be found), and
+    // `job_factory` is the group channel's job factory, which created the job. Once the job is
+    // found, the channel follows the group channel's chain tip, since it doesn't receive the
+    // group's `SetNewPrevHash`.
+    pub(crate) fn validate_group_share(
+        &mut self,
+        share: SubmitSharesStandard,
+        job: Result<StandardJob<'a>, ShareValidationError>,
+        chain_tip: Option<&ChainTip>,
+        job_factory: &JobFactory,
+    ) -> Result<ShareValidationResult, ShareValidationError> {
+        let result = job.and_then(|job| {
+            if let Some(chain_tip) = chain_tip {
+                self.follow_group_chain_tip(chain_tip);
+            }
+            self.check_share(share, Some((&job, job_factory)), None)
+        });
+        if let Err(error) = &result {
+            self.share_accounting
+                .update_rejected_share_accounting(error);
         }
+        result.map(|(result, _)| result)
+    }
 
-        // if job_id is not active, past or stale, return error
-        if !is_active_job && !is_past_job && !is_stale_job {
-            return Err(ShareValidationError::InvalidJobId);
+    // Updates the chain tip to the group channel's one, flushing seen shares if it changed.
+    fn follow_group_chain_tip(&mut self, chain_tip: &ChainTip) {
+        let is_new_chain_tip = match &self.chain_tip {
+            Some(current) => {
+                current.prev_hash().inner_as_ref() != chain_tip.prev_hash().inner_as_ref()
+                    || current.nbits() != chain_tip.nbits()
+                    || current.min_ntime() != chain_tip.min_ntime()
+            }
+            None => true,
+        };
+        if is_new_chain_tip {
+            self.share_accounting.flush_seen_shares();
+            self.chain_tip = Some(chain_tip.clone());
+            self.notify_share_deduplicator();
         }
+    }
 
-        let job = if is_active_job {
-            self.job_store
-                .get_active_job()
-                .expect("active job must exist")
-        } else if is_past_job {
-            self.job_store
-                .get_past_jobs()
-                .get(&job_id)
-                .expect("past job must exist")
-        } else {
-            self.job_store
-                .get_stale_jobs()
-                .get(&job_id)
-                .expect("stale job must exist")
+    // Validates a share, updating the channel state for accepted shares only.
+    //
+    // The share's job is looked up in the channel's job store, unless a group channel job (along
+    // with the job factory that created it) is provided. Accepted shares are also accounted for
+    // in the statistics of the worker identified by `user_identity`, if provided.
+    fn check_share(
+        &mut self,
+        share: SubmitSharesStandard,
+        group_job: Option<(&StandardJob<'a>, &JobFactory)>,
+        user_identity: Option<&UserIdentity>,
+    ) -> Result<(ShareValidationResult, Vec<AuxPowSolution>), ShareValidationError> {
+        let (job, job_factory) = match group_job {
+            Some(group_job) => group_job,
+            None => {
+                let job_id = share.job_id;
+
+                // drop past jobs that exceed the job store's retention policy
+                self.job_store.evict_expired_jobs();
+
+                // check if job_id is active job
+                let is_active_job = self
+                    .job_store
+                    .get_active_job()
+                    .is_some_and(|job| job.get_job_id() == job_id);
+
+                // check if job_id is past job
+                let is_past_job = self.job_store.get_past_jobs().contains_key(&job_id);
+
+                // check if job_id is stale job
+                let is_stale_job = self.job_store.get_stale_jobs().contains_key(&job_id);
+
+                if is_stale_job {
+                    return Err(ShareValidationError::Stale);
+                }
+
+                // if job_id is not active, past or stale, return error
+                if !is_active_job && !is_past_job && !is_stale_job {
+                    if self.job_store.is_job_evicted(job_id) {
+                        return Err(ShareValidationError::JobEvicted);
+                    }
+                    return Err(ShareValidationError::InvalidJobId);
+                }
+
+                let job = if is_active_job {
+                    self.job_store
+                        .get_active_job()
+                        .expect("active job must exist")
+                } else if is_past_job {
+                    self.job_store
+                        .get_past_jobs()
+                        .get(&job_id)
+                        .expect("past job must exist")
+                } else {
+                    self.job_store
+                        .get_stale_jobs()
+                        .get(&job_id)
+                        .expect("stale job must exist")
+                };
+                (job, &self.job_factory)
+            }
         };
 
         let merkle_root: [u8; 32] = job
@@ -569,6 +939,21 @@ where
         let prev_hash = chain_tip.prev_hash();
         let nbits = CompactTarget::from_consensus(chain_tip.nbits());
 
+        // validate ntime: it can't be earlier than the chain tip's min_ntime, nor too far ahead
+        // of the current time
+        if share.ntime < chain_tip.min_ntime()
+            || share.ntime as u64 > self.clock.now().saturating_add(self.max_ntime_drift_secs)
+        {
+            return Err(ShareValidationError::InvalidNtime);
+        }
+
+        // validate version: only the BIP320 general purpose bits (13-28) may differ from the
+        // job's version
+        // ref: https://github.com/bitcoin/bips/blob/master/bip-0320.mediawiki
+        if (share.version & !0x1fffe000) != (job.get_job_message().version & !0x1fffe000) {
+            return Err(ShareValidationError::InvalidVersion);
+        }
+
         // create the header for validation
         let header = Header {
             version: Version::from_consensus(share.version as i32),
@@ -598,54 +983,47 @@ where
         );
 
         // check if a block was found
-        if network_target.is_met_by(hash) {
+        let result = if network_target.is_met_by(hash) {
             self.share_accounting.update_share_accounting(
                 self.target.difficulty_float(),
                 share.sequence_number,
                 hash.to_raw_hash(),
             );
+            if let Some(user_identity) = user_identity {
+                self.share_accounting.update_worker_share_accounting(
+                    user_identity,
+                    self.target.difficulty_float(),
+                    hash_as_diff,
+                );
+            }
 
-            let op_pushbytes_pool_miner_tag = self
-                .job_factory
-                .op_pushbytes_pool_miner_tag()
-                .map_err(|_| ShareValidationError::InvalidCoinbase)?;
-
-            let mut script_sig = job.get_template().coinbase_prefix.to_vec();
-            script_sig.extend(op_pushbytes_pool_miner_tag);
-            script_sig.push(self.extranonce_prefix.len() as u8); // OP_PUSHBYTES_X (for the extranonce)
-            script_sig.extend(job.get_extranonce_prefix());
-
-            let tx_in = TxIn {
-                previous_output: OutPoint::null(),
-                script_sig: script_sig.into(),
-                sequence: Sequence(job.get_template().coinbase_tx_input_sequence),
-                witness: Witness::from(vec![vec![0; 32]]),
-            };
-
-            let coinbase = Transaction {
-                version: TxVersion::non_standard(job.get_template().coinbase_tx_version as i32),
-                lock_time: LockTime::from_consensus(job.get_template().coinbase_tx_locktime),
-                input: vec![tx_in],
-                output: job.get_coinbase_outputs().to_vec(),
-            };
             let mut serialized_coinbase = Vec::new();
-            coinbase
+            job_coinbase(job, job_factory)?
                 .consensus_encode(&mut serialized_coinbase)
                 .map_err(|_| ShareValidationError::InvalidCoinbase)?;
 
-            return Ok(ShareValidationResult::BlockFound(
+            ShareValidationResult::BlockFound(
                 hash.to_raw_hash(),
                 Some(job.get_template().template_id),
                 serialized_coinbase,
-            ));
-        }
-
-        // check if the share hash meets the channel target
-        if block_hash_target <= self.target {
+            )
+        } else if block_hash_target <= self.target {
+            // the share hash meets the channel target
             if self.share_accounting.is_share_seen(hash.to_raw_hash()) {
                 return Err(ShareValidationError::DuplicateShare);
             }
 
+            // check if the share was already accepted on another channel
+            if let Some(share_deduplicator) = &self.share_deduplicator {
+                let prev_hash: [u8; 32] = prev_hash
+                    .inner_as_ref()
+                    .try_into()
+                    .expect("prev hash must be 32 bytes");
+                if !share_deduplicator.insert(prev_hash, hash.to_raw_hash()) {
+                    return Err(ShareValidationError::DuplicateShare);
+                }
+            }
+
             self.share_accounting.update_share_accounting(
                 self.target.difficulty_float(),
                 share.sequence_number,
@@ -655,10 +1033,154 @@ where
             // update the best diff
             self.share_accounting.update_best_diff(hash_as_diff);
 
-            Ok(ShareValidationResult::Valid(hash.to_raw_hash()))
+            if let Some(user_identity) = user_identity {
+                self.share_accounting.update_worker_share_accounting(
+                    user_identity,
+                    self.target.difficulty_float(),
+                    hash_as_diff,
+                );
+            }
+
+            ShareValidationResult::Valid(hash.to_raw_hash())
         } else {
-            Err(ShareValidationError::DoesNotMeetTarget)
-        }
+            return Err(ShareValidationError::DoesNotMeetTarget);
+        };
+
+        // check the accepted share against the targets of the auxiliary chains committed to by
+        // the job
+        let merged_mining_tree = job
+            .get_merged_mining_commitment()
+            .and_then(|commitment| self.merged_mining_trees.find(&commitment));
+        let aux_pow_solutions = match (merged_mining_tree, job_coinbase(job, job_factory)) {
+            (Some(merged_mining_tree), Ok(mut coinbase_tx)) => {
+                // the proofs carry the coinbase without BIP141 data
+                coinbase_tx.input[0].witness = Witness::new();
+                let coinbase_branch: Vec<[u8; 32]> = job
+                    .get_template()
+                    .merkle_path
+                    .inner_as_ref()
+                    .iter()
+                    .map(|hash| {
+                        (*hash)
+                            .try_into()
+                            .expect("merkle path hashes must be 32 bytes")
+                    })
+                    .collect();
+                merged_mining_tree.aux_pow_solutions(
+                    &header,
+                    &consensus::serialize(&coinbase_tx),
+                    &coinbase_branch,
+                )
+            }
+            (Some(_), Err(e)) => {
+                error!(
+                    "failed to rebuild the coinbase of job {}: {:?}",
+                    job.get_job_id(),
+                    e
+                );
+                vec![]
+            }
+            (None, _) => vec![],
+        };
+
+        Ok((result, aux_pow_solutions))
+    }
+}
+
+// Rebuilds the coinbase of a standard job (with BIP141 data), as created by `job_factory`.
+fn job_coinbase(
+    job: &StandardJob<'_>,
+    job_factory: &JobFactory,
+) -> Result<Transaction, ShareValidationError> {
+    let op_pushbytes_pool_miner_tag = job_factory
+        .op_pushbytes_pool_miner_tag_with_commitment(job.get_merged_mining_commitment())
+        .map_err(|_| ShareValidationError::InvalidCoinbase)?;
+
+    let mut script_sig = job.get_template().coinbase_prefix.to_vec();
+    script_sig.extend(op_pushbytes_pool_miner_tag);
+    script_sig.push(job.get_extranonce_prefix().len() as u8); // OP_PUSHBYTES_X (for the extranonce)
+    script_sig.extend(job.get_extranonce_prefix());
+
+    let tx_in = TxIn {
+        previous_output: OutPoint::null(),
+        script_sig: script_sig.into(),
+        sequence: Sequence(job.get_template().coinbase_tx_input_sequence),
+        witness: Witness::from(vec![vec![0; 32]]),
+    };
+
+    Ok(Transaction {
+        version: TxVersion::non_standard(job.get_template().coinbase_tx_version as i32),
+        lock_time: LockTime::from_consensus(job.get_template().coinbase_tx_locktime),
+        input: vec![tx_in],
+        output: job.get_coinbase_outputs().to_vec(),
+    })
+}
+
+/// A point-in-time copy of the state of a [`StandardChannel`].
+///
+/// Created with [`StandardChannel::snapshot`], restored with [`StandardChannel::from_snapshot`].
+#[derive(Debug, Clone)]
+pub struct StandardChannelSnapshot<'a> {
+    channel_id: u32,
+    user_identity: String,
+    extranonce_prefix: Vec<u8>,
+    requested_max_target: Target,
+    target: Target,
+    nominal_hashrate: f32,
+    share_accounting: ShareAccounting,
+    expected_share_per_minute: f32,
+    job_store: JobStoreSnapshot<StandardJob<'a>>,
+    job_factory: JobFactory,
+    chain_tip: Option<ChainTip>,
+    max_ntime_drift_secs: u64,
+    merged_mining_trees: MergedMiningTrees,
+}
+
+impl StandardChannelSnapshot<'_> {
+    /// Returns the channel ID of the snapshotted channel.
+    pub fn get_channel_id(&self) -> u32 {
+        self.channel_id
+    }
+
+    /// Serializes the snapshot into bytes.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SnapshotError> {
+        let mut writer = SnapshotWriter::new();
+        writer.write_u32(self.channel_id);
+        writer.write_string(&self.user_identity)?;
+        writer.write_bytes(&self.extranonce_prefix)?;
+        writer.write_target(&self.requested_max_target)?;
+        writer.write_target(&self.target)?;
+        writer.write_f32(self.nominal_hashrate);
+        self.share_accounting.write_snapshot(&mut writer)?;
+        writer.write_f32(self.expected_share_per_minute);
+        writer.write_bytes(&self.job_store.to_bytes()?)?;
+        self.job_factory.write_snapshot(&mut writer)?;
+        writer.write_option_chain_tip(self.chain_tip.as_ref())?;
+        writer.write_u64(self.max_ntime_drift_secs);
+        self.merged_mining_trees.write_snapshot(&mut writer)?;
+        Ok(writer.into_bytes())
+    }
+
+    /// Deserializes a snapshot previously serialized with [`StandardChannelSnapshot::to_bytes`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<StandardChannelSnapshot<'static>, SnapshotError> {
+        let mut reader = SnapshotReader::new(bytes)?;
+        let snapshot = StandardChannelSnapshot {
+            channel_id: reader.read_u32()?,
+            user_identity: reader.read_string()?,
+            extranonce_prefix: reader.read_bytes()?.to_vec(),
+            requested_max_target: reader.read_target()?,
+            target: reader.read_target()?,
+            nominal_hashrate: reader.read_f32()?,
+            share_accounting: ShareAccounting::read_snapshot(&mut reader, Arc::new(SystemClock))?,
+            expected_share_per_minute: reader.read_f32()?,
+            job_store: JobStoreSnapshot::from_bytes(reader.read_bytes()?)?,
+            job_factory: JobFactory::read_snapshot(&mut reader)?,
+            chain_tip: reader.read_option_chain_tip()?,
+            max_ntime_drift_secs: reader.read_u64()?,
+            merged_mining_trees: MergedMiningTrees::read_snapshot(&mut reader)?,
+        };
+        reader.finish()?;
+        Ok(snapshot)
     }
 }
 
@@ -666,17 +1188,25 @@ where
 mod tests {
     use crate::{
         chain_tip::ChainTip,
+        clock::MockClock,
         server::{
+            auxpow::{AuxChain, MergedMiningTree},
             error::StandardChannelError,
             jobs::{job_store::DefaultJobStore, standard::StandardJob},
             share_accounting::{ShareValidationError, ShareValidationResult},
-            standard::StandardChannel,
+            share_dedup::{DefaultShareDeduplicator, ShareDeduplicator},
+            standard::{StandardChannel, StandardChannelSnapshot},
+            test::{
+                coinbase_reward_outputs, easiest_target, empty_template, standard_share,
+                test_chain_tip, TEST_MIN_NTIME, TEST_VERSION,
+            },
         },
     };
     use binary_sv2::Sv2Option;
-    use bitcoin::{transaction::TxOut, Amount, ScriptBuf, Target};
+    use bitcoin::{hashes::Hash, transaction::TxOut, Amount, ScriptBuf, Target};
+    use extensions_sv2::worker_specific_hashrate_tracking::UserIdentity;
     use mining_sv2::{NewMiningJob, SubmitSharesStandard};
-    use std::convert::TryInto;
+    use std::{convert::TryInto, sync::Arc};
     use template_distribution_sv2::{NewTemplate, SetNewPrevHash as SetNewPrevHashTdp};
 
     const SATS_AVAILABLE_IN_TEMPLATE: u64 = 5000000000;
@@ -1128,6 +1658,17 @@ mod tests {
             res.unwrap_err(),
             ShareValidationError::DoesNotMeetTarget
         ));
+
+        // the rejection is recorded, and no share is accepted
+        let share_accounting = standard_channel.get_share_accounting();
+        assert_eq!(
+            share_accounting
+                .get_rejection_stats(&ShareValidationError::DoesNotMeetTarget)
+                .map(|stats| stats.get_count()),
+            Some(1)
+        );
+        assert_eq!(share_accounting.get_shares_rejected(), 1);
+        assert_eq!(share_accounting.get_shares_accepted(), 0);
     }
 
     #[test]
@@ -1237,6 +1778,67 @@ mod tests {
         assert!(matches!(res, Ok(ShareValidationResult::Valid(_))));
     }
 
+    #[test]
+    fn test_snapshot_and_restore() {
+        let mut channel = StandardChannel::new(
+            1,
+            "user_identity".to_string(),
+            vec![0; 32],
+            easiest_target(),
+            1_000.0,
+            100,
+            1.0,
+            DefaultJobStore::new(),
+            None,
+            None,
+        )
+        .unwrap();
+        channel.set_target(easiest_target());
+        channel.set_chain_tip(test_chain_tip());
+        channel
+            .on_new_template(empty_template(1, false), coinbase_reward_outputs())
+            .unwrap();
+
+        let share = standard_share(1, 1, 1);
+        let res = channel.validate_share(share.clone());
+        assert!(matches!(res, Ok(ShareValidationResult::Valid(_))));
+
+        let bytes = channel.snapshot().to_bytes().unwrap();
+        let snapshot = StandardChannelSnapshot::from_bytes(&bytes).unwrap();
+        assert_eq!(snapshot.get_channel_id(), 1);
+        // serialization is deterministic
+        assert_eq!(snapshot.to_bytes().unwrap(), bytes);
+
+        let mut restored_channel: StandardChannel<'static, DefaultJobStore<_>> =
+            StandardChannel::from_snapshot(snapshot);
+        assert_eq!(restored_channel.snapshot().to_bytes().unwrap(), bytes);
+        assert_eq!(restored_channel.get_target(), channel.get_target());
+        assert_eq!(
+            restored_channel.get_active_job().unwrap().get_job_message(),
+            channel.get_active_job().unwrap().get_job_message()
+        );
+        assert_eq!(
+            restored_channel
+                .get_share_accounting()
+                .get_shares_accepted(),
+            1
+        );
+
+        // seen shares survive the restore
+        let res = restored_channel.validate_share(SubmitSharesStandard {
+            sequence_number: 2,
+            ..share
+        });
+        assert!(matches!(res, Err(ShareValidationError::DuplicateShare)));
+
+        // job ids keep being unique after the restore
+        restored_channel
+            .on_new_template(empty_template(2, false), coinbase_reward_outputs())
+            .unwrap();
+        assert_eq!(restored_channel.get_active_job().unwrap().get_job_id(), 2);
+        assert!(restored_channel.get_past_jobs().contains_key(&1));
+    }
+
     #[test]
     fn test_update_channel() {
         let channel_id = 1;
@@ -1379,4 +1981,271 @@ mod tests {
             .set_extranonce_prefix(new_extranonce_prefix_too_long)
             .is_err());
     }
+
+    #[test]
+    fn test_share_validation_ntime_and_version_bounds() {
+        let channel_id = 1;
+        let mut channel = StandardChannel::new(
+            channel_id,
+            "user_identity".to_string(),
+            vec![0; 32],
+            easiest_target(),
+            1_000.0,
+            100,
+            1.0,
+            DefaultJobStore::new(),
+            None,
+            None,
+        )
+        .unwrap();
+        let clock = MockClock::new(TEST_MIN_NTIME as u64);
+        channel.set_clock(Arc::new(clock.clone()));
+        channel.set_max_ntime_drift_secs(600);
+
+        channel.set_chain_tip(test_chain_tip());
+        channel
+            .on_new_template(empty_template(1, false), coinbase_reward_outputs())
+            .unwrap();
+
+        let share = standard_share(channel_id, 1, 0);
+
+        // ntime earlier than the chain tip's min_ntime
+        let res = channel.validate_share(SubmitSharesStandard {
+            ntime: TEST_MIN_NTIME - 1,
+            ..share.clone()
+        });
+        assert!(matches!(res, Err(ShareValidationError::InvalidNtime)));
+
+        // ntime too far ahead of the current time
+        let res = channel.validate_share(SubmitSharesStandard {
+            ntime: TEST_MIN_NTIME + 601,
+            ..share.clone()
+        });
+        assert!(matches!(res, Err(ShareValidationError::InvalidNtime)));
+
+        // the drift window moves with the clock
+        clock.advance(1);
+        let res = channel.validate_share(SubmitSharesStandard {
+            ntime: TEST_MIN_NTIME + 601,
+            ..share.clone()
+        });
+        assert!(!matches!(res, Err(ShareValidationError::InvalidNtime)));
+
+        // version bits outside of the BIP320 mask can't be rolled
+        let res = channel.validate_share(SubmitSharesStandard {
+            version: TEST_VERSION | 0x1,
+            ..share.clone()
+        });
+        assert!(matches!(res, Err(ShareValidationError::InvalidVersion)));
+
+        // version bits inside of the BIP320 mask can be rolled
+        let res = channel.validate_share(SubmitSharesStandard {
+            version: TEST_VERSION | 0x1fffe000,
+            ..share
+        });
+        assert!(!matches!(res, Err(ShareValidationError::InvalidVersion)));
+    }
+
+    #[test]
+    fn test_share_validation_aux_pow() {
+        let channel_id = 1;
+        let mut channel = StandardChannel::new_for_pool(
+            channel_id,
+            "user_identity".to_string(),
+            vec![0; 32],
+            Target::from_le_bytes([0xff; 32]),
+            1.0,
+            100,
+            1.0,
+            DefaultJobStore::new(),
+            "pool".to_string(),
+        )
+        .unwrap();
+        channel.set_target(easiest_target());
+
+        // the share meets the target of chain 1, but not the target of chain 2
+        let merged_mining_tree = MergedMiningTree::new(vec![
+            AuxChain::new(1, [0xaa; 32], Target::from_le_bytes([0xff; 32])),
+            AuxChain::new(2, [0xbb; 32], Target::from_le_bytes([0; 32])),
+        ])
+        .unwrap();
+        channel.set_merged_mining_tree(Some(merged_mining_tree.clone()));
+
+        channel.set_chain_tip(test_chain_tip());
+        channel
+            .on_new_template(empty_template(1, false), coinbase_reward_outputs())
+            .unwrap();
+        let job = channel.get_active_job().unwrap().clone();
+        assert_eq!(
+            job.get_merged_mining_commitment(),
+            Some(merged_mining_tree.commitment())
+        );
+
+        let share = standard_share(channel_id, 1, 1);
+        let (res, aux_pow_solutions) = channel.validate_share_with_aux_pow(share).unwrap();
+        assert!(matches!(res, ShareValidationResult::Valid(_)));
+        assert_eq!(aux_pow_solutions.len(), 1);
+
+        let solution = &aux_pow_solutions[0];
+        assert_eq!(solution.get_chain_id(), 1);
+        assert_eq!(solution.get_aux_block_hash(), [0xaa; 32]);
+        let aux_pow = solution.get_aux_pow();
+        assert_eq!(
+            aux_pow.get_chain_index(),
+            merged_mining_tree.get_slot(1).unwrap()
+        );
+        assert!(aux_pow.get_coinbase_branch().is_empty());
+        // the rebuilt coinbase is the one the job commits to, and carries no witness
+        assert_eq!(
+            aux_pow
+                .get_coinbase_tx()
+                .compute_txid()
+                .to_raw_hash()
+                .to_byte_array()
+                .to_vec(),
+            job.get_merkle_root().inner_as_ref().to_vec()
+        );
+        assert!(aux_pow.get_coinbase_tx().input[0].witness.is_empty());
+
+        // shares for jobs committing to a previous tree are checked against that tree
+        let new_merged_mining_tree = MergedMiningTree::new(vec![AuxChain::new(
+            1,
+            [0xcc; 32],
+            Target::from_le_bytes([0xff; 32]),
+        )])
+        .unwrap();
+        channel.set_merged_mining_tree(Some(new_merged_mining_tree.clone()));
+        let share = standard_share(channel_id, 2, 2);
+        let (_, aux_pow_solutions) = channel.validate_share_with_aux_pow(share).unwrap();
+        assert_eq!(aux_pow_solutions[0].get_aux_block_hash(), [0xaa; 32]);
+
+        // rejected shares are not checked against auxiliary targets
+        channel.set_target(Target::from_le_bytes([0; 32]));
+        let share = standard_share(channel_id, 3, 3);
+        assert!(matches!(
+            channel.validate_share_with_aux_pow(share),
+            Err(ShareValidationError::DoesNotMeetTarget)
+        ));
+        channel.set_target(easiest_target());
+
+        // the merged mining trees are restored from a snapshot
+        let bytes = channel.snapshot().to_bytes().unwrap();
+        let snapshot = StandardChannelSnapshot::from_bytes(&bytes).unwrap();
+        assert_eq!(snapshot.to_bytes().unwrap(), bytes);
+        let mut restored_channel: StandardChannel<'static, DefaultJobStore<_>> =
+            StandardChannel::from_snapshot(snapshot);
+        assert_eq!(
+            restored_channel.get_merged_mining_tree(),
+            Some(&new_merged_mining_tree)
+        );
+        let share = standard_share(channel_id, 4, 4);
+        let (_, aux_pow_solutions) = restored_channel.validate_share_with_aux_pow(share).unwrap();
+        assert_eq!(aux_pow_solutions[0].get_aux_block_hash(), [0xaa; 32]);
+    }
+
+    #[test]
+    fn test_share_validation_with_share_deduplicator() {
+        // channels sharing the same extranonce prefix, e.g. after `SetExtranoncePrefix`
+        let new_channel = |channel_id| {
+            let mut channel = StandardChannel::new(
+                channel_id,
+                "user_identity".to_string(),
+                vec![0; 32],
+                easiest_target(),
+                1_000.0,
+                100,
+                1.0,
+                DefaultJobStore::new(),
+                None,
+                None,
+            )
+            .unwrap();
+            channel.set_target(easiest_target());
+            channel.set_chain_tip(test_chain_tip());
+            channel
+                .on_new_template(empty_template(1, false), coinbase_reward_outputs())
+                .unwrap();
+            channel
+        };
+
+        let share_deduplicator: Arc<dyn ShareDeduplicator> =
+            Arc::new(DefaultShareDeduplicator::new());
+        let mut channel_a = new_channel(1);
+        let mut channel_b = new_channel(2);
+        channel_a.set_share_deduplicator(Some(share_deduplicator.clone()));
+        channel_b.set_share_deduplicator(Some(share_deduplicator.clone()));
+
+        let res = channel_a.validate_share(standard_share(1, 1, 1));
+        assert!(matches!(res, Ok(ShareValidationResult::Valid(_))));
+
+        // the same header replayed on the other channel is rejected
+        let res = channel_b.validate_share(standard_share(2, 1, 1));
+        assert!(matches!(res, Err(ShareValidationError::DuplicateShare)));
+
+        let res = channel_b.validate_share(standard_share(2, 2, 2));
+        assert!(matches!(res, Ok(ShareValidationResult::Valid(_))));
+
+        // without the deduplicator, the replayed share would be accepted
+        channel_b.set_share_deduplicator(None);
+        let res = channel_b.validate_share(standard_share(2, 3, 1));
+        assert!(matches!(res, Ok(ShareValidationResult::Valid(_))));
+    }
+
+    #[test]
+    fn test_share_validation_with_user_identity() {
+        let channel_id = 1;
+        let mut channel = StandardChannel::new_for_pool(
+            channel_id,
+            "user_identity".to_string(),
+            vec![0; 32],
+            Target::from_le_bytes([0xff; 32]),
+            1.0,
+            100,
+            1.0,
+            DefaultJobStore::new(),
+            "pool".to_string(),
+        )
+        .unwrap();
+        channel.set_target(easiest_target());
+        channel.set_chain_tip(test_chain_tip());
+        channel
+            .on_new_template(empty_template(1, false), coinbase_reward_outputs())
+            .unwrap();
+
+        let share = |sequence_number, nonce| standard_share(channel_id, sequence_number, nonce);
+        let alice = UserIdentity::new("alice").unwrap();
+        let bob = UserIdentity::new("bob").unwrap();
+
+        for nonce in 1..=3 {
+            let res = channel.validate_share_with_user_identity(share(nonce, nonce), Some(&alice));
+            assert!(matches!(res, Ok((ShareValidationResult::Valid(_), _))));
+        }
+        let res = channel.validate_share_with_user_identity(share(4, 4), Some(&bob));
+        assert!(matches!(res, Ok((ShareValidationResult::Valid(_), _))));
+        // shares without a worker identity are only accounted for at the channel level
+        let res = channel.validate_share(share(5, 5));
+        assert!(matches!(res, Ok(ShareValidationResult::Valid(_))));
+
+        // duplicates are not accounted for
+        let res = channel.validate_share_with_user_identity(share(6, 4), Some(&alice));
+        assert!(matches!(res, Err(ShareValidationError::DuplicateShare)));
+
+        let share_accounting = channel.get_share_accounting();
+        assert_eq!(share_accounting.get_shares_accepted(), 5);
+        let alice_share_accounting = share_accounting
+            .get_worker_share_accounting(&alice)
+            .unwrap();
+        assert_eq!(alice_share_accounting.get_shares_accepted(), 3);
+        assert_eq!(
+            alice_share_accounting.get_share_work_sum(),
+            3.0 * channel.get_target().difficulty_float()
+        );
+        assert_eq!(
+            share_accounting
+                .get_worker_share_accounting(&bob)
+                .unwrap()
+                .get_shares_accepted(),
+            1
+        );
+    }
 }
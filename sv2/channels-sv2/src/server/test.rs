@@ -0,0 +1,80 @@
+//! Fixtures shared by the server channel tests.
+//!
+//! Jobs created from [`empty_template`] under [`test_chain_tip`] have no transactions, and shares
+//! submitted for them never meet the network target. Channels whose target is set to
+//! [`easiest_target`] accept every share for such jobs, which lets tests exercise share
+//! validation without mining test vectors.
+use crate::chain_tip::ChainTip;
+use bitcoin::{transaction::TxOut, Amount, ScriptBuf, Target};
+use mining_sv2::{SubmitSharesExtended, SubmitSharesStandard};
+use std::convert::TryInto;
+use template_distribution_sv2::NewTemplate;
+
+pub const SATS_AVAILABLE_IN_TEMPLATE: u64 = 5000000000;
+pub const TEST_VERSION: u32 = 536870912;
+pub const TEST_NBITS: u32 = 453040064;
+pub const TEST_MIN_NTIME: u32 = 1745611105;
+
+/// Target met by every share.
+pub fn easiest_target() -> Target {
+    Target::from_le_bytes([0xff; 32])
+}
+
+/// Chain tip with a network target that test shares don't meet.
+pub fn test_chain_tip() -> ChainTip {
+    ChainTip::new([0xaa; 32].into(), TEST_NBITS, TEST_MIN_NTIME)
+}
+
+/// Template without transactions nor coinbase outputs besides the pool's.
+pub fn empty_template(template_id: u64, future_template: bool) -> NewTemplate<'static> {
+    NewTemplate {
+        template_id,
+        future_template,
+        version: TEST_VERSION,
+        coinbase_tx_version: 2,
+        coinbase_prefix: vec![82, 0].try_into().unwrap(),
+        coinbase_tx_input_sequence: 4294967295,
+        coinbase_tx_value_remaining: SATS_AVAILABLE_IN_TEMPLATE,
+        coinbase_tx_outputs_count: 0,
+        coinbase_tx_outputs: vec![].try_into().unwrap(),
+        coinbase_tx_locktime: 0,
+        merkle_path: vec![].try_into().unwrap(),
+    }
+}
+
+/// Coinbase outputs claiming the whole value of an [`empty_template`].
+pub fn coinbase_reward_outputs() -> Vec<TxOut> {
+    vec![TxOut {
+        value: Amount::from_sat(SATS_AVAILABLE_IN_TEMPLATE),
+        script_pubkey: ScriptBuf::from(vec![0, 20].into_iter().chain([1; 20]).collect::<Vec<u8>>()),
+    }]
+}
+
+/// Extended share for job 1 under [`test_chain_tip`], with an 8 bytes extranonce.
+pub fn extended_share(
+    channel_id: u32,
+    sequence_number: u32,
+    nonce: u32,
+) -> SubmitSharesExtended<'static> {
+    SubmitSharesExtended {
+        channel_id,
+        sequence_number,
+        job_id: 1,
+        nonce,
+        ntime: TEST_MIN_NTIME,
+        version: TEST_VERSION,
+        extranonce: vec![1, 0, 0, 0, 0, 0, 0, 0].try_into().unwrap(),
+    }
+}
+
+/// Standard share for job 1 under [`test_chain_tip`].
+pub fn standard_share(channel_id: u32, sequence_number: u32, nonce: u32) -> SubmitSharesStandard {
+    SubmitSharesStandard {
+        channel_id,
+        sequence_number,
+        job_id: 1,
+        nonce,
+        ntime: TEST_MIN_NTIME,
+        version: TEST_VERSION,
+    }
+}
@@ -1,5 +1,9 @@
-use crate::target::hash_rate_from_target;
+use crate::{
+    clock::{Clock, SystemClock},
+    target::hash_rate_from_target,
+};
 use bitcoin::Target;
+use std::sync::Arc;
 use tracing::debug;
 
 /// Default minimum hashrate (H/s) if not specified.
@@ -18,6 +22,8 @@ pub struct VardiffState {
     pub timestamp_of_last_update: u64,
     /// The lowest hashrate (H/s) the system will allow; values below this are clamped.
     pub min_allowed_hashrate: f32,
+    /// Time source used to measure the elapsed time between updates.
+    clock: Arc<dyn Clock>,
 }
 
 impl VardiffState {
@@ -34,14 +40,25 @@ impl VardiffState {
     /// # Arguments
     /// * `min_allowed_hashrate` - The minimum hashrate to enforce.
     pub fn new_with_min(min_allowed_hashrate: f32) -> Result<Self, VardiffError> {
-        let timestamp_secs = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)?
-            .as_secs();
+        Self::new_with_clock(min_allowed_hashrate, Arc::new(SystemClock))
+    }
+
+    /// Creates a new `VardiffState` with a specific minimum hashrate and time source.
+    ///
+    /// # Arguments
+    /// * `min_allowed_hashrate` - The minimum hashrate to enforce.
+    /// * `clock` - The time source used to measure the elapsed time between updates.
+    pub fn new_with_clock(
+        min_allowed_hashrate: f32,
+        clock: Arc<dyn Clock>,
+    ) -> Result<Self, VardiffError> {
+        let timestamp_secs = clock.now();
 
         Ok(VardiffState {
             shares_since_last_update: 0,
             timestamp_of_last_update: timestamp_secs,
             min_allowed_hashrate,
+            clock,
         })
     }
 
@@ -76,9 +93,7 @@ impl Vardiff for VardiffState {
 
     /// Resets the share counter and updates the timestamp to now.
     fn reset_counter(&mut self) -> Result<(), VardiffError> {
-        let timestamp_secs = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)?
-            .as_secs();
+        let timestamp_secs = self.clock.now();
         self.set_timestamp_of_last_update(timestamp_secs);
         self.set_shares_since_last_update(0);
         Ok(())
@@ -99,12 +114,9 @@ impl Vardiff for VardiffState {
         target: &Target,
         shares_per_minute: f32,
     ) -> Result<Option<f32>, VardiffError> {
-        let now = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .map_err(VardiffError::TimeError)?
-            .as_secs();
+        let now = self.clock.now();
 
-        let delta_time = now - self.timestamp_of_last_update;
+        let delta_time = now.saturating_sub(self.timestamp_of_last_update);
 
         if delta_time <= 15 {
             return Ok(None);
@@ -0,0 +1,221 @@
+use crate::{
+    clock::{Clock, SystemClock},
+    target::hash_rate_from_target,
+};
+use bitcoin::Target;
+use std::sync::Arc;
+use tracing::debug;
+
+use super::{error::VardiffError, Vardiff};
+
+/// Default minimum hashrate (H/s) if not specified.
+const DEFAULT_MIN_HASHRATE: f32 = 1.0;
+
+/// Default time constant (seconds) of the exponential smoothing.
+const DEFAULT_TIME_CONSTANT_SECS: f64 = 180.0;
+
+/// Default relative deviation between the smoothed and the current hashrate that triggers an
+/// update.
+const DEFAULT_UPDATE_THRESHOLD: f32 = 0.2;
+
+/// Minimum number of seconds between two evaluations of the share rate.
+const MIN_UPDATE_INTERVAL_SECS: u64 = 15;
+
+/// Vardiff implementation based on an exponentially weighted moving average (EWMA) of the
+/// realized hashrate.
+///
+/// Every time [`Vardiff::try_vardiff`] is called, the hashrate realized since the last update is
+/// blended into the current hashrate with a weight `alpha = 1 - e^(-Δt / τ)`, where `Δt` is the
+/// time elapsed since the last update and `τ` is the configured time constant. Longer observation
+/// windows therefore weigh more than short ones, which keeps the estimate stable under the natural
+/// noise of share arrivals while still converging exponentially fast after a real change.
+///
+/// The new hashrate is only returned when the smoothed estimate deviates from the current one by
+/// more than `update_threshold`. Until then, shares keep accumulating over a growing window.
+#[derive(Debug)]
+pub struct EwmaVardiff {
+    /// Count of shares received since the last difficulty adjustment.
+    pub shares_since_last_update: u32,
+    /// Unix timestamp (seconds) of the last difficulty adjustment.
+    pub timestamp_of_last_update: u64,
+    /// The lowest hashrate (H/s) the system will allow; values below this are clamped.
+    pub min_allowed_hashrate: f32,
+    /// Time constant `τ` (seconds) of the exponential smoothing.
+    pub time_constant_secs: f64,
+    /// Relative deviation (e.g. `0.2` for 20%) required to trigger an update.
+    pub update_threshold: f32,
+    /// Time source used to measure the elapsed time between updates.
+    clock: Arc<dyn Clock>,
+}
+
+impl EwmaVardiff {
+    /// Creates a new `EwmaVardiff` with the default minimum hashrate, time constant and update
+    /// threshold.
+    pub fn new() -> Result<Self, VardiffError> {
+        Self::new_with_min(DEFAULT_MIN_HASHRATE)
+    }
+
+    /// Creates a new `EwmaVardiff` with a specific minimum hashrate.
+    ///
+    /// # Arguments
+    /// * `min_allowed_hashrate` - The minimum hashrate to enforce.
+    pub fn new_with_min(min_allowed_hashrate: f32) -> Result<Self, VardiffError> {
+        Self::new_with_clock(min_allowed_hashrate, Arc::new(SystemClock))
+    }
+
+    /// Creates a new `EwmaVardiff` with a specific minimum hashrate and time source.
+    ///
+    /// # Arguments
+    /// * `min_allowed_hashrate` - The minimum hashrate to enforce.
+    /// * `clock` - The time source used to measure the elapsed time between updates.
+    pub fn new_with_clock(
+        min_allowed_hashrate: f32,
+        clock: Arc<dyn Clock>,
+    ) -> Result<Self, VardiffError> {
+        Self::new_with_params(
+            min_allowed_hashrate,
+            DEFAULT_TIME_CONSTANT_SECS,
+            DEFAULT_UPDATE_THRESHOLD,
+            clock,
+        )
+    }
+
+    /// Creates a new `EwmaVardiff` with specific smoothing parameters.
+    ///
+    /// # Arguments
+    /// * `min_allowed_hashrate` - The minimum hashrate to enforce.
+    /// * `time_constant_secs` - The time constant `τ` of the exponential smoothing. Smaller values
+    ///   react faster, larger values are less sensitive to noise.
+    /// * `update_threshold` - The relative deviation required to trigger an update.
+    /// * `clock` - The time source used to measure the elapsed time between updates.
+    pub fn new_with_params(
+        min_allowed_hashrate: f32,
+        time_constant_secs: f64,
+        update_threshold: f32,
+        clock: Arc<dyn Clock>,
+    ) -> Result<Self, VardiffError> {
+        let timestamp_secs = clock.now();
+
+        Ok(EwmaVardiff {
+            shares_since_last_update: 0,
+            timestamp_of_last_update: timestamp_secs,
+            min_allowed_hashrate,
+            time_constant_secs,
+            update_threshold,
+            clock,
+        })
+    }
+}
+
+impl Vardiff for EwmaVardiff {
+    fn last_update_timestamp(&self) -> u64 {
+        self.timestamp_of_last_update
+    }
+
+    fn shares_since_last_update(&self) -> u32 {
+        self.shares_since_last_update
+    }
+
+    fn min_allowed_hashrate(&self) -> f32 {
+        self.min_allowed_hashrate
+    }
+
+    fn set_timestamp_of_last_update(&mut self, timestamp_of_last_update: u64) {
+        self.timestamp_of_last_update = timestamp_of_last_update;
+    }
+
+    fn increment_shares_since_last_update(&mut self) {
+        self.shares_since_last_update += 1;
+    }
+
+    fn reset_counter(&mut self) -> Result<(), VardiffError> {
+        let timestamp_secs = self.clock.now();
+        self.timestamp_of_last_update = timestamp_secs;
+        self.shares_since_last_update = 0;
+        Ok(())
+    }
+
+    /// Blends the hashrate realized since the last update into the current hashrate.
+    ///
+    /// It returns `Ok(Some(new_hashrate))` when the smoothed estimate deviates from `hashrate` by
+    /// more than the update threshold, `Ok(None)` otherwise, and `Err` for actual processing
+    /// errors.
+    fn try_vardiff(
+        &mut self,
+        hashrate: f32,
+        target: &Target,
+        shares_per_minute: f32,
+    ) -> Result<Option<f32>, VardiffError> {
+        let now = self.clock.now();
+
+        let delta_time = now.saturating_sub(self.timestamp_of_last_update);
+
+        if delta_time < MIN_UPDATE_INTERVAL_SECS {
+            return Ok(None);
+        }
+
+        let realized_share_per_min =
+            self.shares_since_last_update as f64 / (delta_time as f64 / 60.0);
+
+        // with no shares, the realized hashrate is zero, which hash_rate_from_target rejects
+        let realized_hashrate = if self.shares_since_last_update == 0 {
+            0.0
+        } else {
+            match hash_rate_from_target(target.to_le_bytes().into(), realized_share_per_min) {
+                Ok(hashrate) => hashrate,
+                Err(e) => {
+                    debug!(
+                        target: "vardiff",
+                        "Target->Hashrate conversion failed: {:?}. Falling back using previous hashrate and realized_shares_per_minute", e
+                    );
+                    hashrate as f64 * realized_share_per_min / shares_per_minute as f64
+                }
+            }
+        };
+
+        let alpha = 1.0 - (-(delta_time as f64) / self.time_constant_secs).exp();
+        let smoothed_hashrate =
+            (hashrate as f64 + alpha * (realized_hashrate - hashrate as f64)) as f32;
+        // a zero current hashrate has no meaningful relative deviation, so it is always updated
+        let deviation = if hashrate > 0.0 {
+            (smoothed_hashrate - hashrate).abs() / hashrate
+        } else {
+            f32::INFINITY
+        };
+
+        debug!(
+            target: "vardiff",
+            "EWMA hashrate update check triggered:
+            - Elapsed time: {}s
+            - Shares since last update: {}
+            - Realized hashrate: {:.2} H/s
+            - Smoothing factor: {:.4}
+            - Smoothed hashrate: {:.2} H/s (Δ {:.2}%, previous {:.2} H/s)",
+            delta_time,
+            self.shares_since_last_update,
+            realized_hashrate,
+            alpha,
+            smoothed_hashrate,
+            deviation * 100.0,
+            hashrate,
+        );
+
+        if deviation < self.update_threshold {
+            return Ok(None);
+        }
+
+        let mut new_hashrate = smoothed_hashrate;
+        if new_hashrate < self.min_allowed_hashrate {
+            debug!(
+                target: "vardiff",
+                "New hashrate {:.2} H/s below minimum threshold {:.2} H/s — clamping",
+                new_hashrate,
+                self.min_allowed_hashrate
+            );
+            new_hashrate = self.min_allowed_hashrate;
+        }
+        self.reset_counter()?;
+
+        Ok(Some(new_hashrate))
+    }
+}
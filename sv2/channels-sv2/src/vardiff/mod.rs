@@ -4,6 +4,8 @@ use std::fmt::Debug;
 
 pub mod classic;
 pub mod error;
+pub mod ewma;
+pub mod poisson;
 #[cfg(test)]
 pub mod test;
 
@@ -0,0 +1,225 @@
+use crate::{
+    clock::{Clock, SystemClock},
+    target::hash_rate_from_target,
+};
+use bitcoin::Target;
+use std::sync::Arc;
+use tracing::debug;
+
+use super::{error::VardiffError, Vardiff};
+
+/// Default minimum hashrate (H/s) if not specified.
+const DEFAULT_MIN_HASHRATE: f32 = 1.0;
+
+/// Default z-score of the confidence interval (≈99% two-sided).
+const DEFAULT_Z_SCORE: f64 = 2.576;
+
+/// Minimum number of seconds between two evaluations of the share rate.
+const MIN_UPDATE_INTERVAL_SECS: u64 = 15;
+
+/// Vardiff implementation based on a Poisson confidence interval.
+///
+/// Share arrivals at a fixed target follow a Poisson process. If the current hashrate estimate is
+/// correct, the number of shares observed over `Δt` seconds is a Poisson variable with mean
+/// `λ = shares_per_minute * Δt / 60`.
+///
+/// Every time [`Vardiff::try_vardiff`] is called, a confidence interval for the true mean is
+/// computed from the observed share count `k` (using the Wilson–Hilferty approximation). As long
+/// as `λ` falls inside that interval, the deviation is attributed to noise and no update happens,
+/// so shares keep accumulating over a growing window and the interval keeps narrowing. Once `λ`
+/// falls outside of it, the hashrate is re-estimated from the realized share rate.
+///
+/// When no shares were observed, the upper bound of the interval is used as the realized share
+/// count, which yields the most conservative decrease that is still consistent with the
+/// observation.
+#[derive(Debug)]
+pub struct PoissonVardiff {
+    /// Count of shares received since the last difficulty adjustment.
+    pub shares_since_last_update: u32,
+    /// Unix timestamp (seconds) of the last difficulty adjustment.
+    pub timestamp_of_last_update: u64,
+    /// The lowest hashrate (H/s) the system will allow; values below this are clamped.
+    pub min_allowed_hashrate: f32,
+    /// z-score of the two-sided confidence interval (e.g. `1.96` for 95%, `2.576` for 99%).
+    pub z_score: f64,
+    /// Time source used to measure the elapsed time between updates.
+    clock: Arc<dyn Clock>,
+}
+
+impl PoissonVardiff {
+    /// Creates a new `PoissonVardiff` with the default minimum hashrate and confidence level.
+    pub fn new() -> Result<Self, VardiffError> {
+        Self::new_with_min(DEFAULT_MIN_HASHRATE)
+    }
+
+    /// Creates a new `PoissonVardiff` with a specific minimum hashrate.
+    ///
+    /// # Arguments
+    /// * `min_allowed_hashrate` - The minimum hashrate to enforce.
+    pub fn new_with_min(min_allowed_hashrate: f32) -> Result<Self, VardiffError> {
+        Self::new_with_clock(min_allowed_hashrate, Arc::new(SystemClock))
+    }
+
+    /// Creates a new `PoissonVardiff` with a specific minimum hashrate and time source.
+    ///
+    /// # Arguments
+    /// * `min_allowed_hashrate` - The minimum hashrate to enforce.
+    /// * `clock` - The time source used to measure the elapsed time between updates.
+    pub fn new_with_clock(
+        min_allowed_hashrate: f32,
+        clock: Arc<dyn Clock>,
+    ) -> Result<Self, VardiffError> {
+        Self::new_with_params(min_allowed_hashrate, DEFAULT_Z_SCORE, clock)
+    }
+
+    /// Creates a new `PoissonVardiff` with a specific confidence level.
+    ///
+    /// # Arguments
+    /// * `min_allowed_hashrate` - The minimum hashrate to enforce.
+    /// * `z_score` - The z-score of the confidence interval. Larger values make updates less
+    ///   frequent and more certain.
+    /// * `clock` - The time source used to measure the elapsed time between updates.
+    pub fn new_with_params(
+        min_allowed_hashrate: f32,
+        z_score: f64,
+        clock: Arc<dyn Clock>,
+    ) -> Result<Self, VardiffError> {
+        let timestamp_secs = clock.now();
+
+        Ok(PoissonVardiff {
+            shares_since_last_update: 0,
+            timestamp_of_last_update: timestamp_secs,
+            min_allowed_hashrate,
+            z_score,
+            clock,
+        })
+    }
+
+    /// Returns the `(lower, upper)` bounds of the confidence interval for the mean of a Poisson
+    /// variable, given `k` observed events.
+    pub fn confidence_interval(&self, k: u32) -> (f64, f64) {
+        let z = self.z_score;
+        let lower = if k == 0 {
+            0.0
+        } else {
+            let k = k as f64;
+            k * (1.0 - 1.0 / (9.0 * k) - z / (3.0 * k.sqrt()))
+                .max(0.0)
+                .powi(3)
+        };
+        let k1 = k as f64 + 1.0;
+        let upper = k1 * (1.0 - 1.0 / (9.0 * k1) + z / (3.0 * k1.sqrt())).powi(3);
+        (lower, upper)
+    }
+}
+
+impl Vardiff for PoissonVardiff {
+    fn last_update_timestamp(&self) -> u64 {
+        self.timestamp_of_last_update
+    }
+
+    fn shares_since_last_update(&self) -> u32 {
+        self.shares_since_last_update
+    }
+
+    fn min_allowed_hashrate(&self) -> f32 {
+        self.min_allowed_hashrate
+    }
+
+    fn set_timestamp_of_last_update(&mut self, timestamp_of_last_update: u64) {
+        self.timestamp_of_last_update = timestamp_of_last_update;
+    }
+
+    fn increment_shares_since_last_update(&mut self) {
+        self.shares_since_last_update += 1;
+    }
+
+    fn reset_counter(&mut self) -> Result<(), VardiffError> {
+        let timestamp_secs = self.clock.now();
+        self.timestamp_of_last_update = timestamp_secs;
+        self.shares_since_last_update = 0;
+        Ok(())
+    }
+
+    /// Checks whether the share count observed since the last update is consistent with the
+    /// expected share rate.
+    ///
+    /// It returns `Ok(Some(new_hashrate))` when the expected share count falls outside of the
+    /// confidence interval, `Ok(None)` otherwise, and `Err` for actual processing errors.
+    fn try_vardiff(
+        &mut self,
+        hashrate: f32,
+        target: &Target,
+        shares_per_minute: f32,
+    ) -> Result<Option<f32>, VardiffError> {
+        let now = self.clock.now();
+
+        let delta_time = now.saturating_sub(self.timestamp_of_last_update);
+
+        if delta_time < MIN_UPDATE_INTERVAL_SECS {
+            return Ok(None);
+        }
+
+        let expected_shares = shares_per_minute as f64 * delta_time as f64 / 60.0;
+        let (lower, upper) = self.confidence_interval(self.shares_since_last_update);
+
+        debug!(
+            target: "vardiff",
+            "Poisson hashrate update check triggered:
+            - Elapsed time: {}s
+            - Shares since last update: {}
+            - Expected shares: {:.2}
+            - Confidence interval: [{:.2}, {:.2}]",
+            delta_time,
+            self.shares_since_last_update,
+            expected_shares,
+            lower,
+            upper,
+        );
+
+        if expected_shares >= lower && expected_shares <= upper {
+            return Ok(None);
+        }
+
+        let realized_shares = if self.shares_since_last_update == 0 {
+            upper
+        } else {
+            self.shares_since_last_update as f64
+        };
+        let realized_share_per_min = realized_shares / (delta_time as f64 / 60.0);
+
+        let mut new_hashrate = match hash_rate_from_target(
+            target.to_le_bytes().into(),
+            realized_share_per_min,
+        ) {
+            Ok(hashrate) => hashrate as f32,
+            Err(e) => {
+                debug!(
+                    target: "vardiff",
+                    "Target->Hashrate conversion failed: {:?}. Falling back using previous hashrate and realized_shares_per_minute", e
+                );
+                hashrate * realized_share_per_min as f32 / shares_per_minute
+            }
+        };
+
+        debug!(
+            target: "vardiff",
+            "Calculated new hashrate: {:.2} H/s (previous {:.2} H/s)",
+            new_hashrate,
+            hashrate,
+        );
+
+        if new_hashrate < self.min_allowed_hashrate {
+            debug!(
+                target: "vardiff",
+                "New hashrate {:.2} H/s below minimum threshold {:.2} H/s — clamping",
+                new_hashrate,
+                self.min_allowed_hashrate
+            );
+            new_hashrate = self.min_allowed_hashrate;
+        }
+        self.reset_counter()?;
+
+        Ok(Some(new_hashrate))
+    }
+}
@@ -1,8 +1,14 @@
 /// Classic implementation test suite
 use crate::vardiff::test::{
-    simulate_shares_and_wait, TEST_MIN_ALLOWED_HASHRATE, TEST_SHARES_PER_MINUTE,
+    new_test_clock, simulate_shares_and_wait, TEST_MIN_ALLOWED_HASHRATE, TEST_SHARES_PER_MINUTE,
 };
-use crate::{target::hash_rate_to_target, vardiff::VardiffError, VardiffState};
+use crate::{
+    clock::{Clock, MockClock},
+    target::hash_rate_to_target,
+    vardiff::VardiffError,
+    VardiffState,
+};
+use std::sync::Arc;
 
 use super::{
     test_increment_and_reset_shares, test_try_vardiff_low_hashrate_decrease_target,
@@ -14,13 +20,15 @@ use super::{
     test_try_vardiff_with_shares_less_than_30, test_try_vardiff_with_shares_more_than_60s, Vardiff,
 };
 
-fn new_test_vardiff_state() -> Result<VardiffState, VardiffError> {
-    VardiffState::new_with_min(TEST_MIN_ALLOWED_HASHRATE)
+fn new_test_vardiff_state() -> Result<(VardiffState, MockClock), VardiffError> {
+    let clock = new_test_clock();
+    let vardiff = VardiffState::new_with_clock(TEST_MIN_ALLOWED_HASHRATE, Arc::new(clock.clone()))?;
+    Ok((vardiff, clock))
 }
 
 #[test]
 fn test_initialization_and_getters() {
-    let vardiff = new_test_vardiff_state().expect("Failed to create VardiffState");
+    let (vardiff, _clock) = new_test_vardiff_state().expect("Failed to create VardiffState");
 
     assert_eq!(vardiff.min_allowed_hashrate(), TEST_MIN_ALLOWED_HASHRATE);
     assert_eq!(vardiff.shares_since_last_update(), 0);
@@ -28,62 +36,62 @@ fn test_initialization_and_getters() {
 
 #[test]
 fn test_increment_and_reset_shares_classic() {
-    let mut vardiff = new_test_vardiff_state().expect("Failed to create VardiffState");
-    test_increment_and_reset_shares(&mut vardiff)
+    let (mut vardiff, clock) = new_test_vardiff_state().expect("Failed to create VardiffState");
+    test_increment_and_reset_shares(&mut vardiff, &clock)
 }
 
 #[test]
 fn test_try_vardiff_stable_hashrate_minimal_change_or_no_change_classic() {
-    let mut vardiff = new_test_vardiff_state().expect("Failed to create VardiffState");
-    test_try_vardiff_stable_hashrate_minimal_change_or_no_change(&mut vardiff);
+    let (mut vardiff, clock) = new_test_vardiff_state().expect("Failed to create VardiffState");
+    test_try_vardiff_stable_hashrate_minimal_change_or_no_change(&mut vardiff, &clock);
 }
 
 #[test]
 pub fn test_try_vardiff_low_hashrate_decrease_target_classic() {
-    let mut vardiff = new_test_vardiff_state().expect("Failed to create VardiffState");
-    test_try_vardiff_low_hashrate_decrease_target(&mut vardiff);
+    let (mut vardiff, clock) = new_test_vardiff_state().expect("Failed to create VardiffState");
+    test_try_vardiff_low_hashrate_decrease_target(&mut vardiff, &clock);
 }
 
 #[test]
 pub fn test_try_vardiff_with_shares_less_than_30_classic() {
-    let mut vardiff = new_test_vardiff_state().expect("Failed to create VardiffState");
-    test_try_vardiff_with_shares_less_than_30(&mut vardiff);
+    let (mut vardiff, clock) = new_test_vardiff_state().expect("Failed to create VardiffState");
+    test_try_vardiff_with_shares_less_than_30(&mut vardiff, &clock);
 }
 
 #[test]
 pub fn test_try_vardiff_with_shares_30_to_60s_classic() {
-    let mut vardiff = new_test_vardiff_state().expect("Failed to create VardiffState");
-    test_try_vardiff_with_shares_30_to_60s(&mut vardiff);
+    let (mut vardiff, clock) = new_test_vardiff_state().expect("Failed to create VardiffState");
+    test_try_vardiff_with_shares_30_to_60s(&mut vardiff, &clock);
 }
 
 #[test]
 pub fn test_try_vardiff_with_shares_more_than_60s_classic() {
-    let mut vardiff = new_test_vardiff_state().expect("Failed to create VardiffState");
-    test_try_vardiff_with_shares_more_than_60s(&mut vardiff);
+    let (mut vardiff, clock) = new_test_vardiff_state().expect("Failed to create VardiffState");
+    test_try_vardiff_with_shares_more_than_60s(&mut vardiff, &clock);
 }
 
 #[test]
 pub fn test_try_vardiff_no_shares_30_to_60s_decrease_classic() {
-    let mut vardiff = new_test_vardiff_state().expect("Failed to create VardiffState");
-    test_try_vardiff_no_shares_30_to_60s_decrease(&mut vardiff);
+    let (mut vardiff, clock) = new_test_vardiff_state().expect("Failed to create VardiffState");
+    test_try_vardiff_no_shares_30_to_60s_decrease(&mut vardiff, &clock);
 }
 
 #[test]
 pub fn test_try_vardiff_no_shares_more_than_60s_decrease_classic() {
-    let mut vardiff = new_test_vardiff_state().expect("Failed to create VardiffState");
-    test_try_vardiff_no_shares_more_than_60s_decrease(&mut vardiff);
+    let (mut vardiff, clock) = new_test_vardiff_state().expect("Failed to create VardiffState");
+    test_try_vardiff_no_shares_more_than_60s_decrease(&mut vardiff, &clock);
 }
 
 #[test]
 pub fn test_try_vardiff_no_shares_less_than_30s_decrease_classic() {
-    let mut vardiff = new_test_vardiff_state().expect("Failed to create VardiffState");
-    test_try_vardiff_no_shares_less_than_30s_decrease(&mut vardiff);
+    let (mut vardiff, clock) = new_test_vardiff_state().expect("Failed to create VardiffState");
+    test_try_vardiff_no_shares_less_than_30s_decrease(&mut vardiff, &clock);
 }
 
 #[test]
 fn test_try_vardiff_with_less_spm_than_expected_classic() {
-    let mut vardiff = new_test_vardiff_state().expect("Failed to create VardiffState");
-    test_try_vardiff_with_less_spm_than_expected(&mut vardiff);
+    let (mut vardiff, clock) = new_test_vardiff_state().expect("Failed to create VardiffState");
+    test_try_vardiff_with_less_spm_than_expected(&mut vardiff, &clock);
 }
 
 #[test]
@@ -93,11 +101,10 @@ fn test_try_vardiff_hashrate_clamps_to_minimum() {
         .unwrap()
         .into();
 
-    let mut vardiff = VardiffState::new_with_min(TEST_MIN_ALLOWED_HASHRATE)
-        .expect("Failed to create VardiffState");
+    let (mut vardiff, clock) = new_test_vardiff_state().expect("Failed to create VardiffState");
 
     let simulation_duration_secs = 16;
-    simulate_shares_and_wait(&mut vardiff, 0, simulation_duration_secs);
+    simulate_shares_and_wait(&mut vardiff, &clock, 0, simulation_duration_secs);
 
     let result = vardiff
         .try_vardiff(hashrate, &target, TEST_SHARES_PER_MINUTE)
@@ -115,3 +122,25 @@ fn test_try_vardiff_hashrate_clamps_to_minimum() {
     );
     assert_eq!(vardiff.shares_since_last_update(), 0);
 }
+
+#[test]
+fn test_try_vardiff_retargets_exactly_after_15s() {
+    let initial_hashrate = TEST_MIN_ALLOWED_HASHRATE * 100.0;
+    let target =
+        hash_rate_to_target(initial_hashrate.into(), TEST_SHARES_PER_MINUTE.into()).unwrap();
+
+    let (mut vardiff, clock) = new_test_vardiff_state().expect("Failed to create VardiffState");
+
+    simulate_shares_and_wait(&mut vardiff, &clock, 0, 15);
+    let result = vardiff
+        .try_vardiff(initial_hashrate, &target, TEST_SHARES_PER_MINUTE)
+        .expect("try_vardiff failed");
+    assert_eq!(result, None, "No update is expected before 15s elapsed");
+
+    clock.advance(1);
+    let result = vardiff
+        .try_vardiff(initial_hashrate, &target, TEST_SHARES_PER_MINUTE)
+        .expect("try_vardiff failed");
+    assert_eq!(result, Some(initial_hashrate / 1.5));
+    assert_eq!(vardiff.last_update_timestamp(), clock.now());
+}
@@ -0,0 +1,185 @@
+/// EWMA implementation test suite
+use crate::vardiff::test::{
+    new_test_clock, simulate_shares_and_wait, TEST_INITIAL_HASHRATE, TEST_MIN_ALLOWED_HASHRATE,
+    TEST_SHARES_PER_MINUTE,
+};
+use crate::{
+    clock::MockClock,
+    target::hash_rate_to_target,
+    vardiff::{ewma::EwmaVardiff, VardiffError},
+};
+use bitcoin::Target;
+use std::sync::Arc;
+
+use super::{
+    simulation::assert_converges_on_default_scenarios, test_increment_and_reset_shares,
+    test_try_vardiff_stable_hashrate_minimal_change_or_no_change, Vardiff,
+};
+
+fn new_test_vardiff() -> Result<(EwmaVardiff, MockClock), VardiffError> {
+    let clock = new_test_clock();
+    let vardiff = EwmaVardiff::new_with_clock(TEST_MIN_ALLOWED_HASHRATE, Arc::new(clock.clone()))?;
+    Ok((vardiff, clock))
+}
+
+fn initial_target() -> Target {
+    hash_rate_to_target(TEST_INITIAL_HASHRATE.into(), TEST_SHARES_PER_MINUTE.into()).unwrap()
+}
+
+#[test]
+fn test_initialization_and_getters() {
+    let (vardiff, _clock) = new_test_vardiff().expect("Failed to create EwmaVardiff");
+
+    assert_eq!(vardiff.min_allowed_hashrate(), TEST_MIN_ALLOWED_HASHRATE);
+    assert_eq!(vardiff.shares_since_last_update(), 0);
+}
+
+#[test]
+fn test_increment_and_reset_shares_ewma() {
+    let (mut vardiff, clock) = new_test_vardiff().expect("Failed to create EwmaVardiff");
+    test_increment_and_reset_shares(&mut vardiff, &clock)
+}
+
+#[test]
+fn test_try_vardiff_stable_hashrate_minimal_change_or_no_change_ewma() {
+    let (mut vardiff, clock) = new_test_vardiff().expect("Failed to create EwmaVardiff");
+    test_try_vardiff_stable_hashrate_minimal_change_or_no_change(&mut vardiff, &clock);
+}
+
+#[test]
+fn test_try_vardiff_expected_share_rate_no_change() {
+    let (mut vardiff, clock) = new_test_vardiff().expect("Failed to create EwmaVardiff");
+
+    // exactly the expected amount of shares over 2 minutes
+    simulate_shares_and_wait(&mut vardiff, &clock, 20, 120);
+
+    let result = vardiff
+        .try_vardiff(
+            TEST_INITIAL_HASHRATE,
+            &initial_target(),
+            TEST_SHARES_PER_MINUTE,
+        )
+        .expect("try_vardiff failed");
+    assert_eq!(result, None);
+    // no update, so shares keep accumulating
+    assert_eq!(vardiff.shares_since_last_update(), 20);
+}
+
+#[test]
+fn test_try_vardiff_high_share_rate_increases_hashrate() {
+    let (mut vardiff, clock) = new_test_vardiff().expect("Failed to create EwmaVardiff");
+
+    // 10x the expected share rate over 1 minute
+    simulate_shares_and_wait(&mut vardiff, &clock, 100, 60);
+
+    let new_hashrate = vardiff
+        .try_vardiff(
+            TEST_INITIAL_HASHRATE,
+            &initial_target(),
+            TEST_SHARES_PER_MINUTE,
+        )
+        .expect("try_vardiff failed")
+        .expect("Hashrate should update");
+
+    // the estimate moves towards the realized hashrate, but not all the way
+    assert!(new_hashrate > TEST_INITIAL_HASHRATE);
+    assert!(new_hashrate < 10.0 * TEST_INITIAL_HASHRATE);
+    assert_eq!(vardiff.shares_since_last_update(), 0);
+}
+
+#[test]
+fn test_try_vardiff_no_shares_decreases_hashrate() {
+    let (mut vardiff, clock) = new_test_vardiff().expect("Failed to create EwmaVardiff");
+
+    simulate_shares_and_wait(&mut vardiff, &clock, 0, 120);
+
+    let new_hashrate = vardiff
+        .try_vardiff(
+            TEST_INITIAL_HASHRATE,
+            &initial_target(),
+            TEST_SHARES_PER_MINUTE,
+        )
+        .expect("try_vardiff failed")
+        .expect("Hashrate should update");
+
+    // with no shares, the estimate decays by e^(-Δt/τ)
+    let expected_hashrate = TEST_INITIAL_HASHRATE * (-120.0_f32 / 180.0).exp();
+    assert!(
+        (new_hashrate - expected_hashrate).abs() < 0.01,
+        "Got: {}, Expected: {}",
+        new_hashrate,
+        expected_hashrate
+    );
+}
+
+#[test]
+fn test_try_vardiff_too_early_no_change() {
+    let (mut vardiff, clock) = new_test_vardiff().expect("Failed to create EwmaVardiff");
+
+    simulate_shares_and_wait(&mut vardiff, &clock, 1000, 10);
+
+    let result = vardiff
+        .try_vardiff(
+            TEST_INITIAL_HASHRATE,
+            &initial_target(),
+            TEST_SHARES_PER_MINUTE,
+        )
+        .expect("try_vardiff failed");
+    assert_eq!(result, None);
+}
+
+#[test]
+fn test_try_vardiff_hashrate_clamps_to_minimum() {
+    let hashrate = TEST_MIN_ALLOWED_HASHRATE * 1.5;
+    let target = hash_rate_to_target(hashrate.into(), TEST_SHARES_PER_MINUTE.into()).unwrap();
+
+    let (mut vardiff, clock) = new_test_vardiff().expect("Failed to create EwmaVardiff");
+
+    simulate_shares_and_wait(&mut vardiff, &clock, 0, 600);
+
+    let new_hashrate = vardiff
+        .try_vardiff(hashrate, &target, TEST_SHARES_PER_MINUTE)
+        .expect("try_vardiff failed")
+        .expect("Hashrate should update");
+
+    assert_eq!(
+        new_hashrate, TEST_MIN_ALLOWED_HASHRATE,
+        "Hashrate should be clamped to minimum"
+    );
+}
+
+#[test]
+fn test_try_vardiff_zero_hashrate() {
+    let (mut vardiff, clock) = new_test_vardiff().expect("Failed to create EwmaVardiff");
+
+    simulate_shares_and_wait(&mut vardiff, &clock, 10, 60);
+
+    let new_hashrate = vardiff
+        .try_vardiff(0.0, &initial_target(), TEST_SHARES_PER_MINUTE)
+        .expect("try_vardiff failed")
+        .expect("Hashrate should update");
+
+    assert!(new_hashrate.is_finite());
+    assert!(new_hashrate >= TEST_MIN_ALLOWED_HASHRATE);
+
+    // without shares either, the hashrate is clamped to the minimum
+    simulate_shares_and_wait(&mut vardiff, &clock, 0, 60);
+    let new_hashrate = vardiff
+        .try_vardiff(0.0, &initial_target(), TEST_SHARES_PER_MINUTE)
+        .expect("try_vardiff failed");
+    assert_eq!(new_hashrate, Some(TEST_MIN_ALLOWED_HASHRATE));
+}
+
+#[test]
+fn test_ewma_converges_on_simulated_share_streams() {
+    for (scenario, report) in assert_converges_on_default_scenarios(|clock| {
+        EwmaVardiff::new_with_clock(TEST_MIN_ALLOWED_HASHRATE, clock).unwrap()
+    }) {
+        assert!(
+            report.overshoot < 0.5,
+            "{}: overshoot {}",
+            scenario.name,
+            report.overshoot
+        );
+    }
+}
@@ -1,20 +1,28 @@
 /// Contains a generic test implementation that is agnostic to the Vardiff implementation,
 /// providing methods to verify the correctness of any specific implementation.
-use std::{thread, time::Duration};
-
 mod classic;
+mod ewma;
+mod poisson;
+pub mod simulation;
 
 use super::Vardiff;
-use crate::target::hash_rate_to_target;
+use crate::{clock::MockClock, target::hash_rate_to_target};
 use bitcoin::Target;
 
 pub const TEST_INITIAL_HASHRATE: f32 = 1000.0;
 pub const TEST_SHARES_PER_MINUTE: f32 = 10.0;
 pub const TEST_MIN_ALLOWED_HASHRATE: f32 = 10.0;
+pub const TEST_START_TIMESTAMP: u64 = 1_700_000_000;
+
+// Helper function to create a clock that only moves when the test says so.
+pub fn new_test_clock() -> MockClock {
+    MockClock::new(TEST_START_TIMESTAMP)
+}
 
 // Helper function to simulate a number of shares being found over a given duration.
 pub fn simulate_shares_and_wait<V: Vardiff>(
     vardiff: &mut V,
+    clock: &MockClock,
     num_shares: u32,
     wait_duration_secs: u64,
 ) {
@@ -23,19 +31,12 @@ pub fn simulate_shares_and_wait<V: Vardiff>(
     }
 
     // Rather than waiting for wait_duration,
-    // we are performing time magic and going
-    // back in time.
-    let now = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_secs()
-        - wait_duration_secs;
-
-    vardiff.set_timestamp_of_last_update(now);
+    // we move the vardiff clock forward.
+    clock.advance(wait_duration_secs);
 }
 
 // Verifies that the share counter can be incremented and reset correctly.
-pub fn test_increment_and_reset_shares<V: Vardiff>(vardiff: &mut V) {
+pub fn test_increment_and_reset_shares<V: Vardiff>(vardiff: &mut V, clock: &MockClock) {
     let initial_timestamp = vardiff.last_update_timestamp();
 
     vardiff.increment_shares_since_last_update();
@@ -44,7 +45,7 @@ pub fn test_increment_and_reset_shares<V: Vardiff>(vardiff: &mut V) {
     vardiff.increment_shares_since_last_update();
     assert_eq!(vardiff.shares_since_last_update(), 2);
 
-    thread::sleep(Duration::from_secs(1));
+    clock.advance(1);
 
     vardiff.reset_counter().expect("Failed to reset counter");
     assert_eq!(vardiff.shares_since_last_update(), 0);
@@ -56,7 +57,10 @@ pub fn test_increment_and_reset_shares<V: Vardiff>(vardiff: &mut V) {
 }
 
 // Ensures that `try_vardiff` results in a minimal or no change when the hashrate is stable.
-pub fn test_try_vardiff_stable_hashrate_minimal_change_or_no_change<V: Vardiff>(vardiff: &mut V) {
+pub fn test_try_vardiff_stable_hashrate_minimal_change_or_no_change<V: Vardiff>(
+    vardiff: &mut V,
+    clock: &MockClock,
+) {
     let initial_hashrate = TEST_INITIAL_HASHRATE;
     let iniital_target =
         hash_rate_to_target(initial_hashrate.into(), TEST_SHARES_PER_MINUTE.into())
@@ -68,6 +72,7 @@ pub fn test_try_vardiff_stable_hashrate_minimal_change_or_no_change<V: Vardiff>(
 
     simulate_shares_and_wait(
         vardiff,
+        clock,
         expected_shares_for_duration,
         simulation_duration_secs,
     );
@@ -92,7 +97,10 @@ pub fn test_try_vardiff_stable_hashrate_minimal_change_or_no_change<V: Vardiff>(
 }
 
 // Tests if a high share submission rate correctly increases the difficulty (lowers the target).
-pub fn test_try_vardiff_low_hashrate_decrease_target<V: Vardiff>(vardiff: &mut V) {
+pub fn test_try_vardiff_low_hashrate_decrease_target<V: Vardiff>(
+    vardiff: &mut V,
+    clock: &MockClock,
+) {
     let initial_hashrate = TEST_INITIAL_HASHRATE;
     let initial_target =
         hash_rate_to_target(initial_hashrate.into(), TEST_SHARES_PER_MINUTE.into())
@@ -100,7 +108,7 @@ pub fn test_try_vardiff_low_hashrate_decrease_target<V: Vardiff>(vardiff: &mut V
             .into();
 
     let simulation_duration = 16;
-    simulate_shares_and_wait(vardiff, 16, simulation_duration);
+    simulate_shares_and_wait(vardiff, clock, 16, simulation_duration);
 
     let result = vardiff
         .try_vardiff(initial_hashrate, &initial_target, TEST_SHARES_PER_MINUTE)
@@ -127,7 +135,7 @@ pub fn test_try_vardiff_low_hashrate_decrease_target<V: Vardiff>(vardiff: &mut V
 }
 
 // Checks the difficulty adjustment logic for a high share rate within a 30-second window.
-pub fn test_try_vardiff_with_shares_less_than_30<V: Vardiff>(vardiff: &mut V) {
+pub fn test_try_vardiff_with_shares_less_than_30<V: Vardiff>(vardiff: &mut V, clock: &MockClock) {
     let initial_hashrate = TEST_INITIAL_HASHRATE;
     let initial_target =
         hash_rate_to_target(initial_hashrate.into(), TEST_SHARES_PER_MINUTE.into())
@@ -135,7 +143,7 @@ pub fn test_try_vardiff_with_shares_less_than_30<V: Vardiff>(vardiff: &mut V) {
             .into();
 
     let simulation_duration = 16;
-    simulate_shares_and_wait(vardiff, 500, simulation_duration);
+    simulate_shares_and_wait(vardiff, clock, 500, simulation_duration);
 
     let result = vardiff
         .try_vardiff(initial_hashrate, &initial_target, TEST_SHARES_PER_MINUTE)
@@ -160,7 +168,7 @@ pub fn test_try_vardiff_with_shares_less_than_30<V: Vardiff>(vardiff: &mut V) {
 }
 
 // Checks the difficulty adjustment logic for a high share rate within a 30 to 60-second window.
-pub fn test_try_vardiff_with_shares_30_to_60s<V: Vardiff>(vardiff: &mut V) {
+pub fn test_try_vardiff_with_shares_30_to_60s<V: Vardiff>(vardiff: &mut V, clock: &MockClock) {
     let initial_hashrate = TEST_INITIAL_HASHRATE;
     let initial_target =
         hash_rate_to_target(initial_hashrate.into(), TEST_SHARES_PER_MINUTE.into())
@@ -168,7 +176,7 @@ pub fn test_try_vardiff_with_shares_30_to_60s<V: Vardiff>(vardiff: &mut V) {
             .into();
 
     let simulation_duration = 31;
-    simulate_shares_and_wait(vardiff, 5000, simulation_duration);
+    simulate_shares_and_wait(vardiff, clock, 5000, simulation_duration);
 
     let result = vardiff
         .try_vardiff(initial_hashrate, &initial_target, TEST_SHARES_PER_MINUTE)
@@ -192,7 +200,7 @@ pub fn test_try_vardiff_with_shares_30_to_60s<V: Vardiff>(vardiff: &mut V) {
 }
 
 // Checks the difficulty adjustment logic for a high share rate over a 60-second window.
-pub fn test_try_vardiff_with_shares_more_than_60s<V: Vardiff>(vardiff: &mut V) {
+pub fn test_try_vardiff_with_shares_more_than_60s<V: Vardiff>(vardiff: &mut V, clock: &MockClock) {
     let initial_hashrate = TEST_INITIAL_HASHRATE;
     let initial_target =
         hash_rate_to_target(initial_hashrate.into(), TEST_SHARES_PER_MINUTE.into())
@@ -200,7 +208,7 @@ pub fn test_try_vardiff_with_shares_more_than_60s<V: Vardiff>(vardiff: &mut V) {
             .into();
 
     let simulation_duration = 60;
-    simulate_shares_and_wait(vardiff, 1000, simulation_duration);
+    simulate_shares_and_wait(vardiff, clock, 1000, simulation_duration);
 
     let result = vardiff
         .try_vardiff(initial_hashrate, &initial_target, TEST_SHARES_PER_MINUTE)
@@ -224,7 +232,10 @@ pub fn test_try_vardiff_with_shares_more_than_60s<V: Vardiff>(vardiff: &mut V) {
 }
 
 // Verifies that difficulty decreases when no shares are found within a 30-second window.
-fn test_try_vardiff_no_shares_less_than_30s_decrease<V: Vardiff>(vardiff: &mut V) {
+fn test_try_vardiff_no_shares_less_than_30s_decrease<V: Vardiff>(
+    vardiff: &mut V,
+    clock: &MockClock,
+) {
     let initial_hashrate = TEST_INITIAL_HASHRATE;
     let initial_target =
         hash_rate_to_target(initial_hashrate.into(), TEST_SHARES_PER_MINUTE.into())
@@ -232,7 +243,7 @@ fn test_try_vardiff_no_shares_less_than_30s_decrease<V: Vardiff>(vardiff: &mut V
             .into();
 
     let simulation_duration = 16;
-    simulate_shares_and_wait(vardiff, 0, simulation_duration);
+    simulate_shares_and_wait(vardiff, clock, 0, simulation_duration);
 
     let result = vardiff
         .try_vardiff(initial_hashrate, &initial_target, TEST_SHARES_PER_MINUTE)
@@ -252,7 +263,7 @@ fn test_try_vardiff_no_shares_less_than_30s_decrease<V: Vardiff>(vardiff: &mut V
 }
 
 // Verifies that difficulty decreases when no shares are found within a 30 to 60-second window.
-fn test_try_vardiff_no_shares_30_to_60s_decrease<V: Vardiff>(vardiff: &mut V) {
+fn test_try_vardiff_no_shares_30_to_60s_decrease<V: Vardiff>(vardiff: &mut V, clock: &MockClock) {
     let initial_hashrate = TEST_INITIAL_HASHRATE;
     let initial_target =
         hash_rate_to_target(initial_hashrate.into(), TEST_SHARES_PER_MINUTE.into())
@@ -260,7 +271,7 @@ fn test_try_vardiff_no_shares_30_to_60s_decrease<V: Vardiff>(vardiff: &mut V) {
             .into();
 
     let simulation_duration = 31;
-    simulate_shares_and_wait(vardiff, 0, simulation_duration);
+    simulate_shares_and_wait(vardiff, clock, 0, simulation_duration);
 
     let result = vardiff
         .try_vardiff(initial_hashrate, &initial_target, TEST_SHARES_PER_MINUTE)
@@ -279,7 +290,10 @@ fn test_try_vardiff_no_shares_30_to_60s_decrease<V: Vardiff>(vardiff: &mut V) {
 }
 
 // Verifies that difficulty decreases when no shares are found over a 60-second window.
-fn test_try_vardiff_no_shares_more_than_60s_decrease<V: Vardiff>(vardiff: &mut V) {
+fn test_try_vardiff_no_shares_more_than_60s_decrease<V: Vardiff>(
+    vardiff: &mut V,
+    clock: &MockClock,
+) {
     let initial_hashrate = TEST_INITIAL_HASHRATE;
     let initial_target =
         hash_rate_to_target(initial_hashrate.into(), TEST_SHARES_PER_MINUTE.into())
@@ -287,7 +301,7 @@ fn test_try_vardiff_no_shares_more_than_60s_decrease<V: Vardiff>(vardiff: &mut V
             .into();
 
     let simulation_duration = 60;
-    simulate_shares_and_wait(vardiff, 0, simulation_duration);
+    simulate_shares_and_wait(vardiff, clock, 0, simulation_duration);
 
     let result = vardiff
         .try_vardiff(initial_hashrate, &initial_target, TEST_SHARES_PER_MINUTE)
@@ -305,7 +319,7 @@ fn test_try_vardiff_no_shares_more_than_60s_decrease<V: Vardiff>(vardiff: &mut V
     assert_eq!(vardiff.shares_since_last_update(), 0);
 }
 
-fn test_try_vardiff_with_less_spm_than_expected<V: Vardiff>(vardiff: &mut V) {
+fn test_try_vardiff_with_less_spm_than_expected<V: Vardiff>(vardiff: &mut V, clock: &MockClock) {
     let initial_hashrate = TEST_INITIAL_HASHRATE;
     let initial_target =
         hash_rate_to_target(initial_hashrate.into(), TEST_SHARES_PER_MINUTE.into())
@@ -316,7 +330,7 @@ fn test_try_vardiff_with_less_spm_than_expected<V: Vardiff>(vardiff: &mut V) {
 
     let simulation_duration = 60;
     // testing case when realized_shares_per_minute / shares_per_minute = 0.4
-    simulate_shares_and_wait(vardiff, 4, simulation_duration);
+    simulate_shares_and_wait(vardiff, clock, 4, simulation_duration);
 
     let hashrate_after_60s = vardiff
         .try_vardiff(initial_hashrate, &initial_target, TEST_SHARES_PER_MINUTE)
@@ -331,7 +345,7 @@ fn test_try_vardiff_with_less_spm_than_expected<V: Vardiff>(vardiff: &mut V) {
 
     let simulation_duration = 120;
     // testing case when realized_shares_per_minute / shares_per_minute = 0.5
-    simulate_shares_and_wait(vardiff, 10, simulation_duration);
+    simulate_shares_and_wait(vardiff, clock, 10, simulation_duration);
 
     let hashrate_after_120s = vardiff
         .try_vardiff(
@@ -350,7 +364,7 @@ fn test_try_vardiff_with_less_spm_than_expected<V: Vardiff>(vardiff: &mut V) {
 
     let simulation_duration = 180;
     // testing case when realized_shares_per_minute / shares_per_minute = 0.55
-    simulate_shares_and_wait(vardiff, 16, simulation_duration);
+    simulate_shares_and_wait(vardiff, clock, 16, simulation_duration);
 
     let hashrate_after_180s = vardiff
         .try_vardiff(
@@ -369,7 +383,7 @@ fn test_try_vardiff_with_less_spm_than_expected<V: Vardiff>(vardiff: &mut V) {
 
     let simulation_duration = 240;
     // testing case when realized_shares_per_minute / shares_per_minute = 0.7
-    simulate_shares_and_wait(vardiff, 28, simulation_duration);
+    simulate_shares_and_wait(vardiff, clock, 28, simulation_duration);
 
     let hashrate_after_240s = vardiff
         .try_vardiff(
@@ -388,7 +402,7 @@ fn test_try_vardiff_with_less_spm_than_expected<V: Vardiff>(vardiff: &mut V) {
 
     let simulation_duration = 300;
     // testing case when realized_shares_per_minute / shares_per_minute = 0.85
-    simulate_shares_and_wait(vardiff, 42, simulation_duration);
+    simulate_shares_and_wait(vardiff, clock, 42, simulation_duration);
 
     let hashrate_after_300s = vardiff
         .try_vardiff(
@@ -0,0 +1,179 @@
+/// Poisson implementation test suite
+use crate::vardiff::test::{
+    new_test_clock, simulate_shares_and_wait, TEST_INITIAL_HASHRATE, TEST_MIN_ALLOWED_HASHRATE,
+    TEST_SHARES_PER_MINUTE,
+};
+use crate::{
+    clock::MockClock,
+    target::hash_rate_to_target,
+    vardiff::{poisson::PoissonVardiff, VardiffError},
+};
+use bitcoin::Target;
+use std::sync::Arc;
+
+use super::{
+    simulation::assert_converges_on_default_scenarios, test_increment_and_reset_shares,
+    test_try_vardiff_stable_hashrate_minimal_change_or_no_change, Vardiff,
+};
+
+fn new_test_vardiff() -> Result<(PoissonVardiff, MockClock), VardiffError> {
+    let clock = new_test_clock();
+    let vardiff =
+        PoissonVardiff::new_with_clock(TEST_MIN_ALLOWED_HASHRATE, Arc::new(clock.clone()))?;
+    Ok((vardiff, clock))
+}
+
+fn initial_target() -> Target {
+    hash_rate_to_target(TEST_INITIAL_HASHRATE.into(), TEST_SHARES_PER_MINUTE.into()).unwrap()
+}
+
+#[test]
+fn test_initialization_and_getters() {
+    let (vardiff, _clock) = new_test_vardiff().expect("Failed to create PoissonVardiff");
+
+    assert_eq!(vardiff.min_allowed_hashrate(), TEST_MIN_ALLOWED_HASHRATE);
+    assert_eq!(vardiff.shares_since_last_update(), 0);
+}
+
+#[test]
+fn test_increment_and_reset_shares_poisson() {
+    let (mut vardiff, clock) = new_test_vardiff().expect("Failed to create PoissonVardiff");
+    test_increment_and_reset_shares(&mut vardiff, &clock)
+}
+
+#[test]
+fn test_try_vardiff_stable_hashrate_minimal_change_or_no_change_poisson() {
+    let (mut vardiff, clock) = new_test_vardiff().expect("Failed to create PoissonVardiff");
+    test_try_vardiff_stable_hashrate_minimal_change_or_no_change(&mut vardiff, &clock);
+}
+
+#[test]
+fn test_confidence_interval() {
+    let (vardiff, _clock) = new_test_vardiff().expect("Failed to create PoissonVardiff");
+
+    let (lower, upper) = vardiff.confidence_interval(0);
+    assert_eq!(lower, 0.0);
+    // exact 99% upper bound for k = 0 is 5.30
+    assert!((upper - 5.30).abs() < 0.1, "upper: {upper}");
+
+    let (lower, upper) = vardiff.confidence_interval(100);
+    // exact 99% bounds for k = 100 are [76.3, 129.0]
+    assert!((lower - 76.3).abs() < 0.5, "lower: {lower}");
+    assert!((upper - 129.0).abs() < 0.5, "upper: {upper}");
+}
+
+#[test]
+fn test_try_vardiff_within_confidence_interval_no_change() {
+    let (mut vardiff, clock) = new_test_vardiff().expect("Failed to create PoissonVardiff");
+
+    // 15 shares where 20 are expected is well within noise
+    simulate_shares_and_wait(&mut vardiff, &clock, 15, 120);
+
+    let result = vardiff
+        .try_vardiff(
+            TEST_INITIAL_HASHRATE,
+            &initial_target(),
+            TEST_SHARES_PER_MINUTE,
+        )
+        .expect("try_vardiff failed");
+    assert_eq!(result, None);
+    // no update, so shares keep accumulating
+    assert_eq!(vardiff.shares_since_last_update(), 15);
+}
+
+#[test]
+fn test_try_vardiff_high_share_rate_increases_hashrate() {
+    let (mut vardiff, clock) = new_test_vardiff().expect("Failed to create PoissonVardiff");
+
+    // 6x the expected share rate over 1 minute
+    simulate_shares_and_wait(&mut vardiff, &clock, 60, 60);
+
+    let new_hashrate = vardiff
+        .try_vardiff(
+            TEST_INITIAL_HASHRATE,
+            &initial_target(),
+            TEST_SHARES_PER_MINUTE,
+        )
+        .expect("try_vardiff failed")
+        .expect("Hashrate should update");
+
+    assert_eq!(new_hashrate, 6.0 * TEST_INITIAL_HASHRATE);
+    assert_eq!(vardiff.shares_since_last_update(), 0);
+}
+
+#[test]
+fn test_try_vardiff_no_shares_decreases_hashrate() {
+    let (mut vardiff, clock) = new_test_vardiff().expect("Failed to create PoissonVardiff");
+
+    // 10 shares were expected, the upper bound for 0 observed shares is ~5.3
+    simulate_shares_and_wait(&mut vardiff, &clock, 0, 60);
+
+    let new_hashrate = vardiff
+        .try_vardiff(
+            TEST_INITIAL_HASHRATE,
+            &initial_target(),
+            TEST_SHARES_PER_MINUTE,
+        )
+        .expect("try_vardiff failed")
+        .expect("Hashrate should update");
+
+    let (_, upper) = vardiff.confidence_interval(0);
+    let expected_hashrate = TEST_INITIAL_HASHRATE * upper as f32 / TEST_SHARES_PER_MINUTE;
+    assert!(
+        (new_hashrate - expected_hashrate).abs() / expected_hashrate < 0.01,
+        "Got: {}, Expected: {}",
+        new_hashrate,
+        expected_hashrate
+    );
+}
+
+#[test]
+fn test_try_vardiff_no_shares_too_early_no_change() {
+    let (mut vardiff, clock) = new_test_vardiff().expect("Failed to create PoissonVardiff");
+
+    // only ~2.7 shares expected, seeing none is not significant yet
+    simulate_shares_and_wait(&mut vardiff, &clock, 0, 16);
+
+    let result = vardiff
+        .try_vardiff(
+            TEST_INITIAL_HASHRATE,
+            &initial_target(),
+            TEST_SHARES_PER_MINUTE,
+        )
+        .expect("try_vardiff failed");
+    assert_eq!(result, None);
+}
+
+#[test]
+fn test_try_vardiff_hashrate_clamps_to_minimum() {
+    let hashrate = TEST_MIN_ALLOWED_HASHRATE * 1.5;
+    let target = hash_rate_to_target(hashrate.into(), TEST_SHARES_PER_MINUTE.into()).unwrap();
+
+    let (mut vardiff, clock) = new_test_vardiff().expect("Failed to create PoissonVardiff");
+
+    simulate_shares_and_wait(&mut vardiff, &clock, 0, 600);
+
+    let new_hashrate = vardiff
+        .try_vardiff(hashrate, &target, TEST_SHARES_PER_MINUTE)
+        .expect("try_vardiff failed")
+        .expect("Hashrate should update");
+
+    assert_eq!(
+        new_hashrate, TEST_MIN_ALLOWED_HASHRATE,
+        "Hashrate should be clamped to minimum"
+    );
+}
+
+#[test]
+fn test_poisson_converges_on_simulated_share_streams() {
+    for (scenario, report) in assert_converges_on_default_scenarios(|clock| {
+        PoissonVardiff::new_with_clock(TEST_MIN_ALLOWED_HASHRATE, clock).unwrap()
+    }) {
+        assert!(
+            report.overshoot < 0.5,
+            "{}: overshoot {}",
+            scenario.name,
+            report.overshoot
+        );
+    }
+}
@@ -0,0 +1,310 @@
+/// Deterministic simulation harness for Vardiff implementations.
+///
+/// Replays a stream of share arrivals generated from a seeded pseudo-random Poisson process and
+/// drives any [`Vardiff`] implementation through it, reporting how fast and how cleanly it
+/// converges to the true hashrate of the simulated miner.
+use super::{new_test_clock, Vardiff};
+use crate::{
+    clock::{Clock, MockClock},
+    target::hash_rate_to_target,
+    vardiff::{classic::VardiffState, ewma::EwmaVardiff, poisson::PoissonVardiff},
+};
+use std::sync::Arc;
+
+/// A share arrival scenario to be replayed against a Vardiff implementation.
+#[derive(Debug, Clone)]
+pub struct SimulationScenario {
+    /// Human readable name, used when printing reports.
+    pub name: &'static str,
+    /// Hashrate (H/s) the channel is opened with.
+    pub initial_hashrate: f32,
+    /// Actual hashrate (H/s) of the simulated miner.
+    pub true_hashrate: f32,
+    /// Share rate the channel is configured for.
+    pub shares_per_minute: f32,
+    /// Total simulated time.
+    pub duration_secs: u64,
+    /// Interval between two consecutive `try_vardiff` calls.
+    pub tick_secs: u64,
+    /// Relative distance to `true_hashrate` under which the estimate is considered converged.
+    pub tolerance: f32,
+    /// Seed of the share arrival stream.
+    pub seed: u64,
+}
+
+/// Outcome of replaying a [`SimulationScenario`].
+#[derive(Debug, Clone)]
+pub struct SimulationReport {
+    /// Time after which the estimate stayed within tolerance until the end of the simulation.
+    pub convergence_time_secs: Option<u64>,
+    /// Largest relative excursion past the true hashrate, on the opposite side of where the
+    /// estimate started. Zero if the estimate never crossed the true hashrate.
+    pub overshoot: f32,
+    /// Number of hashrate updates returned by `try_vardiff`.
+    pub updates: u32,
+    /// Estimate at the end of the simulation.
+    pub final_hashrate: f32,
+}
+
+/// xorshift64* pseudo-random generator, good enough for reproducible share streams.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Self(seed.max(1))
+    }
+
+    fn next_f64(&mut self) -> f64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        let x = self.0.wrapping_mul(0x2545_f491_4f6c_dd1d);
+        (x >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    // Knuth's algorithm for small means, normal approximation for large ones.
+    fn poisson(&mut self, lambda: f64) -> u32 {
+        if lambda > 30.0 {
+            let u1 = self.next_f64().max(f64::MIN_POSITIVE);
+            let u2 = self.next_f64();
+            let z = (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos();
+            return (lambda + z * lambda.sqrt()).round().max(0.0) as u32;
+        }
+        let l = (-lambda).exp();
+        let mut k = 0;
+        let mut p = 1.0;
+        loop {
+            p *= self.next_f64();
+            if p <= l {
+                return k;
+            }
+            k += 1;
+        }
+    }
+}
+
+/// Replays `scenario` against `vardiff`.
+///
+/// `clock` must be the clock `vardiff` was created with. Time is simulated by moving it forward,
+/// so hours of mining are replayed in milliseconds.
+pub fn simulate<V: Vardiff>(
+    vardiff: &mut V,
+    clock: &MockClock,
+    scenario: &SimulationScenario,
+) -> SimulationReport {
+    let mut rng = Rng::new(scenario.seed);
+    let mut hashrate = scenario.initial_hashrate;
+    let mut target =
+        hash_rate_to_target(hashrate.into(), scenario.shares_per_minute.into()).unwrap();
+    let started_above = scenario.initial_hashrate > scenario.true_hashrate;
+
+    let mut last_out_of_tolerance = Some(0);
+    let mut overshoot: f32 = 0.0;
+    let mut updates = 0;
+
+    let mut t = 0;
+    while t < scenario.duration_secs {
+        t += scenario.tick_secs;
+        clock.advance(scenario.tick_secs);
+
+        // each share is worth `hashrate * 60 / shares_per_minute` hashes at the current target
+        let expected_shares = scenario.true_hashrate as f64 * scenario.tick_secs as f64
+            / (hashrate as f64 * 60.0 / scenario.shares_per_minute as f64);
+        for _ in 0..rng.poisson(expected_shares) {
+            vardiff.increment_shares_since_last_update();
+        }
+
+        if let Some(new_hashrate) = vardiff
+            .try_vardiff(hashrate, &target, scenario.shares_per_minute)
+            .expect("try_vardiff failed")
+        {
+            hashrate = new_hashrate;
+            target =
+                hash_rate_to_target(hashrate.into(), scenario.shares_per_minute.into()).unwrap();
+            updates += 1;
+        }
+
+        let relative_error = (hashrate - scenario.true_hashrate) / scenario.true_hashrate;
+        if relative_error.abs() > scenario.tolerance {
+            last_out_of_tolerance = Some(t);
+        }
+        if started_above {
+            overshoot = overshoot.max(-relative_error);
+        } else {
+            overshoot = overshoot.max(relative_error);
+        }
+    }
+
+    let convergence_time_secs = match last_out_of_tolerance {
+        Some(t) if t >= scenario.duration_secs => None,
+        Some(t) => Some(t),
+        None => Some(0),
+    };
+
+    SimulationReport {
+        convergence_time_secs,
+        overshoot,
+        updates,
+        final_hashrate: hashrate,
+    }
+}
+
+/// Scenarios every Vardiff implementation is expected to converge on.
+pub fn default_scenarios() -> Vec<SimulationScenario> {
+    let base = SimulationScenario {
+        name: "",
+        initial_hashrate: 0.0,
+        true_hashrate: 1_000_000.0,
+        shares_per_minute: 10.0,
+        duration_secs: 4 * 60 * 60,
+        tick_secs: 10,
+        tolerance: 0.3,
+        seed: 0x5eed,
+    };
+    vec![
+        SimulationScenario {
+            name: "underestimated 100x",
+            initial_hashrate: 10_000.0,
+            ..base.clone()
+        },
+        SimulationScenario {
+            name: "underestimated 2x",
+            initial_hashrate: 500_000.0,
+            seed: 0xbeef,
+            ..base.clone()
+        },
+        SimulationScenario {
+            name: "overestimated 2x",
+            initial_hashrate: 2_000_000.0,
+            seed: 0xcafe,
+            ..base.clone()
+        },
+        SimulationScenario {
+            name: "overestimated 100x",
+            initial_hashrate: 100_000_000.0,
+            seed: 0xf00d,
+            ..base.clone()
+        },
+        SimulationScenario {
+            name: "exact estimate",
+            initial_hashrate: 1_000_000.0,
+            seed: 0xdead,
+            ..base
+        },
+    ]
+}
+
+// Asserts that the vardiff built by `new_vardiff` out of a simulated clock converges on every
+// default scenario, returning the reports.
+pub fn assert_converges_on_default_scenarios<V: Vardiff, F: Fn(Arc<dyn Clock>) -> V>(
+    new_vardiff: F,
+) -> Vec<(SimulationScenario, SimulationReport)> {
+    default_scenarios()
+        .into_iter()
+        .map(|scenario| {
+            let clock = new_test_clock();
+            let report = simulate(&mut new_vardiff(Arc::new(clock.clone())), &clock, &scenario);
+            assert!(
+                report.convergence_time_secs.is_some(),
+                "{}: did not converge, final hashrate {}",
+                scenario.name,
+                report.final_hashrate
+            );
+            (scenario, report)
+        })
+        .collect()
+}
+
+#[test]
+fn test_simulation_is_deterministic() {
+    let scenario = &default_scenarios()[0];
+    let run = || {
+        let clock = new_test_clock();
+        let mut vardiff = VardiffState::new_with_clock(1.0, Arc::new(clock.clone())).unwrap();
+        simulate(&mut vardiff, &clock, scenario)
+    };
+    let a = run();
+    let b = run();
+    assert_eq!(a.updates, b.updates);
+    assert_eq!(a.final_hashrate, b.final_hashrate);
+    assert_eq!(a.convergence_time_secs, b.convergence_time_secs);
+}
+
+// Upper bounds on the `(convergence_time_secs, overshoot)` of each algorithm, for each of the
+// default scenarios in order. Share streams are seeded, so the bounds only leave headroom for
+// tuning changes that don't degrade the algorithm.
+const SIMULATION_BOUNDS: [(&str, [(u64, f32); 5]); 3] = [
+    (
+        "classic",
+        [(60, 0.3), (600, 0.5), (720, 0.4), (1_200, 0.7), (0, 0.25)],
+    ),
+    (
+        "ewma",
+        [(300, 0.1), (150, 0.05), (300, 0.4), (1_800, 0.25), (0, 0.0)],
+    ),
+    (
+        "poisson",
+        [(30, 0.3), (100, 0.25), (360, 0.5), (900, 0.5), (0, 0.0)],
+    ),
+];
+
+#[test]
+fn test_compare_vardiff_algorithms() {
+    let reports = [
+        assert_converges_on_default_scenarios(|clock| {
+            VardiffState::new_with_clock(1.0, clock).unwrap()
+        }),
+        assert_converges_on_default_scenarios(|clock| {
+            EwmaVardiff::new_with_clock(1.0, clock).unwrap()
+        }),
+        assert_converges_on_default_scenarios(|clock| {
+            PoissonVardiff::new_with_clock(1.0, clock).unwrap()
+        }),
+    ];
+
+    for ((algorithm, bounds), results) in SIMULATION_BOUNDS.iter().zip(reports.iter()) {
+        assert_eq!(results.len(), bounds.len());
+        for ((scenario, report), (max_convergence_secs, max_overshoot)) in
+            results.iter().zip(bounds.iter())
+        {
+            let convergence_time_secs = report.convergence_time_secs.unwrap();
+            assert!(
+                convergence_time_secs <= *max_convergence_secs,
+                "{algorithm} {}: converged after {convergence_time_secs}s, expected at most {}s",
+                scenario.name,
+                max_convergence_secs
+            );
+            assert!(
+                report.overshoot <= *max_overshoot,
+                "{algorithm} {}: overshoot {}, expected at most {}",
+                scenario.name,
+                report.overshoot,
+                max_overshoot
+            );
+        }
+    }
+
+    let convergence_time_secs = |algorithm: usize, scenario: usize| {
+        reports[algorithm][scenario]
+            .1
+            .convergence_time_secs
+            .unwrap()
+    };
+    let (classic, ewma, poisson) = (0, 1, 2);
+    let step_scenarios = 0..4;
+    for scenario in step_scenarios {
+        // Poisson converges no slower than classic on every step
+        assert!(
+            convergence_time_secs(poisson, scenario) <= convergence_time_secs(classic, scenario)
+        );
+    }
+    // EWMA smooths out large steps, but converges no slower than classic on 2x steps
+    for scenario in [1, 2] {
+        assert!(convergence_time_secs(ewma, scenario) <= convergence_time_secs(classic, scenario));
+    }
+    // unlike classic, EWMA and Poisson don't retarget a channel opened with the right estimate
+    let exact_estimate = 4;
+    assert!(reports[classic][exact_estimate].1.updates > 0);
+    assert_eq!(reports[ewma][exact_estimate].1.updates, 0);
+    assert_eq!(reports[poisson][exact_estimate].1.updates, 0);
+}